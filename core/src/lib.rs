@@ -25,6 +25,7 @@ pub mod fetch_stage;
 pub mod gen_keys;
 pub mod next_leader;
 pub mod optimistic_confirmation_verifier;
+pub mod packet_forwarder;
 pub mod poh_timing_report_service;
 pub mod poh_timing_reporter;
 pub mod repair;
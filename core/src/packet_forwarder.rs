@@ -0,0 +1,1657 @@
+//! The `packet_forwarder` module relays packets that passed sigverify to a
+//! local UDP receiver (`hoho-recv`) for off-validator analysis.
+//!
+//! The forwarder is configured through environment variables that are read
+//! once, when the first packet is forwarded:
+//!
+//! * `PACKET_FORWARDER_WARMUP_SECS`: skip every packet for this many seconds
+//!   after the first one reaches the forwarder, so the burst a validator sees
+//!   while it starts up and catches up to the cluster doesn't flood a freshly
+//!   started receiver. Packets skipped are counted. Defaults to 0 (no warmup).
+//! * `PACKET_FORWARDER_MIN_PACKET_SIZE`: only forward packets whose serialized
+//!   length exceeds this many bytes. Simple transfers and votes are small while
+//!   DEX swaps are large, so this sheds obvious noise without parsing.
+//!   Defaults to 0 (forward everything).
+//! * `PACKET_FORWARDER_MINTS`: only forward transactions referencing one of
+//!   these comma-separated token mints, see [`MintAllowlist`]. More selective
+//!   than the size filter for tracking specific tokens. Packets without one are
+//!   skipped and counted. Defaults to none (forward everything).
+//! * `PACKET_FORWARDER_MAX_PACKETS_PER_SLOT`: only forward the first this many
+//!   packets passing the other filters in each tip slot, see [`set_tip_slot`],
+//!   to sample a representative slice of traffic without the full volume.
+//!   Packets over the limit are skipped and counted. Defaults to 0 (unlimited).
+//! * `PACKET_FORWARDER_CLOCK`: `wall` (default) or `monotonic`, the clock used
+//!   for the forward header timestamp, see [`ForwardClock`].
+//! * `PACKET_FORWARDER_MODE`: `full` (default) or `thin`, see [`ForwardMode`].
+//! * `PACKET_FORWARDER_GRPC_ENDPOINT`: stream packets to this gRPC endpoint,
+//!   e.g. `http://127.0.0.1:50051`, instead of sending UDP datagrams. Requires
+//!   the `forward-grpc` feature; the service is `core/proto/packet_forwarder.proto`.
+//! * `PACKET_FORWARDER_GRPC_TLS_CA`: encrypt the gRPC stream with TLS, verifying
+//!   the receiver's certificate against this PEM CA bundle. The endpoint should
+//!   then be `https://`. Unset (default) streams in plaintext, which is fine
+//!   over localhost, see [`GrpcTls`].
+//! * `PACKET_FORWARDER_GRPC_TLS_DOMAIN`: the name the receiver's certificate is
+//!   checked against. Defaults to the endpoint's host.
+//! * `PACKET_FORWARDER_GRPC_TLS_CERT`, `PACKET_FORWARDER_GRPC_TLS_KEY`: PEM
+//!   certificate chain and private key the forwarder presents, for receivers
+//!   that require client authentication. Both or neither.
+//! * `PACKET_FORWARDER_ACTIVE_WINDOWS`: only forward during these UTC time of
+//!   day ranges, e.g. `13:30-20:00,23:00-01:00`, see [`ActiveWindows`]. Packets
+//!   outside them are skipped and counted. Defaults to always.
+//! * `PACKET_FORWARDER_INGRESS`: `all` (default) or `direct`, see
+//!   [`ForwardIngress`]. Packets from other ingress are skipped and counted.
+//! * `PACKET_FORWARDER_CHANNEL_FULL`: `drop-newest` (default) or `drop-oldest`,
+//!   what to drop when the queue to the forwarder thread is full, see
+//!   [`ChannelFullPolicy`].
+//! * `PACKET_FORWARDER_REMOVED_SAMPLE`: also forward one in this many packets
+//!   removed before sigverify, e.g. shed by random discard under load, to
+//!   study spam and invalid traffic, see [`forward_removed_packet`]. They go to
+//!   their own UDP destination, in full mode, and only the warmup and active
+//!   windows apply to them. Keep it large: removed traffic is the bulk of a flood.
+//!   Defaults to 0 (off).
+//! * `PACKET_FORWARDER_REMOVED_ADDRESS`: where sampled removed packets go.
+//!   Defaults to `127.0.0.1:33334`.
+//! * `PACKET_FORWARDER_TAG_LEADER`: `true` to add the expected leader to the
+//!   forward header, making it version 2, see below. Defaults to `false`.
+//! * `PACKET_FORWARDER_LOG`: `summary` (default), `detailed` or `silent`, see
+//!   [`ForwardLogLevel`]. Metrics are reported every second regardless.
+//! * `PACKET_FORWARDER_LOG_INTERVAL_SECS`: how often the diagnostic summary of
+//!   the forwarder's counters is logged. Defaults to 60.
+//! * `PACKET_FORWARDER_RATE_DECAY`: weight kept by the moving averages of the
+//!   forwarded and dropped packet rates on each one second sample, in `[0, 1)`,
+//!   see [`RateDecay`]. Defaults to 0.8.
+//!
+//! # Forward header
+//!
+//! Every forwarded datagram is a fixed-size header followed by the packet bytes:
+//!
+//! | offset | size | field                                                     |
+//! |--------|------|-----------------------------------------------------------|
+//! | 0      | 1    | version: [`FORWARD_HEADER_VERSION`]                       |
+//! | 1      | 1    | clock: 0 = wall, 1 = monotonic                            |
+//! | 2      | 1    | mode: 0 = full, 1 = thin                                  |
+//! | 3      | 8    | timestamp: u64 LE microseconds since the UNIX epoch       |
+//! | 11     | 8    | slot: u64 LE tip slot, 0 if not known yet                 |
+//! | 19     | ..   | packet data (full) or thin payload                        |
+//!
+//! With `PACKET_FORWARDER_TAG_LEADER=true` the version is 2 and the leader
+//! follows the slot:
+//!
+//! | offset | size | field                                                     |
+//! |--------|------|-----------------------------------------------------------|
+//! | 19     | 32   | leader: pubkey of the leader after the tip slot, zeros if |
+//! |        |      | not known, see [`set_tip_leader`]                         |
+//! | 51     | ..   | packet data (full) or thin payload                        |
+//!
+//! The version byte names the layout of the rest of the header. A change to the
+//! header bumps it, and receivers skip the versions they don't know, so they can
+//! be upgraded before the validators that forward to them.
+//!
+//! The slot is that of the bank PoH was last reset to, see [`set_tip_slot`], so
+//! a packet lands in that slot or a later one. Receivers can filter by slot
+//! without an RPC round trip per transaction.
+//!
+//! The thin payload is the first signature followed by the distinct program ids
+//! the transaction invokes, in instruction order:
+//!
+//! | offset | size      | field                                                |
+//! |--------|-----------|------------------------------------------------------|
+//! | 0      | 64        | signature                                            |
+//! | 64     | 1         | number of program ids                                |
+//! | 65     | 32 * n    | program ids                                          |
+//!
+//! Wall-clock timestamps are directly comparable with the receiver's clock,
+//! but `SystemTime` can jump backward (or forward) on an NTP adjustment, which
+//! corrupts latency measurements taken across the jump. Monotonic timestamps
+//! are an `Instant` offset from a wall-clock anchor taken at startup: deltas
+//! between forwarded packets are always sane, but the absolute value drifts
+//! from true wall time by however much the system clock has been adjusted
+//! since the validator started.
+
+#[cfg(feature = "forward-grpc")]
+mod grpc;
+
+use {
+    crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender, TrySendError},
+    lazy_static::lazy_static,
+    solana_sdk::{
+        clock::Slot,
+        message::{MESSAGE_HEADER_LENGTH, MESSAGE_VERSION_PREFIX},
+        packet::{Meta, Packet},
+        pubkey::{Pubkey, PUBKEY_BYTES},
+        short_vec::decode_shortu16_len,
+        signature::SIGNATURE_BYTES,
+        transaction::VersionedTransaction,
+    },
+    std::{
+        net::UdpSocket,
+        path::PathBuf,
+        str::FromStr,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc, Mutex, PoisonError, RwLock,
+        },
+        thread,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+};
+
+// 使用 100k 的通道大小来处理每秒约 10k 的数据包
+const CHANNEL_SIZE: usize = 100_000 * 1_000;
+
+const FORWARD_ADDRESS: &str = "127.0.0.1:33333";
+
+const DEFAULT_REMOVED_ADDRESS: &str = "127.0.0.1:33334";
+
+const STATS_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+const DEFAULT_LOG_INTERVAL_SECS: u64 = 60;
+
+const MIN_RESTART_DELAY: Duration = Duration::from_millis(100);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(10);
+
+/// Layout of the forward header, see the module docs.
+pub const FORWARD_HEADER_VERSION: u8 = 1;
+
+pub const FORWARD_HEADER_SIZE: usize = 1 + 1 + 1 + 8 + 8;
+
+/// Layout of the forward header carrying the leader.
+pub const FORWARD_HEADER_VERSION_LEADER: u8 = 2;
+
+pub const FORWARD_HEADER_LEADER_SIZE: usize = FORWARD_HEADER_SIZE + 32;
+
+/// Decay of the exponentially weighted moving averages of the forwarded and
+/// dropped packet rates, sampled every second. Higher is smoother but slower to
+/// follow a change: at 0.8 a step in the rate is 90% reflected after 10 seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateDecay(f64);
+
+impl Default for RateDecay {
+    fn default() -> Self {
+        Self(0.8)
+    }
+}
+
+// Parsing rejects NaN
+impl Eq for RateDecay {}
+
+impl FromStr for RateDecay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<f64>() {
+            Ok(decay) if (0.0..1.0).contains(&decay) => Ok(Self(decay)),
+            _ => Err(format!("rate decay must be in [0, 1): {s}")),
+        }
+    }
+}
+
+/// Clock used for the forward header timestamp.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardClock {
+    /// `SystemTime::now()`, human readable but subject to NTP adjustments
+    #[default]
+    Wall = 0,
+    /// `Instant`-based, anchored to the wall clock at startup
+    Monotonic = 1,
+}
+
+impl FromStr for ForwardClock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wall" => Ok(Self::Wall),
+            "monotonic" => Ok(Self::Monotonic),
+            _ => Err(format!("unknown clock: {s}")),
+        }
+    }
+}
+
+/// What follows the forward header.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardMode {
+    /// The packet bytes exactly as they passed sigverify
+    #[default]
+    Full = 0,
+    /// Only the first signature and the invoked program ids, roughly a tenth of
+    /// the bandwidth for receivers that only care which programs were touched
+    Thin = 1,
+}
+
+impl FromStr for ForwardMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Self::Full),
+            "thin" => Ok(Self::Thin),
+            _ => Err(format!("unknown forward mode: {s}")),
+        }
+    }
+}
+
+impl ForwardClock {
+    fn now_us(&self) -> u64 {
+        match self {
+            Self::Wall => system_time_us(SystemTime::now()),
+            Self::Monotonic => {
+                let (anchor_us, anchor) = *CLOCK_ANCHOR;
+                anchor_us.saturating_add(anchor.elapsed().as_micros() as u64)
+            }
+        }
+    }
+}
+
+fn system_time_us(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+fn write_forward_header(
+    buf: &mut Vec<u8>,
+    clock: ForwardClock,
+    mode: ForwardMode,
+    timestamp_us: u64,
+    slot: u64,
+    leader: Option<&Pubkey>,
+) {
+    buf.push(if leader.is_some() {
+        FORWARD_HEADER_VERSION_LEADER
+    } else {
+        FORWARD_HEADER_VERSION
+    });
+    buf.push(clock as u8);
+    buf.push(mode as u8);
+    buf.extend_from_slice(&timestamp_us.to_le_bytes());
+    buf.extend_from_slice(&slot.to_le_bytes());
+    if let Some(leader) = leader {
+        buf.extend_from_slice(leader.as_ref());
+    }
+}
+
+/// Appends the thin payload for the transaction in `data`, or returns `None`
+/// if it does not deserialize.
+fn write_thin_payload(buf: &mut Vec<u8>, data: &[u8]) -> Option<()> {
+    let tx: VersionedTransaction = bincode::deserialize(data).ok()?;
+    let signature = tx.signatures.first()?;
+    // Program ids can not be loaded from lookup tables, so the static keys suffice
+    let account_keys = tx.message.static_account_keys();
+    let mut program_ids: Vec<&Pubkey> = vec![];
+    for instruction in tx.message.instructions() {
+        let program_id = account_keys.get(usize::from(instruction.program_id_index))?;
+        if !program_ids.contains(&program_id) {
+            program_ids.push(program_id);
+        }
+    }
+    buf.extend_from_slice(signature.as_ref());
+    buf.push(u8::try_from(program_ids.len()).ok()?);
+    for program_id in program_ids {
+        buf.extend_from_slice(program_id.as_ref());
+    }
+    Some(())
+}
+
+/// Which TPU ingress packets are forwarded from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardIngress {
+    /// Every packet that passed sigverify
+    #[default]
+    All,
+    /// Only packets sent straight to this leader's TPU port, leaving out those
+    /// other validators relayed through its TPU forwards port
+    Direct,
+}
+
+impl FromStr for ForwardIngress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "direct" => Ok(Self::Direct),
+            _ => Err(format!("unknown ingress: {s}")),
+        }
+    }
+}
+
+/// What to drop when the queue to the forwarder thread is full.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelFullPolicy {
+    /// The packet being queued, keeping the backlog. Under sustained overload
+    /// the receiver then only sees packets that have waited through all of it.
+    #[default]
+    DropNewest,
+    /// The oldest queued packet, making room for the new one, so the receiver
+    /// sees fresh packets during overload
+    DropOldest,
+}
+
+impl FromStr for ChannelFullPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop-newest" => Ok(Self::DropNewest),
+            "drop-oldest" => Ok(Self::DropOldest),
+            _ => Err(format!("unknown channel full policy: {s}")),
+        }
+    }
+}
+
+/// How much the forwarder logs, separately from the metrics it reports.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardLogLevel {
+    /// Nothing, for operators who only scrape metrics
+    Silent,
+    /// Configuration, state changes and a periodic summary of the counters
+    #[default]
+    Summary,
+    /// As `Summary`, with the summary broken down by destination
+    Detailed,
+}
+
+impl FromStr for ForwardLogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "silent" => Ok(Self::Silent),
+            "summary" => Ok(Self::Summary),
+            "detailed" => Ok(Self::Detailed),
+            _ => Err(format!("unknown log level: {s}")),
+        }
+    }
+}
+
+/// UTC time of day ranges during which packets are forwarded. Each range is
+/// `HH:MM[:SS]-HH:MM[:SS]`, including its start and excluding its end, and wraps
+/// past midnight when it ends before it starts. No ranges means always.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ActiveWindows(Vec<(u32, u32)>);
+
+impl ActiveWindows {
+    fn is_always(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn contains(&self, unix_time_s: u64) -> bool {
+        let second = (unix_time_s % SECONDS_PER_DAY) as u32;
+        self.is_always()
+            || self.0.iter().any(|&(start, end)| {
+                if start < end {
+                    start <= second && second < end
+                } else {
+                    second >= start || second < end
+                }
+            })
+    }
+}
+
+impl FromStr for ActiveWindows {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_time = |time: &str| {
+            let mut parts = time.trim().split(':').map(str::parse::<u32>);
+            let (Some(Ok(hours)), Some(Ok(minutes)), seconds, None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return Err(format!("invalid time: {time}"));
+            };
+            let seconds = seconds
+                .unwrap_or(Ok(0))
+                .map_err(|_| format!("invalid time: {time}"))?;
+            if hours > 23 || minutes > 59 || seconds > 59 {
+                return Err(format!("invalid time: {time}"));
+            }
+            Ok(hours * 3600 + minutes * 60 + seconds)
+        };
+        s.split(',')
+            .filter(|window| !window.trim().is_empty())
+            .map(|window| {
+                let (start, end) = window
+                    .split_once('-')
+                    .ok_or_else(|| format!("invalid window: {window}"))?;
+                let (start, end) = (parse_time(start)?, parse_time(end)?);
+                if start == end {
+                    return Err(format!("empty window: {window}"));
+                }
+                Ok((start, end))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Token mints a transaction must reference to be forwarded. The serialized
+/// static account keys are scanned for them without deserializing the
+/// transaction, so a mint only loaded through an address lookup table is not
+/// seen. Not every swap references its mints: a Raydium AMM swap names only
+/// token accounts, unless the transaction also creates the user's. No mints
+/// means every packet.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MintAllowlist(Vec<Pubkey>);
+
+impl MintAllowlist {
+    fn is_any(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether the transaction in `data` has one of the mints among its static
+    /// account keys. Data that doesn't parse that far has none.
+    fn matches(&self, data: &[u8]) -> bool {
+        self.is_any()
+            || static_account_keys(data).is_some_and(|keys| {
+                keys.chunks_exact(PUBKEY_BYTES)
+                    .any(|key| self.0.iter().any(|mint| mint.as_ref() == key))
+            })
+    }
+}
+
+impl FromStr for MintAllowlist {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|mint| !mint.is_empty())
+            .map(|mint| {
+                mint.parse::<Pubkey>()
+                    .map_err(|_| format!("invalid mint: {mint}"))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// The static account keys of a serialized transaction, 32 bytes each, found
+/// by walking the lengths that precede them.
+fn static_account_keys(data: &[u8]) -> Option<&[u8]> {
+    let (signatures, mut offset) = decode_shortu16_len(data).ok()?;
+    offset = offset.checked_add(signatures.checked_mul(SIGNATURE_BYTES)?)?;
+    // Versioned messages lead with a prefix byte, legacy ones with the header
+    if data.get(offset)? & MESSAGE_VERSION_PREFIX != 0 {
+        offset += 1;
+    }
+    offset += MESSAGE_HEADER_LENGTH;
+    let (keys, len) = decode_shortu16_len(data.get(offset..)?).ok()?;
+    offset += len;
+    data.get(offset..offset.checked_add(keys.checked_mul(PUBKEY_BYTES)?)?)
+}
+
+/// TLS for the gRPC forward stream. The files are read when the stream connects,
+/// so renewed certificates are picked up on the next reconnect; a stream whose
+/// TLS can't be set up is retried rather than opened in plaintext.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcTls {
+    /// PEM CA bundle the receiver's certificate is verified against
+    pub ca_cert: PathBuf,
+    /// Overrides the endpoint's host for certificate verification
+    pub domain: Option<String>,
+    /// PEM certificate chain and private key presented to the receiver
+    pub identity: Option<(PathBuf, PathBuf)>,
+}
+
+impl GrpcTls {
+    fn from_env(warn_invalid: bool) -> Option<Self> {
+        let ca_cert = std::env::var_os("PACKET_FORWARDER_GRPC_TLS_CA")?;
+        let cert = std::env::var_os("PACKET_FORWARDER_GRPC_TLS_CERT");
+        let key = std::env::var_os("PACKET_FORWARDER_GRPC_TLS_KEY");
+        let identity = match (cert, key) {
+            (Some(cert), Some(key)) => Some((cert.into(), key.into())),
+            (None, None) => None,
+            _ => {
+                if warn_invalid {
+                    warn!(
+                        "PACKET_FORWARDER_GRPC_TLS_CERT and PACKET_FORWARDER_GRPC_TLS_KEY must \
+                         be set together, connecting without a client certificate"
+                    );
+                }
+                None
+            }
+        };
+        Some(Self {
+            ca_cert: ca_cert.into(),
+            domain: std::env::var("PACKET_FORWARDER_GRPC_TLS_DOMAIN").ok(),
+            identity,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PacketForwarderConfig {
+    pub warmup: Duration,
+    pub min_packet_size: usize,
+    pub mints: MintAllowlist,
+    pub max_packets_per_slot: u64,
+    pub clock: ForwardClock,
+    pub mode: ForwardMode,
+    pub grpc_endpoint: Option<String>,
+    pub grpc_tls: Option<GrpcTls>,
+    pub active_windows: ActiveWindows,
+    pub ingress: ForwardIngress,
+    pub tag_leader: bool,
+    pub removed_sample: u64,
+    pub removed_address: String,
+    pub channel_full: ChannelFullPolicy,
+    pub log_level: ForwardLogLevel,
+    pub log_interval: Duration,
+    pub rate_decay: RateDecay,
+}
+
+impl PacketForwarderConfig {
+    pub fn from_env() -> Self {
+        let log_level =
+            env_var_or_default("PACKET_FORWARDER_LOG", ForwardLogLevel::default(), true);
+        let warn_invalid = log_level != ForwardLogLevel::Silent;
+        let log_interval_secs = env_var_or_default(
+            "PACKET_FORWARDER_LOG_INTERVAL_SECS",
+            DEFAULT_LOG_INTERVAL_SECS,
+            warn_invalid,
+        );
+        Self {
+            warmup: Duration::from_secs(env_var_or_default(
+                "PACKET_FORWARDER_WARMUP_SECS",
+                0,
+                warn_invalid,
+            )),
+            min_packet_size: env_var_or_default(
+                "PACKET_FORWARDER_MIN_PACKET_SIZE",
+                0,
+                warn_invalid,
+            ),
+            mints: env_var_or_default(
+                "PACKET_FORWARDER_MINTS",
+                MintAllowlist::default(),
+                warn_invalid,
+            ),
+            max_packets_per_slot: env_var_or_default(
+                "PACKET_FORWARDER_MAX_PACKETS_PER_SLOT",
+                0,
+                warn_invalid,
+            ),
+            clock: env_var_or_default(
+                "PACKET_FORWARDER_CLOCK",
+                ForwardClock::default(),
+                warn_invalid,
+            ),
+            mode: env_var_or_default(
+                "PACKET_FORWARDER_MODE",
+                ForwardMode::default(),
+                warn_invalid,
+            ),
+            grpc_endpoint: std::env::var("PACKET_FORWARDER_GRPC_ENDPOINT").ok(),
+            grpc_tls: GrpcTls::from_env(warn_invalid),
+            active_windows: env_var_or_default(
+                "PACKET_FORWARDER_ACTIVE_WINDOWS",
+                ActiveWindows::default(),
+                warn_invalid,
+            ),
+            ingress: env_var_or_default(
+                "PACKET_FORWARDER_INGRESS",
+                ForwardIngress::default(),
+                warn_invalid,
+            ),
+            tag_leader: env_var_or_default("PACKET_FORWARDER_TAG_LEADER", false, warn_invalid),
+            removed_sample: env_var_or_default("PACKET_FORWARDER_REMOVED_SAMPLE", 0, warn_invalid),
+            removed_address: std::env::var("PACKET_FORWARDER_REMOVED_ADDRESS")
+                .unwrap_or_else(|_| DEFAULT_REMOVED_ADDRESS.to_string()),
+            channel_full: env_var_or_default(
+                "PACKET_FORWARDER_CHANNEL_FULL",
+                ChannelFullPolicy::default(),
+                warn_invalid,
+            ),
+            log_level,
+            log_interval: Duration::from_secs(log_interval_secs).max(STATS_REPORT_INTERVAL),
+            rate_decay: env_var_or_default(
+                "PACKET_FORWARDER_RATE_DECAY",
+                RateDecay::default(),
+                warn_invalid,
+            ),
+        }
+    }
+
+    fn logs(&self) -> bool {
+        self.log_level != ForwardLogLevel::Silent
+    }
+
+    /// Whether packets are still skipped `since_start` after the first one.
+    fn warming_up(&self, since_start: Duration) -> bool {
+        since_start < self.warmup
+    }
+
+    fn should_forward(&self, data: &[u8]) -> bool {
+        data.len() > self.min_packet_size
+    }
+
+    /// Whether the ingress a packet arrived through is forwarded from. The fetch
+    /// stage flags packets received on the TPU forwards socket as forwarded.
+    fn accepts_ingress(&self, meta: &Meta) -> bool {
+        match self.ingress {
+            ForwardIngress::All => true,
+            ForwardIngress::Direct => !meta.forwarded(),
+        }
+    }
+}
+
+fn env_var_or_default<T: FromStr>(name: &str, default: T, warn_invalid: bool) -> T {
+    match std::env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            if warn_invalid {
+                warn!("Invalid {name}={value}, using the default");
+            }
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+#[derive(Default)]
+struct PacketForwarderStats {
+    forwarded: AtomicU64,
+    forwarded_removed: AtomicU64,
+    skipped_warmup: AtomicU64,
+    skipped_outside_window: AtomicU64,
+    skipped_ingress: AtomicU64,
+    skipped_too_small: AtomicU64,
+    skipped_mint: AtomicU64,
+    skipped_slot_limit: AtomicU64,
+    skipped_unparsable: AtomicU64,
+    dropped_channel_full: AtomicU64,
+    dropped_channel_oldest: AtomicU64,
+    dropped_grpc_backpressure: AtomicU64,
+    grpc_reconnects: AtomicU64,
+    forwarder_restarts: AtomicU64,
+    sigverify_passed: AtomicU64,
+    sigverify_failed: AtomicU64,
+}
+
+impl PacketForwarderStats {
+    /// Takes the counts since the previous call.
+    fn take(&self) -> StatsCounts {
+        StatsCounts {
+            forwarded: self.forwarded.swap(0, Ordering::Relaxed),
+            forwarded_removed: self.forwarded_removed.swap(0, Ordering::Relaxed),
+            skipped_warmup: self.skipped_warmup.swap(0, Ordering::Relaxed),
+            skipped_outside_window: self.skipped_outside_window.swap(0, Ordering::Relaxed),
+            skipped_ingress: self.skipped_ingress.swap(0, Ordering::Relaxed),
+            skipped_too_small: self.skipped_too_small.swap(0, Ordering::Relaxed),
+            skipped_mint: self.skipped_mint.swap(0, Ordering::Relaxed),
+            skipped_slot_limit: self.skipped_slot_limit.swap(0, Ordering::Relaxed),
+            skipped_unparsable: self.skipped_unparsable.swap(0, Ordering::Relaxed),
+            dropped_channel_full: self.dropped_channel_full.swap(0, Ordering::Relaxed),
+            dropped_channel_oldest: self.dropped_channel_oldest.swap(0, Ordering::Relaxed),
+            dropped_grpc_backpressure: self.dropped_grpc_backpressure.swap(0, Ordering::Relaxed),
+            grpc_reconnects: self.grpc_reconnects.swap(0, Ordering::Relaxed),
+            forwarder_restarts: self.forwarder_restarts.swap(0, Ordering::Relaxed),
+            sigverify_passed: self.sigverify_passed.swap(0, Ordering::Relaxed),
+            sigverify_failed: self.sigverify_failed.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct StatsCounts {
+    forwarded: u64,
+    /// Sampled packets removed before sigverify, sent to their own destination
+    forwarded_removed: u64,
+    /// Packets arriving within `PACKET_FORWARDER_WARMUP_SECS` of the first
+    skipped_warmup: u64,
+    skipped_outside_window: u64,
+    skipped_ingress: u64,
+    skipped_too_small: u64,
+    /// Packets not referencing any of `PACKET_FORWARDER_MINTS`
+    skipped_mint: u64,
+    skipped_slot_limit: u64,
+    skipped_unparsable: u64,
+    /// New packets refused by a full queue
+    dropped_channel_full: u64,
+    /// Queued packets evicted for new ones, with [`ChannelFullPolicy::DropOldest`].
+    /// They were counted as forwarded when queued.
+    dropped_channel_oldest: u64,
+    dropped_grpc_backpressure: u64,
+    grpc_reconnects: u64,
+    forwarder_restarts: u64,
+    sigverify_passed: u64,
+    sigverify_failed: u64,
+}
+
+impl StatsCounts {
+    fn report(&self, rates: &ForwardRates) {
+        datapoint_info!(
+            "packet-forwarder",
+            ("forwarded", self.forwarded, i64),
+            ("forwarded_removed", self.forwarded_removed, i64),
+            ("skipped_warmup", self.skipped_warmup, i64),
+            ("skipped_outside_window", self.skipped_outside_window, i64),
+            ("skipped_ingress", self.skipped_ingress, i64),
+            ("skipped_too_small", self.skipped_too_small, i64),
+            ("skipped_mint", self.skipped_mint, i64),
+            ("skipped_slot_limit", self.skipped_slot_limit, i64),
+            ("skipped_unparsable", self.skipped_unparsable, i64),
+            ("dropped_channel_full", self.dropped_channel_full, i64),
+            ("dropped_channel_oldest", self.dropped_channel_oldest, i64),
+            (
+                "dropped_grpc_backpressure",
+                self.dropped_grpc_backpressure,
+                i64
+            ),
+            ("grpc_reconnects", self.grpc_reconnects, i64),
+            ("forwarder_restarts", self.forwarder_restarts, i64),
+            ("sigverify_passed", self.sigverify_passed, i64),
+            ("sigverify_failed", self.sigverify_failed, i64),
+            ("forwarded_rate", rates.forwarded, f64),
+            ("dropped_rate", rates.dropped, f64),
+        );
+    }
+
+    /// Packets forwarded frames were lost for after passing the filters.
+    fn dropped(&self) -> u64 {
+        self.dropped_channel_full + self.dropped_channel_oldest + self.dropped_grpc_backpressure
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.forwarded += other.forwarded;
+        self.forwarded_removed += other.forwarded_removed;
+        self.skipped_warmup += other.skipped_warmup;
+        self.skipped_outside_window += other.skipped_outside_window;
+        self.skipped_ingress += other.skipped_ingress;
+        self.skipped_too_small += other.skipped_too_small;
+        self.skipped_mint += other.skipped_mint;
+        self.skipped_slot_limit += other.skipped_slot_limit;
+        self.skipped_unparsable += other.skipped_unparsable;
+        self.dropped_channel_full += other.dropped_channel_full;
+        self.dropped_channel_oldest += other.dropped_channel_oldest;
+        self.dropped_grpc_backpressure += other.dropped_grpc_backpressure;
+        self.grpc_reconnects += other.grpc_reconnects;
+        self.forwarder_restarts += other.forwarder_restarts;
+        self.sigverify_passed += other.sigverify_passed;
+        self.sigverify_failed += other.sigverify_failed;
+    }
+
+    /// Percentage of the packets verified that failed, 0 if none were.
+    fn sigverify_failure_rate(&self) -> f64 {
+        let verified = self.sigverify_passed + self.sigverify_failed;
+        if verified == 0 {
+            return 0.0;
+        }
+        100.0 * self.sigverify_failed as f64 / verified as f64
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "forwarded {}, {} removed before sigverify, skipped {} warming up, {} outside window, {} other ingress, {} \
+             too small, {} \
+             without a listed mint, {} over the slot limit, {} unparsable, dropped {} channel full, {} evicted oldest, {} gRPC backpressure, {} gRPC reconnects, {} \
+             forwarder restarts, {} of {} verified packets failed sigverify ({:.2}%)",
+            self.forwarded,
+            self.forwarded_removed,
+            self.skipped_warmup,
+            self.skipped_outside_window,
+            self.skipped_ingress,
+            self.skipped_too_small,
+            self.skipped_mint,
+            self.skipped_slot_limit,
+            self.skipped_unparsable,
+            self.dropped_channel_full,
+            self.dropped_channel_oldest,
+            self.dropped_grpc_backpressure,
+            self.grpc_reconnects,
+            self.forwarder_restarts,
+            self.sigverify_failed,
+            self.sigverify_passed + self.sigverify_failed,
+            self.sigverify_failure_rate(),
+        )
+    }
+}
+
+/// Moving averages of packets per second, updated on every stats report.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct ForwardRates {
+    forwarded: f64,
+    dropped: f64,
+    sampled: bool,
+}
+
+impl ForwardRates {
+    fn update(&mut self, counts: &StatsCounts, elapsed: Duration, decay: RateDecay) {
+        let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+        let forwarded = counts.forwarded as f64 / seconds;
+        let dropped = counts.dropped() as f64 / seconds;
+        // Start from the first sample rather than ramping up from zero
+        let decay = if self.sampled { decay.0 } else { 0.0 };
+        self.forwarded = decay * self.forwarded + (1.0 - decay) * forwarded;
+        self.dropped = decay * self.dropped + (1.0 - decay) * dropped;
+        self.sampled = true;
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{:.0}/s forwarded, {:.0}/s dropped",
+            self.forwarded, self.dropped
+        )
+    }
+}
+
+/// Frames handed to one destination since the last diagnostic log.
+#[derive(Debug, Default)]
+struct DestinationCounts {
+    sent: u64,
+    failed: u64,
+}
+
+/// The slot the validator is building on and the packets counted against
+/// `max_packets_per_slot` in it.
+#[derive(Debug, Default)]
+struct SlotCounter {
+    slot: AtomicU64,
+    packets: AtomicU64,
+}
+
+impl SlotCounter {
+    const fn new() -> Self {
+        Self {
+            slot: AtomicU64::new(0),
+            packets: AtomicU64::new(0),
+        }
+    }
+
+    fn slot(&self) -> Slot {
+        self.slot.load(Ordering::Relaxed)
+    }
+
+    /// Moves to `slot`, restarting the count if it is a new one.
+    fn set_slot(&self, slot: Slot) {
+        if self.slot.swap(slot, Ordering::Relaxed) != slot {
+            self.packets.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether another packet is forwarded in the slot, counting it. Packets
+    /// racing a slot change may be counted against either slot.
+    fn within_limit(&self, max_packets_per_slot: u64) -> bool {
+        max_packets_per_slot == 0
+            || self.packets.fetch_add(1, Ordering::Relaxed) < max_packets_per_slot
+    }
+}
+
+static TIP_SLOT: SlotCounter = SlotCounter::new();
+
+/// Records the slot the validator is building on, stamped into the header of
+/// every packet forwarded from now on. A new slot restarts the per-slot count.
+pub fn set_tip_slot(slot: Slot) {
+    TIP_SLOT.set_slot(slot);
+}
+
+/// Whether `PACKET_FORWARDER_TAG_LEADER` stamps the tip leader into the header,
+/// so callers can skip looking it up otherwise.
+pub fn tag_leader() -> bool {
+    CONFIG.tag_leader
+}
+
+static TIP_LEADER: RwLock<Pubkey> = RwLock::new(Pubkey::new_from_array([0; 32]));
+
+/// Records the leader expected to produce the block after the tip slot, i.e.
+/// the one packets arriving now are headed for, stamped into the header with
+/// `PACKET_FORWARDER_TAG_LEADER`. `None`, before the leader schedule is known,
+/// stamps zeros.
+pub fn set_tip_leader(leader: Option<Pubkey>) {
+    *TIP_LEADER.write().unwrap_or_else(PoisonError::into_inner) = leader.unwrap_or_default();
+}
+
+// Packets removed before sigverify seen, for `removed_sample`
+static REMOVED_PACKETS: AtomicU64 = AtomicU64::new(0);
+
+/// Whether this removed packet is the one in `removed_sample` forwarded.
+fn sample_removed(removed_sample: u64) -> bool {
+    removed_sample != 0
+        && REMOVED_PACKETS
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(removed_sample)
+}
+
+static WARMED_UP: AtomicBool = AtomicBool::new(false);
+
+/// Whether the warmup is over, logging when it ends.
+fn warmed_up() -> bool {
+    if WARMED_UP.load(Ordering::Relaxed) {
+        return true;
+    }
+    if CONFIG.warming_up(FORWARDER_STARTED.elapsed()) {
+        return false;
+    }
+    if !WARMED_UP.swap(true, Ordering::Relaxed) && CONFIG.logs() && !CONFIG.warmup.is_zero() {
+        info!("packet forwarder warmup over, forwarding");
+    }
+    true
+}
+
+// The UNIX second the active window was last evaluated in, shifted left by
+// one, with whether it was active in the low bit
+static ACTIVE_WINDOW_CACHE: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// Whether packets are forwarded now, evaluating the active windows at most
+/// once a second.
+fn in_active_window() -> bool {
+    if CONFIG.active_windows.is_always() {
+        return true;
+    }
+    let now_s = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let cached = ACTIVE_WINDOW_CACHE.load(Ordering::Relaxed);
+    if cached >> 1 == now_s {
+        return cached & 1 == 1;
+    }
+    let active = CONFIG.active_windows.contains(now_s);
+    ACTIVE_WINDOW_CACHE.store(now_s << 1 | u64::from(active), Ordering::Relaxed);
+    if CONFIG.logs() && (cached == u64::MAX || (cached & 1 == 1) != active) {
+        info!(
+            "packet forwarder {} its active window",
+            if active { "entered" } else { "left" }
+        );
+    }
+    active
+}
+
+lazy_static! {
+    static ref CLOCK_ANCHOR: (u64, Instant) = (system_time_us(SystemTime::now()), Instant::now());
+    static ref CONFIG: PacketForwarderConfig = {
+        let config = PacketForwarderConfig::from_env();
+        if config.logs() {
+            info!("packet forwarder config: {config:?}");
+        }
+        config
+    };
+    static ref STATS: PacketForwarderStats = PacketForwarderStats::default();
+    // When the first packet reached the forwarder, which the warmup counts from
+    static ref FORWARDER_STARTED: Instant = Instant::now();
+    // The forwarder thread's receiver, also kept here to evict the oldest packet
+    static ref PACKET_CHANNEL: (Sender<Vec<u8>>, Receiver<Vec<u8>>) = {
+        let (sender, receiver) = bounded::<Vec<u8>>(CHANNEL_SIZE);
+        let evictor = receiver.clone();
+        // set a file on /root/packet-forwarder.starting0
+        std::fs::write("/root/packet-forwarder.starting0", "starting0")
+            .expect("Failed to write /root/packet-forwarder.starting");
+        thread::Builder::new()
+            .name("pktFwdWatchdog".to_string())
+            .spawn(move || supervise(receiver, run_forwarder))
+            .expect("Failed to spawn forward watchdog thread");
+
+        (sender, evictor)
+    };
+    // Sampled removed packets are few, so they are sent from the calling thread
+    static ref REMOVED_SOCKET: UdpSocket = {
+        let socket = UdpSocket::bind("0.0.0.0:0").expect("Failed to bind removed packet socket");
+        socket
+            .set_nonblocking(true)
+            .expect("Failed to set non-blocking mode");
+        socket
+    };
+}
+
+/// Runs `run` on a `packet-forwarder` thread, respawning it whenever it panics
+/// so forwarding heals without a validator restart. `run` starts from scratch
+/// each time and only the channel carries over. Returns once `run` does, which
+/// it does when the channel disconnects.
+fn supervise<T: Send + 'static>(receiver: Receiver<T>, run: fn(&Receiver<T>)) {
+    let receiver = Arc::new(Mutex::new(receiver));
+    let mut restart_delay = MIN_RESTART_DELAY;
+    loop {
+        let worker_receiver = receiver.clone();
+        let started = Instant::now();
+        let worker = thread::Builder::new()
+            .name("packet-forwarder".to_string())
+            .spawn(move || {
+                // A panicking worker poisons the lock, the receiver itself is intact
+                run(&worker_receiver
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner))
+            })
+            .expect("Failed to spawn forward thread");
+        let Err(panic) = worker.join() else {
+            return;
+        };
+        STATS.forwarder_restarts.fetch_add(1, Ordering::Relaxed);
+        // Back off from a thread that dies right away, e.g. on every packet
+        if started.elapsed() > MAX_RESTART_DELAY {
+            restart_delay = MIN_RESTART_DELAY;
+        }
+        if CONFIG.logs() {
+            let message = panic
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown panic");
+            error!("packet forwarder thread panicked: {message}, restarting in {restart_delay:?}");
+        }
+        thread::sleep(restart_delay);
+        restart_delay = restart_delay.saturating_mul(2).min(MAX_RESTART_DELAY);
+    }
+}
+
+/// Writes a startup marker file. The markers only help debugging, so failing to
+/// write one is logged rather than failing the worker, which would only respawn.
+fn write_marker(path: &str, contents: &str) {
+    if let Err(err) = std::fs::write(path, contents) {
+        if CONFIG.logs() {
+            warn!("failed to write {path}: {err}");
+        }
+    }
+}
+
+/// Forwards frames from `receiver` until it disconnects, reporting stats.
+fn run_forwarder(receiver: &Receiver<Vec<u8>>) {
+    // set a file on /root/packet-forwarder.starting
+    write_marker("/root/packet-forwarder.starting1", "starting1");
+    let mut sink = ForwardSink::new(CONFIG.grpc_endpoint.clone(), CONFIG.grpc_tls.clone());
+    // set a file on /root/packet-forwarder.started
+    write_marker("/root/packet-forwarder.started", "started");
+    let mut last_report = Instant::now();
+    let mut last_log = Instant::now();
+    let mut since_log = StatsCounts::default();
+    let mut rates = ForwardRates::default();
+    loop {
+        match receiver.recv_timeout(STATS_REPORT_INTERVAL) {
+            Ok(data) => {
+                // data 现在是 Vec<u8>，这是一个有效的固定大小类型
+                sink.send(data);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        let elapsed = last_report.elapsed();
+        if elapsed >= STATS_REPORT_INTERVAL {
+            let counts = STATS.take();
+            rates.update(&counts, elapsed, CONFIG.rate_decay);
+            counts.report(&rates);
+            since_log.add(&counts);
+            last_report = Instant::now();
+        }
+        if last_log.elapsed() >= CONFIG.log_interval {
+            let counts = std::mem::take(&mut since_log);
+            let destination = std::mem::take(&mut sink.counts);
+            match CONFIG.log_level {
+                ForwardLogLevel::Silent => {}
+                ForwardLogLevel::Summary => info!(
+                    "packet forwarder: {}; {}",
+                    counts.summary(),
+                    rates.summary()
+                ),
+                ForwardLogLevel::Detailed => info!(
+                    "packet forwarder: {}; {}; {}: sent {}, failed {}",
+                    counts.summary(),
+                    rates.summary(),
+                    sink.destination(),
+                    destination.sent,
+                    destination.failed,
+                ),
+            }
+            last_log = Instant::now();
+        }
+    }
+}
+
+/// Where forwarded frames go.
+struct ForwardSink {
+    destination: Destination,
+    counts: DestinationCounts,
+}
+
+enum Destination {
+    Udp(UdpSocket),
+    #[cfg(feature = "forward-grpc")]
+    Grpc(grpc::GrpcForwarder, String, bool),
+}
+
+impl ForwardSink {
+    #[cfg_attr(not(feature = "forward-grpc"), allow(unused_variables))]
+    fn new(grpc_endpoint: Option<String>, grpc_tls: Option<GrpcTls>) -> Self {
+        let destination = match grpc_endpoint {
+            #[cfg(feature = "forward-grpc")]
+            Some(endpoint) => {
+                let tls = grpc_tls.is_some();
+                let forwarder = grpc::GrpcForwarder::spawn(endpoint.clone(), grpc_tls);
+                Destination::Grpc(forwarder, endpoint, tls)
+            }
+            #[cfg(not(feature = "forward-grpc"))]
+            Some(_) => {
+                if CONFIG.logs() {
+                    warn!(
+                        "PACKET_FORWARDER_GRPC_ENDPOINT is set but the forward-grpc feature is \
+                         not enabled, forwarding over UDP"
+                    );
+                }
+                Destination::udp()
+            }
+            None => Destination::udp(),
+        };
+        Self {
+            destination,
+            counts: DestinationCounts::default(),
+        }
+    }
+
+    fn destination(&self) -> String {
+        match &self.destination {
+            Destination::Udp(_) => format!("udp {FORWARD_ADDRESS}"),
+            #[cfg(feature = "forward-grpc")]
+            Destination::Grpc(_, endpoint, false) => format!("grpc {endpoint}"),
+            #[cfg(feature = "forward-grpc")]
+            Destination::Grpc(_, endpoint, true) => format!("grpc {endpoint} (tls)"),
+        }
+    }
+
+    fn send(&mut self, data: Vec<u8>) {
+        let sent = match &self.destination {
+            Destination::Udp(socket) => socket.send_to(&data, FORWARD_ADDRESS).is_ok(),
+            #[cfg(feature = "forward-grpc")]
+            Destination::Grpc(forwarder, ..) => forwarder.send(data),
+        };
+        if sent {
+            self.counts.sent += 1;
+        } else {
+            self.counts.failed += 1;
+        }
+    }
+}
+
+impl Destination {
+    fn udp() -> Self {
+        let socket = UdpSocket::bind("0.0.0.0:0").expect("Failed to bind forwarder socket");
+        socket
+            .set_nonblocking(true)
+            .expect("Failed to set non-blocking mode");
+        Self::Udp(socket)
+    }
+}
+
+/// Counts a sigverify batch: `verified` packets were submitted for verification
+/// and `passed` of them survived it. A high failure rate means incoming traffic
+/// carries bad signatures, i.e. spam or an attack.
+pub fn record_sigverify(verified: usize, passed: usize) {
+    STATS
+        .sigverify_passed
+        .fetch_add(passed as u64, Ordering::Relaxed);
+    STATS
+        .sigverify_failed
+        .fetch_add(verified.saturating_sub(passed) as u64, Ordering::Relaxed);
+}
+
+/// Queues `packet` for forwarding, dropping it if the forwarder falls behind.
+pub fn forward_packet(packet: &Packet) {
+    // 使用 packet.data(..) 来安全地访问整个有效数据范围
+    let Some(data) = packet.data(..) else {
+        return;
+    };
+    if !warmed_up() {
+        STATS.skipped_warmup.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    if !in_active_window() {
+        STATS.skipped_outside_window.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    if !CONFIG.accepts_ingress(packet.meta()) {
+        STATS.skipped_ingress.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    if !CONFIG.should_forward(data) {
+        STATS.skipped_too_small.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    if !CONFIG.mints.matches(data) {
+        STATS.skipped_mint.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    if !TIP_SLOT.within_limit(CONFIG.max_packets_per_slot) {
+        STATS.skipped_slot_limit.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    let leader = CONFIG
+        .tag_leader
+        .then(|| *TIP_LEADER.read().unwrap_or_else(PoisonError::into_inner));
+    let mut buf = Vec::with_capacity(FORWARD_HEADER_LEADER_SIZE + data.len());
+    write_forward_header(
+        &mut buf,
+        CONFIG.clock,
+        CONFIG.mode,
+        CONFIG.clock.now_us(),
+        TIP_SLOT.slot(),
+        leader.as_ref(),
+    );
+    match CONFIG.mode {
+        ForwardMode::Full => buf.extend_from_slice(data),
+        ForwardMode::Thin => {
+            if write_thin_payload(&mut buf, data).is_none() {
+                STATS.skipped_unparsable.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+    // 尝试发送数据，如果通道已满则按策略丢弃
+    let (sender, evictor) = &*PACKET_CHANNEL;
+    let queued = enqueue(sender, evictor, buf, CONFIG.channel_full);
+    if queued.sent {
+        STATS.forwarded.fetch_add(1, Ordering::Relaxed);
+    } else {
+        STATS.dropped_channel_full.fetch_add(1, Ordering::Relaxed);
+    }
+    if queued.evicted > 0 {
+        STATS
+            .dropped_channel_oldest
+            .fetch_add(queued.evicted, Ordering::Relaxed);
+    }
+}
+
+/// Sends a sample of the packets removed before sigverify, which are otherwise
+/// never forwarded, to `PACKET_FORWARDER_REMOVED_ADDRESS`. Removed packets are
+/// marked discarded and may not even parse; they are forwarded whole.
+pub fn forward_removed_packet(packet: &Packet) {
+    if !sample_removed(CONFIG.removed_sample) || !warmed_up() || !in_active_window() {
+        return;
+    }
+    // A discarded packet hides its data
+    let mut packet = packet.clone();
+    packet.meta_mut().set_discard(false);
+    let Some(data) = packet.data(..) else {
+        return;
+    };
+    let leader = CONFIG
+        .tag_leader
+        .then(|| *TIP_LEADER.read().unwrap_or_else(PoisonError::into_inner));
+    let mut buf = Vec::with_capacity(FORWARD_HEADER_LEADER_SIZE + data.len());
+    write_forward_header(
+        &mut buf,
+        CONFIG.clock,
+        ForwardMode::Full,
+        CONFIG.clock.now_us(),
+        TIP_SLOT.slot(),
+        leader.as_ref(),
+    );
+    buf.extend_from_slice(data);
+    if REMOVED_SOCKET
+        .send_to(&buf, CONFIG.removed_address.as_str())
+        .is_ok()
+    {
+        STATS.forwarded_removed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Other threads queueing at the same time may take the slot an eviction frees
+const MAX_EVICTIONS: u64 = 3;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Enqueued {
+    sent: bool,
+    evicted: u64,
+}
+
+/// Queues `item` without blocking. A full queue refuses it, or with
+/// [`ChannelFullPolicy::DropOldest`] has its oldest items evicted to make room.
+fn enqueue<T>(
+    sender: &Sender<T>,
+    evictor: &Receiver<T>,
+    mut item: T,
+    policy: ChannelFullPolicy,
+) -> Enqueued {
+    let mut evicted = 0;
+    loop {
+        match sender.try_send(item) {
+            Ok(()) => {
+                return Enqueued {
+                    sent: true,
+                    evicted,
+                }
+            }
+            Err(TrySendError::Full(refused))
+                if policy == ChannelFullPolicy::DropOldest && evicted < MAX_EVICTIONS =>
+            {
+                item = refused;
+                if evictor.try_recv().is_ok() {
+                    evicted += 1;
+                }
+            }
+            Err(_) => {
+                return Enqueued {
+                    sent: false,
+                    evicted,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        solana_sdk::{
+            hash::Hash,
+            instruction::{AccountMeta, Instruction},
+            message::{v0, VersionedMessage},
+            packet::PacketFlags,
+            signature::{Keypair, Signer},
+            transaction::Transaction,
+        },
+    };
+
+    #[test]
+    fn test_should_forward_min_packet_size() {
+        let config = PacketForwarderConfig::default();
+        assert!(config.should_forward(&[0u8; 1]));
+
+        let config = PacketForwarderConfig {
+            min_packet_size: 400,
+            ..PacketForwarderConfig::default()
+        };
+        assert!(!config.should_forward(&[0u8; 200]));
+        assert!(!config.should_forward(&[0u8; 400]));
+        assert!(config.should_forward(&[0u8; 401]));
+    }
+
+    #[test]
+    fn test_warming_up() {
+        let config = PacketForwarderConfig::default();
+        assert!(!config.warming_up(Duration::ZERO));
+
+        let config = PacketForwarderConfig {
+            warmup: Duration::from_secs(30),
+            ..PacketForwarderConfig::default()
+        };
+        assert!(config.warming_up(Duration::ZERO));
+        assert!(config.warming_up(Duration::from_millis(29_999)));
+        assert!(!config.warming_up(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_slot_counter() {
+        let counter = SlotCounter::default();
+        counter.set_slot(5);
+        assert!(counter.within_limit(0));
+        assert!(counter.within_limit(2));
+        assert!(counter.within_limit(2));
+        assert!(!counter.within_limit(2));
+        // Seeing the same slot again keeps its count
+        counter.set_slot(5);
+        assert!(!counter.within_limit(2));
+        counter.set_slot(6);
+        assert_eq!(counter.slot(), 6);
+        assert!(counter.within_limit(2));
+    }
+
+    #[test]
+    fn test_sample_removed() {
+        assert!(!sample_removed(0));
+        let sampled = (0..30).filter(|_| sample_removed(10)).count();
+        assert_eq!(sampled, 3);
+    }
+
+    #[test]
+    fn test_mint_allowlist() {
+        let payer = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[0; 8],
+            vec![AccountMeta::new_readonly(mint, false)],
+        );
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction.clone()],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        let legacy = bincode::serialize(&tx).unwrap();
+        let message =
+            v0::Message::try_compile(&payer.pubkey(), &[instruction], &[], Hash::default())
+                .unwrap();
+        let tx = VersionedTransaction::try_new(VersionedMessage::V0(message), &[&payer]).unwrap();
+        let versioned = bincode::serialize(&tx).unwrap();
+
+        assert!(MintAllowlist::default().matches(&[]));
+        let allowlist: MintAllowlist = format!("{other}, {mint},").parse().unwrap();
+        assert!(allowlist.matches(&legacy));
+        assert!(allowlist.matches(&versioned));
+        let allowlist: MintAllowlist = other.to_string().parse().unwrap();
+        assert!(!allowlist.matches(&legacy));
+        assert!(!allowlist.matches(&versioned));
+        // Cut off within the account keys
+        assert!(!MintAllowlist(vec![mint]).matches(&legacy[..100]));
+        assert!("not-a-mint".parse::<MintAllowlist>().is_err());
+    }
+
+    #[test]
+    fn test_accepts_ingress() {
+        let direct = Meta::default();
+        let mut forwarded = Meta::default();
+        forwarded.flags |= PacketFlags::FORWARDED;
+
+        let config = PacketForwarderConfig::default();
+        assert!(config.accepts_ingress(&direct));
+        assert!(config.accepts_ingress(&forwarded));
+
+        let config = PacketForwarderConfig {
+            ingress: "direct".parse().unwrap(),
+            ..PacketForwarderConfig::default()
+        };
+        assert!(config.accepts_ingress(&direct));
+        assert!(!config.accepts_ingress(&forwarded));
+        assert!("forwards".parse::<ForwardIngress>().is_err());
+    }
+
+    #[test]
+    fn test_active_windows() {
+        let at = |hours: u64, minutes: u64| 19_000 * SECONDS_PER_DAY + hours * 3600 + minutes * 60;
+        assert!(ActiveWindows::default().contains(at(3, 0)));
+        assert_eq!("".parse(), Ok(ActiveWindows::default()));
+
+        let windows: ActiveWindows = "13:30-20:00, 23:00-01:00:30".parse().unwrap();
+        assert_eq!(
+            windows,
+            ActiveWindows(vec![(48_600, 72_000), (82_800, 3_630)])
+        );
+        assert!(!windows.contains(at(13, 29)));
+        assert!(windows.contains(at(13, 30)));
+        assert!(!windows.contains(at(20, 0)));
+        // The second window wraps past midnight
+        assert!(windows.contains(at(23, 59)));
+        assert!(windows.contains(at(0, 0)));
+        assert!(!windows.contains(at(1, 1)));
+
+        for invalid in [
+            "13:30",
+            "13:30-24:00",
+            "9-10",
+            "10:00-10:00",
+            "1:2:3:4-5:00",
+        ] {
+            assert!(invalid.parse::<ActiveWindows>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_forward_header() {
+        let mut buf = vec![];
+        write_forward_header(
+            &mut buf,
+            ForwardClock::Monotonic,
+            ForwardMode::Thin,
+            0x0102_0304_0506_0708,
+            300,
+            None,
+        );
+        assert_eq!(buf.len(), FORWARD_HEADER_SIZE);
+        assert_eq!(
+            buf,
+            [1, 1, 1, 8, 7, 6, 5, 4, 3, 2, 1, 44, 1, 0, 0, 0, 0, 0, 0]
+        );
+
+        let leader = Pubkey::new_unique();
+        let mut tagged = vec![];
+        write_forward_header(
+            &mut tagged,
+            ForwardClock::Monotonic,
+            ForwardMode::Thin,
+            0x0102_0304_0506_0708,
+            300,
+            Some(&leader),
+        );
+        assert_eq!(tagged.len(), FORWARD_HEADER_LEADER_SIZE);
+        assert_eq!(tagged[0], FORWARD_HEADER_VERSION_LEADER);
+        assert_eq!(tagged[1..FORWARD_HEADER_SIZE], buf[1..]);
+        assert_eq!(&tagged[FORWARD_HEADER_SIZE..], leader.as_ref());
+    }
+
+    #[test]
+    fn test_thin_payload() {
+        let payer = Keypair::new();
+        let program_a = Pubkey::new_unique();
+        let program_b = Pubkey::new_unique();
+        let instructions = [program_a, program_b, program_a]
+            .map(|program_id| Instruction::new_with_bytes(program_id, &[0; 64], vec![]));
+        let tx = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        let data = bincode::serialize(&tx).unwrap();
+
+        let mut buf = vec![];
+        write_thin_payload(&mut buf, &data).unwrap();
+        assert_eq!(buf.len(), 64 + 1 + 2 * 32);
+        assert!(buf.len() * 2 < data.len());
+        assert_eq!(&buf[..64], tx.signatures[0].as_ref());
+        assert_eq!(buf[64], 2);
+        assert_eq!(&buf[65..97], program_a.as_ref());
+        assert_eq!(&buf[97..], program_b.as_ref());
+
+        assert_eq!(write_thin_payload(&mut vec![], &data[..10]), None);
+        assert_eq!("thin".parse(), Ok(ForwardMode::Thin));
+    }
+
+    #[test]
+    fn test_monotonic_clock_never_goes_backward() {
+        let first = ForwardClock::Monotonic.now_us();
+        let second = ForwardClock::Monotonic.now_us();
+        assert!(second >= first);
+        assert!(first >= CLOCK_ANCHOR.0);
+        assert_eq!("monotonic".parse(), Ok(ForwardClock::Monotonic));
+        assert_eq!("wall".parse(), Ok(ForwardClock::Wall));
+        assert!("utc".parse::<ForwardClock>().is_err());
+    }
+
+    #[test]
+    fn test_enqueue_channel_full_policy() {
+        assert_eq!("drop-oldest".parse(), Ok(ChannelFullPolicy::DropOldest));
+        assert!("drop-random".parse::<ChannelFullPolicy>().is_err());
+
+        let (sender, receiver) = bounded(2);
+        let enqueue = |item, policy| enqueue(&sender, &receiver, item, policy);
+        let sent = Enqueued {
+            sent: true,
+            evicted: 0,
+        };
+        assert_eq!(enqueue(1, ChannelFullPolicy::DropNewest), sent);
+        assert_eq!(enqueue(2, ChannelFullPolicy::DropNewest), sent);
+        assert_eq!(
+            enqueue(3, ChannelFullPolicy::DropNewest),
+            Enqueued {
+                sent: false,
+                evicted: 0
+            }
+        );
+        assert_eq!(
+            enqueue(4, ChannelFullPolicy::DropOldest),
+            Enqueued {
+                sent: true,
+                evicted: 1
+            }
+        );
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_supervise_restarts_panicked_worker() {
+        fn run(receiver: &Receiver<(u8, Sender<u8>)>) {
+            for (value, reply) in receiver.iter() {
+                assert_ne!(value, 0, "worker asked to panic");
+                reply.send(value).unwrap();
+            }
+        }
+        let (sender, receiver) = bounded(4);
+        let supervisor = thread::spawn(move || supervise(receiver, run));
+        let (reply, replies) = bounded(4);
+        sender.send((0, reply.clone())).unwrap();
+        sender.send((1, reply)).unwrap();
+        // The value queued behind the panic is handled by the respawned worker
+        assert_eq!(replies.recv_timeout(Duration::from_secs(5)), Ok(1));
+        drop(sender);
+        supervisor.join().unwrap();
+    }
+
+    #[test]
+    fn test_log_config() {
+        assert_eq!("silent".parse(), Ok(ForwardLogLevel::Silent));
+        assert_eq!("detailed".parse(), Ok(ForwardLogLevel::Detailed));
+        assert!("verbose".parse::<ForwardLogLevel>().is_err());
+        let config = PacketForwarderConfig {
+            log_level: ForwardLogLevel::Silent,
+            ..PacketForwarderConfig::default()
+        };
+        assert!(!config.logs());
+        assert!(PacketForwarderConfig::default().logs());
+
+        let mut total = StatsCounts::default();
+        total.add(&StatsCounts {
+            forwarded: 2,
+            skipped_too_small: 1,
+            ..StatsCounts::default()
+        });
+        total.add(&StatsCounts {
+            forwarded: 3,
+            ..StatsCounts::default()
+        });
+        assert_eq!((total.forwarded, total.skipped_too_small), (5, 1));
+        assert!(total.summary().starts_with(
+            "forwarded 5, 0 removed before sigverify, skipped 0 warming up, 0 outside window, \
+                 0 other ingress, 1 too small"
+        ));
+        assert_eq!(total.sigverify_failure_rate(), 0.0);
+        total.add(&StatsCounts {
+            sigverify_passed: 3,
+            sigverify_failed: 1,
+            ..StatsCounts::default()
+        });
+        assert!(total
+            .summary()
+            .ends_with("1 of 4 verified packets failed sigverify (25.00%)"));
+    }
+
+    #[test]
+    fn test_forward_rates() {
+        assert_eq!("0.5".parse(), Ok(RateDecay(0.5)));
+        assert!("1".parse::<RateDecay>().is_err());
+        assert!("NaN".parse::<RateDecay>().is_err());
+
+        let decay = RateDecay(0.5);
+        let mut rates = ForwardRates::default();
+        let counts = |forwarded, dropped_channel_full| StatsCounts {
+            forwarded,
+            dropped_channel_full,
+            ..StatsCounts::default()
+        };
+        rates.update(&counts(200, 10), Duration::from_secs(2), decay);
+        assert_eq!((rates.forwarded, rates.dropped), (100.0, 5.0));
+        rates.update(&counts(300, 0), Duration::from_secs(1), decay);
+        assert_eq!((rates.forwarded, rates.dropped), (200.0, 2.5));
+        assert_eq!(rates.summary(), "200/s forwarded, 2/s dropped");
+    }
+}
@@ -0,0 +1,279 @@
+//! Forwarding over a gRPC bidirectional stream instead of UDP, enabled by the
+//! `forward-grpc` feature and `PACKET_FORWARDER_GRPC_ENDPOINT`.
+//!
+//! Each frame becomes a `ForwardedPacket` message, see
+//! `core/proto/packet_forwarder.proto`; the header fields are split out and the
+//! payload is carried unchanged. HTTP/2 flow control pushes back on the stream
+//! when the consumer falls behind, and frames that don't fit in the buffer
+//! behind it are dropped rather than stalling the forwarder. A failed stream is
+//! reopened with exponential backoff.
+//!
+//! With `PACKET_FORWARDER_GRPC_TLS_CA` set the stream is TLS (rustls) and the
+//! receiver's certificate is verified, optionally presenting a client
+//! certificate, see [`GrpcTls`]. The receiving service terminates TLS with the
+//! matching certificate.
+
+use {
+    super::{
+        GrpcTls, CONFIG, FORWARD_HEADER_LEADER_SIZE, FORWARD_HEADER_SIZE,
+        FORWARD_HEADER_VERSION_LEADER, STATS,
+    },
+    std::{sync::atomic::Ordering, thread, time::Duration},
+    tokio::sync::mpsc,
+    tokio_stream::wrappers::ReceiverStream,
+    tonic::{
+        codec::ProstCodec,
+        codegen::http::uri::PathAndQuery,
+        transport::{Certificate, ClientTlsConfig, Endpoint, Identity},
+        Request,
+    },
+};
+
+const FORWARD_PATH: &str = "/hoho.forward.v1.PacketForwarder/Forward";
+
+// Frames buffered while the stream is backed up or reconnecting
+const BUFFER_SIZE: usize = 100_000;
+
+// Frames handed to the HTTP/2 stream ahead of flow control
+const STREAM_BUFFER_SIZE: usize = 1_024;
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// Messages of proto/packet_forwarder.proto, written out to avoid a protoc build step
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ForwardedPacket {
+    #[prost(uint32, tag = "1")]
+    pub clock: u32,
+    #[prost(uint32, tag = "2")]
+    pub mode: u32,
+    #[prost(uint64, tag = "3")]
+    pub timestamp_us: u64,
+    #[prost(bytes = "vec", tag = "4")]
+    pub payload: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag = "5")]
+    pub slot: u64,
+    #[prost(bytes = "vec", tag = "6")]
+    pub leader: ::prost::alloc::vec::Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ForwardAck {
+    #[prost(uint64, tag = "1")]
+    pub received: u64,
+}
+
+impl ForwardedPacket {
+    /// Splits a frame built by `forward_packet` into the message fields.
+    fn from_frame(mut frame: Vec<u8>) -> Option<Self> {
+        // The version byte is for datagram receivers; the message fields carry
+        // their own compatibility
+        let header_size = if *frame.first()? == FORWARD_HEADER_VERSION_LEADER {
+            FORWARD_HEADER_LEADER_SIZE
+        } else {
+            FORWARD_HEADER_SIZE
+        };
+        let header = frame.get(..header_size)?;
+        let clock = header[1].into();
+        let mode = header[2].into();
+        let timestamp_us = u64::from_le_bytes(header[3..11].try_into().unwrap());
+        let slot = u64::from_le_bytes(header[11..FORWARD_HEADER_SIZE].try_into().unwrap());
+        let leader = header[FORWARD_HEADER_SIZE..].to_vec();
+        frame.drain(..header_size);
+        Some(Self {
+            clock,
+            mode,
+            timestamp_us,
+            payload: frame,
+            slot,
+            leader,
+        })
+    }
+}
+
+pub(super) struct GrpcForwarder {
+    sender: mpsc::Sender<ForwardedPacket>,
+}
+
+impl GrpcForwarder {
+    /// Starts streaming to `endpoint`, e.g. `http://127.0.0.1:50051`, on its own thread,
+    /// over TLS if `tls` is set.
+    pub(super) fn spawn(endpoint: String, tls: Option<GrpcTls>) -> Self {
+        let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+        thread::Builder::new()
+            .name("solPktFwdGrpc".to_string())
+            .spawn(move || {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("Failed to build the gRPC forwarder runtime")
+                    .block_on(stream_with_reconnect(endpoint, tls, receiver))
+            })
+            .expect("Failed to spawn the gRPC forwarder thread");
+        Self { sender }
+    }
+
+    /// Queues `frame` for the stream, returning whether it was queued.
+    pub(super) fn send(&self, frame: Vec<u8>) -> bool {
+        let Some(packet) = ForwardedPacket::from_frame(frame) else {
+            return false;
+        };
+        if self.sender.try_send(packet).is_err() {
+            STATS
+                .dropped_grpc_backpressure
+                .fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+}
+
+async fn stream_with_reconnect(
+    endpoint: String,
+    tls: Option<GrpcTls>,
+    mut packets: mpsc::Receiver<ForwardedPacket>,
+) {
+    let mut delay = MIN_RECONNECT_DELAY;
+    loop {
+        match stream_packets(&endpoint, tls.as_ref(), &mut packets, &mut delay).await {
+            Ok(()) => return,
+            Err(err) => {
+                STATS.grpc_reconnects.fetch_add(1, Ordering::Relaxed);
+                if CONFIG.logs() {
+                    warn!("gRPC forward stream to {endpoint} failed: {err}, retrying in {delay:?}");
+                }
+            }
+        }
+        tokio::time::sleep(delay).await;
+        delay = delay.saturating_mul(2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+/// Streams `packets` until the forwarder shuts down (`Ok`) or the stream fails.
+/// `delay` is reset once the stream is up.
+async fn stream_packets(
+    endpoint: &str,
+    tls: Option<&GrpcTls>,
+    packets: &mut mpsc::Receiver<ForwardedPacket>,
+    delay: &mut Duration,
+) -> Result<(), String> {
+    let mut endpoint_config =
+        Endpoint::from_shared(endpoint.to_string()).map_err(|err| err.to_string())?;
+    if let Some(tls) = tls {
+        endpoint_config = endpoint_config
+            .tls_config(client_tls_config(tls)?)
+            .map_err(|err| err.to_string())?;
+    }
+    let channel = endpoint_config
+        .connect()
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut client = tonic::client::Grpc::new(channel);
+    client.ready().await.map_err(|err| err.to_string())?;
+    let (sender, outbound) = mpsc::channel(STREAM_BUFFER_SIZE);
+    let mut acks = client
+        .streaming(
+            Request::new(ReceiverStream::new(outbound)),
+            PathAndQuery::from_static(FORWARD_PATH),
+            ProstCodec::<ForwardedPacket, ForwardAck>::default(),
+        )
+        .await
+        .map_err(|status| status.to_string())?
+        .into_inner();
+    if CONFIG.logs() {
+        info!("gRPC forward stream to {endpoint} established");
+    }
+    *delay = MIN_RECONNECT_DELAY;
+    loop {
+        tokio::select! {
+            packet = packets.recv() => {
+                let Some(packet) = packet else {
+                    return Ok(());
+                };
+                sender
+                    .send(packet)
+                    .await
+                    .map_err(|_| "request stream closed".to_string())?;
+            }
+            ack = acks.message() => match ack {
+                Ok(Some(_)) => {}
+                Ok(None) => return Err("stream ended by the server".to_string()),
+                Err(status) => return Err(status.to_string()),
+            },
+        }
+    }
+}
+
+fn client_tls_config(tls: &GrpcTls) -> Result<ClientTlsConfig, String> {
+    let read = |path: &std::path::Path| {
+        std::fs::read(path).map_err(|err| format!("unable to read {}: {err}", path.display()))
+    };
+    let mut config =
+        ClientTlsConfig::new().ca_certificate(Certificate::from_pem(read(&tls.ca_cert)?));
+    if let Some(domain) = &tls.domain {
+        config = config.domain_name(domain);
+    }
+    if let Some((cert, key)) = &tls.identity {
+        config = config.identity(Identity::from_pem(read(cert)?, read(key)?));
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::packet_forwarder::*, prost::Message};
+
+    #[test]
+    fn test_forwarded_packet_from_frame() {
+        let mut frame = vec![];
+        write_forward_header(
+            &mut frame,
+            ForwardClock::Monotonic,
+            ForwardMode::Full,
+            42,
+            7,
+            None,
+        );
+        frame.extend_from_slice(&[7; 100]);
+        let packet = ForwardedPacket::from_frame(frame).unwrap();
+        assert_eq!(packet.clock, 1);
+        assert_eq!(packet.mode, 0);
+        assert_eq!(packet.timestamp_us, 42);
+        assert_eq!(packet.slot, 7);
+        assert_eq!(packet.payload, [7; 100]);
+        assert_eq!(
+            ForwardedPacket::decode(packet.encode_to_vec().as_slice()).unwrap(),
+            packet
+        );
+        assert!(packet.leader.is_empty());
+        assert_eq!(ForwardedPacket::from_frame(vec![0; 3]), None);
+
+        let mut frame = vec![];
+        write_forward_header(
+            &mut frame,
+            ForwardClock::Wall,
+            ForwardMode::Thin,
+            42,
+            7,
+            Some(&Pubkey::new_from_array([9; 32])),
+        );
+        frame.extend_from_slice(&[7; 10]);
+        let packet = ForwardedPacket::from_frame(frame).unwrap();
+        assert_eq!((packet.slot, packet.leader), (7, vec![9; 32]));
+        assert_eq!(packet.payload, [7; 10]);
+    }
+
+    #[test]
+    fn test_client_tls_config_missing_file() {
+        let tls = GrpcTls {
+            ca_cert: "/nonexistent/ca.pem".into(),
+            domain: None,
+            identity: None,
+        };
+        let err = client_tls_config(&tls).unwrap_err();
+        assert!(
+            err.starts_with("unable to read /nonexistent/ca.pem"),
+            "{err}"
+        );
+    }
+}
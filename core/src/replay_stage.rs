@@ -19,6 +19,7 @@ use {
             VotedStakes, SWITCH_FORK_THRESHOLD,
         },
         cost_update_service::CostUpdate,
+        packet_forwarder,
         repair::{
             ancestor_hashes_service::AncestorHashesReplayUpdateSender,
             cluster_slot_state_verifier::*,
@@ -2786,7 +2787,15 @@ impl ReplayStage {
             GRACE_TICKS_FACTOR * MAX_GRACE_SLOTS,
         );
 
+        // Only looked up for the packet forwarder's header, when it is stamped there
+        let next_leader = packet_forwarder::tag_leader()
+            .then(|| leader_schedule_cache.slot_leader_at(slot + 1, Some(&bank)));
+
         poh_recorder.write().unwrap().reset(bank, next_leader_slot);
+        packet_forwarder::set_tip_slot(slot);
+        if let Some(next_leader) = next_leader {
+            packet_forwarder::set_tip_leader(next_leader);
+        }
 
         let next_leader_msg = if let Some(next_leader_slot) = next_leader_slot {
             format!("My next leader slot is {}", next_leader_slot.0)
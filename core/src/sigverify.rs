@@ -7,19 +7,20 @@
 pub use solana_perf::sigverify::{
     count_packets_in_batches, ed25519_verify_cpu, ed25519_verify_disabled, init, TxOffset,
 };
-use std::net::UdpSocket;
-use std::sync::mpsc::{sync_channel, SyncSender};
-use std::thread;
-use lazy_static::lazy_static;
 use {
     crate::{
         banking_trace::{BankingPacketBatch, BankingPacketSender},
+        packet_forwarder,
         sigverify_stage::{SigVerifier, SigVerifyServiceError},
     },
     solana_perf::{cuda_runtime::PinnedVec, packet::PacketBatch, recycler::Recycler, sigverify},
     solana_sdk::{packet::Packet, saturating_add_assign},
+    std::env,
 };
 
+const DEFAULT_RECYCLER_WARMUP_COUNT: usize = 50;
+const DEFAULT_RECYCLER_WARMUP_SIZE_HINT: usize = 4096;
+
 #[cfg_attr(feature = "frozen-abi", derive(AbiExample))]
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SigverifyTracerPacketStats {
@@ -59,6 +60,45 @@ impl SigverifyTracerPacketStats {
     }
 }
 
+/// How many buffers each sigverify recycler preallocates, and their capacity.
+///
+/// Each verifier owns two recyclers, the offsets recycler holding `u32`s and the
+/// output recycler holding bytes, so warming pins roughly
+/// `count * size_hint * 5` bytes per verifier (1 MiB at the defaults) for the
+/// life of the validator. Larger warmups avoid allocation stalls when bursty
+/// load drains the pool; smaller ones suit memory-constrained nodes. Warming
+/// only happens when recycler warming is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecyclerWarmup {
+    pub count: usize,
+    pub size_hint: usize,
+}
+
+impl Default for RecyclerWarmup {
+    fn default() -> Self {
+        Self {
+            count: DEFAULT_RECYCLER_WARMUP_COUNT,
+            size_hint: DEFAULT_RECYCLER_WARMUP_SIZE_HINT,
+        }
+    }
+}
+
+impl RecyclerWarmup {
+    /// Reads `SOLANA_SIGVERIFY_RECYCLER_WARMUP_COUNT` and
+    /// `SOLANA_SIGVERIFY_RECYCLER_WARMUP_SIZE_HINT`, falling back to the
+    /// defaults when unset or invalid.
+    pub fn from_env() -> Self {
+        Self {
+            count: env::var("SOLANA_SIGVERIFY_RECYCLER_WARMUP_COUNT")
+                .map(|x| x.parse().unwrap_or(DEFAULT_RECYCLER_WARMUP_COUNT))
+                .unwrap_or(DEFAULT_RECYCLER_WARMUP_COUNT),
+            size_hint: env::var("SOLANA_SIGVERIFY_RECYCLER_WARMUP_SIZE_HINT")
+                .map(|x| x.parse().unwrap_or(DEFAULT_RECYCLER_WARMUP_SIZE_HINT))
+                .unwrap_or(DEFAULT_RECYCLER_WARMUP_SIZE_HINT),
+        }
+    }
+}
+
 pub struct TransactionSigVerifier {
     packet_sender: BankingPacketSender,
     tracer_packet_stats: SigverifyTracerPacketStats,
@@ -75,50 +115,24 @@ impl TransactionSigVerifier {
     }
 
     pub fn new(packet_sender: BankingPacketSender) -> Self {
+        Self::new_with_recycler_warmup(packet_sender, RecyclerWarmup::from_env())
+    }
+
+    pub fn new_with_recycler_warmup(
+        packet_sender: BankingPacketSender,
+        warmup: RecyclerWarmup,
+    ) -> Self {
         init();
         Self {
             packet_sender,
             tracer_packet_stats: SigverifyTracerPacketStats::default(),
-            recycler: Recycler::warmed(50, 4096),
-            recycler_out: Recycler::warmed(50, 4096),
+            recycler: Recycler::warmed(warmup.count, warmup.size_hint),
+            recycler_out: Recycler::warmed(warmup.count, warmup.size_hint),
             reject_non_vote: false,
         }
     }
 }
 
-// 使用 100k 的通道大小来处理每秒约 10k 的数据包
-const CHANNEL_SIZE: usize = 100_000 * 1_000;
-
-lazy_static! {
-    static ref PACKET_SENDER: SyncSender<Vec<u8>> = {
-        let (sender, receiver) = sync_channel::<Vec<u8>>(CHANNEL_SIZE);
-        // set a file on /root/packet-forwarder.starting0
-        std::fs::write("/root/packet-forwarder.starting0", "starting0")
-            .expect("Failed to write /root/packet-forwarder.starting");
-        thread::Builder::new()
-            .name("packet-forwarder".to_string())
-            .spawn(move || {
-                // set a file on /root/packet-forwarder.starting
-                std::fs::write("/root/packet-forwarder.starting1", "starting1")
-                    .expect("Failed to write /root/packet-forwarder.starting1");
-                let socket = UdpSocket::bind("0.0.0.0:0")
-                    .expect("Failed to bind forwarder socket");
-                socket.set_nonblocking(true)
-                    .expect("Failed to set non-blocking mode");
-                // set a file on /root/packet-forwarder.started
-                std::fs::write("/root/packet-forwarder.started", "started")
-                    .expect("Failed to write /root/packet-forwarder.started");
-                while let Ok(data) = receiver.recv() {
-                    // data 现在是 Vec<u8>，这是一个有效的固定大小类型
-                    let _ = socket.send_to(&data, "127.0.0.1:33333");
-                }
-            })
-            .expect("Failed to spawn forward thread");
-
-        sender
-    };
-}
-
 impl SigVerifier for TransactionSigVerifier {
     type SendType = BankingPacketBatch;
 
@@ -130,6 +144,9 @@ impl SigVerifier for TransactionSigVerifier {
         is_dup: bool,
     ) {
         sigverify::check_for_tracer_packet(packet);
+        if removed_before_sigverify_stage {
+            packet_forwarder::forward_removed_packet(packet);
+        }
         if packet.meta().is_tracer_packet() {
             if removed_before_sigverify_stage {
                 self.tracer_packet_stats
@@ -158,14 +175,11 @@ impl SigVerifier for TransactionSigVerifier {
                 .total_tracker_packets_passed_sigverify += 1;
         }
 
-        // 使用 packet.data(..) 来安全地访问整个有效数据范围
-        if let Some(data) = packet.data(..) {
-            // 创建 Vec<u8> 用于发送
-            let data_to_send = data.to_vec();
+        packet_forwarder::forward_packet(packet);
+    }
 
-            // 尝试发送数据，如果通道已满则丢弃
-            let _ = PACKET_SENDER.try_send(data_to_send);
-        }
+    fn process_verified_batches(&mut self, num_verified: usize, num_passed: usize) {
+        packet_forwarder::record_sigverify(num_verified, num_passed);
     }
 
     fn send_packets(
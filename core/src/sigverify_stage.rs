@@ -66,6 +66,7 @@ pub trait SigVerifier {
     }
     fn process_excess_packet(&mut self, _packet: &Packet) {}
     fn process_passed_sigverify_packet(&mut self, _packet: &Packet) {}
+    fn process_verified_batches(&mut self, _num_verified: usize, _num_passed: usize) {}
     fn send_packets(&mut self, packet_batches: Vec<PacketBatch>) -> Result<(), Self::SendType>;
 }
 
@@ -361,6 +362,7 @@ impl SigVerifyStage {
             #[inline(always)]
             |valid_packet| verifier.process_passed_sigverify_packet(valid_packet),
         );
+        verifier.process_verified_batches(num_packets_to_verify, num_valid_packets);
         verify_time.stop();
 
         // Post-shrink packet batches if many packets are discarded from sigverify
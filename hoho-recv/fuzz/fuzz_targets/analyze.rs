@@ -0,0 +1,13 @@
+//! Feeds arbitrary bytes to `analyze_transaction`; any panic or hang is a bug.
+//!
+//! Run with `cargo fuzz run analyze` from the `hoho-recv` directory.
+#![no_main]
+
+use hoho_recv::amm::AmmRegistry;
+use hoho_recv::analyzer::{analyze_transaction, Resolvers};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // No RPC resolvers: the target must stay deterministic and offline
+    let _ = analyze_transaction(data, &AmmRegistry::default(), &mut Resolvers::default());
+});
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::address_lookup_table::state::AddressLookupTable;
+use solana_sdk::message::v0::{LoadedAddresses, MessageAddressTableLookup};
+use solana_sdk::message::{AccountKeys, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+
+/// Returns the message's account keys in the order instruction account indices refer to.
+///
+/// Static keys come first exactly as serialized (writable signers, readonly signers,
+/// writable non-signers, readonly non-signers), followed by the writable addresses of
+/// every lookup in order, then the readonly addresses of every lookup in order. Without
+/// `loaded` only the static keys are returned, so indices into lookup tables resolve to
+/// nothing rather than to the wrong account.
+pub fn resolve_account_keys(
+    message: &VersionedMessage,
+    loaded: Option<&LoadedAddresses>,
+) -> Vec<Pubkey> {
+    AccountKeys::new(message.static_account_keys(), loaded)
+        .iter()
+        .copied()
+        .collect()
+}
+
+/// Loads the addresses referenced by `lookups` from the given table contents.
+///
+/// Returns `None` if a table is missing or an index is out of range.
+pub fn load_addresses(
+    lookups: &[MessageAddressTableLookup],
+    tables: &HashMap<Pubkey, Vec<Pubkey>>,
+) -> Option<LoadedAddresses> {
+    lookups
+        .iter()
+        .map(|lookup| {
+            let addresses = tables.get(&lookup.account_key)?;
+            let select = |indexes: &[u8]| -> Option<Vec<Pubkey>> {
+                indexes
+                    .iter()
+                    .map(|index| addresses.get(*index as usize).copied())
+                    .collect()
+            };
+            Some(LoadedAddresses {
+                writable: select(&lookup.writable_indexes)?,
+                readonly: select(&lookup.readonly_indexes)?,
+            })
+        })
+        .collect()
+}
+
+/// Fetches and caches address lookup table contents over RPC.
+pub struct LookupTableResolver {
+    rpc_client: Arc<RpcClient>,
+    tables: HashMap<Pubkey, Vec<Pubkey>>,
+}
+
+impl LookupTableResolver {
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        Self::with_capacity(rpc_client, 0)
+    }
+
+    /// Sizes the cache for `capacity` tables up front.
+    pub fn with_capacity(rpc_client: Arc<RpcClient>, capacity: usize) -> Self {
+        Self {
+            rpc_client,
+            tables: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Resolves the loaded addresses of a V0 message, returning `None` for legacy messages.
+    pub fn resolve(&mut self, message: &VersionedMessage) -> Option<LoadedAddresses> {
+        let lookups = message.address_table_lookups()?;
+        for lookup in lookups {
+            // Tables are append-only, so a cached table only needs refreshing when the
+            // message references an index past its end.
+            let max_index = lookup
+                .writable_indexes
+                .iter()
+                .chain(&lookup.readonly_indexes)
+                .max()
+                .map(|index| *index as usize);
+            let stale = match (self.tables.get(&lookup.account_key), max_index) {
+                (None, _) => true,
+                (Some(addresses), Some(max_index)) => max_index >= addresses.len(),
+                (Some(_), None) => false,
+            };
+            if stale {
+                let addresses = self.fetch_table(&lookup.account_key)?;
+                self.tables.insert(lookup.account_key, addresses);
+            }
+        }
+        load_addresses(lookups, &self.tables)
+    }
+
+    fn fetch_table(&self, table: &Pubkey) -> Option<Vec<Pubkey>> {
+        let data = match self.rpc_client.get_account_data(table) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("Error fetching lookup table {}: {}", table, e);
+                return None;
+            }
+        };
+        AddressLookupTable::deserialize(&data)
+            .map(|table| table.addresses.to_vec())
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::instruction::CompiledInstruction;
+    use solana_sdk::message::{v0, MessageHeader};
+
+    #[test]
+    fn test_resolve_account_keys_with_lookup_table() {
+        let payer = Pubkey::new_unique();
+        let user_source = Pubkey::new_unique();
+        let raydium_v4 = Pubkey::from_str("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8").unwrap();
+        let amm = Pubkey::new_unique();
+        let serum_program = Pubkey::new_unique();
+        let pool_coin = Pubkey::new_unique();
+        let serum_market = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let table_key = Pubkey::new_unique();
+        let table = vec![serum_program, Pubkey::new_unique(), amm, pool_coin];
+        let other_table_key = Pubkey::new_unique();
+        let other_table = vec![authority, serum_market];
+
+        // Static: [payer (signer), user_source (writable), raydium_v4 (readonly)]
+        // Loaded: writable [amm, pool_coin, serum_market], readonly [serum_program, authority]
+        let message = VersionedMessage::V0(v0::Message {
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys: vec![payer, user_source, raydium_v4],
+            recent_blockhash: Hash::default(),
+            instructions: vec![CompiledInstruction::new_from_raw_parts(
+                2,
+                vec![9],
+                vec![3, 6, 7, 4, 5, 1, 0],
+            )],
+            address_table_lookups: vec![
+                MessageAddressTableLookup {
+                    account_key: table_key,
+                    writable_indexes: vec![2, 3],
+                    readonly_indexes: vec![0],
+                },
+                MessageAddressTableLookup {
+                    account_key: other_table_key,
+                    writable_indexes: vec![1],
+                    readonly_indexes: vec![0],
+                },
+            ],
+        });
+
+        let tables = HashMap::from([(table_key, table), (other_table_key, other_table)]);
+        let loaded = load_addresses(message.address_table_lookups().unwrap(), &tables).unwrap();
+        assert_eq!(loaded.writable, vec![amm, pool_coin, serum_market]);
+        assert_eq!(loaded.readonly, vec![serum_program, authority]);
+
+        let account_keys = resolve_account_keys(&message, Some(&loaded));
+        assert_eq!(
+            account_keys,
+            vec![
+                payer,
+                user_source,
+                raydium_v4,
+                amm,
+                pool_coin,
+                serum_market,
+                serum_program,
+                authority
+            ]
+        );
+
+        let ix = &message.instructions()[0];
+        assert_eq!(account_keys[ix.program_id_index as usize], raydium_v4);
+        let accounts: Vec<Pubkey> = ix
+            .accounts
+            .iter()
+            .map(|index| account_keys[*index as usize])
+            .collect();
+        assert_eq!(
+            accounts,
+            vec![amm, serum_program, authority, pool_coin, serum_market, user_source, payer]
+        );
+
+        // Without the table only the static keys resolve
+        assert_eq!(resolve_account_keys(&message, None).len(), 3);
+    }
+
+    #[test]
+    fn test_load_addresses_out_of_range() {
+        let table_key = Pubkey::new_unique();
+        let tables = HashMap::from([(table_key, vec![Pubkey::new_unique()])]);
+        let lookups = [MessageAddressTableLookup {
+            account_key: table_key,
+            writable_indexes: vec![1],
+            readonly_indexes: vec![],
+        }];
+        assert_eq!(load_addresses(&lookups, &tables), None);
+        assert_eq!(load_addresses(&lookups, &HashMap::new()), None);
+    }
+}
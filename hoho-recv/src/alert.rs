@@ -0,0 +1,270 @@
+//! Rate-of-change alerts for a watched mint (`--watch-mint`).
+//!
+//! Swaps touching the mint are kept in a sliding window. An alert fires when the
+//! volume traded within the window crosses a threshold, or when the price moves by
+//! more than a percentage between the oldest and newest swap in the window. After an
+//! alert of a kind fires it is suppressed for one window, so a sustained move produces
+//! one alert per window rather than one per swap.
+//!
+//! Forwarded packets have not executed yet, so there are no `ray_log` reserves to
+//! price from. The price is the limit price each swap signs for, the other side's
+//! amount per unit of the watched mint using `min_amount_out`, which tracks the pool
+//! price within the traders' slippage settings. Amounts are raw token units.
+use std::collections::VecDeque;
+use std::fmt;
+use solana_sdk::pubkey::Pubkey;
+use crate::analyzer::AnalyzedSwap;
+
+// Bounds memory during bursts regardless of the window length
+const MAX_WINDOW_SAMPLES: usize = 10_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchConfig {
+    pub mint: Pubkey,
+    pub window_us: u64,
+    /// Alert when the raw volume of the mint within the window reaches this amount
+    pub volume_threshold: Option<u64>,
+    /// Alert when the price moves by at least this many percent within the window
+    pub price_change_percent: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Alert {
+    Volume {
+        mint: Pubkey,
+        volume: u64,
+        swaps: usize,
+        window_us: u64,
+    },
+    Price {
+        mint: Pubkey,
+        from: f64,
+        to: f64,
+        change_percent: f64,
+        window_us: u64,
+    },
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Alert::Volume { mint, volume, swaps, window_us } => write!(
+                f,
+                "ALERT {}: volume {} over {} swaps in the last {}s",
+                mint,
+                volume,
+                swaps,
+                window_us / 1_000_000
+            ),
+            Alert::Price { mint, from, to, change_percent, window_us } => write!(
+                f,
+                "ALERT {}: price moved {:+.2}% ({} -> {}) in the last {}s",
+                mint,
+                change_percent,
+                from,
+                to,
+                window_us / 1_000_000
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    at_us: u64,
+    volume: u64,
+    price: Option<f64>,
+}
+
+pub struct RateOfChangeMonitor {
+    config: WatchConfig,
+    samples: VecDeque<Sample>,
+    volume: u64,
+    volume_alerted_at_us: Option<u64>,
+    price_alerted_at_us: Option<u64>,
+}
+
+impl RateOfChangeMonitor {
+    pub fn new(config: WatchConfig) -> Self {
+        Self {
+            config,
+            samples: VecDeque::new(),
+            volume: 0,
+            volume_alerted_at_us: None,
+            price_alerted_at_us: None,
+        }
+    }
+
+    /// Records `swap` if it trades the watched mint, returning any alerts it triggers.
+    pub fn observe(&mut self, swap: &AnalyzedSwap, now_us: u64) -> Vec<Alert> {
+        let Some(sample) = self.sample(swap, now_us) else {
+            return vec![];
+        };
+        self.expire(now_us);
+        if self.samples.len() == MAX_WINDOW_SAMPLES {
+            self.pop_oldest();
+        }
+        self.volume = self.volume.saturating_add(sample.volume);
+        self.samples.push_back(sample);
+
+        let mut alerts = vec![];
+        if let Some(threshold) = self.config.volume_threshold {
+            if self.volume >= threshold && self.cooled_down(self.volume_alerted_at_us, now_us) {
+                self.volume_alerted_at_us = Some(now_us);
+                alerts.push(Alert::Volume {
+                    mint: self.config.mint,
+                    volume: self.volume,
+                    swaps: self.samples.len(),
+                    window_us: self.config.window_us,
+                });
+            }
+        }
+        if let (Some(threshold), Some(to)) = (self.config.price_change_percent, sample.price) {
+            let from = self.samples.iter().find_map(|sample| sample.price);
+            if let Some(from) = from.filter(|from| *from > 0.0) {
+                let change_percent = (to - from) / from * 100.0;
+                if change_percent.abs() >= threshold && self.cooled_down(self.price_alerted_at_us, now_us) {
+                    self.price_alerted_at_us = Some(now_us);
+                    alerts.push(Alert::Price {
+                        mint: self.config.mint,
+                        from,
+                        to,
+                        change_percent,
+                        window_us: self.config.window_us,
+                    });
+                }
+            }
+        }
+        alerts
+    }
+
+    fn sample(&self, swap: &AnalyzedSwap, now_us: u64) -> Option<Sample> {
+        let (volume, other) = if swap.source_mint == Some(self.config.mint) {
+            (swap.amount_in, swap.min_amount_out)
+        } else if swap.destination_mint == Some(self.config.mint) {
+            (swap.min_amount_out, swap.amount_in)
+        } else {
+            return None;
+        };
+        Some(Sample {
+            at_us: now_us,
+            volume,
+            price: (volume > 0).then(|| other as f64 / volume as f64),
+        })
+    }
+
+    fn expire(&mut self, now_us: u64) {
+        while self
+            .samples
+            .front()
+            .is_some_and(|sample| now_us.saturating_sub(sample.at_us) > self.config.window_us)
+        {
+            self.pop_oldest();
+        }
+    }
+
+    fn pop_oldest(&mut self) {
+        if let Some(sample) = self.samples.pop_front() {
+            self.volume = self.volume.saturating_sub(sample.volume);
+        }
+    }
+
+    fn cooled_down(&self, alerted_at_us: Option<u64>, now_us: u64) -> bool {
+        alerted_at_us.is_none_or(|at_us| now_us.saturating_sub(at_us) > self.config.window_us)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Signature;
+    use solana_sdk::transaction::TransactionVersion;
+    use crate::analyzer::Dex;
+    use crate::compute_budget::PriorityFee;
+
+    fn swap(source_mint: Pubkey, destination_mint: Pubkey, amount_in: u64, min_amount_out: u64) -> AnalyzedSwap {
+        AnalyzedSwap {
+            signature: Signature::default(),
+            co_signatures: vec![],
+            dex: Dex::RaydiumV4,
+            discriminator: 9,
+            amount_in,
+            min_amount_out,
+            pool_coin_account: None,
+            pool_pc_account: None,
+            model_data_account: None,
+            user_source_account: None,
+            user_destination_account: None,
+            user_owner: None,
+            source_mint: Some(source_mint),
+            destination_mint: Some(destination_mint),
+            source_decimals: None,
+            destination_decimals: None,
+            transfers: vec![],
+            priority_fee: PriorityFee::default(),
+            compute_units_consumed: None,
+            lookup_tables: vec![],
+            transaction_size: 0,
+            version: TransactionVersion::LEGACY,
+            front_run_protected: false,
+            zero_min_out: false,
+            pool_fee_bps: None,
+            expected_amount_out: None,
+            realized_amount_out: None,
+            realized_amount_in: None,
+            mint_events: vec![],
+            burn_events: vec![],
+            authority_changes: vec![],
+            payer_sol_balance: None,
+            observers: None,
+            price_impact_bps: None,
+            pool_reserves: None,
+        }
+    }
+
+    #[test]
+    fn test_volume_alert() {
+        let mint = Pubkey::new_unique();
+        let sol = Pubkey::new_unique();
+        let mut monitor = RateOfChangeMonitor::new(WatchConfig {
+            mint,
+            window_us: 10,
+            volume_threshold: Some(100),
+            price_change_percent: None,
+        });
+        assert!(monitor.observe(&swap(sol, Pubkey::new_unique(), 1_000, 1_000), 0).is_empty());
+        assert!(monitor.observe(&swap(mint, sol, 60, 60), 0).is_empty());
+        // Buying the mint counts its output amount
+        assert_eq!(
+            monitor.observe(&swap(sol, mint, 1, 40), 5),
+            vec![Alert::Volume { mint, volume: 100, swaps: 2, window_us: 10 }]
+        );
+        // Suppressed for a window
+        assert!(monitor.observe(&swap(mint, sol, 60, 60), 6).is_empty());
+        // The first two swaps expire and the remaining 120 alerts again after the cooldown
+        assert_eq!(monitor.observe(&swap(mint, sol, 60, 60), 16).len(), 1);
+        assert_eq!(monitor.samples.len(), 2);
+    }
+
+    #[test]
+    fn test_price_alert() {
+        let mint = Pubkey::new_unique();
+        let sol = Pubkey::new_unique();
+        let mut monitor = RateOfChangeMonitor::new(WatchConfig {
+            mint,
+            window_us: 1_000,
+            volume_threshold: None,
+            price_change_percent: Some(10.0),
+        });
+        // Selling 100 of the mint for at least 50 prices it at 0.5
+        assert!(monitor.observe(&swap(mint, sol, 100, 50), 0).is_empty());
+        assert!(monitor.observe(&swap(mint, sol, 100, 54), 1).is_empty());
+        // Buying 100 of the mint for 40 prices it at 0.4, down 20% from the window start
+        let alerts = monitor.observe(&swap(sol, mint, 40, 100), 2);
+        let [Alert::Price { from, to, change_percent, .. }] = alerts.as_slice() else {
+            panic!("expected a price alert, got {:?}", alerts);
+        };
+        assert_eq!((*from, *to), (0.5, 0.4));
+        assert!((change_percent + 20.0).abs() < 1e-9);
+    }
+}
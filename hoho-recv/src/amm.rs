@@ -0,0 +1,375 @@
+//! Data-driven decoding of swap instructions for minor AMMs.
+//!
+//! Most AMMs encode a swap as a discriminator followed by the input amount and the
+//! minimum output amount, so they can be described by an [`AmmSpec`] instead of a
+//! dedicated analyzer. [`AmmRegistry::default`] ships Lifinity, Saber and Aldrin;
+//! more can be loaded from a JSON file with `--amm-specs`:
+//!
+//! ```json
+//! [
+//!   {
+//!     "name": "lifinity_v2",
+//!     "program_id": "2wT8Yq49kHgDzXuPxZSaeLaH1qbmGXtEyPy64bL7aD3c",
+//!     "curve": "constant_product",
+//!     "swap_discriminator": [248, 198, 158, 145, 225, 117, 135, 200],
+//!     "amount_in_offset": 8,
+//!     "min_out_offset": 16,
+//!     "min_data_len": 24,
+//!     "max_data_len": 24,
+//!     "accounts": { "pool_coin": 5, "pool_pc": 6 }
+//!   }
+//! ]
+//! ```
+//!
+//! `min_data_len` and `max_data_len` bound the length of the instruction data
+//! taken for a swap, both optional. Another instruction sharing the discriminator
+//! but longer or shorter than the swap would otherwise be decoded as one, reading
+//! its amounts from unrelated fields.
+//!
+//! Account positions shift between program versions, so the layouts of every DEX,
+//! including the dedicated Raydium analyzers, can also be replaced without
+//! recompiling with `--account-layouts`, a JSON object keyed by DEX name:
+//!
+//! ```json
+//! { "raydium_v4": { "pool_coin": 5, "pool_pc": 6, "user_source": 15, "user_destination": 16, "user_owner": 17 } }
+//! ```
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+use serde_derive::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use crate::analyzer::{Curve, Dex};
+use crate::program_ids;
+
+pub const LIFINITY_V2_PROGRAM_ID: &str = "2wT8Yq49kHgDzXuPxZSaeLaH1qbmGXtEyPy64bL7aD3c";
+pub const SABER_PROGRAM_ID: &str = "SSwpkEEcbUqx4vtoEByFjSkhKdCT862DNVb52nZg1UZ";
+pub const ALDRIN_V2_PROGRAM_ID: &str = "CURVGoZn8zycx6FXwwevgBTB2gVvdbGTEpvMJDbgs2t4";
+
+// sha256("global:swap")[..8], shared by every Anchor program with a `swap` instruction
+const ANCHOR_SWAP_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+
+/// Positions of the interesting accounts in a swap instruction's account list.
+/// Unset positions are not decoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountLayout {
+    pub pool_coin: Option<usize>,
+    pub pool_pc: Option<usize>,
+    /// Stable pools only: the account holding the curve's precomputed model
+    pub model_data: Option<usize>,
+    pub user_source: Option<usize>,
+    pub user_destination: Option<usize>,
+    pub user_owner: Option<usize>,
+}
+
+impl AccountLayout {
+    /// Number of accounts an instruction needs for every position to exist.
+    pub fn min_accounts(&self) -> usize {
+        [
+            self.pool_coin,
+            self.pool_pc,
+            self.model_data,
+            self.user_source,
+            self.user_destination,
+            self.user_owner,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|position| position + 1)
+        .max()
+        .unwrap_or_default()
+    }
+}
+
+/// How to recognize and decode one AMM's swap instruction.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AmmSpec {
+    /// Stable identifier used in output and metric names
+    pub name: String,
+    pub program_id: Pubkey,
+    pub curve: Curve,
+    /// Prefix of the instruction data that identifies a swap
+    pub swap_discriminator: Vec<u8>,
+    /// Offset of the u64 LE input amount in the instruction data
+    pub amount_in_offset: usize,
+    /// Offset of the u64 LE minimum output amount in the instruction data
+    pub min_out_offset: usize,
+    /// Lengths of instruction data accepted as a swap
+    pub data_len: RangeInclusive<usize>,
+    pub accounts: AccountLayout,
+}
+
+impl AmmSpec {
+    /// Returns `(amount_in, min_amount_out)` if `data` is this AMM's swap instruction.
+    pub fn decode_amounts(&self, data: &[u8]) -> Option<(u64, u64)> {
+        if !data.starts_with(&self.swap_discriminator) {
+            return None;
+        }
+        let read_u64 = |offset: usize| {
+            Some(u64::from_le_bytes(data.get(offset..offset.checked_add(8)?)?.try_into().ok()?))
+        };
+        Some((read_u64(self.amount_in_offset)?, read_u64(self.min_out_offset)?))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AmmSpecConfig {
+    name: String,
+    program_id: String,
+    #[serde(default)]
+    curve: CurveConfig,
+    swap_discriminator: Vec<u8>,
+    amount_in_offset: usize,
+    min_out_offset: usize,
+    min_data_len: Option<usize>,
+    max_data_len: Option<usize>,
+    #[serde(default)]
+    accounts: AccountLayout,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CurveConfig {
+    #[default]
+    ConstantProduct,
+    Stable,
+}
+
+impl TryFrom<AmmSpecConfig> for AmmSpec {
+    type Error = String;
+
+    fn try_from(config: AmmSpecConfig) -> Result<Self, Self::Error> {
+        let program_id = Pubkey::from_str(&config.program_id)
+            .map_err(|e| format!("{}: invalid program id: {}", config.name, e))?;
+        let data_len = config.min_data_len.unwrap_or(0)..=config.max_data_len.unwrap_or(usize::MAX);
+        if data_len.is_empty() {
+            return Err(format!("{}: min_data_len exceeds max_data_len", config.name));
+        }
+        Ok(Self {
+            name: config.name,
+            program_id,
+            curve: match config.curve {
+                CurveConfig::ConstantProduct => Curve::ConstantProduct,
+                CurveConfig::Stable => Curve::Stable,
+            },
+            swap_discriminator: config.swap_discriminator,
+            amount_in_offset: config.amount_in_offset,
+            min_out_offset: config.min_out_offset,
+            data_len,
+            accounts: config.accounts,
+        })
+    }
+}
+
+/// The AMMs decoded from their [`AmmSpec`], and the account layouts of every DEX.
+/// Specs live for the rest of the process so that a matched swap can refer to its
+/// spec without copying it.
+pub struct AmmRegistry {
+    specs: Vec<&'static AmmSpec>,
+    /// Replacements for the layouts of the dedicated analyzers
+    layouts: HashMap<Dex, AccountLayout>,
+}
+
+impl Default for AmmRegistry {
+    fn default() -> Self {
+        let spec = |name: &str, program_id: &str, curve, swap_discriminator: &[u8], offsets: (usize, usize), data_len, pool: (usize, usize)| {
+            AmmSpec {
+                name: name.to_string(),
+                program_id: program_ids::builtin_program_id(program_id),
+                curve,
+                swap_discriminator: swap_discriminator.to_vec(),
+                amount_in_offset: offsets.0,
+                min_out_offset: offsets.1,
+                data_len,
+                accounts: AccountLayout {
+                    pool_coin: Some(pool.0),
+                    pool_pc: Some(pool.1),
+                    ..AccountLayout::default()
+                },
+            }
+        };
+        Self::new(vec![
+            // swap(amount_in, minimum_amount_out); pool source/destination vaults at 5 and 6
+            spec(
+                "lifinity_v2",
+                LIFINITY_V2_PROGRAM_ID,
+                Curve::ConstantProduct,
+                &ANCHOR_SWAP_DISCRIMINATOR,
+                (8, 16),
+                24..=24,
+                (5, 6),
+            ),
+            // Swap { amount_in, minimum_amount_out }; pool source/destination at 4 and 5
+            spec(
+                "saber",
+                SABER_PROGRAM_ID,
+                Curve::Stable,
+                &[1],
+                (1, 9),
+                17..=17,
+                (4, 5),
+            ),
+            // swap(tokens, min_tokens, side); base/quote vaults at 3 and 4
+            spec(
+                "aldrin_v2",
+                ALDRIN_V2_PROGRAM_ID,
+                Curve::ConstantProduct,
+                &ANCHOR_SWAP_DISCRIMINATOR,
+                (8, 16),
+                25..=25,
+                (3, 4),
+            ),
+        ])
+    }
+}
+
+impl AmmRegistry {
+    pub fn new(specs: Vec<AmmSpec>) -> Self {
+        Self {
+            specs: specs.into_iter().map(|spec| &*Box::leak(Box::new(spec))).collect(),
+            layouts: HashMap::new(),
+        }
+    }
+
+    /// Adds the specs in a JSON file, replacing built-in specs for the same program.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+        let configs: Vec<AmmSpecConfig> = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let specs = configs
+            .into_iter()
+            .map(AmmSpec::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let count = specs.len();
+        for spec in Self::new(specs).specs {
+            self.specs.retain(|existing| existing.program_id != spec.program_id);
+            self.specs.push(spec);
+        }
+        Ok(count)
+    }
+
+    /// Replaces account layouts from a JSON object keyed by DEX name, returning how
+    /// many were replaced. Unknown names are an error so typos don't go unnoticed.
+    pub fn load_layouts<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+        let layouts: HashMap<String, AccountLayout> = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        for (name, layout) in &layouts {
+            if let Some(dex) = Dex::ALL.into_iter().find(|dex| dex.name() == name) {
+                self.layouts.insert(dex, *layout);
+            } else if let Some(position) = self.specs.iter().position(|spec| spec.name == *name) {
+                let spec = AmmSpec {
+                    accounts: *layout,
+                    ..self.specs[position].clone()
+                };
+                self.specs[position] = Box::leak(Box::new(spec));
+            } else {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown DEX {}", name)));
+            }
+        }
+        Ok(layouts.len())
+    }
+
+    /// The account layout used to decode `dex` swaps.
+    pub fn layout(&self, dex: Dex) -> AccountLayout {
+        match dex {
+            Dex::Amm(spec) => spec.accounts,
+            dex => self.layouts.get(&dex).copied().unwrap_or_else(|| dex.default_layout()),
+        }
+    }
+
+    pub fn find(&self, program_id: &Pubkey) -> Option<&'static AmmSpec> {
+        self.specs.iter().copied().find(|spec| spec.program_id == *program_id)
+    }
+
+    pub fn specs(&self) -> impl Iterator<Item = &'static AmmSpec> + '_ {
+        self.specs.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_decode_amounts() {
+        let registry = AmmRegistry::default();
+        let saber = registry
+            .find(&Pubkey::from_str("SSwpkEEcbUqx4vtoEByFjSkhKdCT862DNVb52nZg1UZ").unwrap())
+            .unwrap();
+        let data = [&[1u8][..], &5u64.to_le_bytes(), &4u64.to_le_bytes()].concat();
+        assert_eq!(saber.decode_amounts(&data), Some((5, 4)));
+        assert_eq!(saber.decode_amounts(&data[..16]), None);
+        assert_eq!(saber.decode_amounts(&[2; 17]), None);
+
+        let lifinity = registry.specs().find(|spec| spec.name == "lifinity_v2").unwrap();
+        let data = [&ANCHOR_SWAP_DISCRIMINATOR[..], &7u64.to_le_bytes(), &6u64.to_le_bytes()].concat();
+        assert_eq!(lifinity.decode_amounts(&data), Some((7, 6)));
+    }
+
+    #[test]
+    fn test_load_specs() {
+        let program_id = Pubkey::new_unique();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"[{{"name": "test_amm", "program_id": "{}", "curve": "stable", "swap_discriminator": [3],
+                 "amount_in_offset": 1, "min_out_offset": 9, "min_data_len": 17,
+                 "accounts": {{"pool_coin": 1, "pool_pc": 2}}}}]"#,
+            program_id
+        )
+        .unwrap();
+
+        let mut registry = AmmRegistry::default();
+        let builtin = registry.specs().count();
+        assert_eq!(registry.load(file.path()).unwrap(), 1);
+        assert_eq!(registry.specs().count(), builtin + 1);
+        let spec = registry.find(&program_id).unwrap();
+        assert_eq!(spec.name, "test_amm");
+        assert_eq!(spec.curve, Curve::Stable);
+        assert_eq!(spec.data_len, 17..=usize::MAX);
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, r#"[{{"name": "bad", "program_id": "nope"}}]"#).unwrap();
+        assert!(registry.load(file.path()).is_err());
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"[{{"name": "bad", "program_id": "{}", "swap_discriminator": [3], "amount_in_offset": 1,
+                 "min_out_offset": 9, "min_data_len": 18, "max_data_len": 17}}]"#,
+            program_id
+        )
+        .unwrap();
+        assert!(registry.load(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_load_layouts() {
+        let mut registry = AmmRegistry::default();
+        assert_eq!(registry.layout(Dex::RaydiumV4).min_accounts(), 18);
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"{{"raydium_v4": {{"pool_coin": 4, "pool_pc": 5, "user_source": 14}},
+                "saber": {{"user_owner": 2}}}}"#
+        )
+        .unwrap();
+        assert_eq!(registry.load_layouts(file.path()).unwrap(), 2);
+        let v4 = registry.layout(Dex::RaydiumV4);
+        assert_eq!((v4.pool_coin, v4.user_source, v4.user_owner), (Some(4), Some(14), None));
+        assert_eq!(v4.min_accounts(), 15);
+        assert_eq!(registry.layout(Dex::RaydiumStable).model_data, Some(6));
+        let saber = registry.specs().find(|spec| spec.name == "saber").unwrap();
+        assert_eq!(registry.layout(Dex::Amm(saber)).user_owner, Some(2));
+        assert_eq!(registry.layout(Dex::Amm(saber)).pool_coin, None);
+        assert_eq!(registry.find(&saber.program_id), Some(saber));
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, r#"{{"raydium_v5": {{}}}}"#).unwrap();
+        assert!(registry.load_layouts(file.path()).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! Formatting of the SOL and token amounts printed for a swap (`--amount-format`).
+//!
+//! `ui` scales an amount by its decimals, 9 for SOL, and keeps the raw integer next
+//! to it; a token amount whose decimals are unknown is printed raw. `lamports`
+//! prints integers labeled with their smallest unit and `raw` bare integers, for
+//! output that is grepped or parsed. The format is chosen once for the process;
+//! `--load-shed` overrides it with `raw` while the receiver is overloaded.
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use crate::token_transfers;
+
+const SOL_DECIMALS: u8 = 9;
+
+static AMOUNT_FORMAT: OnceLock<AmountFormat> = OnceLock::new();
+
+static FORCE_RAW: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AmountFormat {
+    Raw,
+    Lamports,
+    #[default]
+    Ui,
+}
+
+impl AmountFormat {
+    pub const NAMES: [&'static str; 3] = ["raw", "lamports", "ui"];
+
+    pub fn format(self, amount: Amount) -> String {
+        let (raw, decimals, unit, symbol) = match amount {
+            Amount::Sol(lamports) => (lamports, Some(SOL_DECIMALS), "lamports", " SOL"),
+            Amount::Token { amount, decimals } => (amount, decimals, "base units", ""),
+        };
+        match (self, decimals) {
+            (AmountFormat::Raw, _) => raw.to_string(),
+            (AmountFormat::Lamports, _) => format!("{} {}", raw, unit),
+            (AmountFormat::Ui, Some(decimals)) => {
+                format!("{}{} (raw value: {})", token_transfers::ui_amount(raw, decimals), symbol, raw)
+            }
+            (AmountFormat::Ui, None) => format!("{} (raw value, decimals unknown)", raw),
+        }
+    }
+}
+
+impl FromStr for AmountFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(AmountFormat::Raw),
+            "lamports" => Ok(AmountFormat::Lamports),
+            "ui" => Ok(AmountFormat::Ui),
+            _ => Err(format!("unknown amount format {:?}, expected one of {:?}", s, Self::NAMES)),
+        }
+    }
+}
+
+/// An amount to print, in the smallest unit of what it counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Amount {
+    Sol(u64),
+    Token { amount: u64, decimals: Option<u8> },
+}
+
+/// Sets the format of every amount printed from now on. Only the first call has an
+/// effect; until then amounts are printed as `ui`.
+pub fn set_amount_format(format: AmountFormat) {
+    let _ = AMOUNT_FORMAT.set(format);
+}
+
+/// Prints every amount `raw` while `enabled`, whatever the process's format.
+pub fn force_raw(enabled: bool) {
+    FORCE_RAW.store(enabled, Ordering::Relaxed);
+}
+
+/// Formats `amount` in the process's amount format.
+pub fn format_amount(amount: Amount) -> String {
+    if FORCE_RAW.load(Ordering::Relaxed) {
+        return AmountFormat::Raw.format(amount);
+    }
+    AMOUNT_FORMAT.get().copied().unwrap_or_default().format(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amount_format() {
+        let sol = Amount::Sol(1_500_000_000);
+        let token = Amount::Token { amount: 2_500_000, decimals: Some(6) };
+        let unknown = Amount::Token { amount: 42, decimals: None };
+        let formatted = |format: AmountFormat| [sol, token, unknown].map(|amount| format.format(amount));
+        assert_eq!(formatted(AmountFormat::Raw), ["1500000000", "2500000", "42"]);
+        assert_eq!(
+            formatted(AmountFormat::Lamports),
+            ["1500000000 lamports", "2500000 base units", "42 base units"]
+        );
+        assert_eq!(
+            formatted(AmountFormat::Ui),
+            [
+                "1.5 SOL (raw value: 1500000000)",
+                "2.5 (raw value: 2500000)",
+                "42 (raw value, decimals unknown)",
+            ]
+        );
+        assert_eq!("lamports".parse(), Ok(AmountFormat::Lamports));
+        assert!("sol".parse::<AmountFormat>().is_err());
+    }
+}
@@ -0,0 +1,1481 @@
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use chrono::Utc;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::v0::{LoadedAddresses, MessageAddressTableLookup};
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{TransactionVersion, VersionedTransaction};
+use crate::address_lookup::{self, LookupTableResolver};
+use crate::amm::{AccountLayout, AmmRegistry, AmmSpec};
+use crate::amounts::{self, Amount};
+use crate::compute_budget::{self, PriorityFee};
+use crate::cpi::CpiResolver;
+use crate::front_run_markers;
+use crate::known_decimals;
+use crate::payer_balance::PayerBalanceResolver;
+use crate::pool_fees::PoolFeeResolver;
+use crate::program_ids;
+use crate::ray_log::{self, PoolReserves};
+use crate::timing::{self, Stage};
+use crate::token_metadata::TokenMetadataResolver;
+use crate::token_transfers::{self, AuthorityChange, BurnEvent, MintEvent, TokenEvent, TokenTransfer};
+use crate::transfers::{self, SolTransfer};
+
+// Raydium DEX program IDs
+pub const RAYDIUM_V4_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+pub const RAYDIUM_STABLE_PROGRAM_ID: &str = "5quBtoiQqxF9Jv6KYKctB59NT3gtJD2Y65kdnB1Uev3h";
+// The routing program front-ends swap through, which forwards to the AMM pools
+pub const RAYDIUM_SWAP_PROGRAM: &str = "27haf8L6oxUeXrHrgEgsexjSY5hbVUWEmvv9Nyxg8vQv";
+// Where Pump.fun tokens trade once they graduate from the bonding curve
+pub const PUMP_AMM_PROGRAM_ID: &str = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA";
+// Raydium LaunchLab, where tokens trade on a bonding curve until they migrate to a pool
+pub const RAYDIUM_LAUNCHPAD_PROGRAM_ID: &str = "LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj";
+
+// The pool's AMM account, holding its fee rate, in a V4 or router swap
+const RAYDIUM_AMM_ACCOUNT: usize = 1;
+
+/// Instructions index accounts with a u8, so no valid swap needs more; see
+/// [`set_max_accounts`]
+pub const DEFAULT_MAX_ACCOUNTS: usize = 256;
+
+static DEBUG_STRUCTS: OnceLock<bool> = OnceLock::new();
+static QUIET: OnceLock<bool> = OnceLock::new();
+static MAX_ACCOUNTS: OnceLock<usize> = OnceLock::new();
+static DATA_LEN_REJECTS: AtomicU64 = AtomicU64::new(0);
+static SHORT_ACCOUNT_LISTS: AtomicU64 = AtomicU64::new(0);
+
+// discriminator + amount_in (u64 LE) + min_amount_out (u64 LE)
+const SWAP_DATA_LEN: usize = 1 + 8 + 8;
+
+// SwapBaseOut carries max_amount_in and amount_out in the same places
+const RAYDIUM_SWAP_BASE_OUT: u8 = 11;
+
+// Pump AMM instructions are Anchor's: sha256("global:buy") and sha256("global:sell")
+// prefixes, each followed by two u64 LE amounts. buy(base_amount_out,
+// max_quote_amount_in) spends quote for an exact amount of base; sell(base_amount_in,
+// min_quote_amount_out) the reverse.
+const PUMP_AMM_BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+const PUMP_AMM_SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+// Positions of the pool's base and quote mints in both instructions
+const PUMP_AMM_BASE_MINT: usize = 3;
+const PUMP_AMM_QUOTE_MINT: usize = 4;
+// A buy may carry a trailing track_volume flag
+const PUMP_AMM_DATA_LEN: RangeInclusive<usize> = 24..=25;
+
+// LaunchLab trades are Anchor instructions too, each followed by two u64 LE amounts
+// and a u64 share fee rate: buy_exact_in and sell_exact_in(amount_in,
+// minimum_amount_out), buy_exact_out and sell_exact_out(amount_out,
+// maximum_amount_in). Buys spend the quote token for the launched base token.
+const LAUNCHPAD_BUY_EXACT_IN_DISCRIMINATOR: [u8; 8] = [250, 234, 13, 123, 213, 156, 19, 236];
+const LAUNCHPAD_BUY_EXACT_OUT_DISCRIMINATOR: [u8; 8] = [24, 211, 116, 40, 105, 3, 153, 56];
+const LAUNCHPAD_SELL_EXACT_IN_DISCRIMINATOR: [u8; 8] = [149, 39, 222, 155, 211, 124, 152, 26];
+const LAUNCHPAD_SELL_EXACT_OUT_DISCRIMINATOR: [u8; 8] = [95, 200, 71, 34, 8, 9, 11, 166];
+// Positions of the pool's base and quote mints in all four
+const LAUNCHPAD_BASE_MINT: usize = 9;
+const LAUNCHPAD_QUOTE_MINT: usize = 10;
+const LAUNCHPAD_DATA_LEN: usize = 8 + 8 + 8 + 8;
+
+/// DEX programs the analyzer recognizes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Dex {
+    RaydiumV4,
+    RaydiumStable,
+    /// Swaps routed through the Raydium swap program
+    RaydiumRoute,
+    /// Pump.fun tokens after graduating from the bonding curve
+    PumpAmm,
+    /// Raydium LaunchLab bonding curves, before tokens migrate to a pool
+    RaydiumLaunchpad,
+    /// An AMM decoded from its [`AmmSpec`]
+    Amm(&'static AmmSpec),
+}
+
+impl Dex {
+    /// The DEXes with a dedicated analyzer
+    pub const ALL: [Dex; 5] = [Dex::RaydiumV4, Dex::RaydiumStable, Dex::RaydiumRoute, Dex::PumpAmm, Dex::RaydiumLaunchpad];
+
+    /// Returns the DEX `program_id` belongs to, checking dedicated analyzers first.
+    pub fn from_program_id(program_id: &Pubkey, amms: &AmmRegistry) -> Option<Dex> {
+        Dex::ALL
+            .into_iter()
+            .find(|dex| dex.program_id() == *program_id)
+            .or_else(|| amms.find(program_id).map(Dex::Amm))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dex::RaydiumV4 => "raydium_v4",
+            Dex::RaydiumStable => "raydium_stable",
+            Dex::RaydiumRoute => "raydium_route",
+            Dex::PumpAmm => "pump_amm",
+            Dex::RaydiumLaunchpad => "raydium_launchpad",
+            Dex::Amm(spec) => &spec.name,
+        }
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        let id = match self {
+            Dex::RaydiumV4 => RAYDIUM_V4_PROGRAM_ID,
+            Dex::RaydiumStable => RAYDIUM_STABLE_PROGRAM_ID,
+            Dex::RaydiumRoute => RAYDIUM_SWAP_PROGRAM,
+            Dex::PumpAmm => PUMP_AMM_PROGRAM_ID,
+            Dex::RaydiumLaunchpad => RAYDIUM_LAUNCHPAD_PROGRAM_ID,
+            Dex::Amm(spec) => return spec.program_id,
+        };
+        program_ids::builtin_program_id(id)
+    }
+
+    pub fn curve(&self) -> Curve {
+        match self {
+            // LaunchLab curves default to constant product over virtual reserves
+            Dex::RaydiumV4 | Dex::RaydiumRoute | Dex::PumpAmm | Dex::RaydiumLaunchpad => Curve::ConstantProduct,
+            Dex::RaydiumStable => Curve::Stable,
+            Dex::Amm(spec) => spec.curve,
+        }
+    }
+
+    /// Lengths of instruction data taken for a swap. Anything else is rejected
+    /// rather than decoded, even when it matches the swap's discriminator.
+    pub fn data_len(&self) -> RangeInclusive<usize> {
+        match self {
+            Dex::RaydiumV4 | Dex::RaydiumStable | Dex::RaydiumRoute => SWAP_DATA_LEN..=SWAP_DATA_LEN,
+            Dex::PumpAmm => PUMP_AMM_DATA_LEN,
+            Dex::RaydiumLaunchpad => LAUNCHPAD_DATA_LEN..=LAUNCHPAD_DATA_LEN,
+            Dex::Amm(spec) => spec.data_len.clone(),
+        }
+    }
+
+    /// Positions of the interesting accounts in the swap instruction, unless replaced
+    /// through [`AmmRegistry::load_layouts`].
+    pub fn default_layout(&self) -> AccountLayout {
+        match self {
+            Dex::Amm(spec) => spec.accounts,
+            // amm target orders sits at 4, ahead of the pool token accounts; the router
+            // passes the V4 accounts through in the same order
+            Dex::RaydiumV4 | Dex::RaydiumRoute => AccountLayout {
+                pool_coin: Some(5),
+                pool_pc: Some(6),
+                model_data: None,
+                user_source: Some(15),
+                user_destination: Some(16),
+                user_owner: Some(17),
+            },
+            // the stable AMM has no target orders but adds the curve model data account
+            Dex::RaydiumStable => AccountLayout {
+                pool_coin: Some(4),
+                pool_pc: Some(5),
+                model_data: Some(6),
+                user_source: Some(15),
+                user_destination: Some(16),
+                user_owner: Some(17),
+            },
+            // pool | user | global config | base mint | quote mint | user base | user quote
+            // | pool base | pool quote | ...; user source and destination are those of a
+            // sell and trade places for a buy
+            Dex::PumpAmm => AccountLayout {
+                pool_coin: Some(7),
+                pool_pc: Some(8),
+                model_data: None,
+                user_source: Some(5),
+                user_destination: Some(6),
+                user_owner: Some(1),
+            },
+            // payer | authority | global config | platform config | pool state | user base
+            // | user quote | base vault | quote vault | base mint | quote mint | ...; as
+            // with Pump AMM, the user accounts are those of a sell
+            Dex::RaydiumLaunchpad => AccountLayout {
+                pool_coin: Some(7),
+                pool_pc: Some(8),
+                model_data: None,
+                user_source: Some(5),
+                user_destination: Some(6),
+                user_owner: Some(0),
+            },
+        }
+    }
+}
+
+/// Pricing curve of the pool a swap trades against. Slippage semantics differ: a
+/// constant-product pool moves the price with every trade, while a stable pool stays
+/// close to the peg until its reserves become unbalanced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Curve {
+    ConstantProduct,
+    Stable,
+}
+
+impl Curve {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Curve::ConstantProduct => "constant_product",
+            Curve::Stable => "stable",
+        }
+    }
+}
+
+/// A decoded swap instruction. Accounts are `None` when the instruction is too short or
+/// the account lives in a lookup table that was not resolved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnalyzedSwap {
+    /// The transaction's first signature, the fee payer's, which identifies it
+    pub signature: Signature,
+    /// The transaction's other signatures, in order, from co-signers such as a
+    /// separate swap authority or a bot's relayer
+    pub co_signatures: Vec<Signature>,
+    pub dex: Dex,
+    pub discriminator: u8,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub pool_coin_account: Option<Pubkey>,
+    pub pool_pc_account: Option<Pubkey>,
+    /// Stable pools only: the account holding the curve's precomputed model
+    pub model_data_account: Option<Pubkey>,
+    pub user_source_account: Option<Pubkey>,
+    pub user_destination_account: Option<Pubkey>,
+    pub user_owner: Option<Pubkey>,
+    /// Taken from the swap's `transferChecked` movements, or the token metadata
+    /// resolver when enabled
+    pub source_mint: Option<Pubkey>,
+    pub destination_mint: Option<Pubkey>,
+    /// From the swap's `transferChecked` movements, or else the mint's account when
+    /// the token metadata resolver is enabled
+    pub source_decimals: Option<u8>,
+    pub destination_decimals: Option<u8>,
+    /// System program transfers elsewhere in the transaction, e.g. funding or tips
+    pub transfers: Vec<SolTransfer>,
+    /// Compute unit price and limit from the transaction's Compute Budget instructions
+    pub priority_fee: PriorityFee,
+    /// Confirmed transactions only, from their meta: the compute units the whole
+    /// transaction used, to compare with `priority_fee.compute_unit_limit`
+    pub compute_units_consumed: Option<u64>,
+    /// The lookup tables a V0 transaction loads accounts from, with the indexes it
+    /// uses; which tables a sender uses helps attribute its transactions
+    pub lookup_tables: Vec<MessageAddressTableLookup>,
+    /// Serialized size of the transaction in bytes; one sent over TPU fits in
+    /// `PACKET_DATA_SIZE`
+    pub transaction_size: usize,
+    /// The message format the transaction was sent in, legacy or a version number
+    pub version: TransactionVersion,
+    /// Whether the transaction carries a "don't front-run me" marker account
+    pub front_run_protected: bool,
+    /// Whether the swap has no slippage bound: a minimum out of zero, or for an
+    /// exact-out swap a maximum in of `u64::MAX`. Bots sniping a launch do this to
+    /// land at any price.
+    pub zero_min_out: bool,
+    /// Raydium V4 and router swaps, with the pool fee resolver enabled: the pool's
+    /// swap fee
+    pub pool_fee_bps: Option<u32>,
+    /// The output the swap can expect at the pool's current reserves, net of its fee
+    pub expected_amount_out: Option<u64>,
+    /// What the swap's token movements deliver to the user's destination account
+    pub realized_amount_out: Option<u64>,
+    /// Raydium SwapBaseOut swaps with logs: the input spent, from the swap's
+    /// `ray_log`; at most `amount_in`
+    pub realized_amount_in: Option<u64>,
+    /// Token `mintTo`/`mintToChecked` instructions anywhere in the transaction
+    pub mint_events: Vec<MintEvent>,
+    /// Token `burn`/`burnChecked` instructions anywhere in the transaction
+    pub burn_events: Vec<BurnEvent>,
+    /// Token `setAuthority` instructions anywhere in the transaction
+    pub authority_changes: Vec<AuthorityChange>,
+    /// With the payer balance resolver enabled: the lamports held by the fee payer,
+    /// at most the resolver's TTL old
+    pub payer_sol_balance: Option<u64>,
+    /// With `--count-observers`: the forwarders that had sent the transaction
+    /// within the arrival window when this copy arrived, this one's included
+    pub observers: Option<u32>,
+    /// Raydium V4 swaps with logs: how far the swap moved the pool's price, from
+    /// the reserves before it in its `ray_log`, in basis points
+    pub price_impact_bps: Option<u32>,
+    /// Raydium V4 swaps with logs: the pool's reserves after the swap, from its
+    /// `ray_log`
+    pub pool_reserves: Option<PoolReserves>,
+}
+
+impl AnalyzedSwap {
+    pub fn curve(&self) -> Curve {
+        self.dex.curve()
+    }
+
+    /// `legacy`, or the number of a versioned message.
+    pub fn version_name(&self) -> String {
+        match self.version {
+            TransactionVersion::Legacy(_) => "legacy".to_string(),
+            TransactionVersion::Number(version) => version.to_string(),
+        }
+    }
+
+    /// The share of the compute unit limit the transaction consumed, when known;
+    /// above 1 only if the limit was misread.
+    pub fn compute_unit_utilization(&self) -> Option<f64> {
+        let limit = self.priority_fee.compute_unit_limit;
+        let consumed = self.compute_units_consumed?;
+        (limit > 0).then(|| consumed as f64 / f64::from(limit))
+    }
+
+    /// Whether the swap buys an exact `min_amount_out`, spending at most `amount_in`,
+    /// as a Pump AMM buy, a LaunchLab exact-out trade or a Raydium pool's SwapBaseOut
+    /// does.
+    pub fn exact_out(&self) -> bool {
+        match self.dex {
+            Dex::PumpAmm => self.discriminator == PUMP_AMM_BUY_DISCRIMINATOR[0],
+            Dex::RaydiumLaunchpad => [LAUNCHPAD_BUY_EXACT_OUT_DISCRIMINATOR[0], LAUNCHPAD_SELL_EXACT_OUT_DISCRIMINATOR[0]]
+                .contains(&self.discriminator),
+            Dex::RaydiumV4 | Dex::RaydiumStable => self.discriminator == RAYDIUM_SWAP_BASE_OUT,
+            _ => false,
+        }
+    }
+}
+
+/// Why a packet could not be analyzed at all, as opposed to simply not matching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalyzeError {
+    /// The packet is not a bincode-serialized `VersionedTransaction`
+    Deserialize,
+    /// The transaction is structurally invalid, e.g. an instruction indexes past its accounts
+    Sanitize,
+    /// The transaction lists or loads more accounts than [`set_max_accounts`] allows
+    TooManyAccounts,
+}
+
+/// Optional RPC-backed lookups used while analyzing a transaction.
+#[derive(Default)]
+pub struct Resolvers {
+    pub token_metadata: Option<TokenMetadataResolver>,
+    pub lookup_tables: Option<LookupTableResolver>,
+    pub pool_fees: Option<PoolFeeResolver>,
+    pub payer_balances: Option<PayerBalanceResolver>,
+    pub cpi: Option<CpiResolver>,
+}
+
+/// Instructions that decoded as swaps but were rejected for their data length,
+/// since the process started.
+pub fn data_len_rejects() -> u64 {
+    DATA_LEN_REJECTS.load(Ordering::Relaxed)
+}
+
+/// Swaps with fewer accounts than their layout expects, whose accounts were left
+/// undecoded, since the process started.
+pub fn short_account_lists() -> u64 {
+    SHORT_ACCOUNT_LISTS.load(Ordering::Relaxed)
+}
+
+/// Decodes a swap instruction for `dex`, or returns `None` if the data is not one.
+fn decode_swap(
+    dex: Dex,
+    layout: &AccountLayout,
+    account_keys: &[Pubkey],
+    ix: &CompiledInstruction,
+    signature: &Signature,
+) -> Option<AnalyzedSwap> {
+    let (amount_in, min_amount_out) = match dex {
+        Dex::Amm(spec) => spec.decode_amounts(&ix.data)?,
+        Dex::PumpAmm => decode_pump_amm_amounts(&ix.data)?,
+        Dex::RaydiumLaunchpad => decode_launchpad_amounts(&ix.data)?,
+        Dex::RaydiumV4 | Dex::RaydiumStable | Dex::RaydiumRoute => {
+            let data = ix.data.get(..SWAP_DATA_LEN)?;
+            (
+                u64::from_le_bytes(data[1..9].try_into().unwrap()),
+                u64::from_le_bytes(data[9..17].try_into().unwrap()),
+            )
+        }
+    };
+    // Raydium swaps aren't told apart by discriminator, so any longer instruction
+    // would decode, as could another sharing a spec's discriminator
+    let data_len = dex.data_len();
+    if !data_len.contains(&ix.data.len()) {
+        let rejected = DATA_LEN_REJECTS.fetch_add(1, Ordering::Relaxed) + 1;
+        if rejected.is_power_of_two() {
+            eprintln!("Rejecting {} instruction with {} bytes of data, outside its swap's {:?}, {} rejected so far",
+                      dex.name(), ix.data.len(), data_len, rejected);
+        }
+        return None;
+    }
+    let (mut source_mint, mut destination_mint) = (None, None);
+    // A shorter account list means the layout doesn't fit this instruction, and
+    // indexing it anyway would report unrelated accounts
+    let fits = ix.accounts.len() >= layout.min_accounts();
+    if !fits {
+        let short = SHORT_ACCOUNT_LISTS.fetch_add(1, Ordering::Relaxed) + 1;
+        if short.is_power_of_two() {
+            eprintln!("{} swap has {} accounts, its layout expects at least {}; accounts not decoded, {} so far",
+                      dex.name(), ix.accounts.len(), layout.min_accounts(), short);
+        }
+    }
+    let account = |position: Option<usize>| {
+        fits.then_some(())?;
+        instruction_account(account_keys, ix, position?).copied()
+    };
+    let (mut user_source, mut user_destination) = (account(layout.user_source), account(layout.user_destination));
+    // Pump AMM and LaunchLab pass the pool's mints; sells go from base to quote
+    let base_quote_mints = match dex {
+        Dex::PumpAmm => Some((PUMP_AMM_BASE_MINT, PUMP_AMM_QUOTE_MINT, ix.data.starts_with(&PUMP_AMM_BUY_DISCRIMINATOR))),
+        Dex::RaydiumLaunchpad => Some((LAUNCHPAD_BASE_MINT, LAUNCHPAD_QUOTE_MINT, is_launchpad_buy(&ix.data))),
+        _ => None,
+    };
+    if let Some((base_mint, quote_mint, buy)) = base_quote_mints {
+        source_mint = account(Some(base_mint));
+        destination_mint = account(Some(quote_mint));
+        if buy {
+            std::mem::swap(&mut user_source, &mut user_destination);
+            std::mem::swap(&mut source_mint, &mut destination_mint);
+        }
+    }
+    let mut swap = AnalyzedSwap {
+        signature: *signature,
+        co_signatures: Vec::new(),
+        dex,
+        discriminator: ix.data[0],
+        amount_in,
+        min_amount_out,
+        pool_coin_account: account(layout.pool_coin),
+        pool_pc_account: account(layout.pool_pc),
+        model_data_account: account(layout.model_data),
+        user_source_account: user_source,
+        user_destination_account: user_destination,
+        user_owner: account(layout.user_owner),
+        source_mint,
+        destination_mint,
+        source_decimals: None,
+        destination_decimals: None,
+        transfers: Vec::new(),
+        priority_fee: PriorityFee::default(),
+        compute_units_consumed: None,
+        lookup_tables: Vec::new(),
+        transaction_size: 0,
+        version: TransactionVersion::LEGACY,
+        front_run_protected: false,
+        zero_min_out: false,
+        pool_fee_bps: None,
+        expected_amount_out: None,
+        realized_amount_out: None,
+        realized_amount_in: None,
+        mint_events: Vec::new(),
+        burn_events: Vec::new(),
+        authority_changes: Vec::new(),
+        payer_sol_balance: None,
+        observers: None,
+        price_impact_bps: None,
+        pool_reserves: None,
+    };
+    swap.zero_min_out = if swap.exact_out() {
+        swap.amount_in == u64::MAX
+    } else {
+        swap.min_amount_out == 0
+    };
+    Some(swap)
+}
+
+/// Returns `(amount_in, min_amount_out)` of a Pump AMM buy or sell. A buy names the
+/// exact base amount it receives and the most quote it spends, so its `amount_in` is
+/// that maximum; see [`AnalyzedSwap::exact_out`].
+fn decode_pump_amm_amounts(data: &[u8]) -> Option<(u64, u64)> {
+    let read_u64 = |offset: usize| Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().unwrap()));
+    if data.starts_with(&PUMP_AMM_SELL_DISCRIMINATOR) {
+        Some((read_u64(8)?, read_u64(16)?))
+    } else if data.starts_with(&PUMP_AMM_BUY_DISCRIMINATOR) {
+        Some((read_u64(16)?, read_u64(8)?))
+    } else {
+        None
+    }
+}
+
+/// Returns `(amount_in, min_amount_out)` of a LaunchLab trade. Exact-out trades
+/// name the amount they receive and the most they spend, so, as for a Pump AMM
+/// buy, their `amount_in` is that maximum.
+fn decode_launchpad_amounts(data: &[u8]) -> Option<(u64, u64)> {
+    let read_u64 = |offset: usize| Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().unwrap()));
+    if data.starts_with(&LAUNCHPAD_BUY_EXACT_IN_DISCRIMINATOR) || data.starts_with(&LAUNCHPAD_SELL_EXACT_IN_DISCRIMINATOR) {
+        Some((read_u64(8)?, read_u64(16)?))
+    } else if data.starts_with(&LAUNCHPAD_BUY_EXACT_OUT_DISCRIMINATOR)
+        || data.starts_with(&LAUNCHPAD_SELL_EXACT_OUT_DISCRIMINATOR)
+    {
+        Some((read_u64(16)?, read_u64(8)?))
+    } else {
+        None
+    }
+}
+
+fn is_launchpad_buy(data: &[u8]) -> bool {
+    data.starts_with(&LAUNCHPAD_BUY_EXACT_IN_DISCRIMINATOR) || data.starts_with(&LAUNCHPAD_BUY_EXACT_OUT_DISCRIMINATOR)
+}
+
+/// Takes the mints and decimals of the user's token accounts from the transfers
+/// moving tokens out of the source and into the destination, and the amount out
+/// from the latter.
+fn apply_token_transfers(swap: &mut AnalyzedSwap, token_transfers: &[TokenTransfer]) {
+    for transfer in token_transfers {
+        if swap.user_source_account == Some(transfer.source) {
+            swap.source_mint = swap.source_mint.or(transfer.mint);
+            swap.source_decimals = swap.source_decimals.or(transfer.decimals);
+        }
+        if swap.user_destination_account == Some(transfer.destination) {
+            swap.destination_mint = swap.destination_mint.or(transfer.mint);
+            swap.destination_decimals = swap.destination_decimals.or(transfer.decimals);
+            swap.realized_amount_out = Some(swap.realized_amount_out.unwrap_or_default() + transfer.amount);
+        }
+    }
+}
+
+/// Looks up the fee of the Raydium pool `ix` swaps on and, when the swap's source
+/// mint tells which vault it pays into, the output expected net of that fee.
+fn apply_pool_fee(swap: &mut AnalyzedSwap, account_keys: &[Pubkey], ix: &CompiledInstruction, resolver: &mut PoolFeeResolver) {
+    if !matches!(swap.dex, Dex::RaydiumV4 | Dex::RaydiumRoute) {
+        return;
+    }
+    let Some(fee) = instruction_account(account_keys, ix, RAYDIUM_AMM_ACCOUNT).and_then(|amm| resolver.pool_fee(amm)) else {
+        return;
+    };
+    swap.pool_fee_bps = Some(fee.bps());
+    swap.expected_amount_out = (|| {
+        let source_mint = swap.source_mint?;
+        let coin = resolver.vault_balance(&swap.pool_coin_account?)?;
+        let pc = resolver.vault_balance(&swap.pool_pc_account?)?;
+        let (reserve_in, reserve_out) = if source_mint == coin.0 {
+            (coin.1, pc.1)
+        } else if source_mint == pc.0 {
+            (pc.1, coin.1)
+        } else {
+            return None;
+        };
+        Some(fee.amount_out(swap.amount_in, reserve_in, reserve_out))
+    })();
+}
+
+fn analyze_swap_accounts_and_inner_instructions(
+    account_keys: &[Pubkey],
+    tx: &VersionedTransaction,
+    inner_instructions: &[CompiledInstruction],
+    log_messages: &[String],
+    signature: &Signature,
+    amms: &AmmRegistry,
+    resolvers: &mut Resolvers,
+) -> Option<AnalyzedSwap> {
+    let mut metadata_resolver = resolvers.token_metadata.as_mut();
+    let instructions = tx.message.instructions();
+    // Inner instructions, when known, hold the swaps aggregators make through CPI
+    for ix in instructions.iter().chain(inner_instructions) {
+        let Some(program_id) = account_keys.get(ix.program_id_index as usize) else {
+            continue;
+        };
+        let Some(dex) = timing::time(Stage::Dispatch, || Dex::from_program_id(program_id, amms)) else {
+            continue;
+        };
+        let layout = amms.layout(dex);
+        let Some(mut swap) = timing::time(Stage::Decode(dex), || decode_swap(dex, &layout, account_keys, ix, signature)) else {
+            continue;
+        };
+        swap.transfers = timing::time(Stage::Transfers, || transfers::decode_sol_transfers(account_keys, instructions));
+        swap.priority_fee = compute_budget::decode_priority_fee(account_keys, instructions);
+        swap.lookup_tables = tx.message.address_table_lookups().unwrap_or_default().to_vec();
+        swap.transaction_size = bincode::serialized_size(tx).unwrap_or_default() as usize;
+        swap.version = tx.version();
+        swap.co_signatures = tx.signatures.iter().skip(1).copied().collect();
+        swap.front_run_protected = front_run_markers::has_front_run_marker(account_keys);
+        let token_events = timing::time(Stage::TokenTransfers, || {
+            token_transfers::decode_token_events(account_keys, instructions.iter().chain(inner_instructions))
+        });
+        let mut token_transfers = Vec::new();
+        for event in token_events {
+            match event {
+                TokenEvent::Transfer(transfer) => token_transfers.push(transfer),
+                TokenEvent::Mint(mint_event) => swap.mint_events.push(mint_event),
+                TokenEvent::Burn(burn_event) => swap.burn_events.push(burn_event),
+                TokenEvent::Authority(change) => swap.authority_changes.push(change),
+            }
+        }
+        apply_token_transfers(&mut swap, &token_transfers);
+        match swap.dex {
+            Dex::RaydiumV4 | Dex::RaydiumStable if swap.exact_out() => {
+                let log = ray_log::find_swap_base_out(log_messages, swap.amount_in, swap.min_amount_out);
+                swap.realized_amount_in = log.map(|log| log.deduct_in);
+                // A stable pool's price isn't its reserve ratio
+                if swap.dex == Dex::RaydiumV4 {
+                    swap.price_impact_bps = log.and_then(|log| log.price_impact_bps());
+                    swap.pool_reserves = log.and_then(|log| log.reserves_after());
+                }
+            }
+            Dex::RaydiumV4 => {
+                let log = ray_log::find_swap_base_in(log_messages, swap.amount_in, swap.min_amount_out);
+                swap.price_impact_bps = log.and_then(|log| log.price_impact_bps());
+                swap.pool_reserves = log.and_then(|log| log.reserves_after());
+            }
+            _ => {}
+        }
+
+        // 常见代币的小数位数是固定的，无需查询
+        swap.source_decimals = swap
+            .source_decimals
+            .or_else(|| known_decimals::known_decimals(&swap.source_mint?));
+        swap.destination_decimals = swap
+            .destination_decimals
+            .or_else(|| known_decimals::known_decimals(&swap.destination_mint?));
+
+        // 我们还需要获取代币账户的mint地址，这需要调用RPC来获取账户信息
+        // transferChecked 已经给出的 mint 和小数位数不再查询
+        if let Some(resolver) = metadata_resolver.as_deref_mut() {
+            timing::time(Stage::TokenMints, || {
+                swap.source_mint = swap
+                    .source_mint
+                    .or_else(|| resolver.token_account_mint(&swap.user_source_account?));
+                swap.destination_mint = swap
+                    .destination_mint
+                    .or_else(|| resolver.token_account_mint(&swap.user_destination_account?));
+                swap.source_decimals = swap
+                    .source_decimals
+                    .or_else(|| known_decimals::known_decimals(&swap.source_mint?))
+                    .or_else(|| resolver.mint_decimals(&swap.source_mint?));
+                swap.destination_decimals = swap
+                    .destination_decimals
+                    .or_else(|| known_decimals::known_decimals(&swap.destination_mint?))
+                    .or_else(|| resolver.mint_decimals(&swap.destination_mint?));
+            });
+        }
+        if let Some(resolver) = resolvers.pool_fees.as_mut() {
+            timing::time(Stage::PoolFees, || apply_pool_fee(&mut swap, account_keys, ix, resolver));
+        }
+        // 第一个账户是手续费支付者
+        if let (Some(resolver), Some(payer)) = (resolvers.payer_balances.as_mut(), account_keys.first()) {
+            swap.payer_sol_balance = timing::time(Stage::PayerBalance, || resolver.balance(payer));
+        }
+        if !QUIET.get().copied().unwrap_or_default() {
+            print_swap(&swap, metadata_resolver);
+        }
+        return Some(swap);
+    }
+    None
+}
+
+fn print_swap(swap: &AnalyzedSwap, metadata_resolver: Option<&mut TokenMetadataResolver>) {
+    println!("\nSwap Transaction Found! ({})", swap.dex.name());
+    println!("Signature: {} (Primary, Fee Payer)", swap.signature);
+    for (i, co_signature) in swap.co_signatures.iter().enumerate() {
+        println!("Co-signer Signature {}: {}", i + 2, co_signature);
+    }
+    println!("Curve: {}", swap.curve().name());
+
+    // 获取关键账户
+    println!("Source Token Account: {} (User's Token Account)", format_account(swap.user_source_account));
+    println!("Destination Token Account: {} (User's Token Account)", format_account(swap.user_destination_account));
+    println!("Pool Token Account 1: {} (AMM Token Account)", format_account(swap.pool_coin_account));
+    println!("Pool Token Account 2: {} (AMM Token Account)", format_account(swap.pool_pc_account));
+    if swap.curve() == Curve::Stable {
+        println!("Model Data Account: {} (Stable Curve)", format_account(swap.model_data_account));
+    }
+
+    // 解析指令数据
+    println!("\nSwap Amount Details:");
+    // 金额按 --amount-format 的格式显示
+    let (amount_in_label, amount_out_label) = if swap.exact_out() {
+        ("Maximum Amount In", "Amount Out")
+    } else {
+        ("Amount In", "Minimum Amount Out")
+    };
+    println!("{}: {}", amount_in_label, amounts::format_amount(Amount::Token {
+        amount: swap.amount_in,
+        decimals: swap.source_decimals,
+    }));
+    println!("{}: {}", amount_out_label, amounts::format_amount(Amount::Token {
+        amount: swap.min_amount_out,
+        decimals: swap.destination_decimals,
+    }));
+    println!("Priority Fee: {} micro-lamports/CU x {} CU{} = {}",
+             swap.priority_fee.compute_unit_price,
+             swap.priority_fee.compute_unit_limit,
+             if swap.priority_fee.compute_unit_limit_requested { "" } else { " (default limit)" },
+             amounts::format_amount(Amount::Sol(swap.priority_fee.total_lamports())));
+    println!("Transaction Size: {} of {} bytes", swap.transaction_size, PACKET_DATA_SIZE);
+    println!("Transaction Version: {}", swap.version_name());
+    if let Some(lamports) = swap.payer_sol_balance {
+        println!("Fee Payer Balance: {}", amounts::format_amount(Amount::Sol(lamports)));
+    }
+    if swap.front_run_protected {
+        println!("Front-run Protection: marker account present");
+    }
+    if swap.zero_min_out {
+        println!("Slippage Protection: none");
+    }
+    if let Some(bps) = swap.pool_fee_bps {
+        println!("Pool Fee: {} bps", bps);
+    }
+    if let Some(expected) = swap.expected_amount_out {
+        println!("Expected Amount Out: {} (at current reserves, net of pool fee)",
+                 amounts::format_amount(Amount::Token { amount: expected, decimals: swap.destination_decimals }));
+    }
+    if let Some(realized) = swap.realized_amount_out {
+        println!("Realized Amount Out: {}",
+                 amounts::format_amount(Amount::Token { amount: realized, decimals: swap.destination_decimals }));
+        if let Some(expected) = swap.expected_amount_out.filter(|&expected| expected > 0) {
+            println!("Slippage: {:.2}% of expected", (expected as f64 - realized as f64) / expected as f64 * 100.0);
+        }
+    }
+    if let Some(realized) = swap.realized_amount_in {
+        println!("Realized Amount In: {} ({:.2}% of maximum)",
+                 amounts::format_amount(Amount::Token { amount: realized, decimals: swap.source_decimals }),
+                 realized as f64 / swap.amount_in.max(1) as f64 * 100.0);
+    }
+    if let Some(price_impact_bps) = swap.price_impact_bps {
+        println!("Price Impact: {} bps", price_impact_bps);
+    }
+
+    if !swap.lookup_tables.is_empty() {
+        println!("\nLookup Tables:");
+        for lookup in &swap.lookup_tables {
+            println!("{}: writable indexes {:?}, readonly indexes {:?}",
+                     lookup.account_key,
+                     lookup.writable_indexes,
+                     lookup.readonly_indexes);
+        }
+    }
+
+    if !swap.transfers.is_empty() {
+        println!("\nSOL Transfers:");
+        for transfer in &swap.transfers {
+            println!("{} -> {}: {}",
+                     transfer.source,
+                     transfer.destination,
+                     amounts::format_amount(Amount::Sol(transfer.lamports)));
+        }
+    }
+
+    if !swap.mint_events.is_empty() {
+        println!("\nToken Mints:");
+        for event in &swap.mint_events {
+            println!("{} -> {}: {}",
+                     event.mint,
+                     event.destination,
+                     amounts::format_amount(Amount::Token { amount: event.amount, decimals: event.decimals }));
+        }
+    }
+
+    if !swap.burn_events.is_empty() {
+        println!("\nToken Burns:");
+        for event in &swap.burn_events {
+            println!("{} from {}: {}",
+                     event.mint,
+                     event.account,
+                     amounts::format_amount(Amount::Token { amount: event.amount, decimals: event.decimals }));
+        }
+    }
+
+    if !swap.authority_changes.is_empty() {
+        println!("\nAuthority Changes:");
+        for change in &swap.authority_changes {
+            // 铸币权或冻结权变更是高风险信号，单独标出
+            println!("{}{} {}: {}",
+                     if change.is_mint_or_freeze() { "[!] " } else { "" },
+                     change.account,
+                     change.authority_type.name(),
+                     change.new_authority.map_or("revoked".to_string(), |authority| authority.to_string()));
+        }
+    }
+
+    if let Some(resolver) = metadata_resolver {
+        if let Some(mint) = &swap.source_mint {
+            println!("Source Mint: {}", resolver.describe_mint(mint));
+        }
+        if let Some(mint) = &swap.destination_mint {
+            println!("Destination Mint: {}", resolver.describe_mint(mint));
+        }
+    }
+
+    //println current time use std lib
+    let system_time = Utc::now();
+    println!("系统时间: {}", system_time.format("%Y年%m月%d日 %H时%M分%S秒"));
+}
+
+/// Returns the account at `position` in `ix`, or `None` if the instruction has fewer
+/// accounts or the index points at a lookup-table address that was not resolved.
+fn instruction_account<'a>(
+    account_keys: &'a [Pubkey],
+    ix: &CompiledInstruction,
+    position: usize,
+) -> Option<&'a Pubkey> {
+    account_keys.get(*ix.accounts.get(position)? as usize)
+}
+
+fn format_account(account: Option<Pubkey>) -> String {
+    account.map_or_else(|| "<unresolved>".to_string(), |account| account.to_string())
+}
+
+/// Makes every matched swap also print the `Debug` form of its transaction and of the
+/// [`AnalyzedSwap`] (`--debug-struct`), for reproducing parsing bugs. Only the first
+/// call has an effect.
+pub fn set_debug_structs(enabled: bool) {
+    let _ = DEBUG_STRUCTS.set(enabled);
+}
+
+/// Stops the analyzer printing each transaction and swap it decodes, so `bench`
+/// measures analysis rather than the console. Only the first call has an effect.
+pub fn set_quiet(quiet: bool) {
+    let _ = QUIET.set(quiet);
+}
+
+/// Skips transactions with more than `max` accounts, static and loaded, before
+/// their accounts are walked (`--max-accounts`), bounding the work a pathological
+/// transaction can cause. Defaults to [`DEFAULT_MAX_ACCOUNTS`]. Only the first
+/// call has an effect.
+pub fn set_max_accounts(max: usize) {
+    let _ = MAX_ACCOUNTS.set(max);
+}
+
+fn check_account_count(count: usize) -> Result<(), AnalyzeError> {
+    if count > MAX_ACCOUNTS.get().copied().unwrap_or(DEFAULT_MAX_ACCOUNTS) {
+        return Err(AnalyzeError::TooManyAccounts);
+    }
+    Ok(())
+}
+
+/// Analyzes a serialized `VersionedTransaction`, returning the first swap it contains, if any.
+pub fn analyze_transaction(
+    data: &[u8],
+    amms: &AmmRegistry,
+    resolvers: &mut Resolvers,
+) -> Result<Option<AnalyzedSwap>, AnalyzeError> {
+    let tx: VersionedTransaction = timing::time(Stage::Deserialize, || bincode::deserialize(data))
+        .map_err(|_| AnalyzeError::Deserialize)?;
+    analyze_versioned_transaction(&tx, None, &[], &[], amms, resolvers)
+}
+
+/// Analyzes a deserialized transaction. `loaded_addresses`, when known, e.g. from the
+/// RPC status meta of a confirmed transaction, takes precedence over the lookup table
+/// resolver. `inner_instructions`, also only known once confirmed, are searched for
+/// the swap's token transfers, and `log_messages` for its `ray_log`.
+pub fn analyze_versioned_transaction(
+    tx: &VersionedTransaction,
+    loaded_addresses: Option<LoadedAddresses>,
+    inner_instructions: &[CompiledInstruction],
+    log_messages: &[String],
+    amms: &AmmRegistry,
+    resolvers: &mut Resolvers,
+) -> Result<Option<AnalyzedSwap>, AnalyzeError> {
+    // Checked ahead of sanitizing, which walks the accounts too
+    check_account_count(tx.message.static_account_keys().len())?;
+    timing::time(Stage::Sanitize, || tx.sanitize()).map_err(|_| AnalyzeError::Sanitize)?;
+
+    let signature = tx.signatures.first().ok_or(AnalyzeError::Sanitize)?;
+    if !QUIET.get().copied().unwrap_or_default() {
+        println!("Transaction signature: {}", signature);
+    }
+
+    // V0 instructions may index into lookup tables, which only resolve with RPC
+    let loaded_addresses = loaded_addresses.or_else(|| {
+        let resolver = resolvers.lookup_tables.as_mut()?;
+        timing::time(Stage::LookupTables, || resolver.resolve(&tx.message))
+    });
+    let account_keys =
+        address_lookup::resolve_account_keys(&tx.message, loaded_addresses.as_ref());
+    check_account_count(account_keys.len())?;
+
+    // 解析内部指令
+    let mut swap = analyze_swap_accounts_and_inner_instructions(&account_keys,
+                                                                tx,
+                                                                inner_instructions,
+                                                                log_messages,
+                                                                signature,
+                                                                amms,
+                                                                resolvers);
+    // 聚合器通过 CPI 调用 DEX 时，swap 只出现在确认后的内部指令里
+    if swap.is_none() && inner_instructions.is_empty() {
+        let confirmed = resolvers
+            .cpi
+            .as_ref()
+            .filter(|resolver| resolver.invokes_candidate(&account_keys, tx.message.instructions()))
+            .and_then(|resolver| timing::time(Stage::Cpi, || resolver.fetch(signature)));
+        if let Some(confirmed) = confirmed {
+            let loaded_addresses = confirmed.loaded_addresses.as_ref().or(loaded_addresses.as_ref());
+            let account_keys = address_lookup::resolve_account_keys(&tx.message, loaded_addresses);
+            swap = analyze_swap_accounts_and_inner_instructions(&account_keys,
+                                                                tx,
+                                                                &confirmed.inner_instructions,
+                                                                &confirmed.log_messages,
+                                                                signature,
+                                                                amms,
+                                                                resolvers);
+        }
+    }
+    if let Some(swap) = swap.as_ref().filter(|_| DEBUG_STRUCTS.get().copied().unwrap_or_default()) {
+        println!("\nTransaction: {:#?}", tx);
+        println!("Loaded Addresses: {:#?}", loaded_addresses);
+        println!("AnalyzedSwap: {:#?}", swap);
+    }
+    Ok(swap)
+}
+
+fn analyze_message_accounts(
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+    signature: &Signature,
+) -> Option<()> {
+    println!("\nAccount addresses:");
+    for (i, key) in account_keys.iter().enumerate() {
+        println!("Account {}: {}", i, key);
+    }
+
+    // Raydium 和其他重要合约地址
+    let raydium_v4 = program_ids::builtin_program_id(RAYDIUM_V4_PROGRAM_ID);
+    let raydium_swap = program_ids::builtin_program_id(RAYDIUM_SWAP_PROGRAM);
+    let token_program = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    for (i, ix) in instructions.iter().enumerate() {
+        let Some(program_id) = account_keys.get(ix.program_id_index as usize) else {
+            continue;
+        };
+        println!("\nInstruction {} Program ID: {}", i, program_id);
+
+        if *program_id == raydium_v4 || *program_id == raydium_swap {
+            println!("Found Raydium transaction! Signature: {}", signature);
+            println!("\nSwap Account Details:");
+
+            // 解析关键账户
+            for (idx, account_idx) in ix.accounts.iter().enumerate() {
+                let Some(account) = account_keys.get(*account_idx as usize) else {
+                    continue;
+                };
+                match idx {
+                    0 => println!("Token Program: {}", account),
+                    1 => println!("AMM Account: {}", account),
+                    2 => println!("AMM Authority: {}", account),
+                    5 => println!("Pool Token Account 1: {}", account),
+                    6 => println!("Pool Token Account 2: {}", account),
+                    15 => println!("User Source Token Account: {}", account),
+                    16 => println!("User Destination Token Account: {}", account),
+                    17 => println!("User Authority: {}", account),
+                    _ => println!("Account {}: {}", idx, account),
+                }
+            }
+
+            // 打印完整的指令数据（十六进制）
+            println!("\nInstruction data (hex):");
+            for (i, chunk) in ix.data.chunks(32).enumerate() {
+                let hex_string: String = chunk.iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                println!("{:04x}: {}", i * 32, hex_string);
+            }
+
+            // 解析 Raydium 指令数据
+            if ix.data.len() >= 17 {
+                let discriminator = ix.data[0];
+                let amount_in = {
+                    let mut amount_bytes = [0u8; 8];
+                    amount_bytes.copy_from_slice(&ix.data[1..9]);
+                    u64::from_le_bytes(amount_bytes)
+                };
+
+                let min_amount_out = {
+                    let mut amount_bytes = [0u8; 8];
+                    amount_bytes.copy_from_slice(&ix.data[9..17]);
+                    u64::from_le_bytes(amount_bytes)
+                };
+
+                println!("\nParsed Swap Details:");
+                println!("Discriminator: {}", discriminator);
+                println!("Amount In: {}", amounts::format_amount(Amount::Token { amount: amount_in, decimals: None }));
+                println!("Minimum Amount Out: {}", amounts::format_amount(Amount::Token {
+                    amount: min_amount_out,
+                    decimals: None,
+                }));
+            }
+
+            return Some(());
+        }
+    }
+    None
+}
+
+fn parse_raydium_instruction(data: &[u8]) -> Option<(u64, Pubkey)> {
+    let amount = u64::from_le_bytes(data.get(0..8)?.try_into().ok()?);
+    let token_mint = Pubkey::try_from(data.get(8..40)?).ok()?;
+
+    Some((amount, token_mint))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{v0, Message, MessageHeader, VersionedMessage};
+    use crate::amounts::AmountFormat;
+    use crate::cpi;
+    use crate::token_transfers::AuthorityType;
+
+    fn swap_message(program_id: &str, account_keys: Vec<Pubkey>, accounts: Vec<u8>) -> Message {
+        let mut data = vec![9u8];
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.extend_from_slice(&1u64.to_le_bytes());
+        let program_id_index = account_keys.len() as u8;
+        Message {
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys: account_keys
+                .into_iter()
+                .chain([program_ids::builtin_program_id(program_id)])
+                .collect(),
+            recent_blockhash: Hash::default(),
+            instructions: vec![CompiledInstruction::new_from_raw_parts(
+                program_id_index,
+                data,
+                accounts,
+            )],
+        }
+    }
+
+    fn serialize(message: Message) -> Vec<u8> {
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        bincode::serialize(&tx).unwrap()
+    }
+
+    fn raydium_swap_transaction(accounts: Vec<u8>) -> Vec<u8> {
+        serialize(swap_message(
+            RAYDIUM_V4_PROGRAM_ID,
+            vec![Pubkey::new_unique(), Pubkey::new_unique()],
+            accounts,
+        ))
+    }
+
+    #[test]
+    fn test_analyze_transaction_short_account_list() {
+        // Fewer than the 18 accounts a swap carries must not panic, nor decode any accounts
+        let data = raydium_swap_transaction(vec![0, 1, 0, 0, 0, 1, 1]);
+        let short = short_account_lists();
+        let swap = analyze_transaction(&data, &AmmRegistry::default(), &mut Resolvers::default()).unwrap().unwrap();
+        assert!(short_account_lists() > short);
+        assert_eq!(swap.dex, Dex::RaydiumV4);
+        assert_eq!(swap.curve(), Curve::ConstantProduct);
+        assert_eq!(swap.amount_in, 1_000_000);
+        assert_eq!(swap.pool_coin_account, None);
+        assert_eq!(swap.user_source_account, None);
+    }
+
+    #[test]
+    fn test_analyze_transaction_zero_min_out() {
+        let analyze = |discriminator: u8, first: u64, second: u64| {
+            let mut message = swap_message(RAYDIUM_V4_PROGRAM_ID, vec![Pubkey::new_unique()], vec![0]);
+            message.instructions[0].data = [&[discriminator][..], &first.to_le_bytes(), &second.to_le_bytes()].concat();
+            analyze_transaction(&serialize(message), &AmmRegistry::default(), &mut Resolvers::default())
+                .unwrap()
+                .unwrap()
+        };
+        // SwapBaseIn(amount_in, minimum_amount_out)
+        assert!(analyze(9, 1_000_000, 0).zero_min_out);
+        assert!(!analyze(9, 1_000_000, 1).zero_min_out);
+        // SwapBaseOut(max_amount_in, amount_out) is bounded by its maximum in
+        let base_out = analyze(RAYDIUM_SWAP_BASE_OUT, u64::MAX, 500);
+        assert!(base_out.exact_out() && base_out.zero_min_out);
+        assert!(!analyze(RAYDIUM_SWAP_BASE_OUT, 1_000_000, 0).zero_min_out);
+    }
+
+    #[test]
+    fn test_analyze_transaction_data_len() {
+        // A V4 deposit(max_coin_amount, max_pc_amount, base_side) is long enough to
+        // decode as a swap
+        let mut message = swap_message(RAYDIUM_V4_PROGRAM_ID, vec![Pubkey::new_unique()], vec![0]);
+        message.instructions[0].data = [&[3u8][..], &5u64.to_le_bytes(), &6u64.to_le_bytes(), &0u64.to_le_bytes()].concat();
+        let rejects = data_len_rejects();
+        assert_eq!(analyze_transaction(&serialize(message), &AmmRegistry::default(), &mut Resolvers::default()), Ok(None));
+        assert!(data_len_rejects() > rejects);
+        assert_eq!(Dex::RaydiumRoute.data_len(), 17..=17);
+    }
+
+    #[test]
+    fn test_analyze_transaction_raydium_stable() {
+        // SwapBaseIn on the stable AMM: 18 accounts with the model data account at 6
+        let keys: Vec<Pubkey> = (0..18).map(|_| Pubkey::new_unique()).collect();
+        let mut message = swap_message(RAYDIUM_STABLE_PROGRAM_ID, keys.clone(), (0..18).collect());
+        message.instructions[0].data = [
+            &[9u8][..],
+            &2_500_000u64.to_le_bytes(),
+            &2_490_000u64.to_le_bytes(),
+        ]
+        .concat();
+        message.header.num_readonly_unsigned_accounts = 2;
+        let data = serialize(message);
+
+        let swap = analyze_transaction(&data, &AmmRegistry::default(), &mut Resolvers::default()).unwrap().unwrap();
+        assert_eq!(
+            swap,
+            AnalyzedSwap {
+                signature: Signature::default(),
+                co_signatures: vec![],
+                dex: Dex::RaydiumStable,
+                discriminator: 9,
+                amount_in: 2_500_000,
+                min_amount_out: 2_490_000,
+                pool_coin_account: Some(keys[4]),
+                pool_pc_account: Some(keys[5]),
+                model_data_account: Some(keys[6]),
+                user_source_account: Some(keys[15]),
+                user_destination_account: Some(keys[16]),
+                user_owner: Some(keys[17]),
+                source_mint: None,
+                destination_mint: None,
+                source_decimals: None,
+                destination_decimals: None,
+                transfers: vec![],
+                priority_fee: PriorityFee {
+                    compute_unit_price: 0,
+                    compute_unit_limit: 200_000,
+                    compute_unit_limit_requested: false,
+                },
+                compute_units_consumed: None,
+                lookup_tables: vec![],
+                transaction_size: data.len(),
+                version: TransactionVersion::LEGACY,
+                front_run_protected: false,
+                zero_min_out: false,
+                pool_fee_bps: None,
+                expected_amount_out: None,
+                realized_amount_out: None,
+                realized_amount_in: None,
+                mint_events: vec![],
+                burn_events: vec![],
+                authority_changes: vec![],
+                payer_sol_balance: None,
+                observers: None,
+                price_impact_bps: None,
+                pool_reserves: None,
+            }
+        );
+        assert_eq!(swap.curve(), Curve::Stable);
+
+        // The same accounts on V4 map to different pool accounts and no model data
+        let v4 = serialize(swap_message(RAYDIUM_V4_PROGRAM_ID, keys.clone(), (0..18).collect()));
+        let swap = analyze_transaction(&v4, &AmmRegistry::default(), &mut Resolvers::default()).unwrap().unwrap();
+        assert_eq!(swap.curve(), Curve::ConstantProduct);
+        assert_eq!(swap.pool_coin_account, Some(keys[5]));
+        assert_eq!(swap.model_data_account, None);
+    }
+
+    #[test]
+    fn test_analyze_transaction_registry_amm() {
+        let keys: Vec<Pubkey> = (0..9).map(|_| Pubkey::new_unique()).collect();
+        let mut message = swap_message("SSwpkEEcbUqx4vtoEByFjSkhKdCT862DNVb52nZg1UZ", keys.clone(), (0..9).collect());
+        message.instructions[0].data = [&[1u8][..], &300u64.to_le_bytes(), &299u64.to_le_bytes()].concat();
+        message.header.num_readonly_unsigned_accounts = 2;
+
+        let swap = analyze_transaction(&serialize(message), &AmmRegistry::default(), &mut Resolvers::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(swap.dex.name(), "saber");
+        assert_eq!(swap.curve(), Curve::Stable);
+        assert_eq!((swap.amount_in, swap.min_amount_out), (300, 299));
+        assert_eq!((swap.pool_coin_account, swap.pool_pc_account), (Some(keys[4]), Some(keys[5])));
+        assert_eq!(swap.user_source_account, None);
+    }
+
+    #[test]
+    fn test_analyze_transaction_ignores_non_swap_instruction() {
+        let mut message = swap_message(RAYDIUM_STABLE_PROGRAM_ID, vec![Pubkey::new_unique()], vec![0]);
+        message.instructions[0].data = vec![4, 1, 2, 3];
+        assert_eq!(analyze_transaction(&serialize(message), &AmmRegistry::default(), &mut Resolvers::default()), Ok(None));
+    }
+
+    #[test]
+    fn test_analyze_transaction_out_of_range_index() {
+        let data = raydium_swap_transaction(vec![0; 15].into_iter().chain([1, 200]).collect());
+        assert_eq!(
+            analyze_transaction(&data, &AmmRegistry::default(), &mut Resolvers::default()),
+            Err(AnalyzeError::Sanitize)
+        );
+    }
+
+    #[test]
+    fn test_analyze_transaction_too_many_accounts() {
+        let keys = |count| (0..count).map(|_| Pubkey::new_unique()).collect();
+        // With the program id, one over the default
+        let data = serialize(swap_message(RAYDIUM_V4_PROGRAM_ID, keys(DEFAULT_MAX_ACCOUNTS), (0..18).collect()));
+        assert_eq!(
+            analyze_transaction(&data, &AmmRegistry::default(), &mut Resolvers::default()),
+            Err(AnalyzeError::TooManyAccounts)
+        );
+        let data = serialize(swap_message(RAYDIUM_V4_PROGRAM_ID, keys(DEFAULT_MAX_ACCOUNTS - 1), (0..18).collect()));
+        assert!(analyze_transaction(&data, &AmmRegistry::default(), &mut Resolvers::default()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_analyze_transaction_lookup_tables() {
+        // A V0 swap whose accounts are all static still reports the tables it loads
+        let keys: Vec<Pubkey> = (0..18).map(|_| Pubkey::new_unique()).collect();
+        let legacy = swap_message(RAYDIUM_V4_PROGRAM_ID, keys, (0..18).collect());
+        let lookup = MessageAddressTableLookup {
+            account_key: Pubkey::new_unique(),
+            writable_indexes: vec![4, 1],
+            readonly_indexes: vec![9],
+        };
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::V0(v0::Message {
+                header: legacy.header,
+                account_keys: legacy.account_keys,
+                recent_blockhash: legacy.recent_blockhash,
+                instructions: legacy.instructions,
+                address_table_lookups: vec![lookup.clone()],
+            }),
+        };
+
+        let swap = analyze_transaction(&bincode::serialize(&tx).unwrap(), &AmmRegistry::default(), &mut Resolvers::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(swap.lookup_tables, vec![lookup]);
+        assert_eq!((swap.version.clone(), swap.version_name()), (TransactionVersion::Number(0), "0".to_string()));
+        let value = crate::json_output::swap_json(0, &swap);
+        assert_eq!(value["lookup_tables"][0]["writable_indexes"], serde_json::json!([4, 1]));
+        assert_eq!(value["version"], 0);
+    }
+
+    #[test]
+    fn test_analyze_transaction_route() {
+        let keys: Vec<Pubkey> = (0..18).map(|_| Pubkey::new_unique()).collect();
+        let mut message = swap_message(RAYDIUM_SWAP_PROGRAM, keys.clone(), (0..18).collect());
+        let swap = analyze_transaction(&serialize(message.clone()), &AmmRegistry::default(), &mut Resolvers::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(swap.dex, Dex::RaydiumRoute);
+        assert_eq!((swap.amount_in, swap.min_amount_out), (1_000_000, 1));
+        assert_eq!((swap.source_mint, swap.destination_mint), (None, None));
+        assert_eq!(swap.user_source_account, Some(keys[15]));
+
+        // Router instructions longer than a swap aren't decoded as one
+        message.instructions[0].data.extend_from_slice(&[0; 64]);
+        let swap = analyze_transaction(&serialize(message), &AmmRegistry::default(), &mut Resolvers::default()).unwrap();
+        assert!(swap.is_none());
+    }
+
+    #[test]
+    fn test_analyze_transaction_pump_amm() {
+        let keys: Vec<Pubkey> = (0..17).map(|_| Pubkey::new_unique()).collect();
+        let mut message = swap_message(PUMP_AMM_PROGRAM_ID, keys.clone(), (0..17).collect());
+        let data = |discriminator: [u8; 8]| [&discriminator[..], &500u64.to_le_bytes(), &7u64.to_le_bytes()].concat();
+
+        message.instructions[0].data = data(PUMP_AMM_SELL_DISCRIMINATOR);
+        let sell = analyze_transaction(&serialize(message.clone()), &AmmRegistry::default(), &mut Resolvers::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(sell.dex, Dex::PumpAmm);
+        assert!(!sell.exact_out());
+        assert_eq!((sell.amount_in, sell.min_amount_out), (500, 7));
+        assert_eq!((sell.pool_coin_account, sell.pool_pc_account), (Some(keys[7]), Some(keys[8])));
+        assert_eq!((sell.user_source_account, sell.user_destination_account), (Some(keys[5]), Some(keys[6])));
+        assert_eq!((sell.source_mint, sell.destination_mint), (Some(keys[3]), Some(keys[4])));
+        assert_eq!(sell.user_owner, Some(keys[1]));
+
+        // A buy of 500 base for at most 7 quote
+        message.instructions[0].data = data(PUMP_AMM_BUY_DISCRIMINATOR);
+        let buy = analyze_transaction(&serialize(message.clone()), &AmmRegistry::default(), &mut Resolvers::default())
+            .unwrap()
+            .unwrap();
+        assert!(buy.exact_out());
+        assert_eq!((buy.amount_in, buy.min_amount_out), (7, 500));
+        assert_eq!((buy.user_source_account, buy.user_destination_account), (Some(keys[6]), Some(keys[5])));
+        assert_eq!((buy.source_mint, buy.destination_mint), (Some(keys[4]), Some(keys[3])));
+
+        // Other Pump AMM instructions, e.g. deposits, are not swaps
+        message.instructions[0].data = data([0; 8]);
+        assert_eq!(analyze_transaction(&serialize(message), &AmmRegistry::default(), &mut Resolvers::default()), Ok(None));
+    }
+
+    #[test]
+    fn test_analyze_transaction_raydium_launchpad() {
+        let keys: Vec<Pubkey> = (0..15).map(|_| Pubkey::new_unique()).collect();
+        let mut message = swap_message(RAYDIUM_LAUNCHPAD_PROGRAM_ID, keys.clone(), (0..15).collect());
+        let data = |discriminator: [u8; 8]| {
+            [&discriminator[..], &500u64.to_le_bytes(), &7u64.to_le_bytes(), &0u64.to_le_bytes()].concat()
+        };
+
+        // A buy spending 500 quote for at least 7 base
+        message.instructions[0].data = data(LAUNCHPAD_BUY_EXACT_IN_DISCRIMINATOR);
+        let buy = analyze_transaction(&serialize(message.clone()), &AmmRegistry::default(), &mut Resolvers::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(buy.dex, Dex::RaydiumLaunchpad);
+        assert!(!buy.exact_out());
+        assert_eq!((buy.amount_in, buy.min_amount_out), (500, 7));
+        assert_eq!((buy.pool_coin_account, buy.pool_pc_account), (Some(keys[7]), Some(keys[8])));
+        assert_eq!((buy.user_source_account, buy.user_destination_account), (Some(keys[6]), Some(keys[5])));
+        assert_eq!((buy.source_mint, buy.destination_mint), (Some(keys[10]), Some(keys[9])));
+        assert_eq!(buy.user_owner, Some(keys[0]));
+
+        // A sell of at most 7 base for exactly 500 quote
+        message.instructions[0].data = data(LAUNCHPAD_SELL_EXACT_OUT_DISCRIMINATOR);
+        let sell = analyze_transaction(&serialize(message.clone()), &AmmRegistry::default(), &mut Resolvers::default())
+            .unwrap()
+            .unwrap();
+        assert!(sell.exact_out());
+        assert_eq!((sell.amount_in, sell.min_amount_out), (7, 500));
+        assert_eq!((sell.user_source_account, sell.user_destination_account), (Some(keys[5]), Some(keys[6])));
+        assert_eq!((sell.source_mint, sell.destination_mint), (Some(keys[9]), Some(keys[10])));
+
+        // Other LaunchLab instructions, e.g. creating a pool, are not swaps
+        message.instructions[0].data = data([0; 8]);
+        assert_eq!(analyze_transaction(&serialize(message), &AmmRegistry::default(), &mut Resolvers::default()), Ok(None));
+    }
+
+    #[test]
+    fn test_analyze_versioned_transaction_inner_transfers() {
+        // 18 swap accounts, then the source mint and the token program
+        let keys: Vec<Pubkey> = (0..18)
+            .map(|_| Pubkey::new_unique())
+            .chain([Pubkey::new_unique(), program_ids::builtin_program_id(token_transfers::TOKEN_PROGRAM_ID)])
+            .collect();
+        let message = swap_message(RAYDIUM_V4_PROGRAM_ID, keys.clone(), (0..18).collect());
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        let amount = 1_000_000u64.to_le_bytes();
+        let inner_instructions = vec![
+            // transferChecked of 6 decimals from the user's source into the pool
+            CompiledInstruction::new_from_raw_parts(19, [&[12u8][..], &amount, &[6]].concat(), vec![15, 18, 5, 17]),
+            // Plain transfer from the pool to the user's destination
+            CompiledInstruction::new_from_raw_parts(19, [&[3u8][..], &amount].concat(), vec![6, 16, 4]),
+            // mintTo of the source mint into the pool
+            CompiledInstruction::new_from_raw_parts(19, [&[7u8][..], &amount].concat(), vec![18, 5, 4]),
+            // setAuthority revoking the source mint's mint authority
+            CompiledInstruction::new_from_raw_parts(19, vec![6, 0, 0], vec![18, 4]),
+        ];
+
+        let swap = analyze_versioned_transaction(&tx, None, &inner_instructions, &[], &AmmRegistry::default(), &mut Resolvers::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!((swap.source_mint, swap.source_decimals), (Some(keys[18]), Some(6)));
+        assert_eq!((swap.destination_mint, swap.destination_decimals), (None, None));
+        assert_eq!(swap.realized_amount_out, Some(1_000_000));
+        assert_eq!(
+            swap.mint_events,
+            vec![MintEvent {
+                mint: keys[18],
+                destination: keys[5],
+                amount: 1_000_000,
+                decimals: None,
+            }]
+        );
+        assert_eq!(
+            swap.authority_changes,
+            vec![AuthorityChange {
+                account: keys[18],
+                authority_type: AuthorityType::MintTokens,
+                new_authority: None,
+            }]
+        );
+        assert_eq!(
+            AmountFormat::Ui.format(Amount::Token { amount: swap.amount_in, decimals: swap.source_decimals }),
+            "1 (raw value: 1000000)"
+        );
+    }
+
+    #[test]
+    fn test_analyze_versioned_transaction_ray_log() {
+        let mut message = swap_message(RAYDIUM_V4_PROGRAM_ID, vec![Pubkey::new_unique()], vec![0]);
+        message.instructions[0].data = [&[RAYDIUM_SWAP_BASE_OUT][..], &1_000_000u64.to_le_bytes(), &500u64.to_le_bytes()].concat();
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message.clone()),
+        };
+        let analyze = |log_messages: &[String]| {
+            analyze_versioned_transaction(&tx, None, &[], log_messages, &AmmRegistry::default(), &mut Resolvers::default())
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(analyze(&[]).realized_amount_in, None);
+        let logs = [ray_log::swap_base_out_log(2_000_000, 500, 1), ray_log::swap_base_out_log(1_000_000, 500, 940_000)];
+        assert_eq!(analyze(&logs).realized_amount_in, Some(940_000));
+        assert_eq!(analyze(&logs).price_impact_bps, Some(5));
+
+        message.instructions[0].data = [&[9u8][..], &200_000_000u64.to_le_bytes(), &0u64.to_le_bytes()].concat();
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        let swap = analyze_versioned_transaction(&tx, None, &[], &[ray_log::swap_base_in_log(200_000_000, 0, 90_000_000)], &AmmRegistry::default(), &mut Resolvers::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!((swap.realized_amount_in, swap.price_impact_bps), (None, Some(1_727)));
+        assert_eq!(swap.pool_reserves.map(|reserves| (reserves.coin, reserves.pc)), Some((910_000_000, 2_200_000_000)));
+    }
+
+    #[test]
+    fn test_compute_unit_utilization() {
+        let (_, mut swap) = crate::selftest::swap_base_in_transaction();
+        assert_eq!(swap.compute_unit_utilization(), None);
+        swap.priority_fee.compute_unit_limit = 200_000;
+        swap.compute_units_consumed = Some(50_000);
+        assert_eq!(swap.compute_unit_utilization(), Some(0.25));
+        swap.priority_fee.compute_unit_limit = 0;
+        assert_eq!(swap.compute_unit_utilization(), None);
+    }
+
+    #[test]
+    fn test_analyze_versioned_transaction_co_signers() {
+        let mut message = swap_message(RAYDIUM_V4_PROGRAM_ID, vec![Pubkey::new_unique(), Pubkey::new_unique()], vec![0]);
+        message.header.num_required_signatures = 2;
+        let signatures = vec![Signature::from([1; 64]), Signature::from([2; 64])];
+        let tx = VersionedTransaction {
+            signatures: signatures.clone(),
+            message: VersionedMessage::Legacy(message),
+        };
+        let swap = analyze_versioned_transaction(&tx, None, &[], &[], &AmmRegistry::default(), &mut Resolvers::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(swap.signature, signatures[0]);
+        assert_eq!(swap.co_signatures, signatures[1..]);
+    }
+
+    #[test]
+    fn test_analyze_versioned_transaction_cpi_swap() {
+        // An aggregator instruction at the top level, the V4 swap among its inner instructions
+        let raydium = program_ids::builtin_program_id(RAYDIUM_V4_PROGRAM_ID);
+        let keys: Vec<Pubkey> = (0..18).map(|_| Pubkey::new_unique()).chain([raydium]).collect();
+        let message = swap_message(cpi::JUPITER_V6_PROGRAM_ID, keys.clone(), (0..18).collect());
+        let inner_instructions = vec![CompiledInstruction::new_from_raw_parts(
+            18,
+            message.instructions[0].data.clone(),
+            (0..18).collect(),
+        )];
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        let analyze = |inner_instructions: &[CompiledInstruction]| {
+            analyze_versioned_transaction(&tx, None, inner_instructions, &[], &AmmRegistry::default(), &mut Resolvers::default())
+                .unwrap()
+        };
+        assert_eq!(analyze(&[]), None);
+        let swap = analyze(&inner_instructions).unwrap();
+        assert_eq!(swap.dex, Dex::RaydiumV4);
+        assert_eq!(swap.pool_coin_account, Some(keys[5]));
+        assert_eq!(swap.user_owner, Some(keys[17]));
+    }
+
+    #[test]
+    fn test_analyze_transaction_fuzz() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let amms = AmmRegistry::default();
+        let valid = raydium_swap_transaction((0..17).map(|i| i % 3).collect());
+
+        for _ in 0..10_000 {
+            // Random bytes almost never deserialize, so also mutate a valid swap
+            let len = rng.gen_range(0..512);
+            let random: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let _ = analyze_transaction(&random, &amms, &mut Resolvers::default());
+
+            let mut mutated = valid.clone();
+            for _ in 0..rng.gen_range(1..8) {
+                let index = rng.gen_range(0..mutated.len());
+                mutated[index] = rng.gen();
+            }
+            mutated.truncate(rng.gen_range(0..=mutated.len()));
+            let _ = analyze_transaction(&mutated, &amms, &mut Resolvers::default());
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Tracks how many times each transaction signature arrives within a short window.
+//!
+//! The same transaction regularly reaches the validator more than once, e.g. sent
+//! directly to the TPU and forwarded by another leader. Repeated arrivals within the
+//! window are counted as multi-path arrivals rather than treated as distinct trades,
+//! which makes path and latency differences visible. This is reporting only and is
+//! independent of any deduplication of the output.
+//!
+//! The window can key signatures by all 64 bytes ([`SignatureKey::Full`], the
+//! default) or by their first 8 ([`SignatureKey::Truncated`]), which cuts an entry
+//! from about 140 bytes to about 32 and hashes faster. Signature bytes are
+//! uniformly distributed, so among `n` signatures in the window two collide on 8
+//! bytes with probability about `n^2 / 2^65`: one in 37 million for a million
+//! signatures. A collision counts an unrelated transaction as a repeat arrival, and
+//! a tracker deduplicating the WebSocket feed drops it.
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::str::FromStr;
+use solana_sdk::short_vec::decode_shortu16_len;
+use solana_sdk::signature::{Signature, SIGNATURE_BYTES};
+
+pub const DEFAULT_ARRIVAL_WINDOW_MS: u64 = 2_000;
+
+// Bounds memory if the feed outpaces the window
+const MAX_TRACKED_SIGNATURES: usize = 1_000_000;
+
+/// Returns the first signature of a serialized transaction without deserializing the rest.
+pub fn first_signature(packet: &[u8]) -> Option<Signature> {
+    let (count, offset) = decode_shortu16_len(packet).ok()?;
+    if count == 0 {
+        return None;
+    }
+    Signature::try_from(packet.get(offset..offset + SIGNATURE_BYTES)?).ok()
+}
+
+/// What a window keys signatures by, see the module docs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SignatureKey {
+    #[default]
+    Full,
+    Truncated,
+}
+
+impl SignatureKey {
+    pub const NAMES: [&'static str; 2] = ["full", "truncated"];
+}
+
+impl FromStr for SignatureKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(SignatureKey::Full),
+            "truncated" => Ok(SignatureKey::Truncated),
+            _ => Err(format!("unknown signature key {:?}, expected one of {:?}", s, Self::NAMES)),
+        }
+    }
+}
+
+trait TrackedKey: Copy + Eq + Hash {
+    fn from_signature(signature: &Signature) -> Self;
+}
+
+impl TrackedKey for Signature {
+    fn from_signature(signature: &Signature) -> Self {
+        *signature
+    }
+}
+
+impl TrackedKey for u64 {
+    fn from_signature(signature: &Signature) -> Self {
+        u64::from_le_bytes(signature.as_ref()[..8].try_into().unwrap())
+    }
+}
+
+struct Window<K> {
+    counts: HashMap<K, u32>,
+    // (first arrival, key) in arrival order, for expiry
+    arrivals: VecDeque<(u64, K)>,
+}
+
+impl<K: TrackedKey> Window<K> {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            counts: HashMap::with_capacity(capacity),
+            arrivals: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn record(&mut self, signature: &Signature, now_us: u64, window_us: u64) -> u32 {
+        while let Some((first_us, expired)) = self.arrivals.front() {
+            if now_us.saturating_sub(*first_us) <= window_us
+                && self.arrivals.len() < MAX_TRACKED_SIGNATURES
+            {
+                break;
+            }
+            self.counts.remove(expired);
+            self.arrivals.pop_front();
+        }
+        let key = K::from_signature(signature);
+        let count = self.counts.entry(key).or_insert_with(|| {
+            self.arrivals.push_back((now_us, key));
+            0
+        });
+        *count += 1;
+        *count
+    }
+}
+
+enum Windows {
+    Full(Window<Signature>),
+    Truncated(Window<u64>),
+}
+
+pub struct ArrivalTracker {
+    window_us: u64,
+    windows: Windows,
+}
+
+impl ArrivalTracker {
+    pub fn new(window_ms: u64) -> Self {
+        Self::with_capacity(window_ms, 0)
+    }
+
+    /// Sizes the tracker for `capacity` signatures in the window up front, so a
+    /// burst doesn't stall on rehashing. Capped at the most it ever tracks.
+    pub fn with_capacity(window_ms: u64, capacity: usize) -> Self {
+        Self::with_key(window_ms, capacity, SignatureKey::default())
+    }
+
+    /// Like [`with_capacity`](Self::with_capacity), keying signatures by `key`.
+    pub fn with_key(window_ms: u64, capacity: usize, key: SignatureKey) -> Self {
+        let capacity = capacity.min(MAX_TRACKED_SIGNATURES);
+        Self {
+            window_us: window_ms.saturating_mul(1_000),
+            windows: match key {
+                SignatureKey::Full => Windows::Full(Window::with_capacity(capacity)),
+                SignatureKey::Truncated => Windows::Truncated(Window::with_capacity(capacity)),
+            },
+        }
+    }
+
+    /// Records an arrival of `signature` at `now_us`, returning how many times it has
+    /// arrived within the window, including this one.
+    pub fn record(&mut self, signature: Signature, now_us: u64) -> u32 {
+        match &mut self.windows {
+            Windows::Full(window) => window.record(&signature, now_us, self.window_us),
+            Windows::Truncated(window) => window.record(&signature, now_us, self.window_us),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{Message, VersionedMessage};
+    use solana_sdk::transaction::VersionedTransaction;
+
+    #[test]
+    fn test_arrival_tracker_window() {
+        let mut tracker = ArrivalTracker::new(1);
+        let a = Signature::from([1; SIGNATURE_BYTES]);
+        let b = Signature::from([2; SIGNATURE_BYTES]);
+        assert_eq!(tracker.record(a, 0), 1);
+        assert_eq!(tracker.record(b, 500), 1);
+        assert_eq!(tracker.record(a, 1_000), 2);
+        // a's window started at 0, so it expires while b's is still open
+        assert_eq!(tracker.record(b, 1_400), 2);
+        assert_eq!(tracker.record(a, 1_400), 1);
+        let Windows::Full(window) = &tracker.windows else { unreachable!() };
+        assert_eq!(window.counts.len(), 2);
+
+        let tracker = ArrivalTracker::with_capacity(1, 1_000);
+        let Windows::Full(window) = &tracker.windows else { unreachable!() };
+        assert!(window.counts.capacity() >= 1_000 && window.arrivals.capacity() >= 1_000);
+    }
+
+    #[test]
+    fn test_truncated_signature_key() {
+        let mut full = ArrivalTracker::with_key(1, 0, SignatureKey::Full);
+        let mut truncated = ArrivalTracker::with_key(1, 0, SignatureKey::Truncated);
+        let a = Signature::from([1; SIGNATURE_BYTES]);
+        let mut b = [1; SIGNATURE_BYTES];
+        b[8] = 2;
+        let b = Signature::from(b);
+        let c = Signature::from([3; SIGNATURE_BYTES]);
+        for tracker in [&mut full, &mut truncated] {
+            assert_eq!(tracker.record(a, 0), 1);
+            assert_eq!(tracker.record(c, 0), 1);
+            assert_eq!(tracker.record(a, 100), 2);
+        }
+        // b differs from a past the first 8 bytes, which truncation can't tell apart
+        assert_eq!(full.record(b, 200), 1);
+        assert_eq!(truncated.record(b, 200), 3);
+        assert_eq!("truncated".parse(), Ok(SignatureKey::Truncated));
+        assert!("short".parse::<SignatureKey>().is_err());
+    }
+
+    #[test]
+    fn test_first_signature() {
+        let signature = Signature::from([3; SIGNATURE_BYTES]);
+        let tx = VersionedTransaction {
+            signatures: vec![signature, Signature::default()],
+            message: VersionedMessage::Legacy(Message::new_with_blockhash(&[], None, &Hash::default())),
+        };
+        let data = bincode::serialize(&tx).unwrap();
+        assert_eq!(first_signature(&data), Some(signature));
+        assert_eq!(first_signature(&data[..40]), None);
+        assert_eq!(first_signature(&[0]), None);
+    }
+}
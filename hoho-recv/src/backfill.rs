@@ -0,0 +1,200 @@
+//! Historical analysis of confirmed transactions (`--backfill`).
+//!
+//! Signatures are fetched with `getTransaction` and run through the same analyzer
+//! as live packets. The argument is either one signature or a file with one
+//! signature per line; blank lines and lines starting with `#` are skipped.
+//!
+//! With `--since-slot`, the slots of all signatures are looked up with
+//! `getSignatureStatuses` first, so transactions before the slot are never fetched.
+//!
+//! Unlike live packets, confirmed transactions come with their inner instructions,
+//! whose `transferChecked` movements give the swap's mints and decimals and which
+//! hold the swaps aggregators make through CPI. Their meta also tells the compute
+//! units the transaction consumed, to hold against the limit it requested.
+use std::fs;
+use std::io;
+use std::str::FromStr;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::config::RpcTransactionConfig;
+use solana_rpc_client_api::request::MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS;
+use solana_sdk::bs58;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::v0::LoadedAddresses;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{TransactionError, VersionedTransaction};
+use solana_transaction_status_client_types::option_serializer::OptionSerializer;
+use solana_transaction_status_client_types::{
+    UiInnerInstructions, UiInstruction, UiLoadedAddresses, UiTransactionEncoding,
+};
+
+/// A confirmed transaction with the addresses its lookup tables resolved to and the
+/// instructions its instructions invoked.
+pub struct HistoricalTransaction {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub transaction: VersionedTransaction,
+    pub loaded_addresses: Option<LoadedAddresses>,
+    /// Inner instructions of every instruction, in execution order
+    pub inner_instructions: Vec<CompiledInstruction>,
+    /// Why the transaction failed, `None` if it succeeded or the meta is missing
+    pub err: Option<TransactionError>,
+    /// Program logs, e.g. Raydium's `ray_log`
+    pub log_messages: Vec<String>,
+    /// `None` if the meta is missing or the node doesn't report it
+    pub compute_units_consumed: Option<u64>,
+}
+
+/// Parses `arg` as a signature, or else reads signatures from the file it names.
+pub fn read_signatures(arg: &str) -> io::Result<Vec<Signature>> {
+    if let Ok(signature) = Signature::from_str(arg) {
+        return Ok(vec![signature]);
+    }
+    fs::read_to_string(arg)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            Signature::from_str(line).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", line, e))
+            })
+        })
+        .collect()
+}
+
+/// Drops the signatures confirmed before `slot`. Signatures without a status are
+/// kept, so fetching them reports why.
+pub fn retain_since_slot(
+    rpc_client: &RpcClient,
+    signatures: Vec<Signature>,
+    slot: u64,
+) -> Result<Vec<Signature>, String> {
+    let mut retained = Vec::with_capacity(signatures.len());
+    for chunk in signatures.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+        let statuses = rpc_client
+            .get_signature_statuses_with_history(chunk)
+            .map_err(|e| e.to_string())?
+            .value;
+        retained.extend(
+            chunk
+                .iter()
+                .zip(statuses)
+                .filter(|(_, status)| status.as_ref().is_none_or(|status| status.slot >= slot))
+                .map(|(signature, _)| *signature),
+        );
+    }
+    Ok(retained)
+}
+
+pub fn fetch_transaction(rpc_client: &RpcClient, signature: &Signature) -> Result<HistoricalTransaction, String> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    let confirmed = rpc_client
+        .get_transaction_with_config(signature, config)
+        .map_err(|e| e.to_string())?;
+    let transaction = confirmed
+        .transaction
+        .transaction
+        .decode()
+        .ok_or("transaction could not be decoded")?;
+    let (ui_loaded_addresses, ui_inner_instructions, err, log_messages, compute_units_consumed) =
+        match confirmed.transaction.meta {
+            Some(meta) => (
+                meta.loaded_addresses,
+                meta.inner_instructions,
+                meta.err,
+                meta.log_messages,
+                meta.compute_units_consumed,
+            ),
+            None => (OptionSerializer::None, OptionSerializer::None, None, OptionSerializer::None, OptionSerializer::None),
+        };
+    let loaded_addresses = match ui_loaded_addresses {
+        OptionSerializer::Some(addresses) => Some(loaded_addresses(&addresses)?),
+        _ => None,
+    };
+    let inner_instructions = match ui_inner_instructions {
+        OptionSerializer::Some(inner_instructions) => compiled_inner_instructions(&inner_instructions),
+        _ => Vec::new(),
+    };
+    Ok(HistoricalTransaction {
+        slot: confirmed.slot,
+        block_time: confirmed.block_time,
+        transaction,
+        loaded_addresses,
+        inner_instructions,
+        err,
+        log_messages: log_messages.unwrap_or(Vec::new()),
+        compute_units_consumed: match compute_units_consumed {
+            OptionSerializer::Some(units) => Some(units),
+            _ => None,
+        },
+    })
+}
+
+/// Flattens inner instructions, skipping any that are not base58-encoded compiled
+/// instructions; binary encodings never return the parsed forms.
+fn compiled_inner_instructions(inner_instructions: &[UiInnerInstructions]) -> Vec<CompiledInstruction> {
+    inner_instructions
+        .iter()
+        .flat_map(|inner| &inner.instructions)
+        .filter_map(|ix| match ix {
+            UiInstruction::Compiled(ix) => Some(CompiledInstruction::new_from_raw_parts(
+                ix.program_id_index,
+                bs58::decode(&ix.data).into_vec().ok()?,
+                ix.accounts.clone(),
+            )),
+            UiInstruction::Parsed(_) => None,
+        })
+        .collect()
+}
+
+fn loaded_addresses(addresses: &UiLoadedAddresses) -> Result<LoadedAddresses, String> {
+    let parse = |keys: &[String]| {
+        keys.iter()
+            .map(|key| Pubkey::from_str(key).map_err(|e| format!("loaded address {}: {}", key, e)))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    Ok(LoadedAddresses {
+        writable: parse(&addresses.writable)?,
+        readonly: parse(&addresses.readonly)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_read_signatures() {
+        let signature = Signature::from([7; 64]);
+        assert_eq!(read_signatures(&signature.to_string()).unwrap(), vec![signature]);
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "# swaps to check\n{}\n\n  {}  \n", signature, Signature::default()).unwrap();
+        let path = file.path().to_str().unwrap().to_string();
+        assert_eq!(read_signatures(&path).unwrap(), vec![signature, Signature::default()]);
+
+        writeln!(file, "not-a-signature").unwrap();
+        assert!(read_signatures(&path).is_err());
+    }
+
+    #[test]
+    fn test_loaded_addresses() {
+        let (writable, readonly) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let addresses = UiLoadedAddresses {
+            writable: vec![writable.to_string()],
+            readonly: vec![readonly.to_string()],
+        };
+        assert_eq!(
+            loaded_addresses(&addresses).unwrap(),
+            LoadedAddresses { writable: vec![writable], readonly: vec![readonly] }
+        );
+        let addresses = UiLoadedAddresses { writable: vec!["x".to_string()], readonly: vec![] };
+        assert!(loaded_addresses(&addresses).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! `hoho-recv bench`: analysis throughput over a capture file.
+//!
+//! Every frame of the capture is read into memory before the clock starts, so
+//! disk reads aren't measured, and then analyzed back to back with RPC disabled.
+//! The report gives transactions per second overall and the time each
+//! transaction took by the DEX of the swap it decoded to, so a change to a
+//! decoder can be measured against a known capture. Frames without a forward
+//! header and thin payloads are skipped, as the receiver can't analyze them
+//! either. Built with `analyzer-timing`, the report also breaks the time down by
+//! analyzer stage, at some cost to the throughput measured.
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+use crate::amm::AmmRegistry;
+use crate::analyzer::{analyze_transaction, Resolvers};
+use crate::capture::CaptureReader;
+use crate::forward_header::{ForwardHeader, ForwardMode};
+use crate::timing::{AnalyzerTimings, Histogram};
+
+#[derive(Default)]
+pub struct BenchReport {
+    pub frames: u64,
+    /// Frames without a forward header or with a thin payload
+    pub skipped: u64,
+    pub transactions: u64,
+    pub swaps: u64,
+    /// Transactions that failed to deserialize or sanitize
+    pub errors: u64,
+    pub elapsed: Duration,
+    /// Time per transaction by the DEX of its swap, `none` without one and `error`
+    /// if it failed to analyze
+    pub by_dex: HashMap<&'static str, Histogram>,
+    /// Time per analyzer stage, with the `analyzer-timing` feature
+    pub stages: Option<AnalyzerTimings>,
+}
+
+impl BenchReport {
+    pub fn transactions_per_sec(&self) -> f64 {
+        self.transactions as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Analyzed {} transactions ({} swaps, {} errors) from {} frames in {:?}: {:.0} transactions/sec",
+            self.transactions,
+            self.swaps,
+            self.errors,
+            self.frames,
+            self.elapsed,
+            self.transactions_per_sec()
+        )?;
+        if self.skipped > 0 {
+            write!(f, "\nSkipped {} frames without a full transaction", self.skipped)?;
+        }
+        let mut by_dex: Vec<_> = self.by_dex.iter().collect();
+        by_dex.sort_by_key(|(dex, histogram)| (std::cmp::Reverse(histogram.count()), **dex));
+        write!(f, "\nTime per transaction by DEX:")?;
+        for (dex, histogram) in by_dex {
+            write!(f, "\n  {:24} {}", dex, histogram)?;
+        }
+        if let Some(stages) = &self.stages {
+            write!(f, "\nTime per analyzer stage:\n{}", stages.report().trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Analyzes every frame of the capture read by `reader` with no resolvers.
+pub fn bench_capture<R: Read>(mut reader: CaptureReader<R>, amms: &AmmRegistry) -> io::Result<BenchReport> {
+    let mut report = BenchReport::default();
+    let mut frames = Vec::new();
+    while let Some(frame) = reader.read_frame()? {
+        report.frames += 1;
+        frames.push(frame.data);
+    }
+
+    #[cfg(feature = "analyzer-timing")]
+    crate::timing::enable(Duration::MAX);
+    let mut resolvers = Resolvers::default();
+    let start = Instant::now();
+    for data in &frames {
+        let packet = match ForwardHeader::parse(data) {
+            Ok((header, packet)) if header.mode == ForwardMode::Full => packet,
+            _ => {
+                report.skipped += 1;
+                continue;
+            }
+        };
+        let analyze_start = Instant::now();
+        let result = analyze_transaction(packet, amms, &mut resolvers);
+        let elapsed = analyze_start.elapsed();
+        report.transactions += 1;
+        let dex = match result {
+            Ok(Some(swap)) => {
+                report.swaps += 1;
+                swap.dex.name()
+            }
+            Ok(None) => "none",
+            Err(_) => {
+                report.errors += 1;
+                "error"
+            }
+        };
+        report.by_dex.entry(dex).or_default().record(elapsed);
+    }
+    report.elapsed = start.elapsed();
+    #[cfg(feature = "analyzer-timing")]
+    {
+        report.stages = crate::timing::take();
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::CaptureWriter;
+    use crate::forward_header::ForwardClock;
+    use crate::selftest::swap_base_in_transaction;
+
+    #[test]
+    fn test_bench_capture() {
+        let (transaction, _) = swap_base_in_transaction();
+        let header = ForwardHeader {
+            clock: ForwardClock::Wall,
+            mode: ForwardMode::Full,
+            timestamp_us: 1,
+            slot: None,
+            leader: None,
+        };
+        let thin = ForwardHeader { mode: ForwardMode::Thin, ..header };
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        writer.write_frame(1, &header.encode(&transaction)).unwrap();
+        writer.write_frame(2, &header.encode(&transaction)).unwrap();
+        writer.write_frame(3, &header.encode(&[1, 2, 3])).unwrap();
+        writer.write_frame(4, &thin.encode(&transaction)).unwrap();
+        writer.write_frame(5, &[]).unwrap();
+        let capture = writer.into_inner();
+
+        let report = bench_capture(CaptureReader::new(capture.as_slice()).unwrap(), &AmmRegistry::default()).unwrap();
+        assert_eq!((report.frames, report.skipped, report.transactions), (5, 2, 3));
+        assert_eq!((report.swaps, report.errors), (2, 1));
+        assert_eq!(report.by_dex["raydium_v4"].count(), 2);
+        assert_eq!(report.by_dex["error"].count(), 1);
+        assert!(report.to_string().contains("\n  raydium_v4 "));
+    }
+}
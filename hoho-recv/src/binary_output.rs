@@ -0,0 +1,439 @@
+//! Compact binary output of matched swaps (`--binary DEST`).
+//!
+//! Each matched swap is written as one fixed-size record of [`RECORD_SIZE`]
+//! bytes, with no framing between them, to a file (which may be a named pipe) or
+//! to a TCP connection given as `tcp://HOST:PORT`. Stdout carries the console
+//! report, so a consumer reads from a FIFO or a socket instead. Integers are
+//! little-endian:
+//!
+//! ```text
+//! offset size field
+//! 0      1    version, currently 1
+//! 1      1    dex tag, see dex_tag()
+//! 2      1    instruction discriminator
+//! 3      1    flags: bit 0 exact output, bits 1-7 the keys below that are present
+//! 4      8    received_at_us
+//! 12     64   signature
+//! 76     8    amount_in
+//! 84     8    min_amount_out
+//! 92     8    compute unit price, micro-lamports
+//! 100    4    compute unit limit
+//! 104    32   pool coin account
+//! 136    32   pool pc account
+//! 168    32   user source account
+//! 200    32   user destination account
+//! 232    32   user owner
+//! 264    32   source mint
+//! 296    32   destination mint
+//! 328    1    source decimals, 255 if unknown
+//! 329    1    destination decimals, 255 if unknown
+//! ```
+//!
+//! Absent keys are zeroed. With `--include-packet` records are version 2, the
+//! same fields followed by the packet the swap was decoded from, so a consumer can
+//! re-parse it or verify its signatures; they are no longer fixed-size:
+//!
+//! ```text
+//! 330    2    packet length
+//! 332    *    packet
+//! ```
+//!
+//! [`BinarySwapRecord`] encodes and decodes records.
+//!
+//! Records bound for a TCP connection are queued for a sender thread, so a slow
+//! consumer never stalls analysis. Once `--binary-max-buffered` records are waiting
+//! the consumer is taken to be stuck: the connection is closed and the output
+//! disabled rather than buffering without bound.
+use std::fs::File;
+use std::io::{self, BufWriter, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use crate::analyzer::{AnalyzedSwap, Dex};
+use crate::output::SwapSink;
+
+pub const RECORD_SIZE: usize = 330;
+pub const RECORD_VERSION: u8 = 1;
+pub const RECORD_VERSION_PACKET: u8 = 2;
+
+const EXACT_OUT_FLAG: u8 = 1;
+const UNKNOWN_DECIMALS: u8 = u8::MAX;
+const KEYS_OFFSET: usize = 104;
+const KEY_COUNT: usize = 7;
+
+/// The tag a record identifies `dex` by. AMMs decoded from an
+/// [`AmmSpec`](crate::amm::AmmSpec) all share tag 0.
+pub fn dex_tag(dex: Dex) -> u8 {
+    match dex {
+        Dex::Amm(_) => 0,
+        Dex::RaydiumV4 => 1,
+        Dex::RaydiumStable => 2,
+        Dex::RaydiumRoute => 3,
+        Dex::PumpAmm => 4,
+        Dex::RaydiumLaunchpad => 5,
+    }
+}
+
+/// The fields of one binary record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinarySwapRecord {
+    pub dex_tag: u8,
+    pub discriminator: u8,
+    pub exact_out: bool,
+    pub received_at_us: u64,
+    pub signature: Signature,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub compute_unit_price: u64,
+    pub compute_unit_limit: u32,
+    pub pool_coin_account: Option<Pubkey>,
+    pub pool_pc_account: Option<Pubkey>,
+    pub user_source_account: Option<Pubkey>,
+    pub user_destination_account: Option<Pubkey>,
+    pub user_owner: Option<Pubkey>,
+    pub source_mint: Option<Pubkey>,
+    pub destination_mint: Option<Pubkey>,
+    pub source_decimals: Option<u8>,
+    pub destination_decimals: Option<u8>,
+    /// Version 2 records only
+    pub packet: Option<Vec<u8>>,
+}
+
+impl BinarySwapRecord {
+    pub fn new(received_at_us: u64, swap: &AnalyzedSwap) -> Self {
+        Self {
+            dex_tag: dex_tag(swap.dex),
+            discriminator: swap.discriminator,
+            exact_out: swap.exact_out(),
+            received_at_us,
+            signature: swap.signature,
+            amount_in: swap.amount_in,
+            min_amount_out: swap.min_amount_out,
+            compute_unit_price: swap.priority_fee.compute_unit_price,
+            compute_unit_limit: swap.priority_fee.compute_unit_limit,
+            pool_coin_account: swap.pool_coin_account,
+            pool_pc_account: swap.pool_pc_account,
+            user_source_account: swap.user_source_account,
+            user_destination_account: swap.user_destination_account,
+            user_owner: swap.user_owner,
+            source_mint: swap.source_mint,
+            destination_mint: swap.destination_mint,
+            source_decimals: swap.source_decimals,
+            destination_decimals: swap.destination_decimals,
+            packet: None,
+        }
+    }
+
+    fn keys(&self) -> [Option<Pubkey>; KEY_COUNT] {
+        [
+            self.pool_coin_account,
+            self.pool_pc_account,
+            self.user_source_account,
+            self.user_destination_account,
+            self.user_owner,
+            self.source_mint,
+            self.destination_mint,
+        ]
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut record = vec![0u8; RECORD_SIZE];
+        let mut flags = if self.exact_out { EXACT_OUT_FLAG } else { 0 };
+        for (i, key) in self.keys().into_iter().enumerate() {
+            if let Some(key) = key {
+                flags |= 2 << i;
+                record[KEYS_OFFSET + 32 * i..][..32].copy_from_slice(key.as_ref());
+            }
+        }
+        let version = if self.packet.is_some() { RECORD_VERSION_PACKET } else { RECORD_VERSION };
+        record[..4].copy_from_slice(&[version, self.dex_tag, self.discriminator, flags]);
+        record[4..12].copy_from_slice(&self.received_at_us.to_le_bytes());
+        record[12..76].copy_from_slice(self.signature.as_ref());
+        record[76..84].copy_from_slice(&self.amount_in.to_le_bytes());
+        record[84..92].copy_from_slice(&self.min_amount_out.to_le_bytes());
+        record[92..100].copy_from_slice(&self.compute_unit_price.to_le_bytes());
+        record[100..104].copy_from_slice(&self.compute_unit_limit.to_le_bytes());
+        record[328] = self.source_decimals.unwrap_or(UNKNOWN_DECIMALS);
+        record[329] = self.destination_decimals.unwrap_or(UNKNOWN_DECIMALS);
+        if let Some(packet) = &self.packet {
+            record.extend_from_slice(&(packet.len() as u16).to_le_bytes());
+            record.extend_from_slice(packet);
+        }
+        record
+    }
+
+    /// Decodes the fixed-size part of a record, or returns `None` if it has another
+    /// version. The packet following a version 2 record is left to the caller.
+    pub fn decode(record: &[u8; RECORD_SIZE]) -> Option<Self> {
+        if record[0] != RECORD_VERSION && record[0] != RECORD_VERSION_PACKET {
+            return None;
+        }
+        let flags = record[3];
+        let u64_at = |offset: usize| u64::from_le_bytes(record[offset..offset + 8].try_into().unwrap());
+        let key = |i: usize| {
+            (flags & (2 << i) != 0).then(|| Pubkey::try_from(&record[KEYS_OFFSET + 32 * i..][..32]).unwrap())
+        };
+        let decimals = |value: u8| (value != UNKNOWN_DECIMALS).then_some(value);
+        Some(Self {
+            dex_tag: record[1],
+            discriminator: record[2],
+            exact_out: flags & EXACT_OUT_FLAG != 0,
+            received_at_us: u64_at(4),
+            signature: Signature::try_from(&record[12..76]).unwrap(),
+            amount_in: u64_at(76),
+            min_amount_out: u64_at(84),
+            compute_unit_price: u64_at(92),
+            compute_unit_limit: u32::from_le_bytes(record[100..104].try_into().unwrap()),
+            pool_coin_account: key(0),
+            pool_pc_account: key(1),
+            user_source_account: key(2),
+            user_destination_account: key(3),
+            user_owner: key(4),
+            source_mint: key(5),
+            destination_mint: key(6),
+            source_decimals: decimals(record[328]),
+            destination_decimals: decimals(record[329]),
+            packet: None,
+        })
+    }
+
+    /// Reads the next record from `reader`, returning `None` at the end of the
+    /// stream. A record of another version is an `InvalidData` error.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Self>> {
+        let mut record = [0u8; RECORD_SIZE];
+        match reader.read_exact(&mut record) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let mut decoded = Self::decode(&record)
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, format!("unknown record version {}", record[0])))?;
+        if record[0] == RECORD_VERSION_PACKET {
+            let mut length = [0u8; 2];
+            reader.read_exact(&mut length)?;
+            let mut packet = vec![0u8; u16::from_le_bytes(length) as usize];
+            reader.read_exact(&mut packet)?;
+            decoded.packet = Some(packet);
+        }
+        Ok(Some(decoded))
+    }
+}
+
+pub struct BinarySwapWriter {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    include_packet: bool,
+}
+
+impl BinarySwapWriter {
+    /// Connects to `tcp://HOST:PORT`, queueing up to `max_buffered` records for the
+    /// connection, or creates the file at `destination`. With `include_packet` records
+    /// carry the swap's packet.
+    pub fn create(destination: &str, max_buffered: usize, include_packet: bool) -> io::Result<Self> {
+        let writer: Box<dyn Write + Send> = match destination.strip_prefix("tcp://") {
+            Some(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_nodelay(true)?;
+                let control = stream.try_clone()?;
+                Box::new(QueuedStream::spawn(stream, Some(control), address.to_string(), max_buffered)?)
+            }
+            None => Box::new(File::create(destination)?),
+        };
+        Ok(Self {
+            writer: BufWriter::new(writer),
+            include_packet,
+        })
+    }
+}
+
+impl SwapSink for BinarySwapWriter {
+    fn name(&self) -> &'static str {
+        "binary"
+    }
+
+    /// Writes `swap` as one record and flushes it, so the reader sees it right away.
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, packet: &[u8]) -> io::Result<()> {
+        let mut record = BinarySwapRecord::new(received_at_us, swap);
+        if self.include_packet {
+            record.packet = Some(packet.to_vec());
+        }
+        self.writer.write_all(&record.encode())?;
+        self.writer.flush()
+    }
+}
+
+/// Hands each flushed write to a thread that writes it to the stream, failing once
+/// `max_buffered` writes are waiting or the stream has failed.
+struct QueuedStream {
+    pending: Vec<u8>,
+    sender: SyncSender<Vec<u8>>,
+    /// Shut down to unblock the thread when the consumer is dropped
+    control: Option<TcpStream>,
+    peer: String,
+    max_buffered: usize,
+}
+
+impl QueuedStream {
+    fn spawn<W: Write + Send + 'static>(
+        mut stream: W,
+        control: Option<TcpStream>,
+        peer: String,
+        max_buffered: usize,
+    ) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(max_buffered);
+        let thread_peer = peer.clone();
+        thread::Builder::new().name("binarySender".to_string()).spawn(move || {
+            for data in receiver {
+                if let Err(e) = stream.write_all(&data) {
+                    eprintln!("Binary output connection to {} failed: {}", thread_peer, e);
+                    return;
+                }
+            }
+        })?;
+        Ok(Self {
+            pending: Vec::new(),
+            sender,
+            control,
+            peer,
+            max_buffered,
+        })
+    }
+}
+
+impl Write for QueuedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        match self.sender.try_send(std::mem::take(&mut self.pending)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                if let Some(control) = &self.control {
+                    let _ = control.shutdown(Shutdown::Both);
+                }
+                Err(io::Error::other(format!(
+                    "{} is {} records behind, disconnecting",
+                    self.peer, self.max_buffered
+                )))
+            }
+            Err(TrySendError::Disconnected(_)) => Err(io::Error::new(
+                ErrorKind::BrokenPipe,
+                format!("connection to {} closed", self.peer),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Receiver;
+    use solana_sdk::transaction::TransactionVersion;
+    use crate::compute_budget::PriorityFee;
+
+    /// Writes nothing until told to, like a consumer that stopped reading.
+    struct StalledWriter(Receiver<()>);
+
+    impl Write for StalledWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.recv().map_err(|_| io::Error::from(ErrorKind::BrokenPipe))?;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_queued_stream_disconnects_stalled_consumer() {
+        let (resume, stalled) = mpsc::channel();
+        let mut stream = QueuedStream::spawn(StalledWriter(stalled), None, "consumer".to_string(), 2).unwrap();
+        // One write is taken by the blocked sender thread, two more fill the queue
+        let mut result = Ok(());
+        for _ in 0..10 {
+            stream.write_all(&[1; RECORD_SIZE]).unwrap();
+            result = stream.flush();
+            if result.is_err() {
+                break;
+            }
+        }
+        assert_eq!(result.unwrap_err().to_string(), "consumer is 2 records behind, disconnecting");
+        drop(resume);
+    }
+
+    #[test]
+    fn test_binary_record_round_trip() {
+        let swap = AnalyzedSwap {
+            signature: Signature::from([3; 64]),
+            co_signatures: vec![],
+            dex: Dex::RaydiumStable,
+            discriminator: 9,
+            amount_in: u64::MAX,
+            min_amount_out: 1,
+            pool_coin_account: Some(Pubkey::new_unique()),
+            pool_pc_account: None,
+            model_data_account: Some(Pubkey::new_unique()),
+            user_source_account: Some(Pubkey::new_unique()),
+            user_destination_account: None,
+            user_owner: Some(Pubkey::new_unique()),
+            source_mint: None,
+            destination_mint: Some(Pubkey::new_unique()),
+            source_decimals: None,
+            destination_decimals: Some(6),
+            transfers: vec![],
+            priority_fee: PriorityFee {
+                compute_unit_price: 25_000,
+                compute_unit_limit: 300_000,
+                compute_unit_limit_requested: true,
+            },
+            compute_units_consumed: None,
+            lookup_tables: vec![],
+            transaction_size: 0,
+            version: TransactionVersion::LEGACY,
+            front_run_protected: false,
+            zero_min_out: false,
+            pool_fee_bps: None,
+            expected_amount_out: None,
+            realized_amount_out: None,
+            realized_amount_in: None,
+            mint_events: vec![],
+            burn_events: vec![],
+            authority_changes: vec![],
+            payer_sol_balance: None,
+            observers: None,
+            price_impact_bps: None,
+            pool_reserves: None,
+        };
+        let record = BinarySwapRecord::new(42, &swap);
+        let bytes = [record.encode(), record.encode()].concat();
+        assert_eq!(bytes.len(), 2 * RECORD_SIZE);
+        assert_eq!(bytes[..4], [RECORD_VERSION, 2, 9, 0b1010_1010]);
+
+        let mut reader = &bytes[..];
+        assert_eq!(BinarySwapRecord::read_from(&mut reader).unwrap(), Some(record.clone()));
+        assert_eq!(BinarySwapRecord::read_from(&mut reader).unwrap(), Some(record.clone()));
+        assert_eq!(BinarySwapRecord::read_from(&mut reader).unwrap(), None);
+
+        let with_packet = BinarySwapRecord {
+            packet: Some(vec![7; 1232]),
+            ..record
+        };
+        let bytes = [with_packet.encode(), with_packet.encode()].concat();
+        assert_eq!(bytes.len(), 2 * (RECORD_SIZE + 2 + 1232));
+        let mut reader = &bytes[..];
+        assert_eq!(BinarySwapRecord::read_from(&mut reader).unwrap(), Some(with_packet.clone()));
+        assert_eq!(BinarySwapRecord::read_from(&mut reader).unwrap(), Some(with_packet));
+        assert_eq!(BinarySwapRecord::read_from(&mut reader).unwrap(), None);
+
+        let mut unknown = [0u8; RECORD_SIZE];
+        unknown[0] = RECORD_VERSION_PACKET + 1;
+        assert_eq!(BinarySwapRecord::read_from(&mut &unknown[..]).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,245 @@
+//! Capture file framing shared by `--capture` and the `replay` subcommand.
+//!
+//! A capture file starts with the 8-byte magic `HOHOCAP1` followed by frames:
+//!
+//! ```text
+//! timestamp_us: u64 LE  (wall-clock arrival time, microseconds since UNIX epoch)
+//! len:          u32 LE
+//! data:         [u8; len] (the datagram exactly as received)
+//! ```
+//!
+//! With `--capture-compress` the same bytes are written through zstd, and readers
+//! tell a compressed file by the zstd magic at its start. Every frame is flushed
+//! as a zstd block, so a capture cut short by the process exiting reads up to its
+//! last frame; the zstd frame is only finished, with its checksum, when the
+//! writer is dropped on a clean shutdown.
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const CAPTURE_MAGIC: &[u8; 8] = b"HOHOCAP1";
+
+// Larger than any UDP datagram, so a bigger length means a corrupt file.
+const MAX_FRAME_LEN: usize = 64 * 1024;
+
+// The first bytes of a zstd frame, 0xFD2FB528 LE
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+// Fast enough for a full rate feed on one core, still shrinking captures several times
+const CAPTURE_COMPRESSION_LEVEL: i32 = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub timestamp_us: u64,
+    pub data: Vec<u8>,
+}
+
+pub fn now_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+/// A capture file as written, compressed or not.
+pub enum CaptureFile {
+    Plain(BufWriter<File>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl Write for CaptureFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+impl Drop for CaptureFile {
+    /// Finishes the zstd frame; a plain file's buffer is flushed by its own drop.
+    fn drop(&mut self) {
+        if let Self::Zstd(encoder) = self {
+            if let Err(e) = encoder.do_finish().and_then(|()| encoder.get_mut().flush()) {
+                eprintln!("Error finishing compressed capture: {}", e);
+            }
+        }
+    }
+}
+
+pub type FileCaptureWriter = CaptureWriter<CaptureFile>;
+
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+}
+
+impl FileCaptureWriter {
+    pub fn create<P: AsRef<Path>>(path: P, compress: bool) -> io::Result<Self> {
+        let writer = BufWriter::new(File::create(path)?);
+        Self::new(if compress {
+            CaptureFile::Zstd(zstd::Encoder::new(writer, CAPTURE_COMPRESSION_LEVEL)?)
+        } else {
+            CaptureFile::Plain(writer)
+        })
+    }
+}
+
+impl<W: Write> CaptureWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(CAPTURE_MAGIC)?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_frame(&mut self, timestamp_us: u64, data: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+        self.writer.write_all(&timestamp_us.to_le_bytes())?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(data)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub struct CaptureReader<R: Read> {
+    reader: R,
+}
+
+pub type FileCaptureReader = CaptureReader<Box<dyn Read + Send>>;
+
+impl FileCaptureReader {
+    /// Opens the capture at `path`, decompressing it if it is compressed.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(decompressed(BufReader::new(File::open(path)?))?)
+    }
+}
+
+/// `reader`, through a zstd decoder if it starts with a zstd frame.
+fn decompressed<R: BufRead + Send + 'static>(mut reader: R) -> io::Result<Box<dyn Read + Send>> {
+    if reader.fill_buf()?.starts_with(&ZSTD_MAGIC) {
+        Ok(Box::new(zstd::Decoder::with_buffer(reader)?))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != CAPTURE_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a hoho-recv capture file",
+            ));
+        }
+        Ok(Self { reader })
+    }
+
+    /// Reads the next frame, returning `None` at a clean end of file, or at the end
+    /// of the last zstd block of a compressed capture never finished.
+    pub fn read_frame(&mut self) -> io::Result<Option<Frame>> {
+        let mut timestamp = [0u8; 8];
+        match self.reader.read_exact(&mut timestamp) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let mut len = [0u8; 4];
+        self.reader.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame length {} exceeds {}", len, MAX_FRAME_LEN),
+            ));
+        }
+        let mut data = vec![0u8; len];
+        self.reader.read_exact(&mut data)?;
+        Ok(Some(Frame {
+            timestamp_us: u64::from_le_bytes(timestamp),
+            data,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_round_trip() {
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        writer.write_frame(1, &[1, 2, 3]).unwrap();
+        writer.write_frame(2, &[]).unwrap();
+        let bytes = writer.writer;
+
+        let mut reader = CaptureReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(
+            reader.read_frame().unwrap(),
+            Some(Frame {
+                timestamp_us: 1,
+                data: vec![1, 2, 3]
+            })
+        );
+        assert_eq!(
+            reader.read_frame().unwrap(),
+            Some(Frame {
+                timestamp_us: 2,
+                data: vec![]
+            })
+        );
+        assert_eq!(reader.read_frame().unwrap(), None);
+    }
+
+    #[test]
+    fn test_compressed_capture() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capture.zst");
+        let frames: Vec<_> = (0..100u64)
+            .map(|i| Frame {
+                timestamp_us: i,
+                data: vec![i as u8; 1_000],
+            })
+            .collect();
+        let mut writer = FileCaptureWriter::create(&path, true).unwrap();
+        for frame in &frames {
+            writer.write_frame(frame.timestamp_us, &frame.data).unwrap();
+        }
+        drop(writer);
+        assert!(std::fs::metadata(&path).unwrap().len() < 10_000);
+        let mut reader = FileCaptureReader::open(&path).unwrap();
+        for frame in &frames {
+            assert_eq!(reader.read_frame().unwrap().as_ref(), Some(frame));
+        }
+        assert_eq!(reader.read_frame().unwrap(), None);
+
+        // Cut short after a flush, with the zstd frame never finished
+        let mut writer = CaptureWriter::new(zstd::Encoder::new(Vec::new(), CAPTURE_COMPRESSION_LEVEL).unwrap()).unwrap();
+        writer.write_frame(1, &[1, 2, 3]).unwrap();
+        writer.flush().unwrap();
+        let bytes = writer.into_inner().get_ref().clone();
+        let mut reader = CaptureReader::new(decompressed(io::Cursor::new(bytes)).unwrap()).unwrap();
+        assert_eq!(reader.read_frame().unwrap().unwrap().data, vec![1, 2, 3]);
+        assert_eq!(reader.read_frame().unwrap(), None);
+    }
+
+    #[test]
+    fn test_capture_rejects_bad_magic() {
+        assert!(CaptureReader::new(&b"NOTACAPT"[..]).is_err());
+    }
+}
@@ -0,0 +1,410 @@
+//! ClickHouse output of matched swaps (`--clickhouse-url`, feature `clickhouse`).
+//!
+//! Each matched swap becomes a row of [`COLUMNS`] in `--clickhouse-table`, inserted
+//! through ClickHouse's HTTP interface as `INSERT ... FORMAT JSONEachRow` batches.
+//! A batch is sent once it holds `--clickhouse-batch-size` rows, or
+//! `--clickhouse-flush-ms` after its first row. Batches are built and sent by an
+//! async client on its own thread, fed through a bounded queue: when the server
+//! falls behind the queue fills up and further swaps are dropped and counted, so a
+//! slow server never stalls analysis.
+//!
+//! Connection errors, timeouts, `429` and `5xx` responses are retried with
+//! exponential backoff up to `--clickhouse-retries` times; a batch failing after
+//! that, or with another response, is dropped and its rows counted.
+//!
+//! With `--clickhouse-create-table` the table is created at startup if it doesn't
+//! exist, as [`create_table_sql`] gives. Columns are only ever appended and inserts
+//! skip the ones a table lacks, so a table created by an older version keeps
+//! working and takes new columns with `ALTER TABLE ... ADD COLUMN`.
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use chrono::DateTime;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde_json::{json, Value};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time::Instant;
+use crate::analyzer::AnalyzedSwap;
+use crate::output::SwapSink;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(100);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Columns of the swap table and their ClickHouse types. Pubkeys and signatures
+/// are base58 strings.
+pub const COLUMNS: [(&str, &str); 35] = [
+    ("received_at", "DateTime64(6, 'UTC')"),
+    ("signature", "String"),
+    ("dex", "LowCardinality(String)"),
+    ("curve", "LowCardinality(String)"),
+    ("discriminator", "UInt8"),
+    ("amount_in", "UInt64"),
+    ("min_amount_out", "UInt64"),
+    ("pool_coin_account", "Nullable(String)"),
+    ("pool_pc_account", "Nullable(String)"),
+    ("model_data_account", "Nullable(String)"),
+    ("user_source_account", "Nullable(String)"),
+    ("user_destination_account", "Nullable(String)"),
+    ("user_owner", "Nullable(String)"),
+    ("source_mint", "Nullable(String)"),
+    ("destination_mint", "Nullable(String)"),
+    ("source_decimals", "Nullable(UInt8)"),
+    ("destination_decimals", "Nullable(UInt8)"),
+    ("compute_unit_price", "UInt64"),
+    ("compute_unit_limit", "UInt32"),
+    ("compute_unit_limit_requested", "Bool"),
+    ("priority_fee_lamports", "UInt64"),
+    ("compute_units_consumed", "Nullable(UInt64)"),
+    ("lookup_tables", "Array(String)"),
+    ("co_signatures", "Array(String)"),
+    ("transaction_size", "UInt32"),
+    ("version", "LowCardinality(String)"),
+    ("front_run_protected", "Bool"),
+    ("zero_min_out", "Bool"),
+    ("pool_fee_bps", "Nullable(UInt32)"),
+    ("expected_amount_out", "Nullable(UInt64)"),
+    ("realized_amount_out", "Nullable(UInt64)"),
+    ("realized_amount_in", "Nullable(UInt64)"),
+    ("price_impact_bps", "Nullable(UInt32)"),
+    ("payer_sol_balance", "Nullable(UInt64)"),
+    ("observers", "Nullable(UInt32)"),
+];
+
+/// The statement `--clickhouse-create-table` runs.
+pub fn create_table_sql(table: &str) -> String {
+    let columns: Vec<String> = COLUMNS.iter().map(|(name, kind)| format!("    {} {}", name, kind)).collect();
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (\n{}\n) ENGINE = MergeTree ORDER BY (dex, received_at)",
+        table,
+        columns.join(",\n")
+    )
+}
+
+/// The row of [`COLUMNS`] for `swap`, as a JSONEachRow object.
+pub fn clickhouse_row(received_at_us: u64, swap: &AnalyzedSwap) -> Value {
+    let received_at = DateTime::from_timestamp_micros(received_at_us as i64).unwrap_or_default();
+    let pubkey = |key: Option<solana_sdk::pubkey::Pubkey>| key.map(|key| key.to_string());
+    json!({
+        "received_at": received_at.format("%Y-%m-%d %H:%M:%S%.6f").to_string(),
+        "signature": swap.signature.to_string(),
+        "dex": swap.dex.name(),
+        "curve": swap.curve().name(),
+        "discriminator": swap.discriminator,
+        "amount_in": swap.amount_in,
+        "min_amount_out": swap.min_amount_out,
+        "pool_coin_account": pubkey(swap.pool_coin_account),
+        "pool_pc_account": pubkey(swap.pool_pc_account),
+        "model_data_account": pubkey(swap.model_data_account),
+        "user_source_account": pubkey(swap.user_source_account),
+        "user_destination_account": pubkey(swap.user_destination_account),
+        "user_owner": pubkey(swap.user_owner),
+        "source_mint": pubkey(swap.source_mint),
+        "destination_mint": pubkey(swap.destination_mint),
+        "source_decimals": swap.source_decimals,
+        "destination_decimals": swap.destination_decimals,
+        "compute_unit_price": swap.priority_fee.compute_unit_price,
+        "compute_unit_limit": swap.priority_fee.compute_unit_limit,
+        "compute_unit_limit_requested": swap.priority_fee.compute_unit_limit_requested,
+        "priority_fee_lamports": swap.priority_fee.total_lamports(),
+        "compute_units_consumed": swap.compute_units_consumed,
+        "lookup_tables": swap.lookup_tables.iter().map(|lookup| lookup.account_key.to_string()).collect::<Vec<_>>(),
+        "co_signatures": swap.co_signatures.iter().map(|signature| signature.to_string()).collect::<Vec<_>>(),
+        "transaction_size": swap.transaction_size,
+        "version": swap.version_name(),
+        "front_run_protected": swap.front_run_protected,
+        "zero_min_out": swap.zero_min_out,
+        "pool_fee_bps": swap.pool_fee_bps,
+        "expected_amount_out": swap.expected_amount_out,
+        "realized_amount_out": swap.realized_amount_out,
+        "realized_amount_in": swap.realized_amount_in,
+        "price_impact_bps": swap.price_impact_bps,
+        "payer_sol_balance": swap.payer_sol_balance,
+        "observers": swap.observers,
+    })
+}
+
+/// Whether `table` is a plain, optionally database-qualified, table name.
+fn is_table_name(table: &str) -> bool {
+    let mut parts = table.split('.');
+    parts.clone().count() <= 2
+        && parts.all(|part| {
+            part.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+#[derive(Clone, Debug)]
+pub struct ClickHouseConfig {
+    /// HTTP interface of the server, e.g. `http://localhost:8123`
+    pub url: String,
+    pub table: String,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub create_table: bool,
+    /// Rows sent in one insert at most
+    pub batch_size: usize,
+    /// How long a batch waits for more rows after its first
+    pub flush_interval: Duration,
+    /// Swaps waiting to be inserted before new ones are dropped
+    pub queue_size: usize,
+    /// Attempts after the first for a transient failure
+    pub retries: u32,
+}
+
+pub struct ClickHouseSink {
+    sender: Option<mpsc::Sender<Vec<u8>>>,
+    inserter: Option<JoinHandle<()>>,
+    dropped: u64,
+    failed: Arc<AtomicU64>,
+}
+
+impl ClickHouseSink {
+    /// Creates the table if configured to and starts the thread inserting into it.
+    pub fn spawn(config: ClickHouseConfig) -> io::Result<Self> {
+        if !is_table_name(&config.table) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid table name {:?}", config.table),
+            ));
+        }
+        let client = Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(io::Error::other)?;
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        if config.create_table {
+            runtime
+                .block_on(send_with_retries(&config, || {
+                    request(&client, &config, None).body(create_table_sql(&config.table))
+                }))
+                .map_err(io::Error::other)?;
+        }
+        let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+        let failed = Arc::new(AtomicU64::new(0));
+        let inserter = thread::Builder::new().name("clickhouseInserter".to_string()).spawn({
+            let failed = failed.clone();
+            move || runtime.block_on(insert_all(client, config, receiver, failed))
+        })?;
+        Ok(Self {
+            sender: Some(sender),
+            inserter: Some(inserter),
+            dropped: 0,
+            failed,
+        })
+    }
+
+    /// Queues `row` for inserting, dropping it if the queue is full.
+    fn send(&mut self, row: Vec<u8>) -> io::Result<()> {
+        let Some(sender) = &self.sender else {
+            return Ok(());
+        };
+        match sender.try_send(row) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                if self.dropped.is_power_of_two() {
+                    eprintln!("ClickHouse queue full, {} swaps dropped so far", self.dropped);
+                }
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => Err(io::Error::other("ClickHouse inserter exited")),
+        }
+    }
+}
+
+impl SwapSink for ClickHouseSink {
+    fn name(&self) -> &'static str {
+        "clickhouse"
+    }
+
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, _packet: &[u8]) -> io::Result<()> {
+        self.send(serde_json::to_vec(&clickhouse_row(received_at_us, swap))?)
+    }
+
+    /// Inserts the swaps still queued.
+    fn finish(&mut self) -> io::Result<()> {
+        self.sender = None;
+        if let Some(inserter) = self.inserter.take() {
+            inserter.join().map_err(|_| io::Error::other("ClickHouse inserter panicked"))?;
+        }
+        if self.dropped > 0 {
+            println!("ClickHouse dropped {} swaps", self.dropped);
+        }
+        let failed = self.failed.load(Ordering::Relaxed);
+        if failed > 0 {
+            println!("ClickHouse failed to insert {} swaps", failed);
+        }
+        Ok(())
+    }
+}
+
+/// A request running `query`, or the statement in the body without one.
+fn request(client: &Client, config: &ClickHouseConfig, query: Option<&str>) -> RequestBuilder {
+    let mut request = client.post(&config.url);
+    if let Some(query) = query {
+        request = request.query(&[("query", query), ("input_format_skip_unknown_fields", "1")]);
+    }
+    if let Some(user) = &config.user {
+        request = request.header("X-ClickHouse-User", user);
+    }
+    if let Some(password) = &config.password {
+        request = request.header("X-ClickHouse-Key", password);
+    }
+    request
+}
+
+async fn insert_all(client: Client, config: ClickHouseConfig, mut rows: mpsc::Receiver<Vec<u8>>, failed: Arc<AtomicU64>) {
+    let query = format!("INSERT INTO {} FORMAT JSONEachRow", config.table);
+    let insert = |batch: Vec<u8>, count: u64| {
+        let (client, config, query, failed) = (&client, &config, &query, &failed);
+        async move {
+            let result = send_with_retries(config, || request(client, config, Some(query.as_str())).body(batch.clone())).await;
+            if let Err(e) = result {
+                eprintln!("Error inserting {} swaps into ClickHouse: {}", count, e);
+                failed.fetch_add(count, Ordering::Relaxed);
+            }
+        }
+    };
+    let mut batch = Vec::new();
+    let mut count = 0;
+    let mut deadline = Instant::now();
+    loop {
+        let row = if count == 0 {
+            rows.recv().await
+        } else {
+            match tokio::time::timeout_at(deadline, rows.recv()).await {
+                Ok(row) => row,
+                Err(_) => {
+                    insert(std::mem::take(&mut batch), std::mem::take(&mut count)).await;
+                    continue;
+                }
+            }
+        };
+        let Some(row) = row else {
+            break;
+        };
+        if count == 0 {
+            deadline = Instant::now() + config.flush_interval;
+        }
+        batch.extend_from_slice(&row);
+        batch.push(b'\n');
+        count += 1;
+        if count >= config.batch_size as u64 {
+            insert(std::mem::take(&mut batch), std::mem::take(&mut count)).await;
+        }
+    }
+    if count > 0 {
+        insert(batch, count).await;
+    }
+}
+
+async fn send_with_retries(config: &ClickHouseConfig, request: impl Fn() -> RequestBuilder) -> Result<(), String> {
+    let mut delay = MIN_RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        let error = match request().send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                // ClickHouse explains a failure in the body
+                let message = response.text().await.unwrap_or_default();
+                let error = format!("status {}: {}", status, message.trim());
+                if !is_transient(status) {
+                    return Err(error);
+                }
+                error
+            }
+            Err(e) => e.to_string(),
+        };
+        if attempt == config.retries {
+            return Err(format!("{} after {} attempts", error, attempt + 1));
+        }
+        attempt += 1;
+        tokio::time::sleep(delay).await;
+        delay = delay.saturating_mul(2).min(MAX_RETRY_DELAY);
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_clickhouse_row() {
+        let (_, swap) = crate::selftest::swap_base_in_transaction();
+        let row = clickhouse_row(1_700_000_000_123_456, &swap);
+        let mut keys: Vec<&str> = row.as_object().unwrap().keys().map(String::as_str).collect();
+        let mut columns: Vec<&str> = COLUMNS.iter().map(|(name, _)| *name).collect();
+        keys.sort_unstable();
+        columns.sort_unstable();
+        assert_eq!(keys, columns);
+        assert_eq!(row["received_at"], "2023-11-14 22:13:20.123456");
+        assert_eq!(row["version"], "0");
+        assert!(create_table_sql("hoho.swaps").starts_with("CREATE TABLE IF NOT EXISTS hoho.swaps (\n    received_at DateTime64"));
+
+        assert!(is_table_name("swaps") && is_table_name("hoho.swaps_v2"));
+        assert!(!is_table_name("swaps; DROP TABLE swaps") && !is_table_name("a.b.c") && !is_table_name("1swaps"));
+    }
+
+    #[test]
+    fn test_clickhouse_sink() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        // Fails the first insert with a 503, accepts the retry, and returns its request line and body
+        let server = thread::spawn(move || {
+            let mut request = (String::new(), vec![]);
+            for status in ["503 Service Unavailable", "200 OK"] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_ascii_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(length) = line.strip_prefix("content-length: ") {
+                        content_length = length.parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                request = (request_line, body);
+                let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+            request
+        });
+        let mut sink = ClickHouseSink::spawn(ClickHouseConfig {
+            url,
+            table: "swaps".to_string(),
+            user: None,
+            password: None,
+            create_table: false,
+            batch_size: 2,
+            flush_interval: Duration::from_secs(60),
+            queue_size: 2,
+            retries: 1,
+        })
+        .unwrap();
+        sink.send(b"{\"amount_in\":1}".to_vec()).unwrap();
+        sink.send(b"{\"amount_in\":2}".to_vec()).unwrap();
+        sink.finish().unwrap();
+        let (request_line, body) = server.join().unwrap();
+        assert!(request_line.starts_with("POST /?query=INSERT+INTO+swaps+FORMAT+JSONEachRow&"), "{}", request_line);
+        assert_eq!(body, b"{\"amount_in\":1}\n{\"amount_in\":2}\n");
+        assert_eq!(sink.failed.load(Ordering::Relaxed), 0);
+    }
+}
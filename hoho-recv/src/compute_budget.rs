@@ -0,0 +1,113 @@
+//! Priority fee of a transaction from its Compute Budget instructions.
+//!
+//! The priority fee is `compute_unit_price` micro-lamports for every unit of the
+//! compute unit limit, requested or not, on top of the base signature fee. Without
+//! `SetComputeUnitLimit` the runtime grants a default limit per instruction, and
+//! without `SetComputeUnitPrice` there is no priority fee at all.
+use solana_sdk::compute_budget;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::pubkey::Pubkey;
+
+// Borsh-encoded ComputeBudgetInstruction variant indices
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+// Runtime limits applied when the transaction does not set its own
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PriorityFee {
+    /// Micro-lamports per compute unit, 0 when no price is set
+    pub compute_unit_price: u64,
+    /// The requested limit, or the runtime default when none is requested
+    pub compute_unit_limit: u32,
+    /// Whether `compute_unit_limit` was requested with `SetComputeUnitLimit`
+    pub compute_unit_limit_requested: bool,
+}
+
+impl PriorityFee {
+    /// Lamports paid on top of the base fee, `price × limit` rounded up.
+    pub fn total_lamports(&self) -> u64 {
+        let micro_lamports = u128::from(self.compute_unit_price) * u128::from(self.compute_unit_limit);
+        u64::try_from(micro_lamports.div_ceil(MICRO_LAMPORTS_PER_LAMPORT)).unwrap_or(u64::MAX)
+    }
+}
+
+/// Reads the compute unit limit and price set by `instructions`. As in the runtime,
+/// the last instruction of each kind wins and malformed ones are ignored.
+pub fn decode_priority_fee(account_keys: &[Pubkey], instructions: &[CompiledInstruction]) -> PriorityFee {
+    let mut price = None;
+    let mut limit = None;
+    let mut other_instructions: u32 = 0;
+    for ix in instructions {
+        if account_keys.get(ix.program_id_index as usize) != Some(&compute_budget::id()) {
+            other_instructions = other_instructions.saturating_add(1);
+            continue;
+        }
+        match ix.data.split_first() {
+            Some((&SET_COMPUTE_UNIT_LIMIT, value)) => {
+                limit = value.try_into().ok().map(u32::from_le_bytes).or(limit);
+            }
+            Some((&SET_COMPUTE_UNIT_PRICE, value)) => {
+                price = value.try_into().ok().map(u64::from_le_bytes).or(price);
+            }
+            _ => {}
+        }
+    }
+    PriorityFee {
+        compute_unit_price: price.unwrap_or_default(),
+        compute_unit_limit: limit
+            .unwrap_or_else(|| other_instructions.saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT))
+            .min(MAX_COMPUTE_UNIT_LIMIT),
+        compute_unit_limit_requested: limit.is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::compute_budget::ComputeBudgetInstruction;
+    use solana_sdk::instruction::Instruction;
+    use solana_sdk::message::Message;
+
+    fn priority_fee(instructions: &[Instruction]) -> PriorityFee {
+        let message = Message::new(instructions, Some(&Pubkey::new_unique()));
+        decode_priority_fee(&message.account_keys, &message.instructions)
+    }
+
+    #[test]
+    fn test_decode_priority_fee() {
+        let swap = Instruction::new_with_bytes(Pubkey::new_unique(), &[9], vec![]);
+
+        let fee = priority_fee(&[
+            ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_500),
+            swap.clone(),
+        ]);
+        assert_eq!(
+            fee,
+            PriorityFee {
+                compute_unit_price: 1_500,
+                compute_unit_limit: 300_000,
+                compute_unit_limit_requested: true,
+            }
+        );
+        // 450_000_000 micro-lamports
+        assert_eq!(fee.total_lamports(), 450);
+
+        // A price without a limit pays for the default limit of the other instructions
+        let fee = priority_fee(&[ComputeBudgetInstruction::set_compute_unit_price(3), swap.clone(), swap.clone()]);
+        assert_eq!(fee.compute_unit_limit, 400_000);
+        assert!(!fee.compute_unit_limit_requested);
+        assert_eq!(fee.total_lamports(), 2);
+
+        // A limit alone, or neither, pays the base fee only
+        let fee = priority_fee(&[ComputeBudgetInstruction::set_compute_unit_limit(50_000), swap.clone()]);
+        assert_eq!((fee.compute_unit_price, fee.compute_unit_limit), (0, 50_000));
+        assert_eq!(fee.total_lamports(), 0);
+        assert_eq!(priority_fee(&[swap]).total_lamports(), 0);
+    }
+}
@@ -0,0 +1,65 @@
+//! Swaps reached through cross-program invocation (`--resolve-cpi`).
+//!
+//! An aggregator such as Jupiter swaps by invoking the DEX from its own program, so
+//! the DEX only shows up among the transaction's inner instructions. Those exist
+//! once the transaction executed, so a live packet with no top-level swap that
+//! invokes one of the candidate programs is looked up with `getTransaction`, retried
+//! while it is not confirmed yet. The analyzer waits for it, which can take seconds
+//! and holds back the packets behind it.
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use crate::backfill::{self, HistoricalTransaction};
+
+pub const JUPITER_V6_PROGRAM_ID: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+
+// A transaction usually confirms within a couple of slots of reaching the leader
+const FETCH_ATTEMPTS: usize = 4;
+const FETCH_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+pub struct CpiResolver {
+    rpc_client: Arc<RpcClient>,
+    programs: HashSet<Pubkey>,
+}
+
+impl CpiResolver {
+    pub fn new(rpc_client: Arc<RpcClient>, programs: impl IntoIterator<Item = Pubkey>) -> Self {
+        Self {
+            rpc_client,
+            programs: programs.into_iter().collect(),
+        }
+    }
+
+    /// Whether any top-level instruction invokes a candidate program.
+    pub fn invokes_candidate(&self, account_keys: &[Pubkey], instructions: &[CompiledInstruction]) -> bool {
+        instructions.iter().any(|ix| {
+            account_keys
+                .get(ix.program_id_index as usize)
+                .is_some_and(|program_id| self.programs.contains(program_id))
+        })
+    }
+
+    /// Fetches the confirmed transaction, or returns `None` if it did not confirm
+    /// within the retries.
+    pub fn fetch(&self, signature: &Signature) -> Option<HistoricalTransaction> {
+        let mut attempts = 0;
+        loop {
+            match backfill::fetch_transaction(&self.rpc_client, signature) {
+                Ok(confirmed) => return Some(confirmed),
+                Err(e) => {
+                    attempts += 1;
+                    if attempts == FETCH_ATTEMPTS {
+                        eprintln!("Unable to fetch inner instructions of {}: {}", signature, e);
+                        return None;
+                    }
+                }
+            }
+            thread::sleep(FETCH_RETRY_DELAY);
+        }
+    }
+}
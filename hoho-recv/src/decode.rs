@@ -0,0 +1,95 @@
+//! `hoho-recv decode`: analyzes one encoded `VersionedTransaction` given on the
+//! command line, in a file or on stdin, e.g. one copied from an explorer or a
+//! `getTransaction` response with `"encoding": "base64"`. The analyzer runs exactly
+//! as for a forwarded packet, so `--rpc-url` and the resolvers apply.
+use std::fs;
+use std::io::{self, Read};
+use std::str::FromStr;
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// Hex if the text only has hex digits, base64 otherwise
+    #[default]
+    Auto,
+    Base64,
+    Hex,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Encoding::Auto),
+            "base64" => Ok(Encoding::Base64),
+            "hex" => Ok(Encoding::Hex),
+            _ => Err(format!("unknown encoding {}, expected auto, base64 or hex", s)),
+        }
+    }
+}
+
+/// Reads the encoded transaction from `path`, or from stdin for `-`.
+pub fn read_input(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/// Decodes `text` to the bincode-serialized transaction. Surrounding whitespace is
+/// ignored, as is a `0x` prefix on hex.
+pub fn decode_transaction(text: &str, encoding: Encoding) -> Result<Vec<u8>, String> {
+    let text = text.trim();
+    let encoding = match encoding {
+        Encoding::Auto if !text.is_empty() && text.bytes().all(|b| b.is_ascii_hexdigit()) => Encoding::Hex,
+        Encoding::Auto if text.starts_with("0x") => Encoding::Hex,
+        Encoding::Auto => Encoding::Base64,
+        encoding => encoding,
+    };
+    match encoding {
+        Encoding::Hex => decode_hex(text.strip_prefix("0x").unwrap_or(text)),
+        _ => BASE64_STANDARD.decode(text).map_err(|e| format!("invalid base64: {}", e)),
+    }
+}
+
+fn decode_hex(text: &str) -> Result<Vec<u8>, String> {
+    text.as_bytes()
+        .chunks(2)
+        .enumerate()
+        .map(|(i, digits)| {
+            std::str::from_utf8(digits)
+                .ok()
+                .filter(|digits| digits.len() == 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| format!("invalid hex at offset {}", 2 * i))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selftest::swap_base_in_transaction;
+
+    #[test]
+    fn test_decode_transaction() {
+        let (data, _) = swap_base_in_transaction();
+        let base64 = BASE64_STANDARD.encode(&data);
+        let hex: String = data.iter().map(|b| format!("{:02x}", b)).collect();
+
+        assert_eq!(decode_transaction(&format!("{}\n", base64), Encoding::Auto), Ok(data.clone()));
+        assert_eq!(decode_transaction(&hex, Encoding::Auto), Ok(data.clone()));
+        assert_eq!(decode_transaction(&format!("0x{}", hex.to_uppercase()), Encoding::Auto), Ok(data.clone()));
+        assert_eq!(decode_transaction(&hex, Encoding::Hex), Ok(data));
+        // Hex digits are valid base64 too, so an explicit encoding decides
+        assert_eq!(decode_transaction("abcd", Encoding::Base64), Ok(vec![0x69, 0xb7, 0x1d]));
+        assert!(decode_transaction("abc", Encoding::Hex).is_err());
+        assert!(decode_transaction("zz", Encoding::Hex).is_err());
+        assert!(decode_transaction("not base64!", Encoding::Auto).is_err());
+    }
+}
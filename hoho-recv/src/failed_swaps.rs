@@ -0,0 +1,70 @@
+//! Dropping swaps whose transaction failed on-chain (`--drop-failed`).
+//!
+//! A reverted swap moved no market, and reverted sandwich attempts and failed snipes
+//! make up much of the noise. Whether a transaction failed is only known once it
+//! executed, so a live swap's status is looked up with `getSignatureStatuses`,
+//! retried until it is confirmed, and the analyzer waits for it like `--resolve-cpi`
+//! does. Backfilled transactions carry their error, so they cost no extra call.
+//! A swap whose status can't be had is kept.
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::TransactionError;
+
+// A transaction usually confirms within a couple of slots of reaching the leader
+const STATUS_ATTEMPTS: usize = 4;
+const STATUS_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+pub struct FailedSwapFilter {
+    rpc_client: Arc<RpcClient>,
+}
+
+impl FailedSwapFilter {
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        Self { rpc_client }
+    }
+
+    /// Whether the transaction failed. A status that can't be had is logged and the
+    /// swap kept.
+    pub fn failed(&self, signature: &Signature) -> bool {
+        match self.transaction_error(signature) {
+            Ok(err) => {
+                if let Some(err) = &err {
+                    println!("Dropping failed transaction {}: {}", signature, err);
+                }
+                err.is_some()
+            }
+            Err(e) => {
+                eprintln!("Unable to get the status of {}, keeping it: {}", signature, e);
+                false
+            }
+        }
+    }
+
+    /// Returns the error the confirmed transaction failed with, or `Ok(None)` if it
+    /// succeeded. Errs if it did not confirm within the retries.
+    pub fn transaction_error(&self, signature: &Signature) -> Result<Option<TransactionError>, String> {
+        let mut attempts = 0;
+        loop {
+            let status = self
+                .rpc_client
+                .get_signature_statuses(&[*signature])
+                .map(|response| response.value.into_iter().next().flatten());
+            let reason = match status {
+                Ok(Some(status)) if status.satisfies_commitment(CommitmentConfig::confirmed()) => {
+                    return Ok(status.err);
+                }
+                Ok(_) => "not confirmed".to_string(),
+                Err(e) => e.to_string(),
+            };
+            attempts += 1;
+            if attempts == STATUS_ATTEMPTS {
+                return Err(reason);
+            }
+            thread::sleep(STATUS_RETRY_DELAY);
+        }
+    }
+}
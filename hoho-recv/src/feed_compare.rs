@@ -0,0 +1,208 @@
+//! `hoho-recv compare-feeds`: races forward feeds from redundant forwarders.
+//!
+//! Each feed is sent to a port of its own and named on the command line, e.g.
+//! `--feed a=127.0.0.1:44444 --feed b=127.0.0.1:44445`. The first arrival of a
+//! signature on any feed opens a race; when a second feed delivers it within the
+//! window the race is reported with the first feed's lead, and a signature no other
+//! feed delivers counts as exclusive to its feed. Leads are measured on the
+//! receiver's clock when each datagram is read, so the forwarders' clocks don't
+//! need to agree. Nothing is analyzed.
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+use solana_sdk::signature::Signature;
+use crate::arrivals;
+use crate::capture;
+use crate::forward_header::{ForwardHeader, ForwardMode};
+use crate::thin::ThinTransaction;
+
+// Larger than any UDP datagram
+const MAX_DATAGRAM_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Feed {
+    pub name: String,
+    pub address: SocketAddr,
+}
+
+impl std::str::FromStr for Feed {
+    type Err = String;
+
+    /// Parses `NAME=HOST:PORT`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, address) = s.split_once('=').ok_or_else(|| format!("expected NAME=HOST:PORT, got {}", s))?;
+        Ok(Feed {
+            name: name.to_string(),
+            address: address.parse().map_err(|e| format!("invalid address {}: {}", address, e))?,
+        })
+    }
+}
+
+/// One signature delivered by two feeds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Race {
+    pub signature: Signature,
+    pub winner: usize,
+    pub loser: usize,
+    pub lead_us: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct FeedTally {
+    wins: u64,
+    losses: u64,
+    lead_us: u64,
+    exclusive: u64,
+}
+
+struct Pending {
+    feed: usize,
+    first_us: u64,
+    raced: bool,
+}
+
+pub struct FeedComparison {
+    names: Vec<String>,
+    window_us: u64,
+    pending: HashMap<Signature, Pending>,
+    // (first arrival, signature) in arrival order, for expiry
+    arrivals: VecDeque<(u64, Signature)>,
+    tallies: Vec<FeedTally>,
+}
+
+impl FeedComparison {
+    pub fn new(names: Vec<String>, window_ms: u64) -> Self {
+        Self {
+            tallies: vec![FeedTally::default(); names.len()],
+            names,
+            window_us: window_ms.saturating_mul(1_000),
+            pending: HashMap::new(),
+            arrivals: VecDeque::new(),
+        }
+    }
+
+    /// Records `signature` arriving on `feed` at `now_us`, returning the race it
+    /// completes, if any. Only the first two feeds to deliver a signature race.
+    pub fn record(&mut self, feed: usize, signature: Signature, now_us: u64) -> Option<Race> {
+        self.expire(now_us);
+        let Some(pending) = self.pending.get_mut(&signature) else {
+            self.pending.insert(signature, Pending { feed, first_us: now_us, raced: false });
+            self.arrivals.push_back((now_us, signature));
+            return None;
+        };
+        if pending.raced || pending.feed == feed {
+            return None;
+        }
+        pending.raced = true;
+        let race = Race {
+            signature,
+            winner: pending.feed,
+            loser: feed,
+            lead_us: now_us.saturating_sub(pending.first_us),
+        };
+        self.tallies[race.winner].wins += 1;
+        self.tallies[race.winner].lead_us += race.lead_us;
+        self.tallies[race.loser].losses += 1;
+        Some(race)
+    }
+
+    /// Closes the races whose window has passed by `now_us`.
+    pub fn expire(&mut self, now_us: u64) {
+        while let Some((first_us, signature)) = self.arrivals.front() {
+            if now_us.saturating_sub(*first_us) <= self.window_us {
+                break;
+            }
+            if let Some(pending) = self.pending.remove(signature) {
+                if !pending.raced {
+                    self.tallies[pending.feed].exclusive += 1;
+                }
+            }
+            self.arrivals.pop_front();
+        }
+    }
+
+    pub fn name(&self, feed: usize) -> &str {
+        &self.names[feed]
+    }
+
+    /// One line per feed, e.g. `a: first in 75.0% of 8 races, mean lead 420us, 3 exclusive`.
+    pub fn summary(&self) -> Vec<String> {
+        self.names
+            .iter()
+            .zip(&self.tallies)
+            .map(|(name, tally)| {
+                let races = tally.wins + tally.losses;
+                let win_rate = if races == 0 { 0.0 } else { 100.0 * tally.wins as f64 / races as f64 };
+                let mean_lead_us = tally.lead_us.checked_div(tally.wins).unwrap_or_default();
+                format!("{}: first in {:.1}% of {} races, mean lead {}us, {} exclusive",
+                        name, win_rate, races, mean_lead_us, tally.exclusive)
+            })
+            .collect()
+    }
+}
+
+/// Receives feed `index` on its own socket, sending the signature and receive time
+/// of each datagram. The thread exits once the receiving end is dropped.
+pub fn spawn_feed(index: usize, feed: &Feed, sender: Sender<(usize, Signature, u64)>) -> io::Result<JoinHandle<()>> {
+    let socket = UdpSocket::bind(feed.address)?;
+    thread::Builder::new().name(format!("feed{}", index)).spawn(move || {
+        let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            let size = match socket.recv(&mut buffer) {
+                Ok(size) => size,
+                Err(e) => {
+                    eprintln!("Error receiving from socket: {}", e);
+                    continue;
+                }
+            };
+            let received_at_us = capture::now_us();
+            let Ok((header, packet)) = ForwardHeader::parse(&buffer[..size]) else {
+                continue;
+            };
+            let signature = match header.mode {
+                ForwardMode::Full => arrivals::first_signature(packet),
+                ForwardMode::Thin => ThinTransaction::parse(packet).map(|thin| thin.signature),
+            };
+            if let Some(signature) = signature {
+                if sender.send((index, signature, received_at_us)).is_err() {
+                    return;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feed_comparison() {
+        let mut comparison = FeedComparison::new(vec!["a".to_string(), "b".to_string()], 1);
+        let x = Signature::from([1; 64]);
+        let y = Signature::from([2; 64]);
+        let z = Signature::from([3; 64]);
+        assert_eq!(comparison.record(0, x, 0), None);
+        // A repeat on the same feed is no race
+        assert_eq!(comparison.record(0, x, 100), None);
+        assert_eq!(comparison.record(1, x, 300), Some(Race { signature: x, winner: 0, loser: 1, lead_us: 300 }));
+        assert_eq!(comparison.record(1, y, 400), None);
+        assert_eq!(comparison.record(0, y, 500), Some(Race { signature: y, winner: 1, loser: 0, lead_us: 100 }));
+        assert_eq!(comparison.record(0, z, 600), None);
+        assert_eq!(comparison.record(0, x, 700), None);
+        // z's window closes without b delivering it
+        comparison.expire(2_000);
+        assert_eq!(comparison.record(1, z, 2_000), None);
+        assert_eq!(
+            comparison.summary(),
+            vec![
+                "a: first in 50.0% of 2 races, mean lead 300us, 1 exclusive",
+                "b: first in 50.0% of 2 races, mean lead 100us, 0 exclusive",
+            ]
+        );
+        assert_eq!("a=127.0.0.1:44444".parse(), Ok(Feed { name: "a".to_string(), address: "127.0.0.1:44444".parse().unwrap() }));
+        assert!("127.0.0.1:44444".parse::<Feed>().is_err());
+    }
+}
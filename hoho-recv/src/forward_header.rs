@@ -0,0 +1,218 @@
+//! Parsing of the header the validator-side packet forwarder prepends to
+//! every datagram (see `solana_core::packet_forwarder`):
+//!
+//! ```text
+//! version:      u8     (the layout of the rest, 1 or 2)
+//! clock:        u8     (0 = wall clock, 1 = monotonic anchored to wall clock at startup)
+//! mode:         u8     (0 = full packet, 1 = thin, see `crate::thin`)
+//! timestamp_us: u64 LE (microseconds since the UNIX epoch when the packet passed sigverify)
+//! slot:         u64 LE (the validator's tip slot, 0 if it was not known yet)
+//! leader:       [u8; 32] (version 2 only: the leader after the tip slot, zeros if
+//!                         it was not known yet)
+//! data:         the packet bytes or thin payload
+//! ```
+//!
+//! Forwarders write version 2 with `PACKET_FORWARDER_TAG_LEADER=true`.
+//!
+//! A receiver only parses the versions it knows and counts the rest as dropped, so
+//! when a later version adds fields the receivers can be upgraded ahead of the
+//! forwarders across a fleet. Forwarders from before the version byte existed
+//! send the clock first and must be upgraded together with their receivers.
+//!
+//! A packet lands in the tagged slot or a later one, which is what `--since-slot`
+//! filters on. `--max-packet-age-ms` drops packets by the timestamp instead, when
+//! the analyzer falls behind.
+//!
+//! Monotonic timestamps never jump on NTP adjustments but drift from the
+//! receiver's wall clock, so absolute latencies computed from them are only
+//! as accurate as the validator's clock was at startup.
+use solana_sdk::pubkey::Pubkey;
+
+/// The version [`ForwardHeader::encode`] writes for untagged headers.
+pub const FORWARD_HEADER_VERSION: u8 = 1;
+
+pub const FORWARD_HEADER_SIZE: usize = 1 + 1 + 1 + 8 + 8;
+
+/// The version [`ForwardHeader::encode`] writes for headers with a leader.
+pub const FORWARD_HEADER_VERSION_LEADER: u8 = 2;
+
+pub const FORWARD_HEADER_LEADER_SIZE: usize = FORWARD_HEADER_SIZE + 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderError {
+    /// Too short for its version's layout, or a field out of range
+    Malformed,
+    /// Sent by a forwarder newer (or older) than this receiver
+    UnknownVersion(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForwardClock {
+    Wall,
+    Monotonic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForwardMode {
+    Full,
+    Thin,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ForwardHeader {
+    pub clock: ForwardClock,
+    pub mode: ForwardMode,
+    pub timestamp_us: u64,
+    pub slot: Option<u64>,
+    /// The leader the packet was headed for, `None` for version 1 headers and
+    /// when the forwarder did not know it
+    pub leader: Option<Pubkey>,
+}
+
+impl ForwardHeader {
+    /// Splits a forwarded datagram into its header and packet bytes.
+    pub fn parse(datagram: &[u8]) -> Result<(Self, &[u8]), HeaderError> {
+        match *datagram.first().ok_or(HeaderError::Malformed)? {
+            1 => Self::parse_v1(&datagram[1..]).ok_or(HeaderError::Malformed),
+            2 => Self::parse_v2(&datagram[1..]).ok_or(HeaderError::Malformed),
+            version => Err(HeaderError::UnknownVersion(version)),
+        }
+    }
+
+    fn parse_v1(datagram: &[u8]) -> Option<(Self, &[u8])> {
+        let clock = match *datagram.first()? {
+            0 => ForwardClock::Wall,
+            1 => ForwardClock::Monotonic,
+            _ => return None,
+        };
+        let mode = match *datagram.get(1)? {
+            0 => ForwardMode::Full,
+            1 => ForwardMode::Thin,
+            _ => return None,
+        };
+        let timestamp_us = u64::from_le_bytes(datagram.get(2..10)?.try_into().ok()?);
+        let slot = u64::from_le_bytes(datagram.get(10..18)?.try_into().ok()?);
+        let header = Self {
+            clock,
+            mode,
+            timestamp_us,
+            slot: (slot != 0).then_some(slot),
+            leader: None,
+        };
+        Some((header, &datagram[18..]))
+    }
+
+    fn parse_v2(datagram: &[u8]) -> Option<(Self, &[u8])> {
+        let (mut header, rest) = Self::parse_v1(datagram)?;
+        let leader = Pubkey::new_from_array(rest.get(..32)?.try_into().ok()?);
+        header.leader = (leader != Pubkey::default()).then_some(leader);
+        Some((header, &rest[32..]))
+    }
+
+    /// Prepends the header to `data`, as the forwarder does. Headers with a leader
+    /// are written as version 2.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let clock = match self.clock {
+            ForwardClock::Wall => 0,
+            ForwardClock::Monotonic => 1,
+        };
+        let mode = match self.mode {
+            ForwardMode::Full => 0,
+            ForwardMode::Thin => 1,
+        };
+        let version = if self.leader.is_some() { FORWARD_HEADER_VERSION_LEADER } else { FORWARD_HEADER_VERSION };
+        let mut datagram = Vec::with_capacity(FORWARD_HEADER_LEADER_SIZE + data.len());
+        datagram.extend_from_slice(&[version, clock, mode]);
+        datagram.extend_from_slice(&self.timestamp_us.to_le_bytes());
+        datagram.extend_from_slice(&self.slot.unwrap_or_default().to_le_bytes());
+        if let Some(leader) = self.leader {
+            datagram.extend_from_slice(leader.as_ref());
+        }
+        datagram.extend_from_slice(data);
+        datagram
+    }
+
+    /// Microseconds between the forwarder's timestamp and `now_us`, zero if the
+    /// clocks disagree about the order.
+    pub fn latency_us(&self, now_us: u64) -> u64 {
+        now_us.saturating_sub(self.timestamp_us)
+    }
+
+    /// Whether more than `max_age_us` passed between the forwarder's timestamp and
+    /// `now_us`.
+    pub fn is_older_than(&self, max_age_us: u64, now_us: u64) -> bool {
+        self.latency_us(now_us) > max_age_us
+    }
+
+    /// Whether the packet is known to predate `slot`. Untagged packets are kept.
+    pub fn is_before_slot(&self, slot: u64) -> bool {
+        self.slot.is_some_and(|tagged| tagged < slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forward_header() {
+        let datagram = [1, 1, 0, 8, 7, 6, 5, 4, 3, 2, 1, 44, 1, 0, 0, 0, 0, 0, 0, 0xaa, 0xbb];
+        let (header, data) = ForwardHeader::parse(&datagram).unwrap();
+        assert_eq!(
+            header,
+            ForwardHeader {
+                clock: ForwardClock::Monotonic,
+                mode: ForwardMode::Full,
+                timestamp_us: 0x0102_0304_0506_0708,
+                slot: Some(300),
+                leader: None,
+            }
+        );
+        assert_eq!(data, [0xaa, 0xbb]);
+        assert_eq!(header.encode(data), datagram);
+        assert_eq!(header.latency_us(0x0102_0304_0506_0709), 1);
+        assert_eq!(header.latency_us(0), 0);
+        assert!(header.is_older_than(999, 0x0102_0304_0506_0708 + 1_000));
+        assert!(!header.is_older_than(1_000, 0x0102_0304_0506_0708 + 1_000));
+        assert!(header.is_before_slot(301));
+        assert!(!header.is_before_slot(300));
+
+        assert_eq!(ForwardHeader::parse(&datagram[..18]), Err(HeaderError::Malformed));
+        assert_eq!(ForwardHeader::parse(&[]), Err(HeaderError::Malformed));
+        assert_eq!(ForwardHeader::parse(&[3; 19]), Err(HeaderError::UnknownVersion(3)));
+        assert_eq!(ForwardHeader::parse(&[0; 19]), Err(HeaderError::UnknownVersion(0)));
+        let mut untagged = [0; 19];
+        untagged[0] = 1;
+        untagged[2] = 2;
+        assert_eq!(ForwardHeader::parse(&untagged), Err(HeaderError::Malformed));
+        untagged[2] = 1;
+        let (header, _) = ForwardHeader::parse(&untagged).unwrap();
+        assert_eq!(header.mode, ForwardMode::Thin);
+        assert_eq!(header.slot, None);
+        assert!(!header.is_before_slot(u64::MAX));
+    }
+
+    #[test]
+    fn test_parse_forward_header_leader() {
+        let leader = Pubkey::new_unique();
+        let header = ForwardHeader {
+            clock: ForwardClock::Wall,
+            mode: ForwardMode::Thin,
+            timestamp_us: 7,
+            slot: Some(300),
+            leader: Some(leader),
+        };
+        let datagram = header.encode(&[0xaa]);
+        assert_eq!(datagram.len(), FORWARD_HEADER_LEADER_SIZE + 1);
+        assert_eq!(datagram[0], FORWARD_HEADER_VERSION_LEADER);
+        assert_eq!(ForwardHeader::parse(&datagram), Ok((header, &[0xaa][..])));
+        assert_eq!(ForwardHeader::parse(&datagram[..FORWARD_HEADER_LEADER_SIZE - 1]), Err(HeaderError::Malformed));
+
+        // The forwarder did not know the leader yet
+        let mut unknown = datagram.clone();
+        unknown[FORWARD_HEADER_SIZE..FORWARD_HEADER_LEADER_SIZE].fill(0);
+        let (parsed, data) = ForwardHeader::parse(&unknown).unwrap();
+        assert_eq!(parsed, ForwardHeader { leader: None, ..header });
+        assert_eq!(data, [0xaa]);
+    }
+}
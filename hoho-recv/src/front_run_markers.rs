@@ -0,0 +1,39 @@
+//! Detection of "don't front-run me" marker accounts (`--front-run-markers`).
+//!
+//! A sender asks block engines not to bundle anything ahead of its transaction by
+//! including a marker account among its accounts, read-only and otherwise unused;
+//! Jito's is [`JITO_DONT_FRONT`]. A swap that carries one is flagged as protected.
+//! The markers are set once for the process and default to Jito's.
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use solana_sdk::pubkey::Pubkey;
+
+pub const JITO_DONT_FRONT: &str = "jitodontfront111111111111111111111111111111";
+
+static MARKERS: OnceLock<HashSet<Pubkey>> = OnceLock::new();
+
+/// Replaces the default marker accounts. Only the first call has an effect.
+pub fn set_front_run_markers(markers: impl IntoIterator<Item = Pubkey>) {
+    let _ = MARKERS.set(markers.into_iter().collect());
+}
+
+/// Whether any of `account_keys` is a marker account.
+pub fn has_front_run_marker(account_keys: &[Pubkey]) -> bool {
+    let markers = MARKERS.get_or_init(|| HashSet::from([Pubkey::from_str(JITO_DONT_FRONT).unwrap()]));
+    account_keys.iter().any(|key| markers.contains(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_front_run_marker() {
+        let marker = Pubkey::from_str(JITO_DONT_FRONT).unwrap();
+        let other = Pubkey::new_unique();
+        assert!(has_front_run_marker(&[other, marker]));
+        assert!(!has_front_run_marker(&[other]));
+        assert!(!has_front_run_marker(&[]));
+    }
+}
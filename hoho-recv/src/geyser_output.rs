@@ -0,0 +1,243 @@
+//! Geyser-shaped output of matched swaps (`--geyser FILE`, feature `geyser`).
+//!
+//! Each matched swap's transaction is written as the `SubscribeUpdate` a Yellowstone
+//! gRPC Geyser stream sends for a transaction (`geyser.proto` and
+//! `solana-storage.proto` of yellowstone-grpc-proto), preceded by its length as a
+//! varint like `--protobuf`, so code consuming Geyser transaction notifications
+//! can read the feed with the types it already has. The update's filter is the
+//! swap's DEX name, e.g. `raydium_v4`, for consumers routing by filter.
+//!
+//! The swaps are seen before they land, so the update carries no status meta,
+//! its slot is 0 and its index in the block 0; `created_at` is the time the
+//! packet was received. Everything else the decoded swap adds is left to
+//! `--protobuf` and `--json`.
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use prost::Message as _;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::transaction::VersionedTransaction;
+use crate::analyzer::AnalyzedSwap;
+use crate::output::SwapSink;
+
+// Messages of yellowstone-grpc-proto, written out to avoid a protoc build step.
+// Fields a transaction notification of an unconfirmed swap leaves unset are
+// left out, and the update_oneof holding the transaction is its one variant.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdate {
+    #[prost(string, repeated, tag = "1")]
+    pub filters: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(message, optional, tag = "4")]
+    pub transaction: ::core::option::Option<SubscribeUpdateTransaction>,
+    #[prost(message, optional, tag = "11")]
+    pub created_at: ::core::option::Option<Timestamp>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateTransaction {
+    #[prost(message, optional, tag = "1")]
+    pub transaction: ::core::option::Option<SubscribeUpdateTransactionInfo>,
+    #[prost(uint64, tag = "2")]
+    pub slot: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeUpdateTransactionInfo {
+    #[prost(bytes = "vec", tag = "1")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bool, tag = "2")]
+    pub is_vote: bool,
+    #[prost(message, optional, tag = "3")]
+    pub transaction: ::core::option::Option<Transaction>,
+    #[prost(uint64, tag = "5")]
+    pub index: u64,
+}
+
+// google.protobuf.Timestamp
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Timestamp {
+    #[prost(int64, tag = "1")]
+    pub seconds: i64,
+    #[prost(int32, tag = "2")]
+    pub nanos: i32,
+}
+
+// solana.storage.ConfirmedBlock
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Transaction {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub signatures: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    #[prost(message, optional, tag = "2")]
+    pub message: ::core::option::Option<Message>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Message {
+    #[prost(message, optional, tag = "1")]
+    pub header: ::core::option::Option<MessageHeader>,
+    #[prost(bytes = "vec", repeated, tag = "2")]
+    pub account_keys: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub recent_blockhash: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, repeated, tag = "4")]
+    pub instructions: ::prost::alloc::vec::Vec<CompiledInstruction>,
+    #[prost(bool, tag = "5")]
+    pub versioned: bool,
+    #[prost(message, repeated, tag = "6")]
+    pub address_table_lookups: ::prost::alloc::vec::Vec<MessageAddressTableLookup>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MessageHeader {
+    #[prost(uint32, tag = "1")]
+    pub num_required_signatures: u32,
+    #[prost(uint32, tag = "2")]
+    pub num_readonly_signed_accounts: u32,
+    #[prost(uint32, tag = "3")]
+    pub num_readonly_unsigned_accounts: u32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompiledInstruction {
+    #[prost(uint32, tag = "1")]
+    pub program_id_index: u32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub accounts: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MessageAddressTableLookup {
+    #[prost(bytes = "vec", tag = "1")]
+    pub account_key: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub writable_indexes: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub readonly_indexes: ::prost::alloc::vec::Vec<u8>,
+}
+
+impl Message {
+    fn new(message: &VersionedMessage) -> Self {
+        let header = message.header();
+        Self {
+            header: Some(MessageHeader {
+                num_required_signatures: header.num_required_signatures.into(),
+                num_readonly_signed_accounts: header.num_readonly_signed_accounts.into(),
+                num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts.into(),
+            }),
+            account_keys: message.static_account_keys().iter().map(|key| key.to_bytes().to_vec()).collect(),
+            recent_blockhash: message.recent_blockhash().to_bytes().to_vec(),
+            instructions: message
+                .instructions()
+                .iter()
+                .map(|ix| CompiledInstruction {
+                    program_id_index: ix.program_id_index.into(),
+                    accounts: ix.accounts.clone(),
+                    data: ix.data.clone(),
+                })
+                .collect(),
+            versioned: !matches!(message, VersionedMessage::Legacy(_)),
+            address_table_lookups: message
+                .address_table_lookups()
+                .unwrap_or_default()
+                .iter()
+                .map(|lookup| MessageAddressTableLookup {
+                    account_key: lookup.account_key.to_bytes().to_vec(),
+                    writable_indexes: lookup.writable_indexes.clone(),
+                    readonly_indexes: lookup.readonly_indexes.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl SubscribeUpdate {
+    /// The transaction notification for `tx`, in which `swap` was matched.
+    pub fn new(received_at_us: u64, swap: &AnalyzedSwap, tx: &VersionedTransaction) -> Self {
+        Self {
+            filters: vec![swap.dex.name().to_string()],
+            transaction: Some(SubscribeUpdateTransaction {
+                transaction: Some(SubscribeUpdateTransactionInfo {
+                    signature: swap.signature.as_ref().to_vec(),
+                    is_vote: false,
+                    transaction: Some(Transaction {
+                        signatures: tx.signatures.iter().map(|signature| signature.as_ref().to_vec()).collect(),
+                        message: Some(Message::new(&tx.message)),
+                    }),
+                    index: 0,
+                }),
+                slot: 0,
+            }),
+            created_at: Some(Timestamp {
+                seconds: (received_at_us / 1_000_000) as i64,
+                nanos: (received_at_us % 1_000_000 * 1_000) as i32,
+            }),
+        }
+    }
+}
+
+pub struct GeyserSwapWriter {
+    writer: BufWriter<File>,
+    buffer: Vec<u8>,
+}
+
+impl GeyserSwapWriter {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            buffer: Vec::new(),
+        })
+    }
+}
+
+impl SwapSink for GeyserSwapWriter {
+    fn name(&self) -> &'static str {
+        "geyser"
+    }
+
+    /// Writes the notification for `packet` and flushes it, so a reader on a pipe
+    /// sees it right away.
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, packet: &[u8]) -> io::Result<()> {
+        let tx: VersionedTransaction =
+            bincode::deserialize(packet).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.buffer.clear();
+        SubscribeUpdate::new(received_at_us, swap, &tx)
+            .encode_length_delimited(&mut self.buffer)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.writer.write_all(&self.buffer)?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amm::AmmRegistry;
+    use crate::analyzer::{analyze_transaction, Resolvers};
+    use crate::selftest::swap_base_in_transaction;
+
+    #[test]
+    fn test_geyser_output() {
+        let (data, _) = swap_base_in_transaction();
+        let swap = analyze_transaction(&data, &AmmRegistry::default(), &mut Resolvers::default()).unwrap().unwrap();
+        let tx: VersionedTransaction = bincode::deserialize(&data).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("swaps.pb");
+        let mut writer = GeyserSwapWriter::create(&path).unwrap();
+        writer.write_swap(1_500_000, &swap, &data).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        let update = SubscribeUpdate::decode_length_delimited(bytes.as_slice()).unwrap();
+        assert_eq!(update, SubscribeUpdate::new(1_500_000, &swap, &tx));
+        assert_eq!(update.filters, vec!["raydium_v4".to_string()]);
+        assert_eq!(update.created_at, Some(Timestamp { seconds: 1, nanos: 500_000_000 }));
+        let info = update.transaction.unwrap().transaction.unwrap();
+        assert_eq!(info.signature, swap.signature.as_ref());
+        let message = info.transaction.unwrap().message.unwrap();
+        assert_eq!(message.account_keys.len(), tx.message.static_account_keys().len());
+        assert_eq!(message.instructions[0].data, tx.message.instructions()[0].data);
+        assert!(message.versioned);
+        assert!(writer.write_swap(1, &swap, &[0; 3]).is_err());
+    }
+}
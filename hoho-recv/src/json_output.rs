@@ -0,0 +1,391 @@
+//! JSON lines output of matched swaps (`--json FILE`).
+//!
+//! Each matched swap is written as one JSON object per line. Pubkeys and signatures
+//! are base58 strings and optional accounts are `null` when unresolved. `signature`
+//! is the transaction's first, identifying signature and `co_signatures` lists the
+//! rest, from co-signers, in order. `--fields` keeps only the listed keys of
+//! [`SWAP_FIELDS`], here and in webhook bodies.
+//! `version` is the transaction's message format, `"legacy"` or `0` as below.
+//! `lookup_tables` lists the lookup tables a V0 transaction loads accounts from, in
+//! the `address_table_lookups` form below, whether or not they were resolved. With
+//! `--full-json` every record also carries the whole message under `"message"`:
+//!
+//! ```text
+//! version:               "legacy" or 0
+//! header:                the three header counts
+//! account_keys:          static account keys, in message order
+//! recent_blockhash:      base58
+//! instructions:          [{ program_id_index, program_id, accounts: [index], data: base64 }]
+//! address_table_lookups: V0 only, [{ account_key, writable_indexes, readonly_indexes }]
+//! ```
+//!
+//! With `--include-packet` every record also carries the packet the swap was
+//! decoded from, base64 under `"packet"`, for consumers that re-parse it or verify
+//! its signatures themselves. Like `"message"`, it is not one of [`SWAP_FIELDS`].
+//!
+//! `--top-mints` reports are written between the swaps as objects with a
+//! `"report": "top_mints"` key, see [`mint_activity_json`].
+//!
+//! Instruction account indices are kept as serialized. For V0 messages, indices past
+//! the static keys refer to the lookup tables' writable addresses, then readonly ones,
+//! and `program_id` is always a static key.
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use solana_sdk::message::v0::MessageAddressTableLookup;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+use crate::analyzer::AnalyzedSwap;
+use crate::mint_activity::MintActivityReport;
+use crate::output::SwapSink;
+
+/// The keys of a swap object, in the order they are written.
+pub const SWAP_FIELDS: [&str; 39] = [
+    "received_at_us",
+    "signature",
+    "co_signatures",
+    "dex",
+    "curve",
+    "discriminator",
+    "amount_in",
+    "min_amount_out",
+    "pool_coin_account",
+    "pool_pc_account",
+    "model_data_account",
+    "user_source_account",
+    "user_destination_account",
+    "user_owner",
+    "source_mint",
+    "destination_mint",
+    "source_decimals",
+    "destination_decimals",
+    "compute_unit_price",
+    "compute_unit_limit",
+    "compute_unit_limit_requested",
+    "priority_fee_lamports",
+    "compute_units_consumed",
+    "transfers",
+    "lookup_tables",
+    "transaction_size",
+    "version",
+    "front_run_protected",
+    "zero_min_out",
+    "pool_fee_bps",
+    "expected_amount_out",
+    "realized_amount_out",
+    "realized_amount_in",
+    "mint_events",
+    "burn_events",
+    "authority_changes",
+    "payer_sol_balance",
+    "observers",
+    "price_impact_bps",
+];
+
+static SELECTED_FIELDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+
+/// Checks a `--fields` list against [`SWAP_FIELDS`], naming the unknown ones.
+pub fn parse_swap_fields<'a>(names: impl IntoIterator<Item = &'a str>) -> Result<Vec<&'static str>, String> {
+    let mut unknown = Vec::new();
+    let fields = names
+        .into_iter()
+        .filter_map(|name| {
+            let field = SWAP_FIELDS.iter().copied().find(|field| *field == name);
+            if field.is_none() {
+                unknown.push(name);
+            }
+            field
+        })
+        .collect();
+    if unknown.is_empty() {
+        Ok(fields)
+    } else {
+        Err(format!("unknown fields {:?}, expected any of {:?}", unknown, SWAP_FIELDS))
+    }
+}
+
+/// Restricts every swap object from now on to `fields`. Only the first call has an
+/// effect; until then objects carry all of [`SWAP_FIELDS`].
+pub fn set_swap_fields(fields: Vec<&'static str>) {
+    let _ = SELECTED_FIELDS.set(fields);
+}
+
+fn pubkey_json(pubkey: Option<Pubkey>) -> Value {
+    pubkey.map_or(Value::Null, |pubkey| Value::String(pubkey.to_string()))
+}
+
+fn lookups_json(lookups: &[MessageAddressTableLookup]) -> Value {
+    lookups
+        .iter()
+        .map(|lookup| {
+            json!({
+                "account_key": lookup.account_key.to_string(),
+                "writable_indexes": lookup.writable_indexes,
+                "readonly_indexes": lookup.readonly_indexes,
+            })
+        })
+        .collect()
+}
+
+pub fn swap_json(received_at_us: u64, swap: &AnalyzedSwap) -> Value {
+    let value = json!({
+        "received_at_us": received_at_us,
+        "signature": swap.signature.to_string(),
+        "co_signatures": swap.co_signatures.iter().map(|signature| signature.to_string()).collect::<Vec<_>>(),
+        "dex": swap.dex.name(),
+        "curve": swap.curve().name(),
+        "discriminator": swap.discriminator,
+        "amount_in": swap.amount_in,
+        "min_amount_out": swap.min_amount_out,
+        "pool_coin_account": pubkey_json(swap.pool_coin_account),
+        "pool_pc_account": pubkey_json(swap.pool_pc_account),
+        "model_data_account": pubkey_json(swap.model_data_account),
+        "user_source_account": pubkey_json(swap.user_source_account),
+        "user_destination_account": pubkey_json(swap.user_destination_account),
+        "user_owner": pubkey_json(swap.user_owner),
+        "source_mint": pubkey_json(swap.source_mint),
+        "destination_mint": pubkey_json(swap.destination_mint),
+        "source_decimals": swap.source_decimals,
+        "destination_decimals": swap.destination_decimals,
+        "compute_unit_price": swap.priority_fee.compute_unit_price,
+        "compute_unit_limit": swap.priority_fee.compute_unit_limit,
+        "compute_unit_limit_requested": swap.priority_fee.compute_unit_limit_requested,
+        "priority_fee_lamports": swap.priority_fee.total_lamports(),
+        "compute_units_consumed": swap.compute_units_consumed,
+        "transfers": swap.transfers.iter().map(|transfer| json!({
+            "source": transfer.source.to_string(),
+            "destination": transfer.destination.to_string(),
+            "lamports": transfer.lamports,
+        })).collect::<Vec<_>>(),
+        "lookup_tables": lookups_json(&swap.lookup_tables),
+        "transaction_size": swap.transaction_size,
+        "version": swap.version,
+        "front_run_protected": swap.front_run_protected,
+        "zero_min_out": swap.zero_min_out,
+        "pool_fee_bps": swap.pool_fee_bps,
+        "expected_amount_out": swap.expected_amount_out,
+        "realized_amount_out": swap.realized_amount_out,
+        "realized_amount_in": swap.realized_amount_in,
+        "mint_events": swap.mint_events.iter().map(|event| json!({
+            "mint": event.mint.to_string(),
+            "destination": event.destination.to_string(),
+            "amount": event.amount,
+            "decimals": event.decimals,
+        })).collect::<Vec<_>>(),
+        "burn_events": swap.burn_events.iter().map(|event| json!({
+            "mint": event.mint.to_string(),
+            "account": event.account.to_string(),
+            "amount": event.amount,
+            "decimals": event.decimals,
+        })).collect::<Vec<_>>(),
+        "authority_changes": swap.authority_changes.iter().map(|change| json!({
+            "account": change.account.to_string(),
+            "authority_type": change.authority_type.name(),
+            "new_authority": change.new_authority.map(|authority| authority.to_string()),
+        })).collect::<Vec<_>>(),
+        "payer_sol_balance": swap.payer_sol_balance,
+        "observers": swap.observers,
+        "price_impact_bps": swap.price_impact_bps,
+    });
+    match (SELECTED_FIELDS.get(), value) {
+        (Some(fields), Value::Object(mut all)) => Value::Object(
+            fields
+                .iter()
+                .filter_map(|field| Some((field.to_string(), all.remove(*field)?)))
+                .collect(),
+        ),
+        (_, value) => value,
+    }
+}
+
+/// A `--top-mints` report, told apart from swaps by its `"report"` key.
+pub fn mint_activity_json(report: &MintActivityReport) -> Value {
+    json!({
+        "report": "top_mints",
+        "start_us": report.start_us,
+        "end_us": report.end_us,
+        "swaps": report.swaps,
+        "untracked_swaps": report.untracked_swaps,
+        "top": report.top.iter().map(|(mint, count)| json!({
+            "mint": mint.to_string(),
+            "swaps": count.swaps,
+            "volume": count.volume,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+pub fn message_json(message: &VersionedMessage) -> Value {
+    let account_keys = message.static_account_keys();
+    let header = message.header();
+    let instructions: Vec<Value> = message
+        .instructions()
+        .iter()
+        .map(|ix| {
+            json!({
+                "program_id_index": ix.program_id_index,
+                "program_id": pubkey_json(account_keys.get(ix.program_id_index as usize).copied()),
+                "accounts": ix.accounts,
+                "data": BASE64_STANDARD.encode(&ix.data),
+            })
+        })
+        .collect();
+    let mut value = json!({
+        "version": match message {
+            VersionedMessage::Legacy(_) => json!("legacy"),
+            VersionedMessage::V0(_) => json!(0),
+        },
+        "header": {
+            "num_required_signatures": header.num_required_signatures,
+            "num_readonly_signed_accounts": header.num_readonly_signed_accounts,
+            "num_readonly_unsigned_accounts": header.num_readonly_unsigned_accounts,
+        },
+        "account_keys": account_keys.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+        "recent_blockhash": message.recent_blockhash().to_string(),
+        "instructions": instructions,
+    });
+    if let Some(lookups) = message.address_table_lookups() {
+        value["address_table_lookups"] = lookups_json(lookups);
+    }
+    value
+}
+
+pub struct JsonLinesWriter {
+    writer: BufWriter<File>,
+    full: bool,
+    include_packet: bool,
+}
+
+impl JsonLinesWriter {
+    /// Creates the output file; with `full` each record includes the whole message,
+    /// with `include_packet` the packet itself.
+    pub fn create<P: AsRef<Path>>(path: P, full: bool, include_packet: bool) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            full,
+            include_packet,
+        })
+    }
+}
+
+impl SwapSink for JsonLinesWriter {
+    fn name(&self) -> &'static str {
+        "JSON"
+    }
+
+    /// Writes `swap` as one line and flushes it, so the file survives an exit on match.
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, packet: &[u8]) -> io::Result<()> {
+        let mut value = swap_json(received_at_us, swap);
+        if self.full {
+            // The packet already deserialized once to produce `swap`
+            if let Ok(tx) = bincode::deserialize::<VersionedTransaction>(packet) {
+                value["message"] = message_json(&tx.message);
+            }
+        }
+        if self.include_packet {
+            value["packet"] = Value::String(BASE64_STANDARD.encode(packet));
+        }
+        serde_json::to_writer(&mut self.writer, &value)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    fn write_report(&mut self, report: &MintActivityReport) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, &mint_activity_json(report))?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::instruction::CompiledInstruction;
+    use solana_sdk::message::v0::MessageAddressTableLookup;
+    use solana_sdk::message::{v0, Message, MessageHeader};
+
+    fn header() -> MessageHeader {
+        MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 1,
+        }
+    }
+
+    #[test]
+    fn test_swap_fields() {
+        let (_, swap) = crate::selftest::swap_base_in_transaction();
+        let value = swap_json(0, &swap);
+        let mut keys: Vec<&str> = value.as_object().unwrap().keys().map(String::as_str).collect();
+        let mut fields = SWAP_FIELDS.to_vec();
+        keys.sort_unstable();
+        fields.sort_unstable();
+        assert_eq!(keys, fields);
+        assert_eq!(value["version"], 0);
+
+        assert_eq!(parse_swap_fields(["signature", "amount_in"]), Ok(vec!["signature", "amount_in"]));
+        let error = parse_swap_fields(["signature", "fee_payer"]).unwrap_err();
+        assert!(error.starts_with("unknown fields [\"fee_payer\"]"), "{}", error);
+    }
+
+    #[test]
+    fn test_message_json_legacy() {
+        let keys = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let message = VersionedMessage::Legacy(Message {
+            header: header(),
+            account_keys: keys.clone(),
+            recent_blockhash: Hash::default(),
+            instructions: vec![CompiledInstruction::new_from_raw_parts(1, vec![1, 2, 3], vec![0])],
+        });
+        let value = message_json(&message);
+        assert_eq!(value["version"], "legacy");
+        assert_eq!(value["account_keys"][1], keys[1].to_string());
+        assert_eq!(
+            value["instructions"][0],
+            json!({
+                "program_id_index": 1,
+                "program_id": keys[1].to_string(),
+                "accounts": [0],
+                "data": "AQID",
+            })
+        );
+        assert!(value.get("address_table_lookups").is_none());
+    }
+
+    #[test]
+    fn test_message_json_v0() {
+        let keys = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let table = Pubkey::new_unique();
+        let message = VersionedMessage::V0(v0::Message {
+            header: header(),
+            account_keys: keys.clone(),
+            recent_blockhash: Hash::default(),
+            instructions: vec![CompiledInstruction::new_from_raw_parts(1, vec![], vec![0, 2, 3])],
+            address_table_lookups: vec![MessageAddressTableLookup {
+                account_key: table,
+                writable_indexes: vec![7],
+                readonly_indexes: vec![9],
+            }],
+        });
+        let value = message_json(&message);
+        assert_eq!(value["version"], 0);
+        assert_eq!(value["instructions"][0]["accounts"], json!([0, 2, 3]));
+        assert_eq!(value["instructions"][0]["data"], "");
+        assert_eq!(
+            value["address_table_lookups"],
+            json!([{
+                "account_key": table.to_string(),
+                "writable_indexes": [7],
+                "readonly_indexes": [9],
+            }])
+        );
+        // Round trips as valid JSON
+        let text = serde_json::to_string(&value).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), value);
+    }
+}
@@ -0,0 +1,83 @@
+//! Decimals of well-known mints, known without asking the RPC node (`--decimals`).
+//!
+//! A mint's decimals never change, so for the tokens most swaps trade they are
+//! shipped in [`DEFAULT_DECIMALS`] and consulted before `transferChecked`-less swaps
+//! fall back to `--resolve-token-metadata`. Amounts of these mints are scaled even
+//! without an RPC node. `--decimals MINT:N` adds mints or corrects the table; the
+//! table is set once for the process.
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use solana_sdk::pubkey::Pubkey;
+use crate::quote_filter::{USDC_MINT, USDT_MINT, WSOL_MINT};
+
+pub const DEFAULT_DECIMALS: [(&str, u8); 8] = [
+    (WSOL_MINT, 9),
+    (USDC_MINT, 6),
+    (USDT_MINT, 6),
+    // mSOL
+    ("mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So", 9),
+    // JitoSOL
+    ("J1toso1uCk3RLmjorhTtrVwY9HJ7X8V9yYac6Y7kGCPn", 9),
+    // RAY
+    ("4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R", 6),
+    // JUP
+    ("JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN", 6),
+    // BONK
+    ("DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263", 5),
+];
+
+static DECIMALS: OnceLock<HashMap<Pubkey, u8>> = OnceLock::new();
+
+/// Parses a `--decimals` entry, `MINT:N`.
+pub fn parse_decimals(s: &str) -> Result<(Pubkey, u8), String> {
+    let (mint, decimals) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid decimals {}, expected MINT:N", s))?;
+    let mint = Pubkey::from_str(mint).map_err(|_| format!("invalid mint {} in decimals {}", mint, s))?;
+    let decimals = decimals
+        .parse()
+        .map_err(|_| format!("invalid decimals {} for {}, expected 0 to 255", decimals, mint))?;
+    Ok((mint, decimals))
+}
+
+/// Adds to or replaces entries of the default table. Only the first call has an
+/// effect.
+pub fn set_decimals(overrides: impl IntoIterator<Item = (Pubkey, u8)>) {
+    let _ = DECIMALS.set(decimals_table(overrides));
+}
+
+/// The decimals of `mint`, if it is in the table.
+pub fn known_decimals(mint: &Pubkey) -> Option<u8> {
+    DECIMALS.get_or_init(|| decimals_table([])).get(mint).copied()
+}
+
+fn decimals_table(overrides: impl IntoIterator<Item = (Pubkey, u8)>) -> HashMap<Pubkey, u8> {
+    DEFAULT_DECIMALS
+        .iter()
+        .map(|(mint, decimals)| (Pubkey::from_str(mint).unwrap(), *decimals))
+        .chain(overrides)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_decimals() {
+        let mint = Pubkey::new_unique();
+        assert_eq!(parse_decimals(&format!("{}:3", mint)), Ok((mint, 3)));
+        assert!(parse_decimals(&mint.to_string()).is_err());
+        assert!(parse_decimals(&format!("{}:256", mint)).is_err());
+        assert!(parse_decimals("sol:9").is_err());
+
+        let usdc = Pubkey::from_str(USDC_MINT).unwrap();
+        let table = decimals_table([(mint, 3), (usdc, 7)]);
+        assert_eq!(table.get(&mint), Some(&3));
+        assert_eq!(table.get(&usdc), Some(&7));
+        assert_eq!(table.len(), DEFAULT_DECIMALS.len() + 1);
+        assert_eq!(known_decimals(&Pubkey::from_str(WSOL_MINT).unwrap()), Some(9));
+        assert_eq!(known_decimals(&Pubkey::new_unique()), None);
+    }
+}
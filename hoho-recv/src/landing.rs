@@ -0,0 +1,290 @@
+//! Following matched swaps to the block they land in (`--track-landing`).
+//!
+//! A forwarded packet has only reached the validator; whether the swap executed is
+//! decided later by some leader. Each matched swap's signature is kept pending while
+//! a thread polls the RPC node with `getBlocks` and `getBlock` (signatures only) for
+//! every new confirmed block and resolves the pending signatures it contains. A
+//! landed swap is reported with its slot, how many slots after the forwarder's tip
+//! slot that was, and the latency from forwarding to the block being seen. Blocks
+//! are only fetched once confirmed, so that latency includes the confirmation delay
+//! and the poll interval; the slot count is the sharper measure.
+//!
+//! Swaps still pending after the timeout are reported as not landed; dropped,
+//! expired and failed-before-execution transactions all end up there. The pending
+//! set is bounded, evicting the oldest signature when full.
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::config::RpcBlockConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::TransactionDetails;
+use crate::capture;
+use crate::forward_header::ForwardHeader;
+
+// Bounds memory if swaps arrive faster than they land or expire
+const MAX_PENDING: usize = 100_000;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+// After an outage, skip ahead rather than fetch every block missed
+const MAX_CATCH_UP_SLOTS: u64 = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Landing {
+    pub signature: Signature,
+    pub landed_slot: u64,
+    /// Slots between the forwarder's tip slot and the landing one, if it tagged one
+    pub slots_after: Option<u64>,
+    /// From the forward header timestamp to when the block was seen
+    pub latency_us: u64,
+}
+
+impl fmt::Display for Landing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Landed {} in slot {}", self.signature, self.landed_slot)?;
+        if let Some(slots_after) = self.slots_after {
+            write!(f, ", {} slots after forwarding", slots_after)?;
+        }
+        write!(f, " (seen {}ms after forwarding)", self.latency_us / 1_000)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Pending {
+    forwarded_at_us: u64,
+    forwarded_slot: Option<u64>,
+    tracked_at_us: u64,
+}
+
+/// Signatures waiting for their block.
+pub struct PendingLandings {
+    timeout_us: u64,
+    pending: HashMap<Signature, Pending>,
+    // (tracked at, signature) in tracking order, for expiry
+    order: VecDeque<(u64, Signature)>,
+}
+
+impl PendingLandings {
+    pub fn new(timeout_us: u64) -> Self {
+        Self {
+            timeout_us,
+            pending: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Starts waiting for `signature`, forwarded with `header`. Returns the
+    /// signature evicted to make room, if the set was full.
+    pub fn track(&mut self, signature: Signature, header: &ForwardHeader, now_us: u64) -> Option<Signature> {
+        if self.pending.contains_key(&signature) {
+            return None;
+        }
+        let evicted = (self.pending.len() == MAX_PENDING).then(|| self.pop_oldest()).flatten();
+        self.pending.insert(
+            signature,
+            Pending {
+                forwarded_at_us: header.timestamp_us,
+                forwarded_slot: header.slot,
+                tracked_at_us: now_us,
+            },
+        );
+        self.order.push_back((now_us, signature));
+        evicted
+    }
+
+    /// Resolves the pending signatures among those of the block at `slot`.
+    pub fn resolve<'a>(&mut self, slot: u64, signatures: impl IntoIterator<Item = &'a str>, now_us: u64) -> Vec<Landing> {
+        signatures
+            .into_iter()
+            .filter_map(|signature| Signature::from_str(signature).ok())
+            .filter_map(|signature| {
+                let pending = self.pending.remove(&signature)?;
+                Some(Landing {
+                    signature,
+                    landed_slot: slot,
+                    slots_after: pending.forwarded_slot.map(|forwarded| slot.saturating_sub(forwarded)),
+                    latency_us: now_us.saturating_sub(pending.forwarded_at_us),
+                })
+            })
+            .collect()
+    }
+
+    /// Gives up on the signatures pending for longer than the timeout.
+    pub fn expire(&mut self, now_us: u64) -> Vec<Signature> {
+        let mut expired = vec![];
+        while let Some(&(tracked_at_us, signature)) = self.order.front() {
+            if now_us.saturating_sub(tracked_at_us) <= self.timeout_us {
+                break;
+            }
+            self.order.pop_front();
+            if self.is_tracked_at(&signature, tracked_at_us) {
+                self.pending.remove(&signature);
+                expired.push(signature);
+            }
+        }
+        expired
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Removes the oldest entry still pending; landed ones leave stale entries behind.
+    fn pop_oldest(&mut self) -> Option<Signature> {
+        while let Some((tracked_at_us, signature)) = self.order.pop_front() {
+            if self.is_tracked_at(&signature, tracked_at_us) {
+                self.pending.remove(&signature);
+                return Some(signature);
+            }
+        }
+        None
+    }
+
+    fn is_tracked_at(&self, signature: &Signature, tracked_at_us: u64) -> bool {
+        self.pending
+            .get(signature)
+            .is_some_and(|pending| pending.tracked_at_us == tracked_at_us)
+    }
+}
+
+pub struct LandingTracker {
+    pending: Arc<Mutex<PendingLandings>>,
+}
+
+impl LandingTracker {
+    /// Starts the thread polling `rpc_client` for confirmed blocks.
+    pub fn spawn(rpc_client: Arc<RpcClient>, timeout: Duration) -> io::Result<Self> {
+        let pending = Arc::new(Mutex::new(PendingLandings::new(timeout.as_micros() as u64)));
+        let poller_pending = pending.clone();
+        thread::Builder::new()
+            .name("landingPoller".to_string())
+            .spawn(move || poll_blocks(&rpc_client, &poller_pending, timeout.as_secs()))?;
+        Ok(Self { pending })
+    }
+
+    /// Waits for `signature`, forwarded with `header`, to land.
+    pub fn track(&self, signature: Signature, header: &ForwardHeader) {
+        let evicted = self.pending.lock().unwrap().track(signature, header, capture::now_us());
+        if let Some(evicted) = evicted {
+            println!("Stopped waiting for {} to land, too many pending", evicted);
+        }
+    }
+}
+
+fn poll_blocks(rpc_client: &RpcClient, pending: &Mutex<PendingLandings>, timeout_secs: u64) {
+    let commitment = CommitmentConfig::confirmed();
+    let config = RpcBlockConfig {
+        transaction_details: Some(TransactionDetails::Signatures),
+        rewards: Some(false),
+        commitment: Some(commitment),
+        max_supported_transaction_version: Some(0),
+        ..RpcBlockConfig::default()
+    };
+    let mut next_slot = None;
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        for signature in pending.lock().unwrap().expire(capture::now_us()) {
+            println!("{} did not land within {}s", signature, timeout_secs);
+        }
+        let tip = match rpc_client.get_slot_with_commitment(commitment) {
+            Ok(tip) => tip,
+            Err(e) => {
+                eprintln!("Error fetching the confirmed slot: {}", e);
+                continue;
+            }
+        };
+        let start = next_slot.unwrap_or(tip).max(tip.saturating_sub(MAX_CATCH_UP_SLOTS));
+        if start > tip {
+            continue;
+        }
+        // Skipped slots have no block
+        let slots = match rpc_client.get_blocks_with_commitment(start, Some(tip), commitment) {
+            Ok(slots) => slots,
+            Err(e) => {
+                eprintln!("Error listing blocks {}..={}: {}", start, tip, e);
+                continue;
+            }
+        };
+        for slot in slots {
+            if pending.lock().unwrap().is_empty() {
+                break;
+            }
+            match rpc_client.get_block_with_config(slot, config) {
+                Ok(block) => {
+                    let signatures = block.signatures.unwrap_or_default();
+                    let landings = pending
+                        .lock()
+                        .unwrap()
+                        .resolve(slot, signatures.iter().map(String::as_str), capture::now_us());
+                    for landing in landings {
+                        println!("{}", landing);
+                    }
+                }
+                Err(e) => eprintln!("Error fetching block {}: {}", slot, e),
+            }
+        }
+        next_slot = Some(tip + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forward_header::{ForwardClock, ForwardMode};
+
+    fn header(timestamp_us: u64, slot: Option<u64>) -> ForwardHeader {
+        ForwardHeader {
+            clock: ForwardClock::Wall,
+            mode: ForwardMode::Full,
+            timestamp_us,
+            slot,
+            leader: None,
+        }
+    }
+
+    #[test]
+    fn test_pending_landings() {
+        let mut pending = PendingLandings::new(1_000);
+        let (landing, untagged, lost) = (Signature::new_unique(), Signature::new_unique(), Signature::new_unique());
+        assert_eq!(pending.track(landing, &header(100, Some(10)), 200), None);
+        assert_eq!(pending.track(untagged, &header(150, None), 200), None);
+        assert_eq!(pending.track(lost, &header(300, Some(11)), 400), None);
+        // Tracking again keeps the first arrival
+        assert_eq!(pending.track(landing, &header(500, Some(12)), 500), None);
+        assert_eq!(pending.len(), 3);
+
+        let block = [Signature::new_unique().to_string(), landing.to_string(), untagged.to_string()];
+        assert_eq!(
+            pending.resolve(13, block.iter().map(String::as_str), 1_100),
+            vec![
+                Landing {
+                    signature: landing,
+                    landed_slot: 13,
+                    slots_after: Some(3),
+                    latency_us: 1_000,
+                },
+                Landing {
+                    signature: untagged,
+                    landed_slot: 13,
+                    slots_after: None,
+                    latency_us: 950,
+                },
+            ]
+        );
+        // Landed signatures are not expired
+        assert_eq!(pending.expire(1_300), vec![]);
+        assert_eq!(pending.expire(1_401), vec![lost]);
+        assert!(pending.is_empty());
+        assert!(pending.order.is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+pub mod address_lookup;
+pub mod alert;
+pub mod amm;
+pub mod amounts;
+pub mod arrivals;
+pub mod analyzer;
+pub mod backfill;
+pub mod bench;
+pub mod binary_output;
+pub mod capture;
+#[cfg(feature = "clickhouse")]
+pub mod clickhouse_output;
+pub mod compute_budget;
+pub mod cpi;
+pub mod decode;
+pub mod failed_swaps;
+pub mod feed_compare;
+pub mod forward_header;
+pub mod front_run_markers;
+#[cfg(feature = "geyser")]
+pub mod geyser_output;
+pub mod json_output;
+pub mod known_decimals;
+pub mod mint_activity;
+#[cfg(feature = "nats")]
+pub mod nats_output;
+pub mod landing;
+pub mod load_shed;
+pub mod numa;
+pub mod observers;
+pub mod output;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod payer_balance;
+pub mod pool_fees;
+pub mod pool_prices;
+#[cfg(feature = "redis")]
+pub mod redis_dedup;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+pub mod program_ids;
+#[cfg(feature = "protobuf")]
+pub mod protobuf_output;
+pub mod quote_filter;
+pub mod rate_limit;
+pub mod ray_log;
+pub mod replay;
+pub mod sandwich;
+pub mod selftest;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_output;
+pub mod state_file;
+pub mod stats;
+#[cfg(feature = "statsd")]
+pub mod statsd;
+pub mod thin;
+pub mod timing;
+pub mod token_metadata;
+pub mod token_transfers;
+pub mod transfers;
+pub mod udp_receiver;
+pub mod wash_trade;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+#[cfg(feature = "ws-source")]
+pub mod ws_source;
@@ -0,0 +1,147 @@
+//! Degrading gracefully under sustained overload (`--load-shed`).
+//!
+//! The analyzer can fall behind the feed when RPC lookups are slow or a burst
+//! outlasts its throughput, and the input channel then grows without bound. Once
+//! more than the high-water mark of packets stays queued for the sustain period,
+//! the receiver sheds load: per-swap RPC lookups (the resolvers and
+//! `--drop-failed`) are skipped, amounts are printed `raw`, and only one packet in
+//! `--shed-sample` is analyzed, the rest dropped as `shed`. Full processing is
+//! restored once the queue drains to the low-water mark. Both transitions are
+//! logged.
+//!
+//! Shedding is a coarse mode switch rather than a rate controller: it only has to
+//! outrun the feed until the backlog clears, and the gap between the marks keeps
+//! it from flapping.
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadShedConfig {
+    /// Queued packets above which the receiver counts as overloaded
+    pub high_water: usize,
+    /// Queued packets at or below which full processing resumes
+    pub low_water: usize,
+    /// How long the queue must stay above the high-water mark
+    pub sustain_us: u64,
+    /// While shedding, one packet in this many is analyzed
+    pub sample_every: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadShedEvent {
+    Shedding { queued: usize, sustained_us: u64 },
+    Restored { queued: usize, shed: u64 },
+}
+
+impl fmt::Display for LoadShedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadShedEvent::Shedding { queued, sustained_us } => write!(
+                f,
+                "Overloaded, {} packets queued for {}ms: shedding load (no RPC lookups, raw amounts, sampling packets)",
+                queued,
+                sustained_us / 1_000
+            ),
+            LoadShedEvent::Restored { queued, shed } => write!(
+                f,
+                "Backlog cleared, {} packets queued: restoring full processing ({} packets shed)",
+                queued, shed
+            ),
+        }
+    }
+}
+
+pub struct LoadShedder {
+    config: LoadShedConfig,
+    above_since_us: Option<u64>,
+    shedding: bool,
+    seen: u64,
+    shed: u64,
+}
+
+impl LoadShedder {
+    pub fn new(config: LoadShedConfig) -> Self {
+        Self {
+            config,
+            above_since_us: None,
+            shedding: false,
+            seen: 0,
+            shed: 0,
+        }
+    }
+
+    /// Updates the mode with the current queue depth, returning the transition if
+    /// it changed.
+    pub fn observe(&mut self, queued: usize, now_us: u64) -> Option<LoadShedEvent> {
+        if self.shedding {
+            if queued > self.config.low_water {
+                return None;
+            }
+            self.shedding = false;
+            self.above_since_us = None;
+            let shed = std::mem::take(&mut self.shed);
+            return Some(LoadShedEvent::Restored { queued, shed });
+        }
+        if queued <= self.config.high_water {
+            self.above_since_us = None;
+            return None;
+        }
+        let since_us = *self.above_since_us.get_or_insert(now_us);
+        let sustained_us = now_us.saturating_sub(since_us);
+        if sustained_us < self.config.sustain_us {
+            return None;
+        }
+        self.shedding = true;
+        self.seen = 0;
+        Some(LoadShedEvent::Shedding { queued, sustained_us })
+    }
+
+    pub fn is_shedding(&self) -> bool {
+        self.shedding
+    }
+
+    /// Whether the next packet should be analyzed: always, unless shedding and it
+    /// falls outside the sample.
+    pub fn admit(&mut self) -> bool {
+        if !self.shedding {
+            return true;
+        }
+        let admitted = self.seen.is_multiple_of(self.config.sample_every.max(1));
+        self.seen += 1;
+        if !admitted {
+            self.shed += 1;
+        }
+        admitted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_shedder() {
+        let mut shedder = LoadShedder::new(LoadShedConfig {
+            high_water: 100,
+            low_water: 10,
+            sustain_us: 1_000,
+            sample_every: 3,
+        });
+        assert_eq!(shedder.observe(500, 0), None);
+        // A dip below the mark restarts the sustain period
+        assert_eq!(shedder.observe(50, 500), None);
+        assert_eq!(shedder.observe(500, 600), None);
+        assert_eq!(shedder.observe(500, 1_500), None);
+        assert!(shedder.admit());
+        assert_eq!(
+            shedder.observe(500, 1_600),
+            Some(LoadShedEvent::Shedding { queued: 500, sustained_us: 1_000 })
+        );
+        let admitted: Vec<bool> = (0..6).map(|_| shedder.admit()).collect();
+        assert_eq!(admitted, [true, false, false, true, false, false]);
+        // Below the high-water mark but not yet drained
+        assert_eq!(shedder.observe(50, 1_700), None);
+        assert!(shedder.is_shedding());
+        assert_eq!(shedder.observe(10, 1_800), Some(LoadShedEvent::Restored { queued: 10, shed: 4 }));
+        assert!(shedder.admit());
+    }
+}
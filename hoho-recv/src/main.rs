@@ -1,255 +1,1771 @@
-use std::str::FromStr;
+use std::collections::VecDeque;
 use std::net::UdpSocket;
-use std::sync::mpsc::{self, Sender, Receiver};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Duration, SystemTime};
-use chrono::Utc;
-use solana_sdk::instruction::CompiledInstruction;
-use solana_sdk::message::VersionedMessage;
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use clap::{crate_description, crate_name, value_t_or_exit, values_t_or_exit, App, Arg, SubCommand};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::signature::Signature;
-use solana_sdk::transaction::VersionedTransaction;
+use hoho_recv::address_lookup::LookupTableResolver;
+use hoho_recv::alert::{RateOfChangeMonitor, WatchConfig};
+use hoho_recv::amm::AmmRegistry;
+use hoho_recv::amounts::{self, AmountFormat};
+use hoho_recv::analyzer::{self, analyze_transaction, analyze_versioned_transaction, AnalyzeError, Resolvers};
+#[cfg(feature = "ws-source")]
+use hoho_recv::analyzer::Dex;
+use hoho_recv::arrivals::{self, ArrivalTracker, SignatureKey};
+use hoho_recv::backfill;
+use hoho_recv::bench;
+use hoho_recv::binary_output::BinarySwapWriter;
+use hoho_recv::capture::{self, CaptureReader, CaptureWriter};
+#[cfg(feature = "clickhouse")]
+use hoho_recv::clickhouse_output::{ClickHouseConfig, ClickHouseSink};
+use hoho_recv::decode::{self, Encoding};
+use hoho_recv::cpi::{CpiResolver, JUPITER_V6_PROGRAM_ID};
+use hoho_recv::failed_swaps::FailedSwapFilter;
+use hoho_recv::feed_compare::{self, Feed, FeedComparison};
+use hoho_recv::forward_header::{ForwardHeader, ForwardMode, HeaderError};
+use hoho_recv::front_run_markers::{self, JITO_DONT_FRONT};
+#[cfg(feature = "geyser")]
+use hoho_recv::geyser_output::GeyserSwapWriter;
+use hoho_recv::json_output::{self, JsonLinesWriter};
+use hoho_recv::known_decimals;
+use hoho_recv::mint_activity::MintActivity;
+#[cfg(feature = "nats")]
+use hoho_recv::nats_output::{NatsConfig, NatsSink};
+use hoho_recv::landing::LandingTracker;
+use hoho_recv::load_shed::{LoadShedConfig, LoadShedEvent, LoadShedder};
+use hoho_recv::numa;
+use hoho_recv::observers::ObserverTracker;
+use hoho_recv::output::SwapOutputs;
+use hoho_recv::pool_prices::{self, PoolPriceSink, PoolPrices};
+#[cfg(feature = "otel")]
+use hoho_recv::otel::{OtelConfig, PacketOutcome, PacketTracer};
+#[cfg(feature = "parquet")]
+use hoho_recv::parquet_export::ParquetSwapWriter;
+use hoho_recv::payer_balance::PayerBalanceResolver;
+use hoho_recv::pool_fees::PoolFeeResolver;
+use hoho_recv::quote_filter::{self, QuoteFilter, UnresolvedQuote};
+use hoho_recv::rate_limit::SourceRateLimiter;
+use hoho_recv::program_ids;
+#[cfg(feature = "redis")]
+use hoho_recv::redis_dedup::{RedisDedup, RedisDedupConfig};
+use hoho_recv::replay::{self, ReplayConfig};
+use hoho_recv::sandwich::{SandwichConfig, SandwichDetector};
+use hoho_recv::selftest;
+#[cfg(feature = "protobuf")]
+use hoho_recv::protobuf_output::ProtobufSwapWriter;
+#[cfg(feature = "sqlite")]
+use hoho_recv::sqlite_output::SqliteSwapWriter;
+use hoho_recv::state_file::{StateConfig, StateStore};
+use hoho_recv::stats::{DropReason, ReceiverStats};
+#[cfg(feature = "statsd")]
+use hoho_recv::statsd::{self, StatsdConfig};
+use hoho_recv::thin::ThinTransaction;
+#[cfg(feature = "analyzer-timing")]
+use hoho_recv::timing;
+use hoho_recv::token_metadata::TokenMetadataResolver;
+use hoho_recv::udp_receiver::{self, Datagram, Intake};
+use hoho_recv::wash_trade::{WashTradeConfig, WashTradeDetector};
+#[cfg(feature = "webhook")]
+use hoho_recv::webhook::{WebhookConfig, WebhookSink};
+#[cfg(feature = "ws-source")]
+use hoho_recv::ws_source::{self, WsSourceConfig};
 
-// Raydium DEX program IDs
-const RAYDIUM_V4_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
-const RAYDIUM_SWAP_PROGRAM: &str = "27haf8L6oxUeXrHrgEgsexjSY5hbVUWEmvv9Nyxg8vQv";
+// Address the validator-side forwarder relays packets to
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:44444";
 
-struct UdpClient {
-    socket: UdpSocket,
-    sender: Sender<Vec<u8>>,
-}
+const DEFAULT_ARRIVAL_WINDOW_MS: &str = "2000";
 
-impl UdpClient {
-    fn new(addr: &str) -> Result<(Self, Arc<Mutex<Receiver<Vec<u8>>>>), std::io::Error> {
-        let socket = UdpSocket::bind(addr)?;
-        let (sender, receiver) = mpsc::channel();
-        let receiver = Arc::new(Mutex::new(receiver));
-        Ok((UdpClient { socket, sender }, receiver))
-    }
+const DEFAULT_BINARY_MAX_BUFFERED: &str = "10000";
 
-    fn start_receiving(&self) {
-        let mut buf = [0; 1024 * 64];
-        loop {
-            match self.socket.recv_from(&mut buf) {
-                Ok((size, _)) => {
-                    let data = buf[..size].to_vec();
-                    if let Err(e) = self.sender.send(data) {
-                        eprintln!("Error sending to channel: {}", e);
-                        break;
-                    }
-                }
-                Err(e) => eprintln!("Error receiving data: {}", e),
-            }
-        }
-    }
-}
+// A 2 second window at 50k packets per second
+const DEFAULT_SIGNATURE_CAPACITY: &str = "100000";
 
-fn analyze_swap_accounts_and_inner_instructions(
-    account_keys: &[Pubkey],
-    instructions: &[CompiledInstruction],
-    signature: &Signature,
-) -> Option<()> {
-    let token_program = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").ok()?;
-    let raydium_v4 = Pubkey::from_str(RAYDIUM_V4_PROGRAM_ID).ok()?;
+const DEFAULT_CACHE_CAPACITY: &str = "10000";
 
-    for (i, ix) in instructions.iter().enumerate() {
-        let program_id = account_keys[ix.program_id_index as usize];
+const DEFAULT_PAYER_BALANCE_TTL_SECS: &str = "10";
 
-        if program_id == raydium_v4 {
-            println!("\nRaydium Swap Transaction Found!");
-            println!("Signature: {}", signature);
+const DEFAULT_LANDING_TIMEOUT_SECS: &str = "60";
 
-            // 获取关键账户
-            for (idx, account_idx) in ix.accounts.iter().enumerate() {
-                let account = &account_keys[*account_idx as usize];
-                match idx {
-                    // Token Program accounts
-                    15 => println!("Source Token Account: {} (User's Token Account)", account),
-                    16 => println!("Destination Token Account: {} (User's Token Account)", account),
-                    5 => println!("Pool Token Account 1: {} (AMM Token Account)", account),
-                    6 => println!("Pool Token Account 2: {} (AMM Token Account)", account),
-                    _ => {}
-                }
-            }
+const DEFAULT_RATE_LIMIT_SOURCES: &str = "10000";
 
-            // 解析指令数据
-            if ix.data.len() >= 17 {
-                let amount_in = {
-                    let mut amount_bytes = [0u8; 8];
-                    amount_bytes.copy_from_slice(&ix.data[1..9]);
-                    u64::from_le_bytes(amount_bytes)
-                };
+const DEFAULT_STATE_FLUSH_INTERVAL_SECS: &str = "60";
+const DEFAULT_STATE_MAX_ENTRIES: &str = "100000";
 
-                println!("\nSwap Amount Details:");
-                // 对于SOL，需要除以1e9；对于其他代币，需要根据小数位数调整
-                println!("Amount In: {} (raw value: {})",
-                         amount_in as f64 / 1_000_000_000.0,
-                         amount_in);
+// analyzer::DEFAULT_MAX_ACCOUNTS
+const DEFAULT_MAX_ACCOUNTS: &str = "256";
 
-                // 我们还需要获取代币账户的mint地址
-                // 这需要调用RPC来获取账户信息
-                println!("\nNote: To get token mint addresses, we need to query the token accounts:");
-                println!("User Source Token Account: {}", account_keys[ix.accounts[15] as usize]);
-                println!("User Destination Token Account: {}", account_keys[ix.accounts[16] as usize]);
+// A few seconds of a busy feed behind, held for long enough to rule out a burst
+const DEFAULT_SHED_HIGH_WATER: &str = "10000";
+const DEFAULT_SHED_LOW_WATER: &str = "100";
+const DEFAULT_SHED_SUSTAIN_MS: &str = "2000";
+const DEFAULT_SHED_SAMPLE: &str = "10";
 
-                //println current time use std lib
-                let system_time = Utc::now();
-                println!("系统时间: {}", system_time.format("%Y年%m月%d日 %H时%M分%S秒"));                
+#[cfg(feature = "parquet")]
+const DEFAULT_PARQUET_ROWS_PER_FILE: &str = "100000";
 
-            }
-
-            return Some(());
-        }
-    }
-    None
+fn app() -> App<'static, 'static> {
+    let app = App::new(crate_name!())
+        .about(crate_description!())
+        .arg(
+            Arg::with_name("rpc_url")
+                .long("rpc-url")
+                .value_name("URL")
+                .takes_value(true)
+                .help("JSON RPC URL used for optional account lookups"),
+        )
+        .arg(
+            Arg::with_name("resolve_token_metadata")
+                .long("resolve-token-metadata")
+                .requires("rpc_url")
+                .help("Resolve swap mints and print their Metaplex name and symbol"),
+        )
+        .arg(
+            Arg::with_name("resolve_lookup_tables")
+                .long("resolve-lookup-tables")
+                .requires("rpc_url")
+                .help("Fetch address lookup tables so V0 instructions resolve loaded accounts"),
+        )
+        .arg(
+            Arg::with_name("resolve_pool_fees")
+                .long("resolve-pool-fees")
+                .requires("rpc_url")
+                .help("Fetch Raydium V4 pool fee rates and estimate each swap's output net of fees"),
+        )
+        .arg(
+            Arg::with_name("resolve_payer_balance")
+                .long("resolve-payer-balance")
+                .requires("rpc_url")
+                .help("Fetch the SOL balance of each matched swap's fee payer"),
+        )
+        .arg(
+            Arg::with_name("payer_balance_ttl_secs")
+                .long("payer-balance-ttl-secs")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value(DEFAULT_PAYER_BALANCE_TTL_SECS)
+                .help("How long a fetched fee payer balance is reused"),
+        )
+        .arg(
+            Arg::with_name("drop_failed")
+                .long("drop-failed")
+                .requires("rpc_url")
+                .help("Wait for each matched swap to confirm and drop it if its transaction failed; \
+                       holds back the packets behind it for a second or more"),
+        )
+        .arg(
+            Arg::with_name("track_landing")
+                .long("track-landing")
+                .requires("rpc_url")
+                .help("Poll confirmed blocks and report the slot each matched swap lands in, or that it \
+                       did not land within --landing-timeout-secs"),
+        )
+        .arg(
+            Arg::with_name("landing_timeout_secs")
+                .long("landing-timeout-secs")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value(DEFAULT_LANDING_TIMEOUT_SECS)
+                .help("How long a matched swap is waited for before it is reported as not landed"),
+        )
+        .arg(
+            Arg::with_name("resolve_cpi")
+                .long("resolve-cpi")
+                .requires("rpc_url")
+                .help("Wait for transactions invoking a --cpi-programs program without a top-level swap \
+                       to confirm, and look for the swap in their inner instructions"),
+        )
+        .arg(
+            Arg::with_name("cpi_programs")
+                .long("cpi-programs")
+                .value_name("PUBKEY,...")
+                .takes_value(true)
+                .use_delimiter(true)
+                .default_value(JUPITER_V6_PROGRAM_ID)
+                .help("Aggregator programs whose transactions --resolve-cpi looks into"),
+        )
+        .arg(
+            Arg::with_name("amm_specs")
+                .long("amm-specs")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("JSON file of additional AMM swap layouts to decode"),
+        )
+        .arg(
+            Arg::with_name("account_layouts")
+                .long("account-layouts")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("JSON file replacing the swap account positions of DEXes by name"),
+        )
+        .arg(
+            Arg::with_name("capture")
+                .long("capture")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Record every received datagram to FILE for later replay"),
+        )
+        .arg(
+            Arg::with_name("capture_compress")
+                .long("capture-compress")
+                .requires("capture")
+                .help("Compress the --capture file with zstd; replay and bench detect compressed captures"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Write matched swaps to FILE as JSON lines"),
+        )
+        .arg(
+            Arg::with_name("fields")
+                .long("fields")
+                .value_name("FIELD,...")
+                .takes_value(true)
+                .use_delimiter(true)
+                .help("Only write these keys of each swap in JSON output and webhook bodies, \
+                       e.g. signature,dex,amount_in"),
+        )
+        .arg(
+            Arg::with_name("full_json")
+                .long("full-json")
+                .requires("json")
+                .help("Include every instruction of the message in the JSON output; much larger"),
+        )
+        .arg(
+            Arg::with_name("include_packet")
+                .long("include-packet")
+                .help("Attach each matched swap's original packet to the JSON output, base64 under \
+                       \"packet\", and to binary records, so consumers can re-parse it or verify \
+                       its signatures; roughly doubles their size"),
+        )
+        .arg(
+            Arg::with_name("binary")
+                .long("binary")
+                .value_name("DEST")
+                .takes_value(true)
+                .help("Write matched swaps as fixed-size binary records to a file, pipe or tcp://HOST:PORT"),
+        )
+        .arg(
+            Arg::with_name("binary_max_buffered")
+                .long("binary-max-buffered")
+                .value_name("RECORDS")
+                .takes_value(true)
+                .default_value(DEFAULT_BINARY_MAX_BUFFERED)
+                .help("Records queued for a tcp:// binary output before the connection is closed \
+                       and the output disabled"),
+        )
+        .arg(
+            Arg::with_name("backfill")
+                .long("backfill")
+                .value_name("SIGNATURE_OR_FILE")
+                .takes_value(true)
+                .requires("rpc_url")
+                .help("Analyze confirmed transactions fetched over RPC instead of listening; \
+                       takes a signature or a file with one signature per line"),
+        )
+        .arg(
+            Arg::with_name("since_slot")
+                .long("since-slot")
+                .value_name("SLOT")
+                .takes_value(true)
+                .help("Only analyze transactions at or after SLOT; live packets use the slot \
+                       tagged by the forwarder, backfill looks slots up over RPC"),
+        )
+        .arg(
+            Arg::with_name("max_packet_age_ms")
+                .long("max-packet-age-ms")
+                .value_name("MILLISECONDS")
+                .takes_value(true)
+                .help("Drop packets forwarded more than MILLISECONDS ago without analyzing them, \
+                       so a backlog does not delay fresh packets; compares the forwarder's \
+                       timestamp with this host's clock"),
+        )
+        .arg(
+            Arg::with_name("max_source_rate")
+                .long("max-source-rate")
+                .value_name("PACKETS_PER_SECOND")
+                .takes_value(true)
+                .help("Drop datagrams from any one source IP beyond this rate, allowing bursts of a \
+                       second's worth, so a single noisy sender can't flood the analyzer"),
+        )
+        .arg(
+            Arg::with_name("rate_limit_sources")
+                .long("rate-limit-sources")
+                .value_name("SOURCES")
+                .takes_value(true)
+                .default_value(DEFAULT_RATE_LIMIT_SOURCES)
+                .help("Most recently seen source IPs whose --max-source-rate buckets are kept"),
+        )
+        .arg(
+            Arg::with_name("load_shed")
+                .long("load-shed")
+                .help("While more than --shed-high-water packets stay queued for --shed-sustain-ms, \
+                       skip RPC lookups, print raw amounts and analyze one packet in --shed-sample, \
+                       until the queue drains to --shed-low-water"),
+        )
+        .arg(
+            Arg::with_name("shed_high_water")
+                .long("shed-high-water")
+                .value_name("PACKETS")
+                .takes_value(true)
+                .default_value(DEFAULT_SHED_HIGH_WATER)
+                .help("Queued packets above which the receiver counts as overloaded"),
+        )
+        .arg(
+            Arg::with_name("shed_low_water")
+                .long("shed-low-water")
+                .value_name("PACKETS")
+                .takes_value(true)
+                .default_value(DEFAULT_SHED_LOW_WATER)
+                .help("Queued packets at or below which full processing resumes"),
+        )
+        .arg(
+            Arg::with_name("shed_sustain_ms")
+                .long("shed-sustain-ms")
+                .value_name("MILLISECONDS")
+                .takes_value(true)
+                .default_value(DEFAULT_SHED_SUSTAIN_MS)
+                .help("How long the queue must stay above --shed-high-water before load is shed"),
+        )
+        .arg(
+            Arg::with_name("shed_sample")
+                .long("shed-sample")
+                .value_name("N")
+                .takes_value(true)
+                .default_value(DEFAULT_SHED_SAMPLE)
+                .help("While shedding load, analyze one packet in N and drop the rest"),
+        )
+        .arg(
+            Arg::with_name("continuous")
+                .long("continuous")
+                .help("Keep analyzing after the first matched swap instead of exiting"),
+        )
+        .arg(
+            Arg::with_name("arrival_window_ms")
+                .long("arrival-window-ms")
+                .value_name("MILLISECONDS")
+                .takes_value(true)
+                .default_value(DEFAULT_ARRIVAL_WINDOW_MS)
+                .help("Window in which repeated arrivals of a signature count as multi-path arrivals"),
+        )
+        .arg(
+            Arg::with_name("signature_key")
+                .long("signature-key")
+                .value_name("KEY")
+                .takes_value(true)
+                .possible_values(&SignatureKey::NAMES)
+                .default_value("full")
+                .help("Key the arrival and dedup windows by the full signature, or by its first 8 bytes \
+                       to save memory at a small risk of collisions"),
+        )
+        .arg(
+            Arg::with_name("signature_capacity")
+                .long("signature-capacity")
+                .value_name("SIGNATURES")
+                .takes_value(true)
+                .default_value(DEFAULT_SIGNATURE_CAPACITY)
+                .help("Signatures the arrival and dedup windows are sized for at startup; about the \
+                       packet rate times the window, so bursts don't stall on rehashing"),
+        )
+        .arg(
+            Arg::with_name("cache_capacity")
+                .long("cache-capacity")
+                .value_name("KEYS")
+                .takes_value(true)
+                .default_value(DEFAULT_CACHE_CAPACITY)
+                .help("Keys each RPC resolver cache is sized for at startup"),
+        )
+        .arg(
+            Arg::with_name("state_file")
+                .long("state-file")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("Keep the signatures of reported swaps and the token metadata cache in PATH across \
+                       restarts; swaps the last run reported are dropped as duplicates"),
+        )
+        .arg(
+            Arg::with_name("state_flush_interval")
+                .long("state-flush-interval")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value(DEFAULT_STATE_FLUSH_INTERVAL_SECS)
+                .help("How often the --state-file is written while packets arrive"),
+        )
+        .arg(
+            Arg::with_name("state_max_entries")
+                .long("state-max-entries")
+                .value_name("ENTRIES")
+                .takes_value(true)
+                .default_value(DEFAULT_STATE_MAX_ENTRIES)
+                .help("Signatures, and cache entries of each kind, the --state-file keeps at most"),
+        )
+        .arg(
+            Arg::with_name("annotate_arrivals")
+                .long("annotate-arrivals")
+                .help("Print how many times a matched transaction has arrived within the arrival window"),
+        )
+        .arg(
+            Arg::with_name("count_observers")
+                .long("count-observers")
+                .help("Count the forwarders, by source address, that send each signature within the arrival \
+                       window; repeats from one forwarder are dropped and each swap carries the count so far"),
+        )
+        .arg(
+            Arg::with_name("watch_mint")
+                .long("watch-mint")
+                .value_name("MINT")
+                .takes_value(true)
+                .requires("resolve_token_metadata")
+                .help("Alert on rapid volume or price changes of swaps trading MINT"),
+        )
+        .arg(
+            Arg::with_name("watch_window")
+                .long("watch-window")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value("60")
+                .help("Sliding window for --watch-mint alerts"),
+        )
+        .arg(
+            Arg::with_name("watch_volume")
+                .long("watch-volume")
+                .value_name("RAW_AMOUNT")
+                .takes_value(true)
+                .help("Alert when the watched mint's volume within the window reaches RAW_AMOUNT"),
+        )
+        .arg(
+            Arg::with_name("watch_price_change")
+                .long("watch-price-change")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .default_value("5")
+                .help("Alert when the watched mint's price moves by PERCENT within the window"),
+        )
+        .arg(
+            Arg::with_name("top_mints")
+                .long("top-mints")
+                .value_name("N")
+                .takes_value(true)
+                .help("Report the N mints with the most matched swaps at the end of every --top-mints-interval"),
+        )
+        .arg(
+            Arg::with_name("top_mints_interval")
+                .long("top-mints-interval")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value("60")
+                .help("Interval of the --top-mints reports"),
+        )
+        .arg(
+            Arg::with_name("detect_wash_trades")
+                .long("detect-wash-trades")
+                .help("Flag wallets that buy and sell back about the same amount of a mint within a window"),
+        )
+        .arg(
+            Arg::with_name("wash_window")
+                .long("wash-window")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value("60")
+                .help("Sliding window for --detect-wash-trades"),
+        )
+        .arg(
+            Arg::with_name("wash_size_tolerance")
+                .long("wash-size-tolerance")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .default_value("5")
+                .help("How far apart in size a buy and a sell can be and still count as a wash trade"),
+        )
+        .arg(
+            Arg::with_name("detect_sandwiches")
+                .long("detect-sandwiches")
+                .help("Flag wallets that buy a mint right before another wallet's buy in the same pool and sell it right after"),
+        )
+        .arg(
+            Arg::with_name("sandwich_window")
+                .long("sandwich-window")
+                .value_name("MILLISECONDS")
+                .takes_value(true)
+                .default_value("2000")
+                .help("How long after its front-run a sandwich's back-run can arrive, for --detect-sandwiches"),
+        )
+        .arg(
+            Arg::with_name("price_http")
+                .long("price-http")
+                .value_name("HOST:PORT")
+                .takes_value(true)
+                .help("Serve each pool's latest price from its ray_log as JSON at /prices and /prices/<pool>"),
+        )
+        .arg(
+            Arg::with_name("price_pools")
+                .long("price-pools")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value("10000")
+                .help("How many pools --price-http tracks, forgetting the least recently updated"),
+        )
+        .arg(
+            Arg::with_name("front_run_markers")
+                .long("front-run-markers")
+                .value_name("PUBKEY,...")
+                .takes_value(true)
+                .use_delimiter(true)
+                .default_value(JITO_DONT_FRONT)
+                .help("Flag swaps whose transaction includes any of these \"don't front-run me\" marker accounts"),
+        )
+        .arg(
+            Arg::with_name("quote_mints")
+                .long("quote-mints")
+                .value_name("MINT,...")
+                .takes_value(true)
+                .use_delimiter(true)
+                .help("Only output swaps against one of these mints: pubkeys, or sol, usdc and usdt"),
+        )
+        .arg(
+            Arg::with_name("unresolved_quote")
+                .long("unresolved-quote")
+                .value_name("ACTION")
+                .takes_value(true)
+                .possible_values(&["pass", "drop"])
+                .requires("quote_mints")
+                .help("What --quote-mints does with a swap whose mints aren't all known, pass by default; \
+                       --resolve-token-metadata resolves most"),
+        )
+        .arg(
+            Arg::with_name("amount_format")
+                .long("amount-format")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .possible_values(&AmountFormat::NAMES)
+                .default_value("ui")
+                .help("Print amounts scaled by their decimals (ui), or as integers with (lamports) or without (raw) their unit"),
+        )
+        .arg(
+            Arg::with_name("decimals")
+                .long("decimals")
+                .value_name("MINT:N")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Decimals of a mint, known without RPC; adds to or corrects the built-in table \
+                       of major tokens"),
+        )
+        .arg(
+            Arg::with_name("numa_node")
+                .long("numa-node")
+                .value_name("NODE|INTERFACE")
+                .takes_value(true)
+                .help("Run the receiver's threads on the CPUs of this NUMA node, or of the node the \
+                       network interface is attached to; by default threads are not pinned"),
+        )
+        .arg(
+            Arg::with_name("debug_struct")
+                .long("debug-struct")
+                .help("Also print the full Debug form of each matched transaction and its decoded swap, \
+                       to attach to parsing bug reports; very verbose"),
+        )
+        .arg(
+            Arg::with_name("max_accounts")
+                .long("max-accounts")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value(DEFAULT_MAX_ACCOUNTS)
+                .help("Skip, and count as too_many_accounts, transactions with more accounts than this, \
+                       static and loaded"),
+        )
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about("Replay a capture file to the receiver's listen address")
+                .arg(
+                    Arg::with_name("capture_file")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Capture file written by --capture"),
+                )
+                .arg(
+                    Arg::with_name("destination")
+                        .long("destination")
+                        .value_name("HOST:PORT")
+                        .takes_value(true)
+                        .default_value(DEFAULT_BIND_ADDRESS)
+                        .help("Address to send the frames to"),
+                )
+                .arg(
+                    Arg::with_name("speed")
+                        .long("speed")
+                        .value_name("MULTIPLIER")
+                        .takes_value(true)
+                        .default_value("1.0")
+                        .help("Replay speed relative to the recorded pacing; 0 sends as fast as possible"),
+                )
+                .arg(
+                    Arg::with_name("loop")
+                        .long("loop")
+                        .help("Replay the capture repeatedly for sustained load testing"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare-feeds")
+                .about("Report which of several forward feeds delivers each transaction first")
+                .arg(
+                    Arg::with_name("feed")
+                        .long("feed")
+                        .value_name("NAME=HOST:PORT")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .required(true)
+                        .help("A feed and the address it is forwarded to; give at least two"),
+                )
+                .arg(
+                    Arg::with_name("window_ms")
+                        .long("window-ms")
+                        .value_name("MILLISECONDS")
+                        .takes_value(true)
+                        .default_value(DEFAULT_ARRIVAL_WINDOW_MS)
+                        .help("How long after its first arrival another feed can still deliver a transaction"),
+                )
+                .arg(
+                    Arg::with_name("report_secs")
+                        .long("report-secs")
+                        .value_name("SECONDS")
+                        .takes_value(true)
+                        .default_value("10")
+                        .help("Interval between win rate summaries"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("decode")
+                .about("Analyze a single base64 or hex encoded transaction")
+                .arg(
+                    Arg::with_name("transaction")
+                        .value_name("ENCODED")
+                        .takes_value(true)
+                        .help("Bincode-serialized VersionedTransaction, base64 or hex; read from stdin \
+                               if neither this nor --file is given"),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .long("file")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .conflicts_with("transaction")
+                        .help("Read the encoded transaction from FILE, or stdin for -"),
+                )
+                .arg(
+                    Arg::with_name("encoding")
+                        .long("encoding")
+                        .value_name("ENCODING")
+                        .takes_value(true)
+                        .possible_values(&["auto", "base64", "hex"])
+                        .default_value("auto")
+                        .help("Encoding of the transaction; auto takes text of only hex digits as hex"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Measure analysis throughput over a capture file, with RPC disabled")
+                .arg(
+                    Arg::with_name("capture_file")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Capture file written by --capture"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("selftest")
+                .about("Analyze a built-in Raydium swap to check that parsing works on this platform"),
+        );
+    #[cfg(feature = "statsd")]
+    let app = app
+        .arg(
+            Arg::with_name("statsd_addr")
+                .long("statsd-addr")
+                .value_name("HOST:PORT")
+                .takes_value(true)
+                .help("Send receiver counters to this statsd/DogStatsD agent"),
+        )
+        .arg(
+            Arg::with_name("statsd_interval")
+                .long("statsd-interval")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value("10")
+                .help("Interval between statsd reports"),
+        )
+        .arg(
+            Arg::with_name("statsd_prefix")
+                .long("statsd-prefix")
+                .value_name("PREFIX")
+                .takes_value(true)
+                .default_value("hoho_recv")
+                .help("Prefix for statsd metric names"),
+        );
+    #[cfg(feature = "parquet")]
+    let app = app
+        .arg(
+            Arg::with_name("parquet")
+                .long("parquet")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Export matched swaps to rotating Parquet files named after FILE"),
+        )
+        .arg(
+            Arg::with_name("parquet_rows_per_file")
+                .long("parquet-rows-per-file")
+                .value_name("ROWS")
+                .takes_value(true)
+                .default_value(DEFAULT_PARQUET_ROWS_PER_FILE)
+                .help("Number of swaps buffered before a Parquet file is written"),
+        );
+    #[cfg(feature = "sqlite")]
+    let app = app.arg(
+        Arg::with_name("sqlite")
+            .long("sqlite")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("Insert matched swaps into the swaps table of a SQLite database"),
+    );
+    #[cfg(feature = "protobuf")]
+    let app = app.arg(
+        Arg::with_name("protobuf")
+            .long("protobuf")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("Write matched swaps to a file or pipe as length-delimited protobuf Swap messages"),
+    );
+    #[cfg(feature = "geyser")]
+    let app = app.arg(
+        Arg::with_name("geyser")
+            .long("geyser")
+            .value_name("FILE")
+            .takes_value(true)
+            .help("Write the transactions of matched swaps to a file or pipe as length-delimited Yellowstone \
+                   Geyser SubscribeUpdate transaction notifications"),
+    );
+    #[cfg(feature = "redis")]
+    let app = app
+        .arg(
+            Arg::with_name("redis_dedup")
+                .long("redis-dedup")
+                .value_name("HOST:PORT")
+                .takes_value(true)
+                .help("Report each transaction once across all receivers sharing this Redis"),
+        )
+        .arg(
+            Arg::with_name("redis_dedup_ttl")
+                .long("redis-dedup-ttl")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value("120")
+                .help("How long a receiver's claim on a signature lasts"),
+        )
+        .arg(
+            Arg::with_name("redis_dedup_prefix")
+                .long("redis-dedup-prefix")
+                .value_name("PREFIX")
+                .takes_value(true)
+                .default_value("hoho_recv:sig:")
+                .help("Prefix for the Redis keys"),
+        );
+    #[cfg(feature = "webhook")]
+    let app = app
+        .arg(
+            Arg::with_name("webhook_url")
+                .long("webhook-url")
+                .value_name("URL")
+                .takes_value(true)
+                .help("POST each matched swap as JSON to URL"),
+        )
+        .arg(
+            Arg::with_name("webhook_retries")
+                .long("webhook-retries")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value("3")
+                .help("Retries of a webhook request failing with a connection error, 429 or 5xx"),
+        )
+        .arg(
+            Arg::with_name("webhook_queue_size")
+                .long("webhook-queue-size")
+                .value_name("SWAPS")
+                .takes_value(true)
+                .default_value("10000")
+                .help("Swaps waiting to be posted before new ones are dropped"),
+        );
+    #[cfg(feature = "nats")]
+    let app = app
+        .arg(
+            Arg::with_name("nats_url")
+                .long("nats-url")
+                .value_name("URL")
+                .takes_value(true)
+                .requires("nats_subject")
+                .help("Publish each matched swap as JSON to this NATS server, e.g. nats://localhost:4222"),
+        )
+        .arg(
+            Arg::with_name("nats_subject")
+                .long("nats-subject")
+                .value_name("SUBJECT")
+                .takes_value(true)
+                .help("Subject to publish swaps to"),
+        )
+        .arg(
+            Arg::with_name("nats_queue_size")
+                .long("nats-queue-size")
+                .value_name("SWAPS")
+                .takes_value(true)
+                .default_value("10000")
+                .help("Swaps waiting to be published before new ones are dropped"),
+        );
+    #[cfg(feature = "clickhouse")]
+    let app = app
+        .arg(
+            Arg::with_name("clickhouse_url")
+                .long("clickhouse-url")
+                .value_name("URL")
+                .takes_value(true)
+                .help("Insert matched swaps into ClickHouse through its HTTP interface, e.g. \
+                       http://localhost:8123; the password, if any, is read from CLICKHOUSE_PASSWORD"),
+        )
+        .arg(
+            Arg::with_name("clickhouse_table")
+                .long("clickhouse-table")
+                .value_name("TABLE")
+                .takes_value(true)
+                .default_value("swaps")
+                .help("Table to insert swaps into, optionally qualified by its database"),
+        )
+        .arg(
+            Arg::with_name("clickhouse_user")
+                .long("clickhouse-user")
+                .value_name("USER")
+                .takes_value(true)
+                .help("ClickHouse user to insert as, instead of the server's default"),
+        )
+        .arg(
+            Arg::with_name("clickhouse_create_table")
+                .long("clickhouse-create-table")
+                .help("Create the swap table at startup if it doesn't exist"),
+        )
+        .arg(
+            Arg::with_name("clickhouse_batch_size")
+                .long("clickhouse-batch-size")
+                .value_name("ROWS")
+                .takes_value(true)
+                .default_value("1000")
+                .help("Rows inserted in one batch at most"),
+        )
+        .arg(
+            Arg::with_name("clickhouse_flush_ms")
+                .long("clickhouse-flush-ms")
+                .value_name("MILLISECONDS")
+                .takes_value(true)
+                .default_value("1000")
+                .help("How long a batch waits for more rows after its first before it is inserted"),
+        )
+        .arg(
+            Arg::with_name("clickhouse_queue_size")
+                .long("clickhouse-queue-size")
+                .value_name("SWAPS")
+                .takes_value(true)
+                .default_value("100000")
+                .help("Swaps waiting to be inserted before new ones are dropped"),
+        )
+        .arg(
+            Arg::with_name("clickhouse_retries")
+                .long("clickhouse-retries")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value("3")
+                .help("Retries of an insert failing with a connection error, 429 or 5xx"),
+        );
+    #[cfg(feature = "ws-source")]
+    let app = app
+        .arg(
+            Arg::with_name("ws_url")
+                .long("ws-url")
+                .value_name("URL")
+                .takes_value(true)
+                .requires("rpc_url")
+                .help("Also analyze confirmed transactions of the DEX programs notified on this \
+                       RPC PubSub WebSocket, e.g. without a co-located validator"),
+        )
+        .arg(
+            Arg::with_name("ws_reconnect_secs")
+                .long("ws-reconnect-secs")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .default_value("5")
+                .help("Delay before reconnecting a dropped --ws-url subscription"),
+        );
+    #[cfg(feature = "otel")]
+    let app = app
+        .arg(
+            Arg::with_name("otlp_endpoint")
+                .long("otlp-endpoint")
+                .value_name("URL")
+                .takes_value(true)
+                .help("Export a trace of each analyzed packet's forwarding and analysis to this \
+                       OTLP/gRPC collector, e.g. http://127.0.0.1:4317"),
+        )
+        .arg(
+            Arg::with_name("otlp_sample_ratio")
+                .long("otlp-sample-ratio")
+                .value_name("RATIO")
+                .takes_value(true)
+                .default_value("1")
+                .help("Fraction of packets traced, picked by signature"),
+        );
+    #[cfg(feature = "analyzer-timing")]
+    let app = app.arg(
+        Arg::with_name("analyzer_timing")
+            .long("analyzer-timing")
+            .value_name("SECONDS")
+            .takes_value(true)
+            .help("Time each analyzer stage and print latency histograms every SECONDS"),
+    );
+    app
 }
 
-fn analyze_transaction(data: &[u8]) -> Option<()> {
-    let tx: VersionedTransaction = bincode::deserialize(data).ok()?;
+fn main() {
+    let matches = app().get_matches();
 
-    let signature = tx.signatures.first()?;
-    println!("Transaction signature: {}", signature);
+    // 内置的程序 ID 写错会导致永远匹配不到交易，启动时就报错退出
+    if let Err(e) = program_ids::check_builtin_program_ids() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 
-    // 解析内部指令
-    match &tx.message {
-        VersionedMessage::Legacy(message) => {
-            analyze_swap_accounts_and_inner_instructions(message.account_keys.as_slice(),
-                                                         message.instructions.as_slice(),
-                                                         signature)
-        }
-        VersionedMessage::V0(message) => {
-            analyze_swap_accounts_and_inner_instructions(message.account_keys.as_slice(),
-                                                         message.instructions.as_slice(),
-                                                         signature)
+    if matches.subcommand_matches("selftest").is_some() {
+        match selftest::run() {
+            Ok(()) => println!("Selftest passed"),
+            Err(e) => {
+                eprintln!("Selftest failed: {}", e);
+                std::process::exit(1);
+            }
         }
+        return;
     }
-}
 
+    if let ("replay", Some(matches)) = matches.subcommand() {
+        let config = ReplayConfig {
+            destination: value_t_or_exit!(matches, "destination", std::net::SocketAddr),
+            speed: value_t_or_exit!(matches, "speed", f64),
+            repeat: matches.is_present("loop"),
+        };
+        if let Err(e) = replay::replay_capture(matches.value_of("capture_file").unwrap(), &config) {
+            eprintln!("Replay failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-fn analyze_message_accounts(
-    account_keys: &[Pubkey],
-    instructions: &[CompiledInstruction],
-    signature: &Signature,
-) -> Option<()> {
-    println!("\nAccount addresses:");
-    for (i, key) in account_keys.iter().enumerate() {
-        println!("Account {}: {}", i, key);
+    if let ("compare-feeds", Some(matches)) = matches.subcommand() {
+        let feeds = values_t_or_exit!(matches, "feed", Feed);
+        if feeds.len() < 2 {
+            eprintln!("compare-feeds needs at least two --feed");
+            std::process::exit(1);
+        }
+        let report_interval = std::time::Duration::from_secs(value_t_or_exit!(matches, "report_secs", u64));
+        let mut comparison = FeedComparison::new(
+            feeds.iter().map(|feed| feed.name.clone()).collect(),
+            value_t_or_exit!(matches, "window_ms", u64),
+        );
+        let (sender, receiver) = mpsc::channel();
+        for (index, feed) in feeds.iter().enumerate() {
+            if let Err(e) = feed_compare::spawn_feed(index, feed, sender.clone()) {
+                eprintln!("Unable to receive feed {} on {}: {}", feed.name, feed.address, e);
+                std::process::exit(1);
+            }
+        }
+        let mut last_report = std::time::Instant::now();
+        loop {
+            let timeout = report_interval.saturating_sub(last_report.elapsed());
+            if let Ok((feed, signature, received_at_us)) = receiver.recv_timeout(timeout) {
+                if let Some(race) = comparison.record(feed, signature, received_at_us) {
+                    println!("{}: {} first by {}us over {}",
+                             race.signature,
+                             comparison.name(race.winner),
+                             race.lead_us,
+                             comparison.name(race.loser));
+                }
+            }
+            if last_report.elapsed() >= report_interval {
+                comparison.expire(capture::now_us());
+                for line in comparison.summary() {
+                    println!("{}", line);
+                }
+                last_report = std::time::Instant::now();
+            }
+        }
     }
 
-    // Raydium 和其他重要合约地址
-    let raydium_v4 = Pubkey::from_str(RAYDIUM_V4_PROGRAM_ID).ok()?;
-    let raydium_swap = Pubkey::from_str(RAYDIUM_SWAP_PROGRAM).ok()?;
-    let token_program = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+    amounts::set_amount_format(value_t_or_exit!(matches, "amount_format", AmountFormat));
+    let decimals = matches
+        .values_of("decimals")
+        .map(|values| values.map(known_decimals::parse_decimals).collect::<Result<Vec<_>, _>>())
+        .unwrap_or(Ok(vec![]))
+        .unwrap_or_else(|e| {
+            eprintln!("Invalid --decimals: {}", e);
+            std::process::exit(1);
+        });
+    known_decimals::set_decimals(decimals);
+    analyzer::set_debug_structs(matches.is_present("debug_struct"));
+    analyzer::set_max_accounts(value_t_or_exit!(matches, "max_accounts", usize));
+    if let Some(names) = matches.values_of("fields") {
+        match json_output::parse_swap_fields(names) {
+            Ok(fields) => json_output::set_swap_fields(fields),
+            Err(e) => {
+                eprintln!("Invalid --fields: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    front_run_markers::set_front_run_markers(values_t_or_exit!(matches, "front_run_markers", Pubkey));
 
-    for (i, ix) in instructions.iter().enumerate() {
-        let program_id = account_keys[ix.program_id_index as usize];
-        println!("\nInstruction {} Program ID: {}", i, program_id);
+    if let Some(spec) = matches.value_of("numa_node") {
+        let pinned = numa::resolve_node(spec).and_then(|node| {
+            let cpus = numa::node_cpus(node)?;
+            numa::pin_current_thread(&cpus).map_err(|e| e.to_string())?;
+            Ok((node, cpus.len()))
+        });
+        match pinned {
+            Ok((node, cpu_count)) => println!("Pinned to the {} CPUs of NUMA node {}", cpu_count, node),
+            Err(e) => {
+                eprintln!("Unable to pin to NUMA node {}: {}", spec, e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-        if program_id == raydium_v4 || program_id == raydium_swap {
-            println!("Found Raydium transaction! Signature: {}", signature);
-            println!("\nSwap Account Details:");
+    let capture = matches.value_of("capture").map(|path| {
+        CaptureWriter::create(path, matches.is_present("capture_compress")).unwrap_or_else(|e| {
+            eprintln!("Unable to create capture file {}: {}", path, e);
+            std::process::exit(1);
+        })
+    });
 
-            // 解析关键账户
-            for (idx, account_idx) in ix.accounts.iter().enumerate() {
-                let account = &account_keys[*account_idx as usize];
-                match idx {
-                    0 => println!("Token Program: {}", account),
-                    1 => println!("AMM Account: {}", account),
-                    2 => println!("AMM Authority: {}", account),
-                    5 => println!("Pool Token Account 1: {}", account),
-                    6 => println!("Pool Token Account 2: {}", account),
-                    15 => println!("User Source Token Account: {}", account),
-                    16 => println!("User Destination Token Account: {}", account),
-                    17 => println!("User Authority: {}", account),
-                    _ => println!("Account {}: {}", idx, account),
-                }
+    let rpc_client = matches.value_of("rpc_url").map(|url| {
+        Arc::new(RpcClient::new_with_commitment(
+            url.to_string(),
+            CommitmentConfig::confirmed(),
+        ))
+    });
+    let mut amms = AmmRegistry::default();
+    if let Some(path) = matches.value_of("amm_specs") {
+        match amms.load(path) {
+            Ok(count) => println!("Loaded {} AMM specs from {}", count, path),
+            Err(e) => {
+                eprintln!("Unable to load AMM specs from {}: {}", path, e);
+                std::process::exit(1);
             }
+        }
+    }
+    if let Some(path) = matches.value_of("account_layouts") {
+        match amms.load_layouts(path) {
+            Ok(count) => println!("Loaded {} account layouts from {}", count, path),
+            Err(e) => {
+                eprintln!("Unable to load account layouts from {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-            // 解析程序日志
-            if let Some(ray_log) = find_ray_log(&ix.data) {
-                println!("\nRaydium Log Data:");
-                println!("{}", ray_log);
+    if let ("bench", Some(matches)) = matches.subcommand() {
+        let path = matches.value_of("capture_file").unwrap();
+        analyzer::set_quiet(true);
+        let report = CaptureReader::open(path).and_then(|reader| bench::bench_capture(reader, &amms));
+        match report {
+            Ok(report) => println!("{}", report),
+            Err(e) => {
+                eprintln!("Unable to read capture file {}: {}", path, e);
+                std::process::exit(1);
             }
+        }
+        return;
+    }
+
+    let cache_capacity = value_t_or_exit!(matches, "cache_capacity", usize);
+    let mut resolvers = Resolvers::default();
+    if let Some(rpc_client) = &rpc_client {
+        if matches.is_present("resolve_token_metadata") {
+            resolvers.token_metadata = Some(TokenMetadataResolver::with_capacity(rpc_client.clone(), cache_capacity));
+        }
+        if matches.is_present("resolve_lookup_tables") {
+            resolvers.lookup_tables = Some(LookupTableResolver::with_capacity(rpc_client.clone(), cache_capacity));
+        }
+        if matches.is_present("resolve_pool_fees") {
+            resolvers.pool_fees = Some(PoolFeeResolver::with_capacity(rpc_client.clone(), cache_capacity));
+        }
+        if matches.is_present("resolve_payer_balance") {
+            let ttl = std::time::Duration::from_secs(value_t_or_exit!(matches, "payer_balance_ttl_secs", u64));
+            resolvers.payer_balances = Some(PayerBalanceResolver::with_capacity(rpc_client.clone(), ttl, cache_capacity));
+        }
+        if matches.is_present("resolve_cpi") {
+            let programs = values_t_or_exit!(matches, "cpi_programs", Pubkey);
+            resolvers.cpi = Some(CpiResolver::new(rpc_client.clone(), programs));
+        }
+    }
 
-            // 打印完整的指令数据（十六进制）
-            println!("\nInstruction data (hex):");
-            for (i, chunk) in ix.data.chunks(32).enumerate() {
-                let hex_string: String = chunk.iter()
-                    .map(|b| format!("{:02x}", b))
-                    .collect();
-                println!("{:04x}: {}", i * 32, hex_string);
+    if let ("decode", Some(matches)) = matches.subcommand() {
+        let text = match matches.value_of("transaction") {
+            Some(transaction) => transaction.to_string(),
+            None => {
+                let path = matches.value_of("file").unwrap_or("-");
+                decode::read_input(path).unwrap_or_else(|e| {
+                    eprintln!("Unable to read {}: {}", path, e);
+                    std::process::exit(1);
+                })
             }
+        };
+        let data = decode::decode_transaction(&text, value_t_or_exit!(matches, "encoding", Encoding))
+            .unwrap_or_else(|e| {
+                eprintln!("Unable to decode the transaction: {}", e);
+                std::process::exit(1);
+            });
+        match analyze_transaction(&data, &amms, &mut resolvers) {
+            Ok(Some(_)) => {}
+            Ok(None) => println!("No swap found"),
+            Err(e) => {
+                eprintln!("Unable to analyze the transaction: {:?}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-            // 解析 Raydium 指令数据
-            if ix.data.len() >= 17 {
-                let discriminator = ix.data[0];
-                let amount_in = {
-                    let mut amount_bytes = [0u8; 8];
-                    amount_bytes.copy_from_slice(&ix.data[1..9]);
-                    u64::from_le_bytes(amount_bytes)
-                };
+    let mut outputs = SwapOutputs::default();
+    let include_packet = matches.is_present("include_packet");
+    if let Some(path) = matches.value_of("json") {
+        let writer = JsonLinesWriter::create(path, matches.is_present("full_json"), include_packet).unwrap_or_else(|e| {
+            eprintln!("Unable to create JSON output file {}: {}", path, e);
+            std::process::exit(1);
+        });
+        outputs.add_sink(Box::new(writer));
+    }
+    if let Some(destination) = matches.value_of("binary") {
+        let max_buffered = value_t_or_exit!(matches, "binary_max_buffered", usize);
+        let writer = BinarySwapWriter::create(destination, max_buffered, include_packet).unwrap_or_else(|e| {
+            eprintln!("Unable to open binary output {}: {}", destination, e);
+            std::process::exit(1);
+        });
+        outputs.add_sink(Box::new(writer));
+    }
+    #[cfg(feature = "parquet")]
+    if let Some(path) = matches.value_of("parquet") {
+        let rows_per_file = value_t_or_exit!(matches, "parquet_rows_per_file", usize);
+        outputs.add_sink(Box::new(ParquetSwapWriter::new(path, rows_per_file)));
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = matches.value_of("sqlite") {
+        let writer = SqliteSwapWriter::create(path).unwrap_or_else(|e| {
+            eprintln!("Unable to open SQLite database {}: {}", path, e);
+            std::process::exit(1);
+        });
+        outputs.add_sink(Box::new(writer));
+    }
+    #[cfg(feature = "protobuf")]
+    if let Some(path) = matches.value_of("protobuf") {
+        let writer = ProtobufSwapWriter::create(path).unwrap_or_else(|e| {
+            eprintln!("Unable to create protobuf output {}: {}", path, e);
+            std::process::exit(1);
+        });
+        outputs.add_sink(Box::new(writer));
+    }
+    #[cfg(feature = "geyser")]
+    if let Some(path) = matches.value_of("geyser") {
+        let writer = GeyserSwapWriter::create(path).unwrap_or_else(|e| {
+            eprintln!("Unable to create Geyser output {}: {}", path, e);
+            std::process::exit(1);
+        });
+        outputs.add_sink(Box::new(writer));
+    }
+    if let Some(address) = matches.value_of("price_http") {
+        let prices = Arc::new(Mutex::new(PoolPrices::new(value_t_or_exit!(matches, "price_pools", usize))));
+        pool_prices::spawn_server(address, prices.clone()).unwrap_or_else(|e| {
+            eprintln!("Unable to serve prices on {}: {}", address, e);
+            std::process::exit(1);
+        });
+        outputs.add_sink(Box::new(PoolPriceSink::new(prices)));
+    }
+    #[cfg(feature = "webhook")]
+    if let Some(url) = matches.value_of("webhook_url") {
+        let sink = WebhookSink::spawn(WebhookConfig {
+            url: url.to_string(),
+            queue_size: value_t_or_exit!(matches, "webhook_queue_size", usize),
+            retries: value_t_or_exit!(matches, "webhook_retries", u32),
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("Unable to start the webhook output: {}", e);
+            std::process::exit(1);
+        });
+        outputs.add_sink(Box::new(sink));
+    }
+    #[cfg(feature = "nats")]
+    if let Some(url) = matches.value_of("nats_url") {
+        let sink = NatsSink::spawn(NatsConfig {
+            url: url.to_string(),
+            subject: matches.value_of("nats_subject").unwrap().to_string(),
+            queue_size: value_t_or_exit!(matches, "nats_queue_size", usize),
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("Unable to connect to NATS at {}: {}", url, e);
+            std::process::exit(1);
+        });
+        outputs.add_sink(Box::new(sink));
+    }
+    #[cfg(feature = "clickhouse")]
+    if let Some(url) = matches.value_of("clickhouse_url") {
+        let sink = ClickHouseSink::spawn(ClickHouseConfig {
+            url: url.to_string(),
+            table: matches.value_of("clickhouse_table").unwrap().to_string(),
+            user: matches.value_of("clickhouse_user").map(str::to_string),
+            password: std::env::var("CLICKHOUSE_PASSWORD").ok(),
+            create_table: matches.is_present("clickhouse_create_table"),
+            batch_size: value_t_or_exit!(matches, "clickhouse_batch_size", usize),
+            flush_interval: std::time::Duration::from_millis(value_t_or_exit!(matches, "clickhouse_flush_ms", u64)),
+            queue_size: value_t_or_exit!(matches, "clickhouse_queue_size", usize),
+            retries: value_t_or_exit!(matches, "clickhouse_retries", u32),
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("Unable to start the ClickHouse output: {}", e);
+            std::process::exit(1);
+        });
+        outputs.add_sink(Box::new(sink));
+    }
+    outputs.watch = matches.is_present("watch_mint").then(|| {
+        RateOfChangeMonitor::new(WatchConfig {
+            mint: value_t_or_exit!(matches, "watch_mint", Pubkey),
+            window_us: value_t_or_exit!(matches, "watch_window", u64).saturating_mul(1_000_000),
+            volume_threshold: matches
+                .is_present("watch_volume")
+                .then(|| value_t_or_exit!(matches, "watch_volume", u64)),
+            price_change_percent: Some(value_t_or_exit!(matches, "watch_price_change", f64)),
+        })
+    });
+    outputs.mint_activity = matches.is_present("top_mints").then(|| {
+        MintActivity::new(
+            value_t_or_exit!(matches, "top_mints_interval", u64).saturating_mul(1_000_000),
+            value_t_or_exit!(matches, "top_mints", usize),
+        )
+    });
+    outputs.wash_trades = matches.is_present("detect_wash_trades").then(|| {
+        WashTradeDetector::new(WashTradeConfig {
+            window_us: value_t_or_exit!(matches, "wash_window", u64).saturating_mul(1_000_000),
+            size_tolerance_percent: value_t_or_exit!(matches, "wash_size_tolerance", f64),
+        })
+    });
+    outputs.sandwiches = matches.is_present("detect_sandwiches").then(|| {
+        SandwichDetector::new(SandwichConfig {
+            window_us: value_t_or_exit!(matches, "sandwich_window", u64).saturating_mul(1_000),
+        })
+    });
 
-                let min_amount_out = {
-                    let mut amount_bytes = [0u8; 8];
-                    amount_bytes.copy_from_slice(&ix.data[9..17]);
-                    u64::from_le_bytes(amount_bytes)
-                };
+    let stats = Arc::new(ReceiverStats::default());
+    let since_slot = matches
+        .is_present("since_slot")
+        .then(|| value_t_or_exit!(matches, "since_slot", u64));
 
-                println!("\nParsed Swap Details:");
-                println!("Discriminator: {}", discriminator);
-                println!("Amount In: {} lamports", amount_in);
-                println!("Minimum Amount Out: {} tokens", min_amount_out);
-            }
+    #[cfg(feature = "analyzer-timing")]
+    let timing_interval = matches
+        .is_present("analyzer_timing")
+        .then(|| std::time::Duration::from_secs(value_t_or_exit!(matches, "analyzer_timing", u64)));
 
-            return Some(());
+    if let Some(arg) = matches.value_of("backfill") {
+        #[cfg(feature = "analyzer-timing")]
+        if let Some(interval) = timing_interval {
+            timing::enable(interval);
         }
+        let signatures = backfill::read_signatures(arg).unwrap_or_else(|e| {
+            eprintln!("Unable to read signatures from {}: {}", arg, e);
+            std::process::exit(1);
+        });
+        let rpc_client = rpc_client.unwrap();
+        let drop_failed = matches.is_present("drop_failed");
+        let signatures = match since_slot {
+            Some(slot) => {
+                let count = signatures.len();
+                let signatures = backfill::retain_since_slot(&rpc_client, signatures, slot).unwrap_or_else(|e| {
+                    eprintln!("Unable to look up signature slots: {}", e);
+                    std::process::exit(1);
+                });
+                println!("Skipping {} signatures before slot {}", count - signatures.len(), slot);
+                signatures
+            }
+            None => signatures,
+        };
+        for signature in signatures {
+            let historical = match backfill::fetch_transaction(&rpc_client, &signature) {
+                Ok(historical) => historical,
+                Err(e) => {
+                    eprintln!("Unable to fetch {}: {}", signature, e);
+                    continue;
+                }
+            };
+            let swap = analyze_versioned_transaction(
+                &historical.transaction,
+                historical.loaded_addresses,
+                &historical.inner_instructions,
+                &historical.log_messages,
+                &amms,
+                &mut resolvers,
+            );
+            match swap {
+                Ok(Some(_)) if drop_failed && historical.err.is_some() => {
+                    println!("Dropping failed transaction {}: {}", signature, historical.err.unwrap());
+                    stats.record_drop(DropReason::Failed);
+                }
+                Ok(Some(mut swap)) => {
+                    stats.record_match(swap.dex);
+                    println!("Slot: {}", historical.slot);
+                    swap.compute_units_consumed = historical.compute_units_consumed;
+                    if let (Some(consumed), Some(utilization)) =
+                        (swap.compute_units_consumed, swap.compute_unit_utilization())
+                    {
+                        println!("Compute Units Consumed: {} of {} ({:.1}% of the limit)",
+                                 consumed,
+                                 swap.priority_fee.compute_unit_limit,
+                                 utilization * 100.0);
+                    }
+                    // Block time stands in for the receive time of a live packet
+                    let received_at_us = historical
+                        .block_time
+                        .map_or(0, |block_time| (block_time.max(0) as u64).saturating_mul(1_000_000));
+                    let packet = bincode::serialize(&historical.transaction).unwrap();
+                    outputs.record(received_at_us, &swap, &packet);
+                }
+                Ok(None) => println!("{}: no swap found", signature),
+                Err(e) => eprintln!("Unable to analyze {}: {:?}", signature, e),
+            }
+        }
+        outputs.finish();
+        return;
     }
-    None
-}
 
-fn find_ray_log(data: &[u8]) -> Option<String> {
-    // Base64 解码处理
-    if data.len() > 8 {
-        // 这里需要具体实现，从程序日志中解析出ray_log的内容
-        None
-    } else {
-        None
+    let socket = UdpSocket::bind(DEFAULT_BIND_ADDRESS).unwrap_or_else(|e| {
+        eprintln!("Unable to bind {}: {}", DEFAULT_BIND_ADDRESS, e);
+        std::process::exit(1);
+    });
+    let (sender, receiver) = mpsc::channel();
+    #[cfg(feature = "ws-source")]
+    if let Some(url) = matches.value_of("ws_url") {
+        let config = WsSourceConfig {
+            url: url.to_string(),
+            program_ids: Dex::ALL.iter().map(Dex::program_id).chain(amms.specs().map(|spec| spec.program_id)).collect(),
+            reconnect_delay: std::time::Duration::from_secs(value_t_or_exit!(matches, "ws_reconnect_secs", u64)),
+        };
+        if let Err(e) = ws_source::spawn(config, rpc_client.clone().unwrap(), sender.clone()) {
+            eprintln!("Unable to start the WebSocket feed: {}", e);
+            std::process::exit(1);
+        }
     }
-}
+    let intake = Intake {
+        capture,
+        rate_limiter: matches.is_present("max_source_rate").then(|| {
+            SourceRateLimiter::new(
+                value_t_or_exit!(matches, "max_source_rate", u64),
+                value_t_or_exit!(matches, "rate_limit_sources", usize),
+            )
+        }),
+        stats: stats.clone(),
+    };
+    let receiver_thread = udp_receiver::spawn(socket, intake, sender).unwrap_or_else(|e| {
+        eprintln!("Unable to start receiving: {}", e);
+        std::process::exit(1);
+    });
 
+    let continuous = matches.is_present("continuous");
+    let max_packet_age_us = matches
+        .is_present("max_packet_age_ms")
+        .then(|| value_t_or_exit!(matches, "max_packet_age_ms", u64).saturating_mul(1_000));
+    let annotate_arrivals = matches.is_present("annotate_arrivals");
+    let mut observer_tracker = matches
+        .is_present("count_observers")
+        .then(|| ObserverTracker::new(value_t_or_exit!(matches, "arrival_window_ms", u64)));
+    let mut load_shedder = matches.is_present("load_shed").then(|| {
+        LoadShedder::new(LoadShedConfig {
+            high_water: value_t_or_exit!(matches, "shed_high_water", usize),
+            low_water: value_t_or_exit!(matches, "shed_low_water", usize),
+            sustain_us: value_t_or_exit!(matches, "shed_sustain_ms", u64).saturating_mul(1_000),
+            sample_every: value_t_or_exit!(matches, "shed_sample", u64),
+        })
+    });
+    let quote_filter = matches.values_of("quote_mints").map(|mints| {
+        let mints = mints.map(quote_filter::parse_quote_mint).collect::<Result<Vec<_>, _>>();
+        let mints = mints.unwrap_or_else(|e| {
+            eprintln!("Invalid --quote-mints: {}", e);
+            std::process::exit(1);
+        });
+        let unresolved = if matches.is_present("unresolved_quote") {
+            value_t_or_exit!(matches, "unresolved_quote", UnresolvedQuote)
+        } else {
+            UnresolvedQuote::Pass
+        };
+        QuoteFilter::new(mints, unresolved)
+    });
+    let failed_filter = rpc_client
+        .as_ref()
+        .filter(|_| matches.is_present("drop_failed"))
+        .map(|rpc_client| FailedSwapFilter::new(rpc_client.clone()));
+    let landing_tracker = rpc_client
+        .as_ref()
+        .filter(|_| matches.is_present("track_landing"))
+        .map(|rpc_client| {
+            let timeout = std::time::Duration::from_secs(value_t_or_exit!(matches, "landing_timeout_secs", u64));
+            LandingTracker::spawn(rpc_client.clone(), timeout).unwrap_or_else(|e| {
+                eprintln!("Unable to start the landing tracker: {}", e);
+                std::process::exit(1);
+            })
+        });
+    let signature_capacity = value_t_or_exit!(matches, "signature_capacity", usize);
+    let signature_key = value_t_or_exit!(matches, "signature_key", SignatureKey);
+    let mut arrival_tracker = ArrivalTracker::with_key(
+        value_t_or_exit!(matches, "arrival_window_ms", u64),
+        signature_capacity,
+        signature_key,
+    );
 
-fn parse_raydium_instruction(data: &[u8]) -> Option<(u64, Pubkey)> {
-    if data.len() < 9 {
-        return None;
-    }
+    let mut state_store = matches.value_of("state_file").map(|path| {
+        let config = StateConfig {
+            path: path.into(),
+            flush_interval_us: value_t_or_exit!(matches, "state_flush_interval", u64).saturating_mul(1_000_000),
+            max_entries: value_t_or_exit!(matches, "state_max_entries", usize),
+        };
+        let store = StateStore::open(config, capture::now_us()).unwrap_or_else(|e| {
+            eprintln!("Unable to read state file {}: {}", path, e);
+            std::process::exit(1);
+        });
+        if let Some(resolver) = resolvers.token_metadata.as_mut() {
+            store.restore_mints(resolver);
+        }
+        println!("Restored {} reported signatures from {}", store.restored_signatures(), path);
+        store
+    });
 
-    let amount = u64::from_le_bytes(data[0..8].try_into().ok()?);
-    let mut pubkey_bytes = [0u8; 32];
-    pubkey_bytes.copy_from_slice(&data[8..40]);
-    let token_mint = Pubkey::new_from_array(pubkey_bytes);
+    #[cfg(feature = "statsd")]
+    if matches.is_present("statsd_addr") {
+        let config = StatsdConfig {
+            address: value_t_or_exit!(matches, "statsd_addr", std::net::SocketAddr),
+            interval: std::time::Duration::from_secs(value_t_or_exit!(matches, "statsd_interval", u64)),
+            prefix: matches.value_of("statsd_prefix").unwrap().to_string(),
+        };
+        if let Err(e) = statsd::spawn_reporter(config, stats.clone()) {
+            eprintln!("Unable to start statsd reporter: {}", e);
+            std::process::exit(1);
+        }
+    }
 
-    Some((amount, token_mint))
-}
+    #[cfg(feature = "redis")]
+    let mut redis_dedup = matches.is_present("redis_dedup").then(|| {
+        RedisDedup::new(RedisDedupConfig {
+            address: value_t_or_exit!(matches, "redis_dedup", std::net::SocketAddr),
+            ttl: std::time::Duration::from_secs(value_t_or_exit!(matches, "redis_dedup_ttl", u64)),
+            key_prefix: matches.value_of("redis_dedup_prefix").unwrap().to_string(),
+        })
+    });
 
-fn main() {
-    let (client, receiver) = UdpClient::new("127.0.0.1:44444").unwrap();
+    // The WebSocket feed delivers transactions the UDP feed may already have seen
+    #[cfg(feature = "ws-source")]
+    let mut ws_dedup = matches
+        .is_present("ws_url")
+        .then(|| ArrivalTracker::with_key(ws_source::DEDUP_WINDOW_MS, signature_capacity, signature_key));
 
-    let receiver_thread = thread::spawn(move || {
-        client.start_receiving();
+    #[cfg(feature = "otel")]
+    let packet_tracer = matches.value_of("otlp_endpoint").map(|endpoint| {
+        let config = OtelConfig {
+            endpoint: endpoint.to_string(),
+            sample_ratio: value_t_or_exit!(matches, "otlp_sample_ratio", f64),
+        };
+        PacketTracer::spawn(config).unwrap_or_else(|e| {
+            eprintln!("Unable to start the trace exporter: {}", e);
+            std::process::exit(1);
+        })
     });
 
     let consumer_thread = thread::spawn(move || {
-        while let Ok(rx) = receiver.lock() {
-            if let Ok(data) = rx.recv() {
-                if let Some(()) = analyze_transaction(&data) {
-                    println!("Found target transaction, exiting...");
-                    std::process::exit(0);
+        // Timings are collected per thread, so enable them where the analyzer runs
+        #[cfg(feature = "analyzer-timing")]
+        if let Some(interval) = timing_interval {
+            timing::enable(interval);
+        }
+        // Drained from the channel as it fills, so the queue depth is known
+        let mut queued = VecDeque::new();
+        // Stand in for `resolvers` while shedding load
+        let mut no_resolvers = Resolvers::default();
+        loop {
+            if queued.is_empty() {
+                match receiver.recv() {
+                    Ok(datagram) => queued.push_back(datagram),
+                    Err(_) => break,
+                }
+            }
+            queued.extend(receiver.try_iter());
+            let Datagram { data, source } = queued.pop_front().unwrap();
+            if let Some(state) = state_store.as_mut() {
+                state.maybe_flush(resolvers.token_metadata.as_ref(), capture::now_us());
+            }
+            #[cfg(feature = "otel")]
+            let picked_up = std::time::SystemTime::now();
+            stats.packets_received.fetch_add(1, Ordering::Relaxed);
+            let shedding = match load_shedder.as_mut() {
+                Some(shedder) => {
+                    if let Some(event) = shedder.observe(queued.len(), capture::now_us()) {
+                        amounts::force_raw(matches!(event, LoadShedEvent::Shedding { .. }));
+                        eprintln!("{}", event);
+                    }
+                    if !shedder.admit() {
+                        stats.record_drop(DropReason::Shed);
+                        continue;
+                    }
+                    shedder.is_shedding()
+                }
+                None => false,
+            };
+            let (header, packet) = match ForwardHeader::parse(&data) {
+                Ok(parsed) => parsed,
+                Err(HeaderError::Malformed) => {
+                    let skipped = stats.record_drop(DropReason::Malformed);
+                    eprintln!("Skipping datagram without a forward header, {} skipped so far", skipped);
+                    continue;
+                }
+                Err(HeaderError::UnknownVersion(version)) => {
+                    let skipped = stats.record_drop(DropReason::UnknownVersion);
+                    // 转发端版本比接收端新时每个包都会走到这里，只偶尔记录
+                    if skipped.is_power_of_two() {
+                        eprintln!("Skipping datagrams with unknown forward header version {}, {} skipped so far",
+                                  version, skipped);
+                    }
+                    continue;
+                }
+            };
+            if max_packet_age_us.is_some_and(|max_age_us| header.is_older_than(max_age_us, capture::now_us())) {
+                let dropped = stats.record_drop(DropReason::Stale);
+                // Logging every drop would only deepen the backlog
+                if dropped.is_power_of_two() {
+                    eprintln!("Dropping stale packets, {} dropped so far", dropped);
+                }
+                continue;
+            }
+            if since_slot.is_some_and(|slot| header.is_before_slot(slot)) {
+                stats.record_drop(DropReason::BeforeSlot);
+                continue;
+            }
+            let thin = match header.mode {
+                ForwardMode::Full => None,
+                ForwardMode::Thin => match ThinTransaction::parse(packet) {
+                    Some(thin) => Some(thin),
+                    None => {
+                        let skipped = stats.record_drop(DropReason::Malformed);
+                        eprintln!("Skipping malformed thin payload, {} skipped so far", skipped);
+                        continue;
+                    }
+                },
+            };
+            let signature = match &thin {
+                Some(thin) => Some(thin.signature),
+                None => arrivals::first_signature(packet),
+            };
+            let arrival_count = signature
+                .map(|signature| arrival_tracker.record(signature, capture::now_us()))
+                .unwrap_or(1);
+            if arrival_count > 1 {
+                stats.multi_path_arrivals.fetch_add(1, Ordering::Relaxed);
+            }
+            let observers = match (observer_tracker.as_mut(), signature, source) {
+                (Some(tracker), Some(signature), Some(source)) => match tracker.record(signature, source, capture::now_us()) {
+                    Some(observers) => Some(observers),
+                    None => {
+                        stats.record_drop(DropReason::Dedup);
+                        continue;
+                    }
+                },
+                _ => None,
+            };
+            // Copies from further forwarders are counted, not duplicates
+            #[cfg(any(feature = "ws-source", feature = "redis"))]
+            let repeat_observation = observers.is_some_and(|observers| observers > 1);
+            #[cfg(feature = "ws-source")]
+            if let (Some(dedup), Some(signature)) = (ws_dedup.as_mut(), signature) {
+                if dedup.record(signature, capture::now_us()) > 1 && !repeat_observation {
+                    stats.record_drop(DropReason::Dedup);
+                    continue;
+                }
+            }
+            #[cfg(feature = "redis")]
+            if let (Some(dedup), Some(signature)) = (redis_dedup.as_mut(), signature) {
+                // Without Redis, fall back to this receiver's own arrivals
+                if !repeat_observation && !dedup.claim(&signature).unwrap_or(arrival_count == 1) {
+                    stats.record_drop(DropReason::Dedup);
+                    continue;
+                }
+            }
+            if let (Some(state), Some(signature)) = (state_store.as_ref(), signature) {
+                if state.reported_before(&signature) {
+                    stats.record_drop(DropReason::Dedup);
+                    continue;
                 }
             }
+            if let Some(thin) = thin {
+                if let Some(dex) = thin.dex(&amms) {
+                    stats.record_match(dex);
+                    if let Some(state) = state_store.as_mut() {
+                        state.record_reported(thin.signature);
+                    }
+                    println!("\n{} touched {} ({} programs invoked)",
+                             thin.signature, dex.name(), thin.program_ids.len());
+                    if let Some(tracker) = &landing_tracker {
+                        tracker.track(thin.signature, &header);
+                    }
+                    if annotate_arrivals {
+                        println!("Arrivals: {}", arrival_count);
+                    }
+                    if let Some(observers) = observers {
+                        println!("Observers: {}", observers);
+                    }
+                    println!("Forward latency: {}us ({:?} clock)",
+                             header.latency_us(capture::now_us()),
+                             header.clock);
+                    if let Some(leader) = header.leader {
+                        println!("Leader: {}", leader);
+                    }
+                    if !continuous {
+                        println!("Found target transaction, exiting...");
+                        println!("Packets: {}", stats.snapshot().drop_summary());
+                        if let Some(state) = state_store.as_mut() {
+                            state.flush(resolvers.token_metadata.as_ref(), capture::now_us());
+                        }
+                        std::process::exit(0);
+                    }
+                }
+                continue;
+            }
+            // A panic on one bad packet must not stop the consumer while the receiver
+            // keeps queueing. The hook has logged it; the packet is printed so it can be
+            // reproduced with `decode` and added to the fuzz corpus. The resolvers are
+            // only caches, so one left mid-update is harmless.
+            let resolvers = if shedding { &mut no_resolvers } else { &mut resolvers };
+            let analyzed = panic::catch_unwind(AssertUnwindSafe(|| analyze_transaction(packet, &amms, resolvers)));
+            #[cfg(feature = "otel")]
+            let trace = |outcome| {
+                if let (Some(tracer), Some(signature)) = (&packet_tracer, &signature) {
+                    tracer.record(signature, &header, picked_up, std::time::SystemTime::now(), outcome);
+                }
+            };
+            let Ok(analyzed) = analyzed else {
+                #[cfg(feature = "otel")]
+                trace(PacketOutcome::Dropped(DropReason::Panicked.name()));
+                let panicked = stats.record_drop(DropReason::Panicked);
+                eprintln!("Analyzer panicked on packet {}, {} so far", BASE64_STANDARD.encode(packet), panicked);
+                continue;
+            };
+            match analyzed {
+                Ok(Some(swap)) if quote_filter.as_ref().is_some_and(|filter| !filter.accepts(&swap)) => {
+                    #[cfg(feature = "otel")]
+                    trace(PacketOutcome::Dropped(DropReason::QuoteMint.name()));
+                    stats.record_drop(DropReason::QuoteMint);
+                }
+                Ok(Some(swap)) if !shedding && failed_filter.as_ref().is_some_and(|filter| filter.failed(&swap.signature)) => {
+                    #[cfg(feature = "otel")]
+                    trace(PacketOutcome::Dropped(DropReason::Failed.name()));
+                    stats.record_drop(DropReason::Failed);
+                }
+                Ok(Some(mut swap)) => {
+                    #[cfg(feature = "otel")]
+                    trace(PacketOutcome::Swap);
+                    swap.observers = observers;
+                    stats.record_match(swap.dex);
+                    if let Some(state) = state_store.as_mut() {
+                        state.record_reported(swap.signature);
+                    }
+                    if annotate_arrivals {
+                        println!("Arrivals: {}", arrival_count);
+                    }
+                    if let Some(observers) = observers {
+                        println!("Observers: {}", observers);
+                    }
+                    outputs.record(capture::now_us(), &swap, packet);
+                    if let Some(tracker) = &landing_tracker {
+                        tracker.track(swap.signature, &header);
+                    }
+                    println!("Forward latency: {}us ({:?} clock)",
+                             header.latency_us(capture::now_us()),
+                             header.clock);
+                    if let Some(leader) = header.leader {
+                        println!("Leader: {}", leader);
+                    }
+                    if !continuous {
+                        println!("Found target transaction, exiting...");
+                        println!("Packets: {}", stats.snapshot().drop_summary());
+                        if let Some(state) = state_store.as_mut() {
+                            state.flush(resolvers.token_metadata.as_ref(), capture::now_us());
+                        }
+                        outputs.finish();
+                        std::process::exit(0);
+                    }
+                }
+                Ok(None) => {
+                    #[cfg(feature = "otel")]
+                    trace(PacketOutcome::NoSwap);
+                }
+                Err(AnalyzeError::TooManyAccounts) => {
+                    #[cfg(feature = "otel")]
+                    trace(PacketOutcome::Dropped(DropReason::TooManyAccounts.name()));
+                    let skipped = stats.record_drop(DropReason::TooManyAccounts);
+                    if skipped.is_power_of_two() {
+                        eprintln!("Skipping packet with more than --max-accounts accounts, {} skipped so far", skipped);
+                    }
+                }
+                Err(e) => {
+                    #[cfg(feature = "otel")]
+                    trace(PacketOutcome::Dropped(DropReason::Malformed.name()));
+                    let skipped = stats.record_drop(DropReason::Malformed);
+                    eprintln!("Skipping malformed packet ({:?}), {} skipped so far", e, skipped);
+                }
+            }
+        }
+        println!("Packets: {}", stats.snapshot().drop_summary());
+        outputs.finish();
+        if let Some(state) = state_store.as_mut() {
+            state.flush(resolvers.token_metadata.as_ref(), capture::now_us());
+        }
+        #[cfg(feature = "otel")]
+        if let Some(tracer) = packet_tracer {
+            tracer.finish();
         }
     });
 
     receiver_thread.join().unwrap();
     consumer_thread.join().unwrap();
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_defaults() {
+        // Flags with defaults mustn't pull in the flags they only make sense with
+        for args in [
+            &["hoho-recv"][..],
+            &["hoho-recv", "selftest"],
+            &["hoho-recv", "--unresolved-quote", "drop", "--quote-mints", "usdc"],
+        ] {
+            assert!(app().get_matches_from_safe(args).is_ok(), "{:?}", args);
+        }
+        let matches = app().get_matches_from(["hoho-recv"]);
+        assert!(!matches.is_present("unresolved_quote"));
+        assert!(app().get_matches_from_safe(["hoho-recv", "--unresolved-quote", "drop"]).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Periodic report of the most active mints (`--top-mints`).
+//!
+//! Matched swaps are counted per mint over fixed intervals. When a swap arrives
+//! after its interval ended, the interval's top mints by swap count are reported,
+//! with the raw volume traded of each, and counting starts over. A swap counts for
+//! both its mints; mints that aren't known are not counted. Reports go to the
+//! console and to the outputs that take them. An interval with no swaps after it is
+//! only reported once the next swap arrives.
+use std::collections::HashMap;
+use std::fmt;
+use solana_sdk::pubkey::Pubkey;
+use crate::analyzer::AnalyzedSwap;
+
+// Bounds memory when swaps spread over many fresh mints; later mints go uncounted
+const MAX_TRACKED_MINTS: usize = 100_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MintCount {
+    pub swaps: u64,
+    /// Raw units of the mint swapped in or out
+    pub volume: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MintActivityReport {
+    pub start_us: u64,
+    pub end_us: u64,
+    pub swaps: u64,
+    /// Swaps touching a mint that found the counters full
+    pub untracked_swaps: u64,
+    /// Most swaps first
+    pub top: Vec<(Pubkey, MintCount)>,
+}
+
+impl fmt::Display for MintActivityReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Top {} mints of {} swaps in the last {}s:",
+            self.top.len(),
+            self.swaps,
+            (self.end_us - self.start_us) / 1_000_000
+        )?;
+        for (rank, (mint, count)) in self.top.iter().enumerate() {
+            write!(f, "\n  {}. {}: {} swaps, volume {}", rank + 1, mint, count.swaps, count.volume)?;
+        }
+        if self.untracked_swaps > 0 {
+            write!(f, "\n  ({} swaps on untracked mints)", self.untracked_swaps)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct MintActivity {
+    interval_us: u64,
+    top_n: usize,
+    start_us: Option<u64>,
+    swaps: u64,
+    untracked_swaps: u64,
+    counts: HashMap<Pubkey, MintCount>,
+}
+
+impl MintActivity {
+    pub fn new(interval_us: u64, top_n: usize) -> Self {
+        Self {
+            interval_us: interval_us.max(1),
+            top_n,
+            start_us: None,
+            swaps: 0,
+            untracked_swaps: 0,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Counts `swap`, returning the report of the interval before it if that ended.
+    pub fn observe(&mut self, swap: &AnalyzedSwap, now_us: u64) -> Option<MintActivityReport> {
+        let start_us = *self.start_us.get_or_insert(now_us);
+        let report = (now_us.saturating_sub(start_us) >= self.interval_us).then(|| {
+            let end_us = start_us + self.interval_us;
+            let report = self.report(start_us, end_us);
+            // Intervals stay aligned to the first swap, skipping those without swaps
+            self.start_us = Some(end_us + (now_us - end_us) / self.interval_us * self.interval_us);
+            self.swaps = 0;
+            self.untracked_swaps = 0;
+            self.counts.clear();
+            report
+        });
+
+        self.swaps += 1;
+        let amount_out = swap.realized_amount_out.unwrap_or(swap.min_amount_out);
+        let amount_in = swap.realized_amount_in.unwrap_or(swap.amount_in);
+        for (mint, volume) in [(swap.source_mint, amount_in), (swap.destination_mint, amount_out)] {
+            let Some(mint) = mint else {
+                continue;
+            };
+            if self.counts.len() == MAX_TRACKED_MINTS && !self.counts.contains_key(&mint) {
+                self.untracked_swaps += 1;
+                continue;
+            }
+            let count = self.counts.entry(mint).or_default();
+            count.swaps += 1;
+            count.volume = count.volume.saturating_add(volume);
+        }
+        report
+    }
+
+    fn report(&self, start_us: u64, end_us: u64) -> MintActivityReport {
+        let mut top: Vec<_> = self.counts.iter().map(|(mint, count)| (*mint, *count)).collect();
+        top.sort_unstable_by(|(a_mint, a), (b_mint, b)| {
+            (b.swaps, b.volume).cmp(&(a.swaps, a.volume)).then(a_mint.cmp(b_mint))
+        });
+        top.truncate(self.top_n);
+        MintActivityReport {
+            start_us,
+            end_us,
+            swaps: self.swaps,
+            untracked_swaps: self.untracked_swaps,
+            top,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Signature;
+    use solana_sdk::transaction::TransactionVersion;
+    use crate::analyzer::Dex;
+    use crate::compute_budget::PriorityFee;
+
+    fn swap(source_mint: Pubkey, destination_mint: Pubkey, amount_in: u64, min_amount_out: u64) -> AnalyzedSwap {
+        AnalyzedSwap {
+            signature: Signature::default(),
+            co_signatures: vec![],
+            dex: Dex::RaydiumV4,
+            discriminator: 9,
+            amount_in,
+            min_amount_out,
+            pool_coin_account: None,
+            pool_pc_account: None,
+            model_data_account: None,
+            user_source_account: None,
+            user_destination_account: None,
+            user_owner: None,
+            source_mint: Some(source_mint),
+            destination_mint: Some(destination_mint),
+            source_decimals: None,
+            destination_decimals: None,
+            transfers: vec![],
+            priority_fee: PriorityFee::default(),
+            compute_units_consumed: None,
+            lookup_tables: vec![],
+            transaction_size: 0,
+            version: TransactionVersion::LEGACY,
+            front_run_protected: false,
+            zero_min_out: false,
+            pool_fee_bps: None,
+            expected_amount_out: None,
+            realized_amount_out: None,
+            realized_amount_in: None,
+            mint_events: vec![],
+            burn_events: vec![],
+            authority_changes: vec![],
+            payer_sol_balance: None,
+            observers: None,
+            price_impact_bps: None,
+            pool_reserves: None,
+        }
+    }
+
+    #[test]
+    fn test_mint_activity() {
+        let (sol, bonk, wif) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut activity = MintActivity::new(10, 2);
+        assert_eq!(activity.observe(&swap(sol, bonk, 5, 100), 3), None);
+        assert_eq!(activity.observe(&swap(bonk, sol, 50, 2), 5), None);
+        assert_eq!(activity.observe(&swap(sol, wif, 1, 7), 12), None);
+        // The interval from 3 ended at 13
+        let report = activity.observe(&swap(wif, sol, 7, 1), 40).unwrap();
+        assert_eq!((report.start_us, report.end_us, report.swaps), (3, 13, 3));
+        assert_eq!(
+            report.top,
+            vec![(sol, MintCount { swaps: 3, volume: 8 }), (bonk, MintCount { swaps: 2, volume: 150 })]
+        );
+        // Counting restarted in the interval 33..43 holding the last swap
+        let report = activity.observe(&swap(sol, bonk, 1, 1), 43).unwrap();
+        assert_eq!((report.start_us, report.end_us, report.swaps), (33, 43, 1));
+        assert_eq!(report.top, vec![(wif, MintCount { swaps: 1, volume: 7 }), (sol, MintCount { swaps: 1, volume: 1 })]);
+    }
+}
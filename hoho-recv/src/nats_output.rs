@@ -0,0 +1,160 @@
+//! NATS output of matched swaps (`--nats-url`, feature `nats`).
+//!
+//! Each matched swap is published to `--nats-subject` as the same JSON object
+//! `--json` writes, one message per swap, and `--top-mints` reports the same way.
+//! Messages are handed to an async client on its own thread through a bounded
+//! queue; when the server falls behind the queue fills up and further swaps are
+//! dropped and counted, so a slow server never stalls analysis. The client
+//! reconnects on its own after losing the server, buffering what is published in
+//! the meantime.
+use std::io;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use async_nats::{Client, ConnectOptions};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use crate::analyzer::AnalyzedSwap;
+use crate::json_output::{mint_activity_json, swap_json};
+use crate::mint_activity::MintActivityReport;
+use crate::output::SwapSink;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct NatsConfig {
+    /// Server to connect to, e.g. `nats://localhost:4222`
+    pub url: String,
+    pub subject: String,
+    /// Swaps waiting to be published before new ones are dropped
+    pub queue_size: usize,
+}
+
+pub struct NatsSink {
+    sender: Option<mpsc::Sender<Vec<u8>>>,
+    publisher: Option<JoinHandle<()>>,
+    dropped: u64,
+}
+
+impl NatsSink {
+    /// Connects to `config.url` and starts the thread publishing to it.
+    pub fn spawn(config: NatsConfig) -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let client = runtime
+            .block_on(
+                ConnectOptions::new()
+                    .name("hoho-recv")
+                    .connection_timeout(CONNECT_TIMEOUT)
+                    .connect(config.url.as_str()),
+            )
+            .map_err(io::Error::other)?;
+        let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+        let publisher = thread::Builder::new()
+            .name("natsPublisher".to_string())
+            .spawn(move || runtime.block_on(publish_all(client, config.subject, receiver)))?;
+        Ok(Self {
+            sender: Some(sender),
+            publisher: Some(publisher),
+            dropped: 0,
+        })
+    }
+
+    /// Queues `message` for publishing, dropping it if the queue is full.
+    fn send(&mut self, message: Vec<u8>) -> io::Result<()> {
+        let Some(sender) = &self.sender else {
+            return Ok(());
+        };
+        match sender.try_send(message) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                if self.dropped.is_power_of_two() {
+                    eprintln!("NATS queue full, {} swaps dropped so far", self.dropped);
+                }
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => Err(io::Error::other("NATS publisher exited")),
+        }
+    }
+}
+
+impl SwapSink for NatsSink {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, _packet: &[u8]) -> io::Result<()> {
+        self.send(serde_json::to_vec(&swap_json(received_at_us, swap))?)
+    }
+
+    fn write_report(&mut self, report: &MintActivityReport) -> io::Result<()> {
+        self.send(serde_json::to_vec(&mint_activity_json(report))?)
+    }
+
+    /// Publishes the swaps still queued and waits for the server to have them.
+    fn finish(&mut self) -> io::Result<()> {
+        self.sender = None;
+        if let Some(publisher) = self.publisher.take() {
+            publisher.join().map_err(|_| io::Error::other("NATS publisher panicked"))?;
+        }
+        if self.dropped > 0 {
+            println!("NATS dropped {} swaps", self.dropped);
+        }
+        Ok(())
+    }
+}
+
+async fn publish_all(client: Client, subject: String, mut messages: mpsc::Receiver<Vec<u8>>) {
+    while let Some(message) = messages.recv().await {
+        if let Err(e) = client.publish(subject.clone(), message.into()).await {
+            eprintln!("Error publishing swap to NATS: {}", e);
+        }
+    }
+    if let Err(e) = client.flush().await {
+        eprintln!("Error flushing NATS output: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_nats_sink() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("nats://{}", listener.local_addr().unwrap());
+        // Speaks just enough of the protocol to take one message, returning its subject and payload
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            reader
+                .get_mut()
+                .write_all(b"INFO {\"server_id\":\"test\",\"version\":\"2.10.0\",\"max_payload\":1048576}\r\n")
+                .unwrap();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    panic!("connection closed before a message was published");
+                }
+                if line.starts_with("PING") {
+                    reader.get_mut().write_all(b"PONG\r\n").unwrap();
+                } else if let Some(publish) = line.strip_prefix("PUB ") {
+                    let (subject, len) = publish.trim_end().split_once(' ').unwrap();
+                    let mut payload = vec![0; len.parse::<usize>().unwrap() + 2];
+                    reader.read_exact(&mut payload).unwrap();
+                    payload.truncate(payload.len() - 2);
+                    return (subject.to_string(), payload);
+                }
+            }
+        });
+        let mut sink = NatsSink::spawn(NatsConfig {
+            url,
+            subject: "swaps.raydium".to_string(),
+            queue_size: 1,
+        })
+        .unwrap();
+        sink.send(b"{\"amount_in\":1}".to_vec()).unwrap();
+        sink.finish().unwrap();
+        assert_eq!(server.join().unwrap(), ("swaps.raydium".to_string(), b"{\"amount_in\":1}".to_vec()));
+    }
+}
@@ -0,0 +1,86 @@
+//! Pinning the receiver to the CPUs of one NUMA node (`--numa-node`).
+//!
+//! On a multi-socket host the NIC is attached to one socket. Datagrams are written
+//! into memory on that node, so receiving and analyzing them from the other socket
+//! pays a cross-socket access on every packet, roughly one and a half to two times
+//! the latency of a local one. Running on the NIC's node avoids that; the gain
+//! shows in tail latency at high packet rates and is nil on a single-socket host.
+//!
+//! The main thread is pinned before any other thread starts, and threads inherit
+//! their creator's CPU set, so the receiver, the analyzer and the output threads all
+//! run on the node. Linux only; elsewhere the option is rejected.
+use std::fs;
+use std::io;
+
+/// Resolves `spec`, a node number or the name of the network interface whose node
+/// to use, e.g. `eth0`.
+pub fn resolve_node(spec: &str) -> Result<usize, String> {
+    if let Ok(node) = spec.parse() {
+        return Ok(node);
+    }
+    let path = format!("/sys/class/net/{}/device/numa_node", spec);
+    let node = fs::read_to_string(&path).map_err(|e| format!("unable to read {}: {}", path, e))?;
+    // -1 when the platform doesn't report a node for the device
+    node.trim()
+        .parse()
+        .map_err(|_| format!("{} has no NUMA node ({})", spec, node.trim()))
+}
+
+/// Returns the CPUs of `node`.
+pub fn node_cpus(node: usize) -> Result<Vec<usize>, String> {
+    let path = format!("/sys/devices/system/node/node{}/cpulist", node);
+    let list = fs::read_to_string(&path).map_err(|e| format!("unable to read {}: {}", path, e))?;
+    parse_cpu_list(&list)
+}
+
+/// Parses a kernel CPU list such as `0-7,16-23`.
+pub fn parse_cpu_list(list: &str) -> Result<Vec<usize>, String> {
+    let mut cpus = vec![];
+    for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+        let parse = |cpu: &str| cpu.parse::<usize>().map_err(|_| format!("invalid CPU list {}", list.trim()));
+        match range.split_once('-') {
+            Some((first, last)) => cpus.extend(parse(first)?..=parse(last)?),
+            None => cpus.push(parse(range)?),
+        }
+    }
+    if cpus.is_empty() {
+        return Err("empty CPU list".to_string());
+    }
+    Ok(cpus)
+}
+
+/// Restricts the calling thread, and the threads it starts afterwards, to `cpus`.
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread(cpus: &[usize]) -> io::Result<()> {
+    // SAFETY: cpu_set_t is plain data, zeroed is the empty set and CPU_SET checks
+    // the index against the set's size
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for &cpu in cpus {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_current_thread(_cpus: &[usize]) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "CPU pinning is only supported on Linux"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11\n"), Ok(vec![0, 1, 2, 3, 8, 10, 11]));
+        assert_eq!(parse_cpu_list("5"), Ok(vec![5]));
+        assert!(parse_cpu_list("\n").is_err());
+        assert!(parse_cpu_list("0-x").is_err());
+        assert_eq!(resolve_node("1"), Ok(1));
+    }
+}
@@ -0,0 +1,83 @@
+//! Counting the forwarders that observed each transaction (`--count-observers`).
+//!
+//! Across a monitored fleet, how many validators a transaction reaches measures how
+//! widely it propagated. The forward header doesn't identify its forwarder, so one
+//! is told apart by the address its datagrams come from. Arrivals are keyed by
+//! (signature, forwarder): a repeat from a forwarder that already sent the
+//! signature within the arrival window is a duplicate and dropped as `dedup`,
+//! while each new forwarder's copy is analyzed and carries the number of
+//! forwarders seen so far. The last copy output for a signature has the full
+//! count. Packets from feeds without an address, such as `--ws-url`, aren't
+//! counted.
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use solana_sdk::signature::Signature;
+
+// Bounds memory if the feed outpaces the window
+const MAX_TRACKED_SIGNATURES: usize = 1_000_000;
+// More than any monitored fleet; later forwarders count once between them
+const MAX_OBSERVERS: usize = 1_024;
+
+pub struct ObserverTracker {
+    window_us: u64,
+    observers: HashMap<Signature, Vec<SocketAddr>>,
+    // (first arrival, signature) in arrival order, for expiry
+    arrivals: VecDeque<(u64, Signature)>,
+}
+
+impl ObserverTracker {
+    pub fn new(window_ms: u64) -> Self {
+        Self {
+            window_us: window_ms.saturating_mul(1_000),
+            observers: HashMap::new(),
+            arrivals: VecDeque::new(),
+        }
+    }
+
+    /// Records an arrival of `signature` from `forwarder` at `now_us`, returning the
+    /// number of forwarders that have sent it within the window, or `None` if this
+    /// forwarder already had.
+    pub fn record(&mut self, signature: Signature, forwarder: SocketAddr, now_us: u64) -> Option<u32> {
+        while let Some((first_us, expired)) = self.arrivals.front() {
+            if now_us.saturating_sub(*first_us) <= self.window_us && self.arrivals.len() < MAX_TRACKED_SIGNATURES {
+                break;
+            }
+            self.observers.remove(expired);
+            self.arrivals.pop_front();
+        }
+        let observers = self.observers.entry(signature).or_insert_with(|| {
+            self.arrivals.push_back((now_us, signature));
+            Vec::new()
+        });
+        if observers.contains(&forwarder) {
+            return None;
+        }
+        if observers.len() < MAX_OBSERVERS {
+            observers.push(forwarder);
+        }
+        Some(observers.len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::SIGNATURE_BYTES;
+
+    #[test]
+    fn test_observer_tracker() {
+        let mut tracker = ObserverTracker::new(1);
+        let a = Signature::from([1; SIGNATURE_BYTES]);
+        let b = Signature::from([2; SIGNATURE_BYTES]);
+        let (one, two): (SocketAddr, SocketAddr) = ("10.0.0.1:8001".parse().unwrap(), "10.0.0.2:8001".parse().unwrap());
+        assert_eq!(tracker.record(a, one, 0), Some(1));
+        assert_eq!(tracker.record(a, one, 100), None);
+        assert_eq!(tracker.record(b, one, 500), Some(1));
+        assert_eq!(tracker.record(a, two, 1_000), Some(2));
+        // a's window started at 0, so it expires while b's is still open
+        assert_eq!(tracker.record(a, one, 1_200), Some(1));
+        assert_eq!(tracker.record(b, two, 1_200), Some(2));
+        assert_eq!(tracker.record(b, one, 1_400), None);
+        assert_eq!(tracker.observers.len(), 2);
+    }
+}
@@ -0,0 +1,206 @@
+//! OpenTelemetry traces of each packet's way through the pipeline (`--otlp-endpoint`,
+//! feature `otel`).
+//!
+//! Every packet that reaches the analyzer becomes a trace exported over OTLP/gRPC to
+//! a collector. The root `packet` span runs from when the forwarder stamped the packet
+//! to the end of analysis. It has two children:
+//! - `forward` covers forwarding, the network and the receive queue, up to when the
+//!   consumer picked the packet up.
+//! - `analyze` covers the analysis itself.
+//!
+//! The forwarder exports nothing itself. Its part is taken from the header timestamp,
+//! so there is no `forward` span for a `monotonic` header clock.
+//!
+//! The trace id is the first 16 bytes of the transaction's signature, and the packet
+//! span id the header timestamp, so a packet's trace is the same on every receiver
+//! and can be looked up by signature. `--otlp-sample-ratio` samples on the trace id,
+//! so receivers sharing a ratio keep the same transactions. Spans are exported in
+//! batches from their own thread; when the collector falls behind, spans are dropped
+//! rather than stalling analysis.
+use std::io;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use opentelemetry::sdk::trace::{self as sdktrace, Sampler, TracerProvider};
+use opentelemetry::sdk::Resource;
+use opentelemetry::trace::{Span, SpanBuilder, SpanId, TraceContextExt, TraceId, Tracer, TracerProvider as _};
+use opentelemetry::{runtime, Context, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use solana_sdk::signature::Signature;
+use tokio::sync::oneshot;
+use crate::forward_header::{ForwardClock, ForwardHeader};
+
+#[derive(Clone, Debug)]
+pub struct OtelConfig {
+    /// OTLP/gRPC collector, e.g. `http://127.0.0.1:4317`
+    pub endpoint: String,
+    /// Fraction of packets traced, in `[0, 1]`
+    pub sample_ratio: f64,
+}
+
+/// What became of a traced packet, the `outcome` attribute of its spans.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketOutcome {
+    Swap,
+    NoSwap,
+    Dropped(&'static str),
+}
+
+impl PacketOutcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            PacketOutcome::Swap => "swap",
+            PacketOutcome::NoSwap => "no_swap",
+            PacketOutcome::Dropped(reason) => reason,
+        }
+    }
+}
+
+/// The trace of a packet, named after its transaction's signature.
+pub fn trace_id(signature: &Signature) -> TraceId {
+    let mut id = [0u8; 16];
+    id.copy_from_slice(&signature.as_ref()[..16]);
+    TraceId::from_bytes(id)
+}
+
+/// The packet span, named after the forward header timestamp.
+pub fn packet_span_id(header: &ForwardHeader) -> SpanId {
+    SpanId::from_bytes(header.timestamp_us.to_be_bytes())
+}
+
+/// When the forwarder stamped the packet, if its clock is comparable to ours.
+fn forwarded_at(header: &ForwardHeader) -> Option<SystemTime> {
+    (header.clock == ForwardClock::Wall).then(|| UNIX_EPOCH + Duration::from_micros(header.timestamp_us))
+}
+
+pub struct PacketTracer {
+    provider: TracerProvider,
+    tracer: sdktrace::Tracer,
+    stop: Option<oneshot::Sender<()>>,
+    exporter: Option<JoinHandle<()>>,
+}
+
+impl PacketTracer {
+    /// Starts the thread exporting to `config.endpoint`. The collector is connected
+    /// to lazily, so one that is down only costs the spans sent meanwhile.
+    pub fn spawn(config: OtelConfig) -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        // The channel and the batch processor spawn their tasks onto the runtime
+        let provider = {
+            let _guard = runtime.enter();
+            let exporter = opentelemetry_otlp::SpanExporterBuilder::from(
+                opentelemetry_otlp::new_exporter().tonic().with_endpoint(config.endpoint),
+            )
+            .build_span_exporter()
+            .map_err(io::Error::other)?;
+            TracerProvider::builder()
+                .with_batch_exporter(exporter, runtime::Tokio)
+                .with_config(
+                    sdktrace::config()
+                        .with_sampler(Sampler::TraceIdRatioBased(config.sample_ratio))
+                        .with_resource(Resource::new([KeyValue::new("service.name", "hoho-recv")])),
+                )
+                .build()
+        };
+        let tracer = provider.tracer("hoho-recv");
+        let (stop, stopped) = oneshot::channel();
+        let exporter = thread::Builder::new()
+            .name("otelExporter".to_string())
+            .spawn(move || {
+                let _ = runtime.block_on(stopped);
+            })?;
+        Ok(Self {
+            provider,
+            tracer,
+            stop: Some(stop),
+            exporter: Some(exporter),
+        })
+    }
+
+    /// Records the trace of a packet picked up from the queue at `picked_up` and
+    /// analyzed from then until `analyzed`.
+    pub fn record(
+        &self,
+        signature: &Signature,
+        header: &ForwardHeader,
+        picked_up: SystemTime,
+        analyzed: SystemTime,
+        outcome: PacketOutcome,
+    ) {
+        let forwarded_at = forwarded_at(header);
+        let mut attributes = vec![
+            KeyValue::new("signature", signature.to_string()),
+            KeyValue::new("outcome", outcome.name()),
+        ];
+        if let Some(slot) = header.slot {
+            attributes.push(KeyValue::new("slot", slot as i64));
+        }
+        if let Some(leader) = header.leader {
+            attributes.push(KeyValue::new("leader", leader.to_string()));
+        }
+        let mut packet = self.tracer.build(
+            SpanBuilder::from_name("packet")
+                .with_trace_id(trace_id(signature))
+                .with_span_id(packet_span_id(header))
+                .with_start_time(forwarded_at.unwrap_or(picked_up))
+                .with_attributes(attributes),
+        );
+        let parent = Context::new().with_remote_span_context(packet.span_context().clone());
+        if let Some(forwarded_at) = forwarded_at {
+            self.tracer
+                .build_with_context(SpanBuilder::from_name("forward").with_start_time(forwarded_at), &parent)
+                .end_with_timestamp(picked_up);
+        }
+        self.tracer
+            .build_with_context(
+                SpanBuilder::from_name("analyze")
+                    .with_start_time(picked_up)
+                    .with_attributes([KeyValue::new("outcome", outcome.name())]),
+                &parent,
+            )
+            .end_with_timestamp(analyzed);
+        packet.end_with_timestamp(analyzed);
+    }
+
+    /// Exports the spans still batched and stops the exporter thread.
+    pub fn finish(mut self) {
+        for result in self.provider.force_flush() {
+            if let Err(e) = result {
+                eprintln!("Error exporting traces: {}", e);
+            }
+        }
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(exporter) = self.exporter.take() {
+            let _ = exporter.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forward_header::ForwardMode;
+
+    #[test]
+    fn test_trace_ids() {
+        let signature = Signature::from([7u8; 64]);
+        assert_eq!(trace_id(&signature), TraceId::from_bytes([7u8; 16]));
+
+        let mut header = ForwardHeader {
+            clock: ForwardClock::Wall,
+            mode: ForwardMode::Full,
+            timestamp_us: 1_700_000_000_000_001,
+            slot: Some(42),
+            leader: None,
+        };
+        assert_eq!(packet_span_id(&header), SpanId::from_bytes(1_700_000_000_000_001u64.to_be_bytes()));
+        assert_eq!(
+            forwarded_at(&header).unwrap().duration_since(UNIX_EPOCH).unwrap(),
+            Duration::from_micros(1_700_000_000_000_001)
+        );
+        header.clock = ForwardClock::Monotonic;
+        assert_eq!(forwarded_at(&header), None);
+        assert_eq!(PacketOutcome::Dropped("quote_mint").name(), "quote_mint");
+    }
+}
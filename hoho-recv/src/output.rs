@@ -0,0 +1,85 @@
+//! Fan-out of matched swaps to the configured outputs, shared by the live
+//! receiver and backfill.
+use std::io;
+use crate::alert::RateOfChangeMonitor;
+use crate::analyzer::AnalyzedSwap;
+use crate::mint_activity::{MintActivity, MintActivityReport};
+use crate::sandwich::SandwichDetector;
+use crate::wash_trade::WashTradeDetector;
+
+/// A destination for matched swaps.
+pub trait SwapSink {
+    /// Short name used in error messages
+    fn name(&self) -> &'static str;
+
+    /// Records `swap`, decoded from the serialized transaction `packet`.
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, packet: &[u8]) -> io::Result<()>;
+
+    /// Records a `--top-mints` report. Outputs with no place for one ignore it.
+    fn write_report(&mut self, _report: &MintActivityReport) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Writes out anything buffered; called before the process exits.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Every output a matched swap goes to besides the console.
+#[derive(Default)]
+pub struct SwapOutputs {
+    sinks: Vec<Box<dyn SwapSink + Send>>,
+    pub watch: Option<RateOfChangeMonitor>,
+    pub wash_trades: Option<WashTradeDetector>,
+    pub sandwiches: Option<SandwichDetector>,
+    pub mint_activity: Option<MintActivity>,
+}
+
+impl SwapOutputs {
+    pub fn add_sink(&mut self, sink: Box<dyn SwapSink + Send>) {
+        self.sinks.push(sink);
+    }
+
+    /// Sends `swap` to every sink, dropping sinks that fail.
+    pub fn record(&mut self, received_at_us: u64, swap: &AnalyzedSwap, packet: &[u8]) {
+        if let Some(monitor) = self.watch.as_mut() {
+            for alert in monitor.observe(swap, received_at_us) {
+                println!("{}", alert);
+            }
+        }
+        if let Some(detector) = self.wash_trades.as_mut() {
+            for suspected in detector.observe(swap, received_at_us) {
+                println!("{}", suspected);
+            }
+        }
+        if let Some(suspected) = self.sandwiches.as_mut().and_then(|detector| detector.observe(swap, received_at_us)) {
+            println!("{}", suspected);
+        }
+        if let Some(report) = self.mint_activity.as_mut().and_then(|activity| activity.observe(swap, received_at_us)) {
+            println!("{}", report);
+            self.sinks.retain_mut(|sink| match sink.write_report(&report) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("Error writing {} output, disabled: {}", sink.name(), e);
+                    false
+                }
+            });
+        }
+        self.sinks.retain_mut(|sink| match sink.write_swap(received_at_us, swap, packet) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Error writing {} output, disabled: {}", sink.name(), e);
+                false
+            }
+        });
+    }
+
+    pub fn finish(&mut self) {
+        for sink in &mut self.sinks {
+            if let Err(e) = sink.finish() {
+                eprintln!("Error finishing {} output: {}", sink.name(), e);
+            }
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Columnar export of matched swaps for DuckDB/pandas (`--parquet`).
+//!
+//! Rows are buffered in memory and written out as a complete Parquet file whenever
+//! `rows_per_file` rows have accumulated, and once more on shutdown. Each flush writes
+//! a new file next to the configured path, `swaps.parquet` producing
+//! `swaps-00000.parquet`, `swaps-00001.parquet`, ... so a glob reads the whole capture.
+//!
+//! The column set is [`swap_schema`]. Columns are only ever appended to it, so readers
+//! written against an older schema keep working.
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch, StringArray, UInt32Array, UInt64Array, UInt8Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use solana_sdk::pubkey::Pubkey;
+use crate::analyzer::AnalyzedSwap;
+use crate::output::SwapSink;
+
+/// Schema of the exported swap rows. Pubkeys and signatures are base58 strings.
+pub fn swap_schema() -> Schema {
+    let pubkey = |name| Field::new(name, DataType::Utf8, true);
+    Schema::new(vec![
+        Field::new("received_at_us", DataType::UInt64, false),
+        Field::new("signature", DataType::Utf8, false),
+        Field::new("dex", DataType::Utf8, false),
+        Field::new("curve", DataType::Utf8, false),
+        Field::new("discriminator", DataType::UInt8, false),
+        Field::new("amount_in", DataType::UInt64, false),
+        Field::new("min_amount_out", DataType::UInt64, false),
+        pubkey("pool_coin_account"),
+        pubkey("pool_pc_account"),
+        pubkey("model_data_account"),
+        pubkey("user_source_account"),
+        pubkey("user_destination_account"),
+        pubkey("user_owner"),
+        pubkey("source_mint"),
+        pubkey("destination_mint"),
+        Field::new("compute_unit_price", DataType::UInt64, false),
+        Field::new("compute_unit_limit", DataType::UInt32, false),
+        Field::new("priority_fee_lamports", DataType::UInt64, false),
+        Field::new("source_decimals", DataType::UInt8, true),
+        Field::new("destination_decimals", DataType::UInt8, true),
+        // Comma-separated lookup table accounts, null for transactions without any
+        Field::new("lookup_tables", DataType::Utf8, true),
+        Field::new("compute_unit_limit_requested", DataType::Boolean, false),
+        Field::new("transaction_size", DataType::UInt32, false),
+        Field::new("front_run_protected", DataType::Boolean, false),
+        Field::new("zero_min_out", DataType::Boolean, false),
+        Field::new("pool_fee_bps", DataType::UInt32, true),
+        Field::new("expected_amount_out", DataType::UInt64, true),
+        Field::new("realized_amount_out", DataType::UInt64, true),
+        Field::new("payer_sol_balance", DataType::UInt64, true),
+        Field::new("realized_amount_in", DataType::UInt64, true),
+        // Comma-separated signatures after the first, null for single-signer transactions
+        Field::new("co_signatures", DataType::Utf8, true),
+        Field::new("compute_units_consumed", DataType::UInt64, true),
+        Field::new("observers", DataType::UInt32, true),
+        Field::new("price_impact_bps", DataType::UInt32, true),
+        // "legacy" or the version number, as in the JSON output
+        Field::new("version", DataType::Utf8, false),
+    ])
+}
+
+/// Buffers swaps and writes them to rotating Parquet files.
+pub struct ParquetSwapWriter {
+    path: PathBuf,
+    rows_per_file: usize,
+    rows: Vec<(u64, AnalyzedSwap)>,
+    files_written: usize,
+}
+
+impl ParquetSwapWriter {
+    pub fn new<P: AsRef<Path>>(path: P, rows_per_file: usize) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            rows_per_file: rows_per_file.max(1),
+            rows: Vec::new(),
+            files_written: 0,
+        }
+    }
+
+    /// Buffers `swap`, writing a file once `rows_per_file` rows are pending.
+    pub fn push(&mut self, received_at_us: u64, swap: &AnalyzedSwap) -> io::Result<()> {
+        self.rows.push((received_at_us, swap.clone()));
+        if self.rows.len() >= self.rows_per_file {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending rows to a new file, returning its path, or `None` if there were none.
+    pub fn flush(&mut self) -> io::Result<Option<PathBuf>> {
+        if self.rows.is_empty() {
+            return Ok(None);
+        }
+        let path = rotated_path(&self.path, self.files_written);
+        let batch = record_batch(&self.rows).map_err(io::Error::other)?;
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path)?, batch.schema(), None).map_err(io::Error::other)?;
+        writer.write(&batch).map_err(io::Error::other)?;
+        writer.close().map_err(io::Error::other)?;
+        self.rows.clear();
+        self.files_written += 1;
+        Ok(Some(path))
+    }
+}
+
+impl SwapSink for ParquetSwapWriter {
+    fn name(&self) -> &'static str {
+        "parquet"
+    }
+
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, _packet: &[u8]) -> io::Result<()> {
+        self.push(received_at_us, swap)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if let Some(path) = self.flush()? {
+            println!("Wrote parquet file {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("swaps");
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("parquet");
+    path.with_file_name(format!("{}-{:05}.{}", stem, index, extension))
+}
+
+fn record_batch(rows: &[(u64, AnalyzedSwap)]) -> Result<RecordBatch, arrow_schema::ArrowError> {
+    let strings = |f: &dyn Fn(&AnalyzedSwap) -> String| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|(_, swap)| f(swap))))
+    };
+    let pubkeys = |f: &dyn Fn(&AnalyzedSwap) -> Option<Pubkey>| -> ArrayRef {
+        Arc::new(StringArray::from_iter(
+            rows.iter().map(|(_, swap)| f(swap).map(|key| key.to_string())),
+        ))
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(rows.iter().map(|(at, _)| *at))),
+        strings(&|swap| swap.signature.to_string()),
+        strings(&|swap| swap.dex.name().to_string()),
+        strings(&|swap| swap.curve().name().to_string()),
+        Arc::new(UInt8Array::from_iter_values(rows.iter().map(|(_, swap)| swap.discriminator))),
+        Arc::new(UInt64Array::from_iter_values(rows.iter().map(|(_, swap)| swap.amount_in))),
+        Arc::new(UInt64Array::from_iter_values(rows.iter().map(|(_, swap)| swap.min_amount_out))),
+        pubkeys(&|swap| swap.pool_coin_account),
+        pubkeys(&|swap| swap.pool_pc_account),
+        pubkeys(&|swap| swap.model_data_account),
+        pubkeys(&|swap| swap.user_source_account),
+        pubkeys(&|swap| swap.user_destination_account),
+        pubkeys(&|swap| swap.user_owner),
+        pubkeys(&|swap| swap.source_mint),
+        pubkeys(&|swap| swap.destination_mint),
+        Arc::new(UInt64Array::from_iter_values(rows.iter().map(|(_, swap)| swap.priority_fee.compute_unit_price))),
+        Arc::new(UInt32Array::from_iter_values(rows.iter().map(|(_, swap)| swap.priority_fee.compute_unit_limit))),
+        Arc::new(UInt64Array::from_iter_values(rows.iter().map(|(_, swap)| swap.priority_fee.total_lamports()))),
+        Arc::new(UInt8Array::from_iter(rows.iter().map(|(_, swap)| swap.source_decimals))),
+        Arc::new(UInt8Array::from_iter(rows.iter().map(|(_, swap)| swap.destination_decimals))),
+        Arc::new(StringArray::from_iter(rows.iter().map(|(_, swap)| {
+            (!swap.lookup_tables.is_empty()).then(|| {
+                let keys: Vec<String> = swap.lookup_tables.iter().map(|lookup| lookup.account_key.to_string()).collect();
+                keys.join(",")
+            })
+        }))),
+        Arc::new(BooleanArray::from_iter(rows.iter().map(|(_, swap)| Some(swap.priority_fee.compute_unit_limit_requested)))),
+        Arc::new(UInt32Array::from_iter_values(rows.iter().map(|(_, swap)| swap.transaction_size as u32))),
+        Arc::new(BooleanArray::from_iter(rows.iter().map(|(_, swap)| Some(swap.front_run_protected)))),
+        Arc::new(BooleanArray::from_iter(rows.iter().map(|(_, swap)| Some(swap.zero_min_out)))),
+        Arc::new(UInt32Array::from_iter(rows.iter().map(|(_, swap)| swap.pool_fee_bps))),
+        Arc::new(UInt64Array::from_iter(rows.iter().map(|(_, swap)| swap.expected_amount_out))),
+        Arc::new(UInt64Array::from_iter(rows.iter().map(|(_, swap)| swap.realized_amount_out))),
+        Arc::new(UInt64Array::from_iter(rows.iter().map(|(_, swap)| swap.payer_sol_balance))),
+        Arc::new(UInt64Array::from_iter(rows.iter().map(|(_, swap)| swap.realized_amount_in))),
+        Arc::new(StringArray::from_iter(rows.iter().map(|(_, swap)| {
+            (!swap.co_signatures.is_empty()).then(|| {
+                let signatures: Vec<String> = swap.co_signatures.iter().map(|signature| signature.to_string()).collect();
+                signatures.join(",")
+            })
+        }))),
+        Arc::new(UInt64Array::from_iter(rows.iter().map(|(_, swap)| swap.compute_units_consumed))),
+        Arc::new(UInt32Array::from_iter(rows.iter().map(|(_, swap)| swap.observers))),
+        Arc::new(UInt32Array::from_iter(rows.iter().map(|(_, swap)| swap.price_impact_bps))),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|(_, swap)| swap.version_name()))),
+    ];
+    RecordBatch::try_new(Arc::new(swap_schema()), columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use solana_sdk::signature::Signature;
+    use solana_sdk::transaction::TransactionVersion;
+    use crate::analyzer::Dex;
+    use crate::compute_budget::PriorityFee;
+
+    fn swap(amount_in: u64) -> AnalyzedSwap {
+        AnalyzedSwap {
+            signature: Signature::default(),
+            co_signatures: vec![],
+            dex: Dex::RaydiumStable,
+            discriminator: 9,
+            amount_in,
+            min_amount_out: 1,
+            pool_coin_account: Some(Pubkey::new_unique()),
+            pool_pc_account: None,
+            model_data_account: None,
+            user_source_account: None,
+            user_destination_account: None,
+            user_owner: None,
+            source_mint: None,
+            destination_mint: None,
+            source_decimals: None,
+            destination_decimals: None,
+            transfers: vec![],
+            priority_fee: PriorityFee::default(),
+            compute_units_consumed: None,
+            lookup_tables: vec![],
+            transaction_size: 0,
+            version: TransactionVersion::LEGACY,
+            front_run_protected: false,
+            zero_min_out: false,
+            pool_fee_bps: None,
+            expected_amount_out: None,
+            realized_amount_out: None,
+            realized_amount_in: None,
+            mint_events: vec![],
+            burn_events: vec![],
+            authority_changes: vec![],
+            payer_sol_balance: None,
+            observers: None,
+            price_impact_bps: None,
+            pool_reserves: None,
+        }
+    }
+
+    #[test]
+    fn test_parquet_rotation_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = ParquetSwapWriter::new(dir.path().join("swaps.parquet"), 2);
+        for amount_in in 0..3 {
+            writer.push(amount_in, &swap(amount_in)).unwrap();
+        }
+        assert_eq!(writer.files_written, 1);
+        assert_eq!(writer.flush().unwrap(), Some(dir.path().join("swaps-00001.parquet")));
+        assert_eq!(writer.flush().unwrap(), None);
+
+        let file = File::open(dir.path().join("swaps-00000.parquet")).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        assert_eq!(reader.schema().as_ref(), &swap_schema());
+        let batches: Vec<RecordBatch> = reader.build().unwrap().map(Result::unwrap).collect();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 2);
+        let curve = batches[0].column_by_name("curve").unwrap();
+        let curve = curve.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(curve.value(0), "stable");
+        let pool_pc = batches[0].column_by_name("pool_pc_account").unwrap();
+        assert!(pool_pc.is_null(0));
+    }
+}
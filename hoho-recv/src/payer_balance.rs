@@ -0,0 +1,93 @@
+//! SOL balance of a matched swap's fee payer (`--resolve-payer-balance`).
+//!
+//! A swap from a wallet holding thousands of SOL reads differently from one made by
+//! a freshly funded bot, so the fee payer's balance is looked up with `getBalance`.
+//! Balances change with every trade, so each is cached only for the configured TTL:
+//! long enough to spare the RPC node a burst of swaps from one wallet, short enough
+//! to stay roughly current.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+pub struct PayerBalanceResolver {
+    rpc_client: Arc<RpcClient>,
+    ttl: Duration,
+    balances: HashMap<Pubkey, (Instant, u64)>,
+    last_prune: Instant,
+}
+
+impl PayerBalanceResolver {
+    pub fn new(rpc_client: Arc<RpcClient>, ttl: Duration) -> Self {
+        Self::with_capacity(rpc_client, ttl, 0)
+    }
+
+    /// Sizes the cache for `capacity` payers up front.
+    pub fn with_capacity(rpc_client: Arc<RpcClient>, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            rpc_client,
+            ttl,
+            balances: HashMap::with_capacity(capacity),
+            last_prune: Instant::now(),
+        }
+    }
+
+    /// Returns the lamports held by `payer`, or `None` if the RPC call failed.
+    pub fn balance(&mut self, payer: &Pubkey) -> Option<u64> {
+        let now = Instant::now();
+        if let Some(lamports) = self.cached(payer, now) {
+            return Some(lamports);
+        }
+        match self
+            .rpc_client
+            .get_balance_with_commitment(payer, self.rpc_client.commitment())
+        {
+            Ok(response) => {
+                self.insert(*payer, response.value, now);
+                Some(response.value)
+            }
+            Err(e) => {
+                eprintln!("Error fetching balance of {}: {}", payer, e);
+                None
+            }
+        }
+    }
+
+    fn cached(&self, payer: &Pubkey, now: Instant) -> Option<u64> {
+        let (fetched_at, lamports) = self.balances.get(payer)?;
+        (now.saturating_duration_since(*fetched_at) < self.ttl).then_some(*lamports)
+    }
+
+    // Expired balances are dropped once per TTL, so one-off wallets don't pile up
+    fn insert(&mut self, payer: Pubkey, lamports: u64, now: Instant) {
+        if now.saturating_duration_since(self.last_prune) >= self.ttl {
+            let ttl = self.ttl;
+            self.balances
+                .retain(|_, (fetched_at, _)| now.saturating_duration_since(*fetched_at) < ttl);
+            self.last_prune = now;
+        }
+        self.balances.insert(payer, (now, lamports));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance_ttl() {
+        let rpc_client = Arc::new(RpcClient::new("http://127.0.0.1:1".to_string()));
+        let mut resolver = PayerBalanceResolver::new(rpc_client, Duration::from_secs(10));
+        let (payer, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let start = Instant::now();
+        resolver.insert(payer, 5_000_000_000, start);
+        assert_eq!(resolver.cached(&payer, start + Duration::from_secs(9)), Some(5_000_000_000));
+        assert_eq!(resolver.cached(&payer, start + Duration::from_secs(10)), None);
+        assert_eq!(resolver.cached(&other, start), None);
+
+        // Inserting after a TTL has passed drops the expired balance
+        resolver.insert(other, 1, start + Duration::from_secs(11));
+        assert_eq!(resolver.balances.len(), 1);
+    }
+}
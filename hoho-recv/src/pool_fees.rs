@@ -0,0 +1,132 @@
+//! Raydium V4 pool fee rates and the output they leave a swap (`--resolve-pool-fees`).
+//!
+//! Each V4 pool stores its swap fee as a numerator and denominator in its AMM
+//! account, 25 / 10000 unless the pool was configured otherwise. The resolver
+//! fetches it once per pool. With the vault balances, fetched fresh for every swap,
+//! it gives the output the swap can expect net of the fee, which for a packet seen
+//! before it lands is close to what it will get. The vault balances leave out the
+//! funds the pool keeps on its OpenBook orders, so the figure is an estimate.
+use std::collections::HashMap;
+use std::sync::Arc;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+// AmmInfo: 16 u64 fields (status .. sys_decimal_value), then the fees as u64
+// pairs: min_separate, trade_fee, pnl, swap_fee
+const SWAP_FEE_NUMERATOR_OFFSET: usize = 16 * 8 + 6 * 8;
+
+// SPL token account layout: mint (32) | owner (32) | amount (8) | ...
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+/// A pool's swap fee, charged on the amount in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolFee {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl PoolFee {
+    /// The fee in basis points, rounded down.
+    pub fn bps(&self) -> u32 {
+        (self.numerator as u128 * 10_000 / self.denominator as u128).min(u32::MAX as u128) as u32
+    }
+
+    /// The output of swapping `amount_in` against constant product reserves, with
+    /// the fee rounded up against the swapper as the pool does.
+    pub fn amount_out(&self, amount_in: u64, reserve_in: u64, reserve_out: u64) -> u64 {
+        let fee = (amount_in as u128 * self.numerator as u128).div_ceil(self.denominator as u128);
+        let amount_in = (amount_in as u128).saturating_sub(fee);
+        let reserve_in = reserve_in as u128 + amount_in;
+        if reserve_in == 0 {
+            return 0;
+        }
+        (reserve_out as u128 * amount_in / reserve_in) as u64
+    }
+}
+
+/// Decodes the swap fee of a Raydium V4 AMM account, or returns `None` if the data is
+/// too short or the denominator is zero.
+pub fn decode_raydium_v4_fee(data: &[u8]) -> Option<PoolFee> {
+    let read_u64 = |offset: usize| Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().unwrap()));
+    let fee = PoolFee {
+        numerator: read_u64(SWAP_FEE_NUMERATOR_OFFSET)?,
+        denominator: read_u64(SWAP_FEE_NUMERATOR_OFFSET + 8)?,
+    };
+    (fee.denominator != 0).then_some(fee)
+}
+
+/// Resolves AMM accounts to their swap fee, caching every lookup (including misses),
+/// and reads pool vault balances, which are never cached.
+pub struct PoolFeeResolver {
+    rpc_client: Arc<RpcClient>,
+    fees: HashMap<Pubkey, Option<PoolFee>>,
+}
+
+impl PoolFeeResolver {
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        Self::with_capacity(rpc_client, 0)
+    }
+
+    /// Sizes the cache for `capacity` pools up front.
+    pub fn with_capacity(rpc_client: Arc<RpcClient>, capacity: usize) -> Self {
+        Self {
+            rpc_client,
+            fees: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the swap fee of the Raydium V4 pool whose AMM account is `amm`.
+    pub fn pool_fee(&mut self, amm: &Pubkey) -> Option<PoolFee> {
+        if let Some(fee) = self.fees.get(amm) {
+            return *fee;
+        }
+        let fee = self.fetch_account_data(amm)?.and_then(|data| decode_raydium_v4_fee(&data));
+        self.fees.insert(*amm, fee);
+        fee
+    }
+
+    /// Returns the mint and current balance of the token account `vault`.
+    pub fn vault_balance(&self, vault: &Pubkey) -> Option<(Pubkey, u64)> {
+        let data = self.fetch_account_data(vault)??;
+        let mint = Pubkey::try_from(data.get(TOKEN_ACCOUNT_MINT_OFFSET..TOKEN_ACCOUNT_MINT_OFFSET + 32)?).ok()?;
+        let amount = data.get(TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8)?;
+        Some((mint, u64::from_le_bytes(amount.try_into().unwrap())))
+    }
+
+    // Returns `None` on RPC errors (not cached, retried next time) and
+    // `Some(None)` when the account does not exist (cached as a miss).
+    fn fetch_account_data(&self, pubkey: &Pubkey) -> Option<Option<Vec<u8>>> {
+        match self
+            .rpc_client
+            .get_account_with_commitment(pubkey, self.rpc_client.commitment())
+        {
+            Ok(response) => Some(response.value.map(|account| account.data)),
+            Err(e) => {
+                eprintln!("Error fetching account {}: {}", pubkey, e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_raydium_v4_fee() {
+        let mut data = vec![0u8; 752];
+        data[SWAP_FEE_NUMERATOR_OFFSET..][..8].copy_from_slice(&25u64.to_le_bytes());
+        data[SWAP_FEE_NUMERATOR_OFFSET + 8..][..8].copy_from_slice(&10_000u64.to_le_bytes());
+        let fee = decode_raydium_v4_fee(&data).unwrap();
+        assert_eq!(fee, PoolFee { numerator: 25, denominator: 10_000 });
+        assert_eq!(fee.bps(), 25);
+        assert_eq!(decode_raydium_v4_fee(&data[..SWAP_FEE_NUMERATOR_OFFSET + 8]), None);
+        assert_eq!(decode_raydium_v4_fee(&[0; 752]), None);
+
+        // 1000 in pays a fee of 2.5, rounded up to 3
+        assert_eq!(fee.amount_out(1_000, 1_000_000, 2_000_000), 2_000_000 * 997 / 1_000_997);
+        assert_eq!(fee.amount_out(1_000, 0, 0), 0);
+    }
+}
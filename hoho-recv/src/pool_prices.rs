@@ -0,0 +1,340 @@
+//! Latest price of each pool swapped in, served over HTTP (`--price-http`).
+//!
+//! A Raydium V4 swap whose `ray_log` is known tells the pool's reserves once it
+//! went through, and their ratio is the pool's price at that point. The latest
+//! price of each pool, keyed by its coin vault, is kept for the `--price-pools`
+//! most recently updated pools, and served as JSON:
+//!
+//! * `GET /prices`: every tracked pool, the most recently updated first
+//! * `GET /prices/<pool>`: one pool, or 404 if it isn't tracked
+//!
+//! A price is pc per coin, in whole tokens when both mints' decimals are known
+//! and in raw units otherwise, as `decimals_adjusted` tells. Logs only come with
+//! confirmed transactions (`--backfill`, `--resolve-cpi`), so swaps of live
+//! packets never update a price.
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use crate::analyzer::AnalyzedSwap;
+use crate::output::SwapSink;
+use crate::ray_log::PoolReserves;
+
+// A client this slow to send its request is dropped rather than block the others
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Header lines read at most, bounding what a client can make the server read
+const MAX_HEADER_LINES: usize = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolPrice {
+    /// The pool's coin vault, which identifies it
+    pub pool: Pubkey,
+    pub coin_mint: Option<Pubkey>,
+    pub pc_mint: Option<Pubkey>,
+    pub reserves: PoolReserves,
+    /// pc per coin
+    pub price: f64,
+    /// Whether `price` is in whole tokens rather than raw units
+    pub decimals_adjusted: bool,
+    pub updated_at_us: u64,
+    /// The swap that last updated the price
+    pub signature: Signature,
+}
+
+impl PoolPrice {
+    /// The pool's price after `swap`, `None` for swaps without the reserves.
+    pub fn from_swap(received_at_us: u64, swap: &AnalyzedSwap) -> Option<Self> {
+        let pool = swap.pool_coin_account?;
+        let reserves = swap.pool_reserves?;
+        let raw_price = reserves.price()?;
+        let (coin_mint, pc_mint, coin_decimals, pc_decimals) = if reserves.coin_to_pc {
+            (swap.source_mint, swap.destination_mint, swap.source_decimals, swap.destination_decimals)
+        } else {
+            (swap.destination_mint, swap.source_mint, swap.destination_decimals, swap.source_decimals)
+        };
+        let (price, decimals_adjusted) = match (coin_decimals, pc_decimals) {
+            (Some(coin_decimals), Some(pc_decimals)) => {
+                (raw_price * 10f64.powi(i32::from(coin_decimals) - i32::from(pc_decimals)), true)
+            }
+            _ => (raw_price, false),
+        };
+        Some(Self {
+            pool,
+            coin_mint,
+            pc_mint,
+            reserves,
+            price,
+            decimals_adjusted,
+            updated_at_us: received_at_us,
+            signature: swap.signature,
+        })
+    }
+}
+
+pub fn pool_price_json(price: &PoolPrice) -> Value {
+    json!({
+        "pool": price.pool.to_string(),
+        "coin_mint": price.coin_mint.map(|mint| mint.to_string()),
+        "pc_mint": price.pc_mint.map(|mint| mint.to_string()),
+        "coin_reserve": price.reserves.coin,
+        "pc_reserve": price.reserves.pc,
+        "price": price.price,
+        "decimals_adjusted": price.decimals_adjusted,
+        "updated_at_us": price.updated_at_us,
+        "signature": price.signature.to_string(),
+    })
+}
+
+#[derive(Clone, Debug)]
+struct TrackedPrice {
+    price: PoolPrice,
+    // Stamp of the pool's latest entry in `order`
+    seen: u64,
+}
+
+/// The latest price of the most recently updated pools.
+pub struct PoolPrices {
+    capacity: usize,
+    prices: HashMap<Pubkey, TrackedPrice>,
+    // (stamp, pool) in the order pools were updated; stale once the pool is updated again
+    order: VecDeque<(u64, Pubkey)>,
+    next_stamp: u64,
+}
+
+impl PoolPrices {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            prices: HashMap::new(),
+            order: VecDeque::new(),
+            next_stamp: 0,
+        }
+    }
+
+    /// Records `price` as its pool's latest, evicting the least recently updated
+    /// pool if a new one doesn't fit.
+    pub fn update(&mut self, price: PoolPrice) {
+        let pool = price.pool;
+        if !self.prices.contains_key(&pool) && self.prices.len() == self.capacity {
+            self.evict_least_recent();
+        }
+        let seen = self.next_stamp;
+        self.next_stamp += 1;
+        self.prices.insert(pool, TrackedPrice { price, seen });
+        self.order.push_back((seen, pool));
+        // Every pool has one live entry, so compacting leaves at most `capacity`
+        if self.order.len() > 2 * self.capacity {
+            let prices = &self.prices;
+            self.order
+                .retain(|(stamp, pool)| prices.get(pool).is_some_and(|tracked| tracked.seen == *stamp));
+        }
+    }
+
+    pub fn get(&self, pool: &Pubkey) -> Option<&PoolPrice> {
+        self.prices.get(pool).map(|tracked| &tracked.price)
+    }
+
+    /// Every tracked pool's price, the most recently updated first.
+    pub fn latest(&self) -> Vec<&PoolPrice> {
+        let mut tracked: Vec<_> = self.prices.values().collect();
+        tracked.sort_unstable_by_key(|tracked| std::cmp::Reverse(tracked.seen));
+        tracked.into_iter().map(|tracked| &tracked.price).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.prices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prices.is_empty()
+    }
+
+    fn evict_least_recent(&mut self) {
+        while let Some((stamp, pool)) = self.order.pop_front() {
+            if self.prices.get(&pool).is_some_and(|tracked| tracked.seen == stamp) {
+                self.prices.remove(&pool);
+                return;
+            }
+        }
+    }
+}
+
+/// Updates the shared [`PoolPrices`] with every matched swap.
+pub struct PoolPriceSink {
+    prices: Arc<Mutex<PoolPrices>>,
+}
+
+impl PoolPriceSink {
+    pub fn new(prices: Arc<Mutex<PoolPrices>>) -> Self {
+        Self { prices }
+    }
+}
+
+impl SwapSink for PoolPriceSink {
+    fn name(&self) -> &'static str {
+        "price"
+    }
+
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, _packet: &[u8]) -> io::Result<()> {
+        if let Some(price) = PoolPrice::from_swap(received_at_us, swap) {
+            self.prices.lock().unwrap().update(price);
+        }
+        Ok(())
+    }
+}
+
+/// Binds `address` and serves `prices` from a thread of its own, one connection
+/// at a time.
+pub fn spawn_server(address: &str, prices: Arc<Mutex<PoolPrices>>) -> io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(address)?;
+    thread::Builder::new().name("priceServer".to_string()).spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| serve(stream, &prices));
+            if let Err(e) = result {
+                eprintln!("Error serving prices: {}", e);
+            }
+        }
+    })
+}
+
+fn serve(stream: TcpStream, prices: &Mutex<PoolPrices>) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers aren't used, but are read so closing doesn't reset the connection
+    for _ in 0..MAX_HEADER_LINES {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+    let (status, body) = respond(&request_line, &prices.lock().unwrap());
+    let body = body.to_string();
+    write!(
+        reader.get_mut(),
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// The status line and body answering `request_line`.
+fn respond(request_line: &str, prices: &PoolPrices) -> (&'static str, Value) {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return ("400 Bad Request", json!({"error": "malformed request"}));
+    };
+    if method != "GET" {
+        return ("405 Method Not Allowed", json!({"error": "only GET is supported"}));
+    }
+    match path.trim_end_matches('/') {
+        "/prices" => ("200 OK", Value::Array(prices.latest().into_iter().map(pool_price_json).collect())),
+        path => match path.strip_prefix("/prices/").map(Pubkey::from_str) {
+            Some(Ok(pool)) => match prices.get(&pool) {
+                Some(price) => ("200 OK", pool_price_json(price)),
+                None => ("404 Not Found", json!({"error": "pool not tracked"})),
+            },
+            Some(Err(_)) => ("400 Bad Request", json!({"error": "invalid pool"})),
+            None => ("404 Not Found", json!({"error": "not found"})),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn price(pool: Pubkey, updated_at_us: u64) -> PoolPrice {
+        PoolPrice {
+            pool,
+            coin_mint: None,
+            pc_mint: None,
+            reserves: PoolReserves {
+                coin: 1_000,
+                pc: 2_000,
+                coin_to_pc: true,
+            },
+            price: 2.0,
+            decimals_adjusted: false,
+            updated_at_us,
+            signature: Signature::default(),
+        }
+    }
+
+    #[test]
+    fn test_pool_price_from_swap() {
+        let (_, mut swap) = crate::selftest::swap_base_in_transaction();
+        assert_eq!(PoolPrice::from_swap(1, &swap), None);
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        swap.pool_coin_account = Some(Pubkey::new_unique());
+        swap.pool_reserves = Some(PoolReserves {
+            coin: 2_000_000_000,
+            pc: 300_000_000,
+            coin_to_pc: false,
+        });
+        (swap.source_mint, swap.destination_mint) = (Some(usdc), Some(sol));
+        (swap.source_decimals, swap.destination_decimals) = (None, None);
+        let price = PoolPrice::from_swap(7, &swap).unwrap();
+        assert_eq!((price.coin_mint, price.pc_mint), (Some(sol), Some(usdc)));
+        assert_eq!((price.price, price.decimals_adjusted), (0.15, false));
+        assert_eq!(price.updated_at_us, 7);
+        // 2 SOL for 300 USDC
+        (swap.source_decimals, swap.destination_decimals) = (Some(6), Some(9));
+        let price = PoolPrice::from_swap(7, &swap).unwrap();
+        assert!((price.price - 150.0).abs() < 1e-9);
+        assert!(price.decimals_adjusted);
+        assert_eq!(pool_price_json(&price)["pc_reserve"], 300_000_000);
+    }
+
+    #[test]
+    fn test_pool_prices() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut prices = PoolPrices::new(2);
+        prices.update(price(a, 1));
+        prices.update(price(b, 2));
+        prices.update(price(a, 3));
+        // c evicts b, which a's update left least recently updated
+        prices.update(price(c, 4));
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices.get(&b), None);
+        assert_eq!(prices.get(&a).map(|price| price.updated_at_us), Some(3));
+        let latest: Vec<_> = prices.latest().into_iter().map(|price| price.pool).collect();
+        assert_eq!(latest, vec![c, a]);
+
+        assert_eq!(respond("GET /prices HTTP/1.1\r\n", &prices).1.as_array().unwrap().len(), 2);
+        assert_eq!(respond(&format!("GET /prices/{} HTTP/1.1\r\n", a), &prices).1["updated_at_us"], 3);
+        assert_eq!(respond(&format!("GET /prices/{} HTTP/1.1\r\n", b), &prices).0, "404 Not Found");
+        assert_eq!(respond("GET /prices/nope HTTP/1.1\r\n", &prices).0, "400 Bad Request");
+        assert_eq!(respond("POST /prices HTTP/1.1\r\n", &prices).0, "405 Method Not Allowed");
+    }
+
+    #[test]
+    fn test_price_server() {
+        let pool = Pubkey::new_unique();
+        let prices = Arc::new(Mutex::new(PoolPrices::new(10)));
+        prices.lock().unwrap().update(price(pool, 5));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        spawn_server(&address, prices).unwrap();
+
+        let mut stream = TcpStream::connect(&address).unwrap();
+        write!(stream, "GET /prices/{} HTTP/1.1\r\nHost: localhost\r\n\r\n", pool).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let body: Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["pool"], pool.to_string());
+        assert_eq!(body["price"], 2.0);
+    }
+}
@@ -0,0 +1,67 @@
+//! The program ids compiled into the receiver, and their validation.
+//!
+//! Program ids are kept as base58 constants next to the code that uses them. A
+//! typo in one would not fail to compile, it would make its program never match,
+//! so [`check_builtin_program_ids`] parses them all once at startup and names the
+//! broken ones, and [`builtin_program_id`] fails loudly rather than quietly.
+use std::str::FromStr;
+use solana_sdk::pubkey::Pubkey;
+use crate::amm::{ALDRIN_V2_PROGRAM_ID, LIFINITY_V2_PROGRAM_ID, SABER_PROGRAM_ID};
+use crate::analyzer::{
+    PUMP_AMM_PROGRAM_ID, RAYDIUM_LAUNCHPAD_PROGRAM_ID, RAYDIUM_STABLE_PROGRAM_ID, RAYDIUM_SWAP_PROGRAM, RAYDIUM_V4_PROGRAM_ID,
+};
+use crate::cpi::JUPITER_V6_PROGRAM_ID;
+use crate::token_metadata::TOKEN_METADATA_PROGRAM_ID;
+use crate::token_transfers::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
+/// Every built-in program id with the name it is reported under.
+pub const BUILTIN_PROGRAM_IDS: [(&str, &str); 12] = [
+    ("raydium_v4", RAYDIUM_V4_PROGRAM_ID),
+    ("raydium_stable", RAYDIUM_STABLE_PROGRAM_ID),
+    ("raydium_swap", RAYDIUM_SWAP_PROGRAM),
+    ("pump_amm", PUMP_AMM_PROGRAM_ID),
+    ("raydium_launchpad", RAYDIUM_LAUNCHPAD_PROGRAM_ID),
+    ("lifinity_v2", LIFINITY_V2_PROGRAM_ID),
+    ("saber", SABER_PROGRAM_ID),
+    ("aldrin_v2", ALDRIN_V2_PROGRAM_ID),
+    ("token", TOKEN_PROGRAM_ID),
+    ("token_2022", TOKEN_2022_PROGRAM_ID),
+    ("token_metadata", TOKEN_METADATA_PROGRAM_ID),
+    ("jupiter_v6", JUPITER_V6_PROGRAM_ID),
+];
+
+fn parse_program_id(id: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(id).map_err(|e| format!("program id {:?} is invalid: {}", id, e))
+}
+
+/// Parses every entry of [`BUILTIN_PROGRAM_IDS`], describing all that fail.
+pub fn check_builtin_program_ids() -> Result<(), String> {
+    let errors: Vec<String> = BUILTIN_PROGRAM_IDS
+        .iter()
+        .filter_map(|(name, id)| Some(format!("built-in {} {}", name, parse_program_id(id).err()?)))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+/// Parses a built-in program id, panicking with the id if it is invalid.
+pub fn builtin_program_id(id: &str) -> Pubkey {
+    parse_program_id(id).unwrap_or_else(|e| panic!("built-in {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_program_ids() {
+        assert_eq!(check_builtin_program_ids(), Ok(()));
+        assert_eq!(builtin_program_id(RAYDIUM_V4_PROGRAM_ID).to_string(), RAYDIUM_V4_PROGRAM_ID);
+        // Zero is not a base58 digit
+        let error = parse_program_id("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp0").unwrap_err();
+        assert!(error.starts_with("program id \"675kPX9"), "{}", error);
+    }
+}
@@ -0,0 +1,315 @@
+//! Length-delimited protobuf output of matched swaps (`--protobuf FILE`).
+//!
+//! Each matched swap is written as a `Swap` message of `proto/swap.proto`, preceded
+//! by its length as a varint, so any protobuf runtime can read the stream with its
+//! delimited reader. The file may be a named pipe; stdout carries the console
+//! report. [`decode_swaps`] reads a stream back.
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use prost::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::TransactionVersion;
+use crate::analyzer::AnalyzedSwap;
+use crate::output::SwapSink;
+
+// Messages of proto/swap.proto, written out to avoid a protoc build step
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Swap {
+    #[prost(uint64, tag = "1")]
+    pub received_at_us: u64,
+    #[prost(bytes = "vec", tag = "2")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "3")]
+    pub dex: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub curve: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "5")]
+    pub discriminator: u32,
+    #[prost(uint64, tag = "6")]
+    pub amount_in: u64,
+    #[prost(uint64, tag = "7")]
+    pub min_amount_out: u64,
+    #[prost(bytes = "vec", optional, tag = "8")]
+    pub pool_coin_account: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "9")]
+    pub pool_pc_account: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "10")]
+    pub model_data_account: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "11")]
+    pub user_source_account: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "12")]
+    pub user_destination_account: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "13")]
+    pub user_owner: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "14")]
+    pub source_mint: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(bytes = "vec", optional, tag = "15")]
+    pub destination_mint: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    #[prost(uint32, optional, tag = "16")]
+    pub source_decimals: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "17")]
+    pub destination_decimals: ::core::option::Option<u32>,
+    #[prost(uint64, tag = "18")]
+    pub compute_unit_price: u64,
+    #[prost(uint32, tag = "19")]
+    pub compute_unit_limit: u32,
+    #[prost(bool, tag = "20")]
+    pub compute_unit_limit_requested: bool,
+    #[prost(uint64, tag = "21")]
+    pub priority_fee_lamports: u64,
+    #[prost(message, repeated, tag = "22")]
+    pub transfers: ::prost::alloc::vec::Vec<SolTransfer>,
+    #[prost(message, repeated, tag = "23")]
+    pub lookup_tables: ::prost::alloc::vec::Vec<AddressTableLookup>,
+    #[prost(uint64, tag = "24")]
+    pub transaction_size: u64,
+    #[prost(bool, tag = "25")]
+    pub front_run_protected: bool,
+    #[prost(bool, tag = "26")]
+    pub zero_min_out: bool,
+    #[prost(uint32, optional, tag = "27")]
+    pub pool_fee_bps: ::core::option::Option<u32>,
+    #[prost(uint64, optional, tag = "28")]
+    pub expected_amount_out: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "29")]
+    pub realized_amount_out: ::core::option::Option<u64>,
+    #[prost(message, repeated, tag = "30")]
+    pub mint_events: ::prost::alloc::vec::Vec<MintEvent>,
+    #[prost(message, repeated, tag = "31")]
+    pub authority_changes: ::prost::alloc::vec::Vec<AuthorityChange>,
+    #[prost(uint64, optional, tag = "32")]
+    pub payer_sol_balance: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "33")]
+    pub realized_amount_in: ::core::option::Option<u64>,
+    #[prost(bytes = "vec", repeated, tag = "34")]
+    pub co_signatures: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    #[prost(message, repeated, tag = "35")]
+    pub burn_events: ::prost::alloc::vec::Vec<BurnEvent>,
+    #[prost(uint64, optional, tag = "36")]
+    pub compute_units_consumed: ::core::option::Option<u64>,
+    #[prost(uint32, optional, tag = "37")]
+    pub observers: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "38")]
+    pub price_impact_bps: ::core::option::Option<u32>,
+    #[prost(uint32, optional, tag = "39")]
+    pub version: ::core::option::Option<u32>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SolTransfer {
+    #[prost(bytes = "vec", tag = "1")]
+    pub source: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub destination: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag = "3")]
+    pub lamports: u64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AddressTableLookup {
+    #[prost(bytes = "vec", tag = "1")]
+    pub account_key: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub writable_indexes: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub readonly_indexes: ::prost::alloc::vec::Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MintEvent {
+    #[prost(bytes = "vec", tag = "1")]
+    pub mint: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub destination: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag = "3")]
+    pub amount: u64,
+    #[prost(uint32, optional, tag = "4")]
+    pub decimals: ::core::option::Option<u32>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BurnEvent {
+    #[prost(bytes = "vec", tag = "1")]
+    pub mint: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub account: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag = "3")]
+    pub amount: u64,
+    #[prost(uint32, optional, tag = "4")]
+    pub decimals: ::core::option::Option<u32>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuthorityChange {
+    #[prost(bytes = "vec", tag = "1")]
+    pub account: ::prost::alloc::vec::Vec<u8>,
+    #[prost(string, tag = "2")]
+    pub authority_type: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", optional, tag = "3")]
+    pub new_authority: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+}
+
+fn pubkey_bytes(pubkey: &Pubkey) -> Vec<u8> {
+    pubkey.to_bytes().to_vec()
+}
+
+impl Swap {
+    pub fn new(received_at_us: u64, swap: &AnalyzedSwap) -> Self {
+        let optional_pubkey = |pubkey: Option<Pubkey>| pubkey.as_ref().map(pubkey_bytes);
+        Self {
+            received_at_us,
+            signature: swap.signature.as_ref().to_vec(),
+            dex: swap.dex.name().to_string(),
+            curve: swap.curve().name().to_string(),
+            discriminator: swap.discriminator.into(),
+            amount_in: swap.amount_in,
+            min_amount_out: swap.min_amount_out,
+            pool_coin_account: optional_pubkey(swap.pool_coin_account),
+            pool_pc_account: optional_pubkey(swap.pool_pc_account),
+            model_data_account: optional_pubkey(swap.model_data_account),
+            user_source_account: optional_pubkey(swap.user_source_account),
+            user_destination_account: optional_pubkey(swap.user_destination_account),
+            user_owner: optional_pubkey(swap.user_owner),
+            source_mint: optional_pubkey(swap.source_mint),
+            destination_mint: optional_pubkey(swap.destination_mint),
+            source_decimals: swap.source_decimals.map(u32::from),
+            destination_decimals: swap.destination_decimals.map(u32::from),
+            compute_unit_price: swap.priority_fee.compute_unit_price,
+            compute_unit_limit: swap.priority_fee.compute_unit_limit,
+            compute_unit_limit_requested: swap.priority_fee.compute_unit_limit_requested,
+            priority_fee_lamports: swap.priority_fee.total_lamports(),
+            transfers: swap
+                .transfers
+                .iter()
+                .map(|transfer| SolTransfer {
+                    source: pubkey_bytes(&transfer.source),
+                    destination: pubkey_bytes(&transfer.destination),
+                    lamports: transfer.lamports,
+                })
+                .collect(),
+            lookup_tables: swap
+                .lookup_tables
+                .iter()
+                .map(|lookup| AddressTableLookup {
+                    account_key: pubkey_bytes(&lookup.account_key),
+                    writable_indexes: lookup.writable_indexes.clone(),
+                    readonly_indexes: lookup.readonly_indexes.clone(),
+                })
+                .collect(),
+            transaction_size: swap.transaction_size as u64,
+            front_run_protected: swap.front_run_protected,
+            zero_min_out: swap.zero_min_out,
+            pool_fee_bps: swap.pool_fee_bps,
+            expected_amount_out: swap.expected_amount_out,
+            realized_amount_out: swap.realized_amount_out,
+            mint_events: swap
+                .mint_events
+                .iter()
+                .map(|event| MintEvent {
+                    mint: pubkey_bytes(&event.mint),
+                    destination: pubkey_bytes(&event.destination),
+                    amount: event.amount,
+                    decimals: event.decimals.map(u32::from),
+                })
+                .collect(),
+            authority_changes: swap
+                .authority_changes
+                .iter()
+                .map(|change| AuthorityChange {
+                    account: pubkey_bytes(&change.account),
+                    authority_type: change.authority_type.name(),
+                    new_authority: change.new_authority.as_ref().map(pubkey_bytes),
+                })
+                .collect(),
+            payer_sol_balance: swap.payer_sol_balance,
+            realized_amount_in: swap.realized_amount_in,
+            co_signatures: swap.co_signatures.iter().map(|signature| signature.as_ref().to_vec()).collect(),
+            burn_events: swap
+                .burn_events
+                .iter()
+                .map(|event| BurnEvent {
+                    mint: pubkey_bytes(&event.mint),
+                    account: pubkey_bytes(&event.account),
+                    amount: event.amount,
+                    decimals: event.decimals.map(u32::from),
+                })
+                .collect(),
+            compute_units_consumed: swap.compute_units_consumed,
+            observers: swap.observers,
+            price_impact_bps: swap.price_impact_bps,
+            version: match swap.version {
+                TransactionVersion::Legacy(_) => None,
+                TransactionVersion::Number(version) => Some(version.into()),
+            },
+        }
+    }
+}
+
+/// Decodes a stream of length-delimited `Swap` messages, as written to `--protobuf`.
+pub fn decode_swaps(mut stream: &[u8]) -> Result<Vec<Swap>, prost::DecodeError> {
+    let mut swaps = Vec::new();
+    while !stream.is_empty() {
+        swaps.push(Swap::decode_length_delimited(&mut stream)?);
+    }
+    Ok(swaps)
+}
+
+pub struct ProtobufSwapWriter {
+    writer: BufWriter<File>,
+    buffer: Vec<u8>,
+}
+
+impl ProtobufSwapWriter {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            buffer: Vec::new(),
+        })
+    }
+}
+
+impl SwapSink for ProtobufSwapWriter {
+    fn name(&self) -> &'static str {
+        "protobuf"
+    }
+
+    /// Writes `swap` and flushes it, so a reader on a pipe sees it right away.
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, _packet: &[u8]) -> io::Result<()> {
+        self.buffer.clear();
+        Swap::new(received_at_us, swap)
+            .encode_length_delimited(&mut self.buffer)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.writer.write_all(&self.buffer)?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amm::AmmRegistry;
+    use crate::analyzer::{analyze_transaction, Resolvers};
+    use crate::selftest::swap_base_in_transaction;
+
+    #[test]
+    fn test_protobuf_output() {
+        let (data, _) = swap_base_in_transaction();
+        let swap = analyze_transaction(&data, &AmmRegistry::default(), &mut Resolvers::default()).unwrap().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("swaps.pb");
+        let mut writer = ProtobufSwapWriter::create(&path).unwrap();
+        writer.write_swap(1, &swap, &data).unwrap();
+        writer.write_swap(2, &swap, &data).unwrap();
+
+        let swaps = decode_swaps(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(swaps, vec![Swap::new(1, &swap), Swap::new(2, &swap)]);
+        let decoded = &swaps[0];
+        assert_eq!(decoded.signature, swap.signature.as_ref());
+        assert_eq!(decoded.dex, "raydium_v4");
+        assert_eq!(decoded.amount_in, swap.amount_in);
+        assert_eq!(decoded.user_owner.as_deref(), swap.user_owner.as_ref().map(|owner| owner.as_ref()));
+        assert_eq!(decoded.source_mint, None);
+        assert!(decode_swaps(&[0x05, 0x08]).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Keeping only swaps against chosen quote tokens (`--quote-mints`).
+//!
+//! A swap is kept when either of its mints is one of the quote mints, e.g. only
+//! SOL- and USDC-denominated pairs with `--quote-mints sol,usdc`. Mints are known
+//! from `transferChecked` instructions and a few DEX layouts, and otherwise only
+//! with `--resolve-token-metadata`; a swap with an unknown mint and no quote mint
+//! among the known ones is passed or dropped per `--unresolved-quote`.
+use std::collections::HashSet;
+use std::str::FromStr;
+use solana_sdk::pubkey::Pubkey;
+use crate::analyzer::AnalyzedSwap;
+
+pub const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+pub const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+pub const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
+
+/// Parses a quote mint: a pubkey, or `sol`, `usdc` or `usdt`.
+pub fn parse_quote_mint(s: &str) -> Result<Pubkey, String> {
+    let address = match s {
+        "sol" | "wsol" => WSOL_MINT,
+        "usdc" => USDC_MINT,
+        "usdt" => USDT_MINT,
+        address => address,
+    };
+    Pubkey::from_str(address).map_err(|_| format!("invalid quote mint {}, expected a pubkey, sol, usdc or usdt", s))
+}
+
+/// What to do with a swap whose mints aren't all known.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnresolvedQuote {
+    #[default]
+    Pass,
+    Drop,
+}
+
+impl FromStr for UnresolvedQuote {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pass" => Ok(UnresolvedQuote::Pass),
+            "drop" => Ok(UnresolvedQuote::Drop),
+            _ => Err(format!("unknown unresolved quote action {}, expected pass or drop", s)),
+        }
+    }
+}
+
+pub struct QuoteFilter {
+    mints: HashSet<Pubkey>,
+    unresolved: UnresolvedQuote,
+}
+
+impl QuoteFilter {
+    pub fn new(mints: impl IntoIterator<Item = Pubkey>, unresolved: UnresolvedQuote) -> Self {
+        Self {
+            mints: mints.into_iter().collect(),
+            unresolved,
+        }
+    }
+
+    /// Whether `swap` trades against one of the quote mints.
+    pub fn accepts(&self, swap: &AnalyzedSwap) -> bool {
+        let mints = [swap.source_mint, swap.destination_mint];
+        if mints.iter().flatten().any(|mint| self.mints.contains(mint)) {
+            return true;
+        }
+        // The unknown mint may be the quote
+        mints.iter().any(Option::is_none) && self.unresolved == UnresolvedQuote::Pass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amm::AmmRegistry;
+    use crate::analyzer::{analyze_transaction, Resolvers};
+    use crate::selftest::swap_base_in_transaction;
+
+    #[test]
+    fn test_quote_filter() {
+        let (data, _) = swap_base_in_transaction();
+        let mut swap = analyze_transaction(&data, &AmmRegistry::default(), &mut Resolvers::default()).unwrap().unwrap();
+        let wsol = parse_quote_mint("sol").unwrap();
+        let usdc = parse_quote_mint("usdc").unwrap();
+        let token = Pubkey::new_unique();
+        let filter = QuoteFilter::new([wsol, usdc], UnresolvedQuote::Pass);
+        let strict = QuoteFilter::new([wsol, usdc], UnresolvedQuote::Drop);
+
+        swap.source_mint = Some(token);
+        swap.destination_mint = Some(usdc);
+        assert!(filter.accepts(&swap));
+        assert!(strict.accepts(&swap));
+        swap.destination_mint = Some(Pubkey::new_unique());
+        assert!(!filter.accepts(&swap));
+        swap.destination_mint = None;
+        assert!(filter.accepts(&swap));
+        assert!(!strict.accepts(&swap));
+        swap.source_mint = Some(wsol);
+        assert!(strict.accepts(&swap));
+
+        assert_eq!(parse_quote_mint(USDT_MINT), parse_quote_mint("usdt"));
+        assert!(parse_quote_mint("btc").is_err());
+        assert_eq!("drop".parse(), Ok(UnresolvedQuote::Drop));
+    }
+}
@@ -0,0 +1,120 @@
+//! Per-source rate limit on the receive socket (`--max-source-rate`).
+//!
+//! An exposed receiver can be flooded by a single sender, or by a forwarder gone
+//! wrong, starving the analyzer of everyone else's packets. Each source IP gets a
+//! token bucket refilled at the configured rate and holding a second's worth, so a
+//! source may burst up to its rate at once. Datagrams beyond that are dropped
+//! before they are captured or analyzed and counted as `rate_limited`.
+//!
+//! Buckets are kept for the `--rate-limit-sources` most recently seen sources. An
+//! evicted source starts over with a full bucket, as it would after being idle.
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_us: u64,
+    // Stamp of the source's latest entry in `order`
+    seen: u64,
+}
+
+pub struct SourceRateLimiter {
+    packets_per_sec: f64,
+    capacity: usize,
+    buckets: HashMap<IpAddr, Bucket>,
+    // (stamp, source) in the order sources were seen; stale once the source is seen again
+    order: VecDeque<(u64, IpAddr)>,
+    next_stamp: u64,
+}
+
+impl SourceRateLimiter {
+    pub fn new(packets_per_sec: u64, capacity: usize) -> Self {
+        Self {
+            packets_per_sec: packets_per_sec as f64,
+            capacity: capacity.max(1),
+            buckets: HashMap::new(),
+            order: VecDeque::new(),
+            next_stamp: 0,
+        }
+    }
+
+    /// Whether a datagram from `source` is within its rate, taking a token if so.
+    pub fn allow(&mut self, source: IpAddr, now_us: u64) -> bool {
+        if !self.buckets.contains_key(&source) && self.buckets.len() == self.capacity {
+            self.evict_least_recent();
+        }
+        let stamp = self.next_stamp;
+        self.next_stamp += 1;
+        let bucket = self.buckets.entry(source).or_insert(Bucket {
+            tokens: self.packets_per_sec,
+            updated_us: now_us,
+            seen: stamp,
+        });
+        let elapsed_secs = now_us.saturating_sub(bucket.updated_us) as f64 / 1_000_000.0;
+        bucket.tokens = (bucket.tokens + elapsed_secs * self.packets_per_sec).min(self.packets_per_sec);
+        bucket.updated_us = now_us;
+        bucket.seen = stamp;
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        self.order.push_back((stamp, source));
+        // Every source has one live entry, so compacting leaves at most `capacity`
+        if self.order.len() > 2 * self.capacity {
+            let buckets = &self.buckets;
+            self.order
+                .retain(|(stamp, source)| buckets.get(source).is_some_and(|bucket| bucket.seen == *stamp));
+        }
+        allowed
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    fn evict_least_recent(&mut self) {
+        while let Some((stamp, source)) = self.order.pop_front() {
+            if self.buckets.get(&source).is_some_and(|bucket| bucket.seen == stamp) {
+                self.buckets.remove(&source);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_source_rate_limiter() {
+        let (a, b, c) = (
+            IpAddr::from(Ipv4Addr::new(10, 0, 0, 1)),
+            IpAddr::from(Ipv4Addr::new(10, 0, 0, 2)),
+            IpAddr::from(Ipv4Addr::new(10, 0, 0, 3)),
+        );
+        let mut limiter = SourceRateLimiter::new(2, 2);
+        // A burst of a second's worth, then nothing until the bucket refills
+        assert!(limiter.allow(a, 0));
+        assert!(limiter.allow(a, 0));
+        assert!(!limiter.allow(a, 100_000));
+        assert!(limiter.allow(a, 500_000));
+        // Other sources have buckets of their own
+        assert!(limiter.allow(b, 500_000));
+        assert!(!limiter.allow(a, 500_000));
+        // c evicts a, which b's traffic left least recently seen
+        assert!(limiter.allow(b, 600_000));
+        assert!(limiter.allow(c, 600_000));
+        assert_eq!(limiter.len(), 2);
+        assert!(limiter.allow(a, 600_000));
+        assert!(!limiter.buckets.contains_key(&b));
+        assert!(limiter.order.len() <= 4);
+    }
+}
@@ -0,0 +1,273 @@
+//! Decoding of the `ray_log` a Raydium AMM swap emits.
+//!
+//! Each swap logs `Program log: ray_log: <base64>`, a bincode-serialized struct of
+//! its kind byte followed by little-endian `u64`s. The instruction of an exact-output
+//! SwapBaseOut only bounds its input; the log tells what it actually spent. Both
+//! swap kinds also log the pool's reserves before the swap, from which with the
+//! amounts swapped follows how far the swap moved the pool's price. Logs only come
+//! with confirmed transactions (`--backfill`, `--resolve-cpi`), so live packets
+//! never have one.
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+
+pub const RAY_LOG_PREFIX: &str = "Program log: ray_log: ";
+
+// LogType of the Raydium AMM: Init, Deposit, Withdraw, SwapBaseIn, SwapBaseOut
+const SWAP_BASE_IN_LOG: u8 = 3;
+const SWAP_BASE_OUT_LOG: u8 = 4;
+
+// log_type (1) | amount_in | minimum_out | direction | user_source | pool_coin | pool_pc | out_amount
+// log_type (1) | max_in | amount_out | direction | user_source | pool_coin | pool_pc | deduct_in
+const SWAP_LOG_LEN: usize = 1 + 7 * 8;
+
+// Direction of a swap log
+const COIN_TO_PC: u64 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapBaseInLog {
+    pub amount_in: u64,
+    pub minimum_out: u64,
+    /// 1 for coin to pc, 2 for pc to coin
+    pub direction: u64,
+    /// The user's source token balance before the swap
+    pub user_source: u64,
+    pub pool_coin: u64,
+    pub pool_pc: u64,
+    /// The output the swap paid out
+    pub out_amount: u64,
+}
+
+impl SwapBaseInLog {
+    /// How far the swap moved the pool's price, in basis points.
+    pub fn price_impact_bps(&self) -> Option<u32> {
+        price_impact_bps(self.direction, self.pool_coin, self.pool_pc, self.amount_in, self.out_amount)
+    }
+
+    pub fn reserves_after(&self) -> Option<PoolReserves> {
+        reserves_after(self.direction, self.pool_coin, self.pool_pc, self.amount_in, self.out_amount)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapBaseOutLog {
+    pub max_in: u64,
+    pub amount_out: u64,
+    /// 1 for coin to pc, 2 for pc to coin
+    pub direction: u64,
+    /// The user's source token balance before the swap
+    pub user_source: u64,
+    pub pool_coin: u64,
+    pub pool_pc: u64,
+    /// The input the swap spent, fee included
+    pub deduct_in: u64,
+}
+
+impl SwapBaseOutLog {
+    /// How far the swap moved the pool's price, in basis points.
+    pub fn price_impact_bps(&self) -> Option<u32> {
+        price_impact_bps(self.direction, self.pool_coin, self.pool_pc, self.deduct_in, self.amount_out)
+    }
+
+    pub fn reserves_after(&self) -> Option<PoolReserves> {
+        reserves_after(self.direction, self.pool_coin, self.pool_pc, self.deduct_in, self.amount_out)
+    }
+}
+
+/// A pool's reserves once a swap went through, in raw token units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolReserves {
+    pub coin: u64,
+    pub pc: u64,
+    /// Whether the swap sold coin for pc, i.e. its source mint is the pool's coin
+    pub coin_to_pc: bool,
+}
+
+impl PoolReserves {
+    /// The pool's price of coin in pc, in raw units; `None` for an empty pool.
+    pub fn price(&self) -> Option<f64> {
+        (self.coin > 0).then(|| self.pc as f64 / self.coin as f64)
+    }
+}
+
+/// The fall in the pool's price of the output, in input per output, that swapping
+/// `amount_in` for `amount_out` makes, in basis points. The reserves are those
+/// before the swap; the input is counted fee included, as the fee stays in the
+/// pool. `None` for an empty pool or an unknown direction.
+fn price_impact_bps(direction: u64, pool_coin: u64, pool_pc: u64, amount_in: u64, amount_out: u64) -> Option<u32> {
+    let (reserve_in, reserve_out) = match direction {
+        COIN_TO_PC => (pool_coin, pool_pc),
+        2 => (pool_pc, pool_coin),
+        _ => return None,
+    };
+    if reserve_in == 0 || reserve_out == 0 {
+        return None;
+    }
+    let (reserve_in, reserve_out) = (reserve_in as f64, reserve_out as f64);
+    let price_before = reserve_out / reserve_in;
+    let price_after = (reserve_out - amount_out as f64).max(0.0) / (reserve_in + amount_in as f64);
+    Some(((1.0 - price_after / price_before) * 10_000.0).round().clamp(0.0, 10_000.0) as u32)
+}
+
+/// The reserves before the swap with `amount_in` added and `amount_out` taken
+/// out, `None` for an unknown direction.
+fn reserves_after(direction: u64, pool_coin: u64, pool_pc: u64, amount_in: u64, amount_out: u64) -> Option<PoolReserves> {
+    let coin_to_pc = match direction {
+        COIN_TO_PC => true,
+        2 => false,
+        _ => return None,
+    };
+    let (coin, pc) = if coin_to_pc {
+        (pool_coin.saturating_add(amount_in), pool_pc.saturating_sub(amount_out))
+    } else {
+        (pool_coin.saturating_sub(amount_out), pool_pc.saturating_add(amount_in))
+    };
+    Some(PoolReserves { coin, pc, coin_to_pc })
+}
+
+/// The `u64`s of a swap `ray_log` line of kind `log_type`, `None` for other lines
+/// and log kinds.
+fn decode_swap_log(log: &str, log_type: u8) -> Option<[u64; 7]> {
+    let data = BASE64_STANDARD.decode(log.strip_prefix(RAY_LOG_PREFIX)?).ok()?;
+    if data.len() != SWAP_LOG_LEN || data[0] != log_type {
+        return None;
+    }
+    Some(std::array::from_fn(|index| {
+        let offset = 1 + index * 8;
+        u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+    }))
+}
+
+/// Decodes a SwapBaseIn `ray_log` line, `None` for other lines and log kinds.
+pub fn decode_swap_base_in(log: &str) -> Option<SwapBaseInLog> {
+    let [amount_in, minimum_out, direction, user_source, pool_coin, pool_pc, out_amount] =
+        decode_swap_log(log, SWAP_BASE_IN_LOG)?;
+    Some(SwapBaseInLog {
+        amount_in,
+        minimum_out,
+        direction,
+        user_source,
+        pool_coin,
+        pool_pc,
+        out_amount,
+    })
+}
+
+/// Decodes a SwapBaseOut `ray_log` line, `None` for other lines and log kinds.
+pub fn decode_swap_base_out(log: &str) -> Option<SwapBaseOutLog> {
+    let [max_in, amount_out, direction, user_source, pool_coin, pool_pc, deduct_in] =
+        decode_swap_log(log, SWAP_BASE_OUT_LOG)?;
+    Some(SwapBaseOutLog {
+        max_in,
+        amount_out,
+        direction,
+        user_source,
+        pool_coin,
+        pool_pc,
+        deduct_in,
+    })
+}
+
+/// Finds the log of the SwapBaseIn with these instruction amounts among a
+/// transaction's logs.
+pub fn find_swap_base_in(log_messages: &[String], amount_in: u64, minimum_out: u64) -> Option<SwapBaseInLog> {
+    log_messages
+        .iter()
+        .filter_map(|log| decode_swap_base_in(log))
+        .find(|log| log.amount_in == amount_in && log.minimum_out == minimum_out)
+}
+
+/// Finds the log of the SwapBaseOut with these instruction amounts among a
+/// transaction's logs. Matching on the amounts tells the swap apart from others
+/// in the same transaction.
+pub fn find_swap_base_out(log_messages: &[String], max_in: u64, amount_out: u64) -> Option<SwapBaseOutLog> {
+    log_messages
+        .iter()
+        .filter_map(|log| decode_swap_base_out(log))
+        .find(|log| log.max_in == max_in && log.amount_out == amount_out)
+}
+
+/// A swap `ray_log` line of kind `log_type`, as the AMM logs it.
+#[cfg(test)]
+fn swap_log(log_type: u8, values: [u64; 7]) -> String {
+    let mut data = vec![log_type];
+    for value in values {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    format!("{}{}", RAY_LOG_PREFIX, BASE64_STANDARD.encode(data))
+}
+
+/// A pc to coin SwapBaseIn `ray_log` line, from a pool of 1e9 coin and 2e9 pc.
+#[cfg(test)]
+pub(crate) fn swap_base_in_log(amount_in: u64, minimum_out: u64, out_amount: u64) -> String {
+    swap_log(SWAP_BASE_IN_LOG, [amount_in, minimum_out, 2, 5_000_000, 1_000_000_000, 2_000_000_000, out_amount])
+}
+
+/// A pc to coin SwapBaseOut `ray_log` line, from a pool of 1e9 coin and 2e9 pc.
+#[cfg(test)]
+pub(crate) fn swap_base_out_log(max_in: u64, amount_out: u64, deduct_in: u64) -> String {
+    swap_log(SWAP_BASE_OUT_LOG, [max_in, amount_out, 2, 5_000_000, 1_000_000_000, 2_000_000_000, deduct_in])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_swap_base_out() {
+        let logs = vec![
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 invoke [1]".to_string(),
+            swap_base_out_log(1_000, 500, 900),
+            swap_base_out_log(2_000, 700, 1_500),
+            // A SwapBaseIn log of the same length
+            format!("{}{}", RAY_LOG_PREFIX, BASE64_STANDARD.encode([&[3u8][..], &[0; 56]].concat())),
+        ];
+        let log = find_swap_base_out(&logs, 2_000, 700).unwrap();
+        assert_eq!((log.max_in, log.amount_out, log.deduct_in), (2_000, 700, 1_500));
+        assert_eq!((log.direction, log.pool_coin), (2, 1_000_000_000));
+        assert_eq!(find_swap_base_out(&logs, 1_000, 501), None);
+        assert_eq!(find_swap_base_out(&logs, 0, 0), None);
+        assert_eq!(decode_swap_base_out("Program log: ray_log: !!"), None);
+    }
+
+    #[test]
+    fn test_find_swap_base_in() {
+        let logs = vec![swap_base_out_log(1_000, 500, 900), swap_base_in_log(1_000, 500, 499)];
+        let log = find_swap_base_in(&logs, 1_000, 500).unwrap();
+        assert_eq!((log.amount_in, log.minimum_out, log.out_amount), (1_000, 500, 499));
+        assert_eq!((log.direction, log.pool_pc), (2, 2_000_000_000));
+        assert_eq!(find_swap_base_in(&logs, 1_000, 499), None);
+    }
+
+    #[test]
+    fn test_price_impact_bps() {
+        // 2e8 pc for 9e7 coin: coin per pc falls from 0.5 to 9.1e8 / 2.2e9
+        let log = decode_swap_base_in(&swap_base_in_log(200_000_000, 0, 90_000_000)).unwrap();
+        assert_eq!(log.price_impact_bps(), Some(1_727));
+        let log = decode_swap_base_out(&swap_base_out_log(300_000_000, 90_000_000, 200_000_000)).unwrap();
+        assert_eq!(log.price_impact_bps(), Some(1_727));
+        // Coin to pc prices the pc in coin instead
+        let log = decode_swap_base_in(&swap_log(SWAP_BASE_IN_LOG, [1_000_000, 0, 1, 0, 1_000_000_000, 2_000_000_000, 1_996_000])).unwrap();
+        assert_eq!(log.price_impact_bps(), Some(20));
+        assert_eq!(price_impact_bps(3, 1, 1, 1, 1), None);
+        assert_eq!(price_impact_bps(1, 0, 1, 1, 1), None);
+        // Draining the pool is all the impact there is
+        assert_eq!(price_impact_bps(1, 10, 10, 10, 10), Some(10_000));
+    }
+
+    #[test]
+    fn test_reserves_after() {
+        let log = decode_swap_base_in(&swap_base_in_log(200_000_000, 0, 90_000_000)).unwrap();
+        let reserves = PoolReserves {
+            coin: 910_000_000,
+            pc: 2_200_000_000,
+            coin_to_pc: false,
+        };
+        assert_eq!(log.reserves_after(), Some(reserves));
+        assert_eq!(reserves.price(), Some(2_200_000_000.0 / 910_000_000.0));
+        let log = decode_swap_base_out(&swap_base_out_log(300_000_000, 90_000_000, 200_000_000)).unwrap();
+        assert_eq!(log.reserves_after(), Some(reserves));
+        assert_eq!(reserves_after(1, 10, 10, 5, 20), Some(PoolReserves { coin: 15, pc: 0, coin_to_pc: true }));
+        assert_eq!(reserves_after(3, 10, 10, 5, 5), None);
+        assert_eq!(PoolReserves { coin: 0, pc: 1, coin_to_pc: true }.price(), None);
+    }
+}
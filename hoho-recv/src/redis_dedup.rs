@@ -0,0 +1,141 @@
+//! Fleet-wide deduplication of signatures through Redis (`--redis-dedup`).
+//!
+//! Receivers behind a load balancer each see a share of the traffic, and the same
+//! transaction often reaches several of them. Before analyzing a transaction a
+//! receiver claims its signature with `SET <prefix><signature> 1 NX PX <ttl>`; only
+//! the receiver whose `SET` succeeds reports it.
+//!
+//! The client speaks just enough RESP for that one command. Every call has a short
+//! timeout so a slow Redis cannot stall the receiver; when Redis is unreachable
+//! [`RedisDedup::claim`] returns `None` and the caller falls back to local
+//! deduplication, treating repeat arrivals within `--arrival-window-ms` as
+//! duplicates, and reconnects after [`RECONNECT_INTERVAL`].
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+use solana_sdk::signature::Signature;
+
+const IO_TIMEOUT: Duration = Duration::from_millis(50);
+pub const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug)]
+pub struct RedisDedupConfig {
+    pub address: SocketAddr,
+    /// How long a claim lasts; longer than any path's delivery delay
+    pub ttl: Duration,
+    pub key_prefix: String,
+}
+
+pub struct RedisDedup {
+    config: RedisDedupConfig,
+    connection: Option<BufReader<TcpStream>>,
+    retry_at: Option<Instant>,
+}
+
+impl RedisDedup {
+    pub fn new(config: RedisDedupConfig) -> Self {
+        Self {
+            config,
+            connection: None,
+            retry_at: None,
+        }
+    }
+
+    /// Claims `signature` for this receiver. `Some(true)` if no receiver has seen it
+    /// within the TTL, `Some(false)` if another one has, `None` if Redis is unavailable.
+    pub fn claim(&mut self, signature: &Signature) -> Option<bool> {
+        if self.retry_at.is_some_and(|retry_at| Instant::now() < retry_at) {
+            return None;
+        }
+        match self.set_nx(signature) {
+            Ok(claimed) => {
+                self.retry_at = None;
+                Some(claimed)
+            }
+            Err(e) => {
+                if self.retry_at.is_none() {
+                    eprintln!("Redis dedup unavailable, deduplicating locally: {}", e);
+                }
+                self.connection = None;
+                self.retry_at = Some(Instant::now() + RECONNECT_INTERVAL);
+                None
+            }
+        }
+    }
+
+    fn set_nx(&mut self, signature: &Signature) -> io::Result<bool> {
+        if self.connection.is_none() {
+            let stream = TcpStream::connect_timeout(&self.config.address, IO_TIMEOUT)?;
+            stream.set_read_timeout(Some(IO_TIMEOUT))?;
+            stream.set_write_timeout(Some(IO_TIMEOUT))?;
+            stream.set_nodelay(true)?;
+            self.connection = Some(BufReader::new(stream));
+        }
+        let connection = self.connection.as_mut().unwrap();
+        let key = format!("{}{}", self.config.key_prefix, signature);
+        let ttl_ms = self.config.ttl.as_millis().max(1).to_string();
+        connection
+            .get_mut()
+            .write_all(&command(&["SET", &key, "1", "NX", "PX", &ttl_ms]))?;
+        let mut reply = String::new();
+        if connection.read_line(&mut reply)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+        }
+        match reply.trim_end() {
+            "+OK" => Ok(true),
+            // Null bulk string: the key already exists
+            "$-1" => Ok(false),
+            reply => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unexpected reply {:?}", reply))),
+        }
+    }
+}
+
+/// Encodes a command as a RESP array of bulk strings.
+fn command(args: &[&str]) -> Vec<u8> {
+    let mut buf = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        buf.extend_from_slice(format!("${}\r\n{}\r\n", arg.len(), arg).as_bytes());
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_command() {
+        assert_eq!(command(&["SET", "k", "1"]), b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\n1\r\n");
+    }
+
+    #[test]
+    fn test_claim() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        // Answers the first SET with OK and the second with a null, then hangs up
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 512];
+            for reply in [&b"+OK\r\n"[..], b"$-1\r\n"] {
+                let _ = stream.read(&mut buf).unwrap();
+                stream.write_all(reply).unwrap();
+            }
+        });
+        let mut dedup = RedisDedup::new(RedisDedupConfig {
+            address,
+            ttl: Duration::from_secs(60),
+            key_prefix: "test:".to_string(),
+        });
+        let signature = Signature::from([3; 64]);
+        assert_eq!(dedup.claim(&signature), Some(true));
+        assert_eq!(dedup.claim(&signature), Some(false));
+        server.join().unwrap();
+        // The server is gone, and further claims back off without reconnecting
+        assert_eq!(dedup.claim(&signature), None);
+        assert!(dedup.retry_at.is_some());
+        assert_eq!(dedup.claim(&signature), None);
+    }
+}
@@ -0,0 +1,56 @@
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::capture::CaptureReader;
+
+#[derive(Clone, Debug)]
+pub struct ReplayConfig {
+    pub destination: SocketAddr,
+    /// Multiplier applied to the recorded inter-frame gaps; `0.0` replays as fast as possible.
+    pub speed: f64,
+    /// Restart from the beginning of the capture after the last frame.
+    pub repeat: bool,
+}
+
+/// Sends every frame of the capture at `path` to `config.destination`,
+/// preserving the recorded pacing scaled by `config.speed`.
+pub fn replay_capture<P: AsRef<Path>>(path: P, config: &ReplayConfig) -> io::Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let mut pass = 0u64;
+    loop {
+        let mut reader = CaptureReader::open(path.as_ref())?;
+        let mut first_frame: Option<(u64, Instant)> = None;
+        let mut frames = 0u64;
+        let start = Instant::now();
+
+        while let Some(frame) = reader.read_frame()? {
+            if config.speed > 0.0 {
+                let (first_us, first_sent) =
+                    *first_frame.get_or_insert((frame.timestamp_us, Instant::now()));
+                let offset_us = frame.timestamp_us.saturating_sub(first_us) as f64 / config.speed;
+                let due = first_sent + Duration::from_micros(offset_us as u64);
+                let now = Instant::now();
+                if due > now {
+                    thread::sleep(due - now);
+                }
+            }
+            if let Err(e) = socket.send_to(&frame.data, config.destination) {
+                eprintln!("Error sending frame: {}", e);
+            }
+            frames += 1;
+        }
+
+        pass += 1;
+        println!(
+            "Replayed {} frames in {:?} (pass {})",
+            frames,
+            start.elapsed(),
+            pass
+        );
+        if !config.repeat || frames == 0 {
+            return Ok(());
+        }
+    }
+}
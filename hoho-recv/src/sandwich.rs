@@ -0,0 +1,288 @@
+//! Detection of suspected sandwich attacks (`--detect-sandwiches`).
+//!
+//! A sandwich is a buy of a mint by an attacker just ahead of a victim's buy of the
+//! same mint in the same pool, then a sell of it back by the attacker right after:
+//! the victim buys at the price the front-run pushed up, and the back-run sells
+//! into it. Swaps are kept per pool for a short window, and a swap that sells back
+//! what an earlier swap of the same owner bought, with another owner's swap in the
+//! same direction between the two, raises a [`SandwichSuspected`] linking the three
+//! signatures. Only the owner's previous swap in the pool is taken as the front-run,
+//! so a round trip closed with nobody between is not matched later around someone
+//! else's swap. The front-run is used up by the match; when several victims sit
+//! between, the first one is reported.
+//!
+//! Arrival order stands in for execution order, which holds for transactions sent
+//! together but not for ones reaching the receiver through different forwarders.
+//! The profit estimate is what the back-run got for the mint less what the
+//! front-run paid for it, in the mint paid with, before fees. Like the wash trade
+//! detection, amounts are the ones signed for (`amount_in` and `min_amount_out`)
+//! unless the realized amount is known, so a back-run's minimum out understates
+//! the profit. Swaps with an unknown pool, owner or mint are skipped.
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use crate::analyzer::AnalyzedSwap;
+
+// Bounds memory during bursts regardless of the window length
+const MAX_TRACKED_SWAPS: usize = 100_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SandwichConfig {
+    /// How long after the front-run the back-run can land
+    pub window_us: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SandwichSuspected {
+    pub attacker: Pubkey,
+    pub victim: Pubkey,
+    /// The pool's coin vault, which identifies it
+    pub pool: Pubkey,
+    /// The mint the front-run bought and the back-run sold
+    pub mint: Pubkey,
+    /// The mint the front-run paid with and the back-run was paid in
+    pub quote_mint: Pubkey,
+    /// The front-run, the victim's swap and the back-run
+    pub signatures: [Signature; 3],
+    /// What the front-run paid, in `quote_mint`
+    pub spent: u64,
+    /// What the back-run got, in `quote_mint`
+    pub received: u64,
+    pub elapsed_us: u64,
+}
+
+impl SandwichSuspected {
+    /// The attacker's estimated profit in `quote_mint`, negative for a loss.
+    pub fn profit(&self) -> i128 {
+        i128::from(self.received) - i128::from(self.spent)
+    }
+}
+
+impl fmt::Display for SandwichSuspected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SANDWICH SUSPECTED {} around {} on pool {}: bought {} for {} then sold for {} {}, est. profit {} \
+             {}ms later ({} -> {} -> {})",
+            self.attacker,
+            self.victim,
+            self.pool,
+            self.mint,
+            self.spent,
+            self.received,
+            self.quote_mint,
+            self.profit(),
+            self.elapsed_us / 1_000,
+            self.signatures[0],
+            self.signatures[1],
+            self.signatures[2]
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PoolSwap {
+    id: u64,
+    at_us: u64,
+    owner: Pubkey,
+    source_mint: Pubkey,
+    destination_mint: Pubkey,
+    amount_in: u64,
+    amount_out: u64,
+    signature: Signature,
+}
+
+pub struct SandwichDetector {
+    config: SandwichConfig,
+    // Swaps in the window by pool, oldest first
+    pools: HashMap<Pubkey, VecDeque<PoolSwap>>,
+    // (id, recorded at, pool) in recording order, for expiry
+    recorded: VecDeque<(u64, u64, Pubkey)>,
+    next_id: u64,
+}
+
+impl SandwichDetector {
+    pub fn new(config: SandwichConfig) -> Self {
+        Self {
+            config,
+            pools: HashMap::new(),
+            recorded: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Records `swap`, returning the sandwich it completes as the back-run, if any.
+    pub fn observe(&mut self, swap: &AnalyzedSwap, now_us: u64) -> Option<SandwichSuspected> {
+        self.expire(now_us);
+        let (Some(pool), Some(owner), Some(source_mint), Some(destination_mint)) =
+            (swap.pool_coin_account, swap.user_owner, swap.source_mint, swap.destination_mint)
+        else {
+            return None;
+        };
+        let back_run = PoolSwap {
+            id: self.next_id,
+            at_us: now_us,
+            owner,
+            source_mint,
+            destination_mint,
+            amount_in: swap.realized_amount_in.unwrap_or(swap.amount_in),
+            amount_out: swap.realized_amount_out.unwrap_or(swap.min_amount_out),
+            signature: swap.signature,
+        };
+        self.next_id += 1;
+        let suspected = self.take_sandwich(&pool, &back_run);
+        self.push(pool, back_run);
+        suspected
+    }
+
+    /// Takes the front-run `back_run` closes around a victim: the back-run owner's
+    /// previous swap in the pool, if it bought what the back-run sells.
+    fn take_sandwich(&mut self, pool: &Pubkey, back_run: &PoolSwap) -> Option<SandwichSuspected> {
+        let swaps = self.pools.get_mut(pool)?;
+        let position = swaps.iter().rposition(|swap| swap.owner == back_run.owner)?;
+        let front_run = swaps[position];
+        if front_run.destination_mint != back_run.source_mint || front_run.source_mint != back_run.destination_mint {
+            return None;
+        }
+        let victim = *swaps.iter().skip(position + 1).find(|victim| victim.destination_mint == front_run.destination_mint)?;
+        swaps.remove(position);
+        Some(SandwichSuspected {
+            attacker: back_run.owner,
+            victim: victim.owner,
+            pool: *pool,
+            mint: front_run.destination_mint,
+            quote_mint: front_run.source_mint,
+            signatures: [front_run.signature, victim.signature, back_run.signature],
+            spent: front_run.amount_in,
+            received: back_run.amount_out,
+            elapsed_us: back_run.at_us.saturating_sub(front_run.at_us),
+        })
+    }
+
+    fn push(&mut self, pool: Pubkey, swap: PoolSwap) {
+        if self.recorded.len() == MAX_TRACKED_SWAPS {
+            self.pop_oldest();
+        }
+        self.recorded.push_back((swap.id, swap.at_us, pool));
+        self.pools.entry(pool).or_default().push_back(swap);
+    }
+
+    fn expire(&mut self, now_us: u64) {
+        while self
+            .recorded
+            .front()
+            .is_some_and(|(_, at_us, _)| now_us.saturating_sub(*at_us) > self.config.window_us)
+        {
+            self.pop_oldest();
+        }
+    }
+
+    fn pop_oldest(&mut self) {
+        let Some((id, _, pool)) = self.recorded.pop_front() else {
+            return;
+        };
+        if let Some(swaps) = self.pools.get_mut(&pool) {
+            // Unless a match already took it, the oldest swap of its pool
+            if swaps.front().is_some_and(|swap| swap.id == id) {
+                swaps.pop_front();
+            }
+            if swaps.is_empty() {
+                self.pools.remove(&pool);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::transaction::TransactionVersion;
+    use crate::analyzer::Dex;
+    use crate::compute_budget::PriorityFee;
+
+    fn swap(pool: Pubkey, owner: Pubkey, source_mint: Pubkey, destination_mint: Pubkey, amount_in: u64, min_amount_out: u64) -> AnalyzedSwap {
+        AnalyzedSwap {
+            signature: Signature::new_unique(),
+            co_signatures: vec![],
+            dex: Dex::RaydiumV4,
+            discriminator: 9,
+            amount_in,
+            min_amount_out,
+            pool_coin_account: Some(pool),
+            pool_pc_account: None,
+            model_data_account: None,
+            user_source_account: None,
+            user_destination_account: None,
+            user_owner: Some(owner),
+            source_mint: Some(source_mint),
+            destination_mint: Some(destination_mint),
+            source_decimals: None,
+            destination_decimals: None,
+            transfers: vec![],
+            priority_fee: PriorityFee::default(),
+            compute_units_consumed: None,
+            lookup_tables: vec![],
+            transaction_size: 0,
+            version: TransactionVersion::LEGACY,
+            front_run_protected: false,
+            zero_min_out: false,
+            pool_fee_bps: None,
+            expected_amount_out: None,
+            realized_amount_out: None,
+            realized_amount_in: None,
+            mint_events: vec![],
+            burn_events: vec![],
+            authority_changes: vec![],
+            payer_sol_balance: None,
+            observers: None,
+            price_impact_bps: None,
+            pool_reserves: None,
+        }
+    }
+
+    #[test]
+    fn test_sandwich_detector() {
+        let (pool, other_pool) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (attacker, victim) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (sol, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut detector = SandwichDetector::new(SandwichConfig { window_us: 1_000 });
+
+        // A round trip with nobody in between is no sandwich
+        assert!(detector.observe(&swap(pool, attacker, sol, mint, 100, 5_000), 0).is_none());
+        assert!(detector.observe(&swap(pool, attacker, mint, sol, 5_000, 99), 1).is_none());
+
+        let front_run = swap(pool, attacker, sol, mint, 1_000, 50_000);
+        assert!(detector.observe(&front_run, 10).is_none());
+        // A victim selling, and one buying in another pool, don't count
+        assert!(detector.observe(&swap(pool, victim, mint, sol, 10_000, 150), 11).is_none());
+        assert!(detector.observe(&swap(other_pool, victim, sol, mint, 300, 10_000), 12).is_none());
+        let victim_swap = swap(pool, victim, sol, mint, 300, 10_000);
+        assert!(detector.observe(&victim_swap, 20).is_none());
+        let back_run = swap(pool, attacker, mint, sol, 50_000, 1_040);
+        let suspected = detector.observe(&back_run, 30).unwrap();
+        assert_eq!(
+            suspected,
+            SandwichSuspected {
+                attacker,
+                victim,
+                pool,
+                mint,
+                quote_mint: sol,
+                signatures: [front_run.signature, victim_swap.signature, back_run.signature],
+                spent: 1_000,
+                received: 1_040,
+                elapsed_us: 20,
+            }
+        );
+        assert_eq!(suspected.profit(), 40);
+
+        // The front-run is used up
+        assert!(detector.observe(&swap(pool, attacker, mint, sol, 50_000, 1_040), 40).is_none());
+        // Everything before 1_040 has expired
+        detector.observe(&swap(pool, victim, sol, mint, 300, 10_000), 1_040);
+        assert!(detector.observe(&swap(pool, attacker, mint, sol, 50_000, 1_040), 1_041).is_none());
+        assert_eq!(detector.recorded.len(), 2);
+        assert_eq!(detector.pools.len(), 1);
+    }
+}
@@ -0,0 +1,154 @@
+//! `hoho-recv selftest`: builds a Raydium V4 SwapBaseIn transaction in memory and
+//! checks that [`analyze_transaction`] decodes it, so the parser can be verified on
+//! a machine without a live feed. The transaction below doubles as a reference for
+//! the layout the analyzer expects.
+use std::str::FromStr;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::{v0, MessageHeader, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{TransactionVersion, VersionedTransaction};
+use crate::amm::AmmRegistry;
+use crate::analyzer::{analyze_transaction, AnalyzedSwap, Curve, Dex, Resolvers, RAYDIUM_V4_PROGRAM_ID};
+use crate::compute_budget::PriorityFee;
+
+const SWAP_BASE_IN: u8 = 9;
+const AMOUNT_IN: u64 = 1_500_000_000;
+const MIN_AMOUNT_OUT: u64 = 42_000_000;
+
+// SwapBaseIn accounts, in instruction order
+const ACCOUNT_NAMES: [&str; 18] = [
+    "token program",
+    "amm",
+    "amm authority",
+    "amm open orders",
+    "amm target orders",
+    "pool coin token account",
+    "pool pc token account",
+    "serum program",
+    "serum market",
+    "serum bids",
+    "serum asks",
+    "serum event queue",
+    "serum coin vault",
+    "serum pc vault",
+    "serum vault signer",
+    "user source token account",
+    "user destination token account",
+    "user owner",
+];
+
+fn account(position: usize) -> Pubkey {
+    Pubkey::new_from_array([position as u8 + 1; 32])
+}
+
+/// Returns the serialized SwapBaseIn transaction and the swap it should decode to.
+pub fn swap_base_in_transaction() -> (Vec<u8>, AnalyzedSwap) {
+    let raydium_v4 = Pubkey::from_str(RAYDIUM_V4_PROGRAM_ID).unwrap();
+    // The user owner pays and signs, so it goes first; the program is the last key
+    let owner = ACCOUNT_NAMES.len() - 1;
+    let account_keys: Vec<Pubkey> = std::iter::once(account(owner))
+        .chain((0..owner).map(account))
+        .chain([raydium_v4])
+        .collect();
+    let key_index = |position: usize| if position == owner { 0 } else { position as u8 + 1 };
+
+    let mut data = vec![SWAP_BASE_IN];
+    data.extend_from_slice(&AMOUNT_IN.to_le_bytes());
+    data.extend_from_slice(&MIN_AMOUNT_OUT.to_le_bytes());
+
+    let message = v0::Message {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 1,
+        },
+        instructions: vec![CompiledInstruction::new_from_raw_parts(
+            (account_keys.len() - 1) as u8,
+            data,
+            (0..ACCOUNT_NAMES.len()).map(key_index).collect(),
+        )],
+        account_keys,
+        recent_blockhash: Hash::default(),
+        address_table_lookups: vec![],
+    };
+    let signature = Signature::from([9; 64]);
+    let tx = VersionedTransaction {
+        signatures: vec![signature],
+        message: VersionedMessage::V0(message),
+    };
+    let data = bincode::serialize(&tx).unwrap();
+    let expected = AnalyzedSwap {
+        signature,
+        co_signatures: vec![],
+        dex: Dex::RaydiumV4,
+        discriminator: SWAP_BASE_IN,
+        amount_in: AMOUNT_IN,
+        min_amount_out: MIN_AMOUNT_OUT,
+        pool_coin_account: Some(account(5)),
+        pool_pc_account: Some(account(6)),
+        model_data_account: None,
+        user_source_account: Some(account(15)),
+        user_destination_account: Some(account(16)),
+        user_owner: Some(account(owner)),
+        source_mint: None,
+        destination_mint: None,
+        source_decimals: None,
+        destination_decimals: None,
+        transfers: vec![],
+        // Only the swap instruction, so the default limit and no priority fee
+        priority_fee: PriorityFee {
+            compute_unit_price: 0,
+            compute_unit_limit: 200_000,
+            compute_unit_limit_requested: false,
+        },
+        compute_units_consumed: None,
+        lookup_tables: vec![],
+        transaction_size: data.len(),
+        version: TransactionVersion::Number(0),
+        front_run_protected: false,
+        zero_min_out: false,
+        pool_fee_bps: None,
+        expected_amount_out: None,
+        realized_amount_out: None,
+        realized_amount_in: None,
+        mint_events: vec![],
+        burn_events: vec![],
+        authority_changes: vec![],
+        payer_sol_balance: None,
+        observers: None,
+        price_impact_bps: None,
+        pool_reserves: None,
+    };
+    (data, expected)
+}
+
+/// Analyzes the reference transaction, describing the first mismatch on failure.
+pub fn run() -> Result<(), String> {
+    let (data, expected) = swap_base_in_transaction();
+    println!("Analyzing a {} byte Raydium V4 SwapBaseIn transaction:", data.len());
+    for (position, name) in ACCOUNT_NAMES.iter().enumerate() {
+        println!("  account {:2} {:32} {}", position, name, account(position));
+    }
+    let swap = analyze_transaction(&data, &AmmRegistry::default(), &mut Resolvers::default())
+        .map_err(|e| format!("analysis failed: {:?}", e))?
+        .ok_or("no swap detected")?;
+    if swap.curve() != Curve::ConstantProduct {
+        return Err(format!("expected a constant-product swap, got {:?}", swap.curve()));
+    }
+    if swap != expected {
+        return Err(format!("decoded {:#?}\nexpected {:#?}", swap, expected));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest() {
+        assert_eq!(run(), Ok(()));
+    }
+}
@@ -0,0 +1,240 @@
+//! SQLite output of matched swaps for ad-hoc SQL (`--sqlite FILE`, feature `sqlite`).
+//!
+//! Swaps are inserted into the `swaps` table, created along with its indexes on the
+//! signature and both mints the first time a file is used. Inserts run on a writer
+//! thread that commits whatever is queued as one transaction, at most
+//! `MAX_BATCH_SIZE` rows at a time; if it falls `QUEUE_SIZE` swaps behind, further
+//! swaps are dropped and counted. The database is in WAL mode, so it can be queried
+//! while the receiver writes to it.
+//!
+//! Pubkeys and signatures are base58 text. SQLite integers are signed, so `u64`
+//! amounts above `i64::MAX` are stored as their two's complement; add 2^64 to a
+//! negative amount to get the raw value back. SOL transfers, lookup tables,
+//! transaction sizes, front-run and slippage protection, pool fees, token mints,
+//! authority changes, fee payer balances and realized inputs are not stored;
+//! `--json` has them.
+use std::io;
+use std::iter;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use rusqlite::{params, Connection};
+use solana_sdk::pubkey::Pubkey;
+use crate::analyzer::AnalyzedSwap;
+use crate::output::SwapSink;
+
+const QUEUE_SIZE: usize = 100_000;
+const MAX_BATCH_SIZE: usize = 10_000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS swaps (
+    id INTEGER PRIMARY KEY,
+    received_at_us INTEGER NOT NULL,
+    signature TEXT NOT NULL,
+    dex TEXT NOT NULL,
+    curve TEXT NOT NULL,
+    discriminator INTEGER NOT NULL,
+    amount_in INTEGER NOT NULL,
+    min_amount_out INTEGER NOT NULL,
+    pool_coin_account TEXT,
+    pool_pc_account TEXT,
+    model_data_account TEXT,
+    user_source_account TEXT,
+    user_destination_account TEXT,
+    user_owner TEXT,
+    source_mint TEXT,
+    destination_mint TEXT,
+    source_decimals INTEGER,
+    destination_decimals INTEGER,
+    compute_unit_price INTEGER NOT NULL,
+    compute_unit_limit INTEGER NOT NULL,
+    priority_fee_lamports INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS swaps_signature ON swaps (signature);
+CREATE INDEX IF NOT EXISTS swaps_source_mint ON swaps (source_mint);
+CREATE INDEX IF NOT EXISTS swaps_destination_mint ON swaps (destination_mint);
+";
+
+const INSERT: &str = "
+INSERT INTO swaps (
+    received_at_us, signature, dex, curve, discriminator, amount_in, min_amount_out,
+    pool_coin_account, pool_pc_account, model_data_account, user_source_account,
+    user_destination_account, user_owner, source_mint, destination_mint,
+    source_decimals, destination_decimals, compute_unit_price, compute_unit_limit,
+    priority_fee_lamports
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)
+";
+
+pub struct SqliteSwapWriter {
+    sender: Option<SyncSender<(u64, AnalyzedSwap)>>,
+    writer: Option<JoinHandle<rusqlite::Result<()>>>,
+    dropped: u64,
+}
+
+impl SqliteSwapWriter {
+    /// Opens or creates the database at `path` and starts the writer thread.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let connection = Connection::open(path).map_err(io::Error::other)?;
+        connection
+            .pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))
+            .map_err(io::Error::other)?;
+        connection.execute_batch(SCHEMA).map_err(io::Error::other)?;
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_SIZE);
+        let writer = thread::Builder::new()
+            .name("sqliteWriter".to_string())
+            .spawn(move || write_batches(connection, receiver))?;
+        Ok(Self {
+            sender: Some(sender),
+            writer: Some(writer),
+            dropped: 0,
+        })
+    }
+}
+
+impl SwapSink for SqliteSwapWriter {
+    fn name(&self) -> &'static str {
+        "SQLite"
+    }
+
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, _packet: &[u8]) -> io::Result<()> {
+        let Some(sender) = &self.sender else {
+            return Ok(());
+        };
+        match sender.try_send((received_at_us, swap.clone())) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                if self.dropped.is_power_of_two() {
+                    eprintln!("SQLite writer behind, {} swaps dropped so far", self.dropped);
+                }
+                Ok(())
+            }
+            // The writer only exits on an error, which `finish` reports
+            Err(TrySendError::Disconnected(_)) => Err(io::Error::other("SQLite writer exited")),
+        }
+    }
+
+    /// Commits the swaps still queued.
+    fn finish(&mut self) -> io::Result<()> {
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            writer
+                .join()
+                .map_err(|_| io::Error::other("SQLite writer panicked"))?
+                .map_err(io::Error::other)?;
+        }
+        if self.dropped > 0 {
+            println!("SQLite output dropped {} swaps", self.dropped);
+        }
+        Ok(())
+    }
+}
+
+fn write_batches(mut connection: Connection, swaps: Receiver<(u64, AnalyzedSwap)>) -> rusqlite::Result<()> {
+    let text = |pubkey: Option<Pubkey>| pubkey.map(|pubkey| pubkey.to_string());
+    while let Ok(first) = swaps.recv() {
+        let transaction = connection.transaction()?;
+        {
+            let mut insert = transaction.prepare_cached(INSERT)?;
+            for (received_at_us, swap) in iter::once(first).chain(swaps.try_iter().take(MAX_BATCH_SIZE - 1)) {
+                insert.execute(params![
+                    received_at_us as i64,
+                    swap.signature.to_string(),
+                    swap.dex.name(),
+                    swap.curve().name(),
+                    swap.discriminator,
+                    swap.amount_in as i64,
+                    swap.min_amount_out as i64,
+                    text(swap.pool_coin_account),
+                    text(swap.pool_pc_account),
+                    text(swap.model_data_account),
+                    text(swap.user_source_account),
+                    text(swap.user_destination_account),
+                    text(swap.user_owner),
+                    text(swap.source_mint),
+                    text(swap.destination_mint),
+                    swap.source_decimals,
+                    swap.destination_decimals,
+                    swap.priority_fee.compute_unit_price as i64,
+                    swap.priority_fee.compute_unit_limit,
+                    swap.priority_fee.total_lamports() as i64,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Signature;
+    use solana_sdk::transaction::TransactionVersion;
+    use crate::analyzer::Dex;
+    use crate::compute_budget::PriorityFee;
+
+    fn swap(signature: Signature, amount_in: u64) -> AnalyzedSwap {
+        AnalyzedSwap {
+            signature,
+            co_signatures: vec![],
+            dex: Dex::RaydiumV4,
+            discriminator: 9,
+            amount_in,
+            min_amount_out: 1,
+            pool_coin_account: None,
+            pool_pc_account: None,
+            model_data_account: None,
+            user_source_account: None,
+            user_destination_account: None,
+            user_owner: None,
+            source_mint: Some(Pubkey::new_unique()),
+            destination_mint: None,
+            source_decimals: Some(6),
+            destination_decimals: None,
+            transfers: vec![],
+            priority_fee: PriorityFee::default(),
+            compute_units_consumed: None,
+            lookup_tables: vec![],
+            transaction_size: 0,
+            version: TransactionVersion::LEGACY,
+            front_run_protected: false,
+            zero_min_out: false,
+            pool_fee_bps: None,
+            expected_amount_out: None,
+            realized_amount_out: None,
+            realized_amount_in: None,
+            mint_events: vec![],
+            burn_events: vec![],
+            authority_changes: vec![],
+            payer_sol_balance: None,
+            observers: None,
+            price_impact_bps: None,
+            pool_reserves: None,
+        }
+    }
+
+    #[test]
+    fn test_sqlite_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("swaps.db");
+        let signature = Signature::from([5; 64]);
+        for amount_in in [7, u64::MAX] {
+            // The second run reopens the existing database
+            let mut writer = SqliteSwapWriter::create(&path).unwrap();
+            writer.write_swap(amount_in, &swap(signature, amount_in), &[]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let connection = Connection::open(&path).unwrap();
+        let mut select = connection
+            .prepare("SELECT amount_in, source_decimals, destination_mint FROM swaps WHERE signature = ?1 ORDER BY id")
+            .unwrap();
+        let rows: Vec<(i64, Option<u8>, Option<String>)> = select
+            .query_map([signature.to_string()], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(rows, vec![(7, Some(6), None), (-1, Some(6), None)]);
+    }
+}
@@ -0,0 +1,188 @@
+//! Dedup and mint cache state kept across restarts (`--state-file`).
+//!
+//! A restarted receiver would otherwise report again the swaps forwarders are still
+//! resending, and look up again every mint it had already resolved. With a state
+//! file, the signatures of the swaps reported and the hits of the token metadata
+//! cache are written to it every `--state-flush-interval` while packets arrive, and
+//! once more on exit. At startup they are read back: a transaction the last run
+//! reported is dropped as `dedup`, and the cache starts out filled. Only the last
+//! run's signatures are dropped this way; repeats within a run are left to the
+//! arrival window as before.
+//!
+//! Each kind of entry is capped at `--state-max-entries`, the oldest signatures
+//! going first. The file is replaced by a rename, so one is never left half
+//! written. A file that can't be decoded, e.g. from another version, is ignored
+//! with a warning rather than blocking the restart.
+use std::collections::{HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::PathBuf;
+use serde_derive::{Deserialize, Serialize};
+use solana_sdk::signature::Signature;
+use crate::token_metadata::{MintCache, TokenMetadataResolver};
+
+// Bumped when the file's layout changes; files of other versions are ignored
+const STATE_VERSION: u32 = 1;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateFile {
+    version: u32,
+    /// Oldest first
+    reported: Vec<Signature>,
+    mints: MintCache,
+}
+
+#[derive(Clone, Debug)]
+pub struct StateConfig {
+    pub path: PathBuf,
+    pub flush_interval_us: u64,
+    /// Signatures, and cache hits of each kind, kept at most
+    pub max_entries: usize,
+}
+
+pub struct StateStore {
+    config: StateConfig,
+    last_flush_us: u64,
+    // Reported by the last run
+    restored: HashSet<Signature>,
+    // Reported by this run or the last, oldest first
+    reported: VecDeque<Signature>,
+    reported_set: HashSet<Signature>,
+    mints: MintCache,
+}
+
+impl StateStore {
+    /// Reads the state `config.path` holds, starting empty if there is none yet.
+    pub fn open(config: StateConfig, now_us: u64) -> io::Result<Self> {
+        let state = match File::open(&config.path) {
+            Ok(file) => match bincode::deserialize_from::<_, StateFile>(BufReader::new(file)) {
+                Ok(state) if state.version == STATE_VERSION => state,
+                Ok(state) => {
+                    eprintln!("Ignoring state file {} of version {}", config.path.display(), state.version);
+                    StateFile::default()
+                }
+                Err(e) => {
+                    eprintln!("Ignoring unreadable state file {}: {}", config.path.display(), e);
+                    StateFile::default()
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => StateFile::default(),
+            Err(e) => return Err(e),
+        };
+        let skip = state.reported.len().saturating_sub(config.max_entries);
+        let reported: VecDeque<_> = state.reported.into_iter().skip(skip).collect();
+        let reported_set: HashSet<_> = reported.iter().copied().collect();
+        Ok(Self {
+            config,
+            last_flush_us: now_us,
+            restored: reported_set.clone(),
+            reported,
+            reported_set,
+            mints: state.mints,
+        })
+    }
+
+    /// Signatures read back from the last run.
+    pub fn restored_signatures(&self) -> usize {
+        self.restored.len()
+    }
+
+    /// Fills `resolver`'s cache with the last run's lookups.
+    pub fn restore_mints(&self, resolver: &mut TokenMetadataResolver) {
+        resolver.restore(self.mints.clone());
+    }
+
+    /// Whether the last run reported the transaction with `signature`.
+    pub fn reported_before(&self, signature: &Signature) -> bool {
+        self.restored.contains(signature)
+    }
+
+    pub fn record_reported(&mut self, signature: Signature) {
+        if !self.reported_set.insert(signature) {
+            return;
+        }
+        self.reported.push_back(signature);
+        if self.reported.len() > self.config.max_entries {
+            if let Some(evicted) = self.reported.pop_front() {
+                self.reported_set.remove(&evicted);
+            }
+        }
+    }
+
+    /// Writes the state if the flush interval has passed since it last was.
+    pub fn maybe_flush(&mut self, resolver: Option<&TokenMetadataResolver>, now_us: u64) {
+        if now_us.saturating_sub(self.last_flush_us) >= self.config.flush_interval_us {
+            self.flush(resolver, now_us);
+        }
+    }
+
+    /// Writes the state, with `resolver`'s cache when it has one; without, the
+    /// cache read at startup is kept.
+    pub fn flush(&mut self, resolver: Option<&TokenMetadataResolver>, now_us: u64) {
+        self.last_flush_us = now_us;
+        if let Some(resolver) = resolver {
+            self.mints = resolver.cached(self.config.max_entries);
+        }
+        if let Err(e) = self.save() {
+            eprintln!("Unable to write state file {}: {}", self.config.path.display(), e);
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let state = StateFile {
+            version: STATE_VERSION,
+            reported: self.reported.iter().copied().collect(),
+            mints: self.mints.clone(),
+        };
+        let mut temp_path = self.config.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        bincode::serialize_into(&mut writer, &state).map_err(io::Error::other)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&temp_path, &self.config.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::SIGNATURE_BYTES;
+
+    #[test]
+    fn test_state_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = StateConfig {
+            path: dir.path().join("state.bin"),
+            flush_interval_us: 1_000,
+            max_entries: 2,
+        };
+        let (a, b, c) = (
+            Signature::from([1; SIGNATURE_BYTES]),
+            Signature::from([2; SIGNATURE_BYTES]),
+            Signature::from([3; SIGNATURE_BYTES]),
+        );
+        let mint = Pubkey::new_unique();
+
+        let mut store = StateStore::open(config.clone(), 0).unwrap();
+        assert_eq!(store.restored_signatures(), 0);
+        store.mints.decimals.push((mint, 6));
+        for signature in [a, b, a, c] {
+            store.record_reported(signature);
+        }
+        store.maybe_flush(None, 999);
+        assert!(!config.path.exists());
+        store.maybe_flush(None, 1_000);
+
+        // a was the oldest of three
+        let store = StateStore::open(config.clone(), 0).unwrap();
+        assert_eq!(store.restored_signatures(), 2);
+        assert!(!store.reported_before(&a));
+        assert!(store.reported_before(&b) && store.reported_before(&c));
+        assert_eq!(store.mints.decimals, vec![(mint, 6)]);
+
+        fs::write(&config.path, b"garbage").unwrap();
+        let store = StateStore::open(config, 0).unwrap();
+        assert_eq!(store.restored_signatures(), 0);
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use crate::analyzer::{self, Dex};
+
+/// Why a received packet never reached the outputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DropReason {
+    /// No forward header, a malformed thin payload or an undecodable transaction
+    Malformed,
+    /// Older than `--max-packet-age-ms`
+    Stale,
+    /// Forwarded before `--since-slot`
+    BeforeSlot,
+    /// Already analyzed from the other feed or by another receiver
+    Dedup,
+    /// A forward header version this receiver doesn't know
+    UnknownVersion,
+    /// A swap against none of the `--quote-mints`
+    QuoteMint,
+    /// A swap whose transaction failed on-chain, with `--drop-failed`
+    Failed,
+    /// The analyzer panicked on it
+    Panicked,
+    /// Left out of the sample while shedding load, with `--load-shed`
+    Shed,
+    /// Over its source's `--max-source-rate`, dropped before the analyzer saw it
+    RateLimited,
+    /// More accounts than `--max-accounts`
+    TooManyAccounts,
+}
+
+impl DropReason {
+    pub const ALL: [DropReason; 11] = [
+        DropReason::Malformed,
+        DropReason::Stale,
+        DropReason::BeforeSlot,
+        DropReason::Dedup,
+        DropReason::UnknownVersion,
+        DropReason::QuoteMint,
+        DropReason::Failed,
+        DropReason::Panicked,
+        DropReason::Shed,
+        DropReason::RateLimited,
+        DropReason::TooManyAccounts,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DropReason::Malformed => "malformed",
+            DropReason::Stale => "stale",
+            DropReason::BeforeSlot => "before_slot",
+            DropReason::Dedup => "dedup",
+            DropReason::UnknownVersion => "unknown_version",
+            DropReason::QuoteMint => "quote_mint",
+            DropReason::Failed => "failed",
+            DropReason::Panicked => "panicked",
+            DropReason::Shed => "shed",
+            DropReason::RateLimited => "rate_limited",
+            DropReason::TooManyAccounts => "too_many_accounts",
+        }
+    }
+}
+
+/// Counters shared between the receiver threads.
+#[derive(Debug, Default)]
+pub struct ReceiverStats {
+    pub packets_received: AtomicU64,
+    pub swaps_matched: AtomicU64,
+    /// Arrivals of a signature already seen within the arrival window
+    pub multi_path_arrivals: AtomicU64,
+    drops: [AtomicU64; DropReason::ALL.len()],
+    dex_matches: Mutex<HashMap<Dex, u64>>,
+}
+
+/// Point-in-time copy of [`ReceiverStats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    pub packets_received: u64,
+    pub swaps_matched: u64,
+    pub multi_path_arrivals: u64,
+    /// Indexed like [`DropReason::ALL`]
+    pub drops: [u64; DropReason::ALL.len()],
+    pub dex_matches: HashMap<Dex, u64>,
+    /// See [`analyzer::data_len_rejects`]
+    pub data_len_rejects: u64,
+}
+
+impl StatsSnapshot {
+    pub fn dropped(&self, reason: DropReason) -> u64 {
+        self.drops[reason as usize]
+    }
+
+    /// e.g. `12 dropped (malformed 2, stale 10, before_slot 0, dedup 0, unknown_version 0, quote_mint 0, failed 0, panicked 0, shed 0, rate_limited 0, too_many_accounts 0)`
+    pub fn drop_summary(&self) -> String {
+        let reasons: Vec<String> = DropReason::ALL
+            .iter()
+            .map(|reason| format!("{} {}", reason.name(), self.dropped(*reason)))
+            .collect();
+        format!("{} dropped ({})", self.drops.iter().sum::<u64>(), reasons.join(", "))
+    }
+}
+
+impl ReceiverStats {
+    pub fn record_match(&self, dex: Dex) {
+        self.swaps_matched.fetch_add(1, Ordering::Relaxed);
+        *self.dex_matches.lock().unwrap().entry(dex).or_default() += 1;
+    }
+
+    /// Counts a dropped packet, returning the drops for `reason` so far.
+    pub fn record_drop(&self, reason: DropReason) -> u64 {
+        self.drops[reason as usize].fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            packets_received: self.packets_received.load(Ordering::Relaxed),
+            swaps_matched: self.swaps_matched.load(Ordering::Relaxed),
+            multi_path_arrivals: self.multi_path_arrivals.load(Ordering::Relaxed),
+            drops: std::array::from_fn(|index| self.drops[index].load(Ordering::Relaxed)),
+            dex_matches: self.dex_matches.lock().unwrap().clone(),
+            data_len_rejects: analyzer::data_len_rejects(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_drop() {
+        let stats = ReceiverStats::default();
+        assert_eq!(stats.record_drop(DropReason::Stale), 1);
+        assert_eq!(stats.record_drop(DropReason::Stale), 2);
+        assert_eq!(stats.record_drop(DropReason::Dedup), 1);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.dropped(DropReason::Stale), 2);
+        assert_eq!(snapshot.dropped(DropReason::Malformed), 0);
+        assert_eq!(snapshot.drop_summary(), "3 dropped (malformed 0, stale 2, before_slot 0, dedup 1, unknown_version 0, quote_mint 0, failed 0, panicked 0, shed 0, rate_limited 0, too_many_accounts 0)");
+    }
+}
@@ -0,0 +1,119 @@
+//! Ships [`ReceiverStats`] to a statsd (or DogStatsD) agent over UDP.
+//!
+//! Every interval the counter deltas since the previous report are sent as
+//! statsd counters (`|c`) and the running totals as gauges (`|g`), newline
+//! separated in a single datagram.
+use std::fmt::Write;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use crate::stats::{DropReason, ReceiverStats, StatsSnapshot};
+
+#[derive(Clone, Debug)]
+pub struct StatsdConfig {
+    pub address: SocketAddr,
+    pub interval: Duration,
+    pub prefix: String,
+}
+
+/// Spawns a thread that reports `stats` to the statsd agent every `config.interval`.
+pub fn spawn_reporter(config: StatsdConfig, stats: Arc<ReceiverStats>) -> io::Result<JoinHandle<()>> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    thread::Builder::new()
+        .name("statsdReporter".to_string())
+        .spawn(move || {
+            let mut previous = StatsSnapshot::default();
+            loop {
+                thread::sleep(config.interval);
+                let current = stats.snapshot();
+                let payload = format_metrics(&config.prefix, &previous, &current);
+                if let Err(e) = socket.send_to(payload.as_bytes(), config.address) {
+                    eprintln!("Error sending statsd metrics: {}", e);
+                }
+                previous = current;
+            }
+        })
+}
+
+fn format_metrics(prefix: &str, previous: &StatsSnapshot, current: &StatsSnapshot) -> String {
+    let mut payload = String::new();
+    let mut push = |name: &str, previous: u64, current: u64| {
+        let _ = writeln!(payload, "{}.{}:{}|c", prefix, name, current.saturating_sub(previous));
+        let _ = writeln!(payload, "{}.{}.total:{}|g", prefix, name, current);
+    };
+    push("packets_received", previous.packets_received, current.packets_received);
+    push("swaps_matched", previous.swaps_matched, current.swaps_matched);
+    push("multi_path_arrivals", previous.multi_path_arrivals, current.multi_path_arrivals);
+    push("data_len_rejects", previous.data_len_rejects, current.data_len_rejects);
+    push("packets_dropped", previous.drops.iter().sum(), current.drops.iter().sum());
+    for reason in DropReason::ALL {
+        push(&format!("packets_dropped.{}", reason.name()), previous.dropped(reason), current.dropped(reason));
+    }
+    for (dex, count) in &current.dex_matches {
+        let before = previous.dex_matches.get(dex).copied().unwrap_or_default();
+        push(&format!("swaps_matched.{}", dex.name()), before, *count);
+    }
+    payload.pop();
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::Dex;
+
+    #[test]
+    fn test_format_metrics() {
+        let previous = StatsSnapshot {
+            packets_received: 10,
+            ..StatsSnapshot::default()
+        };
+        let current = StatsSnapshot {
+            packets_received: 25,
+            swaps_matched: 2,
+            multi_path_arrivals: 1,
+            drops: [1, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            dex_matches: [(Dex::RaydiumV4, 2)].into_iter().collect(),
+            data_len_rejects: 3,
+        };
+        assert_eq!(
+            format_metrics("hoho_recv", &previous, &current),
+            "hoho_recv.packets_received:15|c\n\
+             hoho_recv.packets_received.total:25|g\n\
+             hoho_recv.swaps_matched:2|c\n\
+             hoho_recv.swaps_matched.total:2|g\n\
+             hoho_recv.multi_path_arrivals:1|c\n\
+             hoho_recv.multi_path_arrivals.total:1|g\n\
+             hoho_recv.data_len_rejects:3|c\n\
+             hoho_recv.data_len_rejects.total:3|g\n\
+             hoho_recv.packets_dropped:4|c\n\
+             hoho_recv.packets_dropped.total:4|g\n\
+             hoho_recv.packets_dropped.malformed:1|c\n\
+             hoho_recv.packets_dropped.malformed.total:1|g\n\
+             hoho_recv.packets_dropped.stale:3|c\n\
+             hoho_recv.packets_dropped.stale.total:3|g\n\
+             hoho_recv.packets_dropped.before_slot:0|c\n\
+             hoho_recv.packets_dropped.before_slot.total:0|g\n\
+             hoho_recv.packets_dropped.dedup:0|c\n\
+             hoho_recv.packets_dropped.dedup.total:0|g\n\
+             hoho_recv.packets_dropped.unknown_version:0|c\n\
+             hoho_recv.packets_dropped.unknown_version.total:0|g\n\
+             hoho_recv.packets_dropped.quote_mint:0|c\n\
+             hoho_recv.packets_dropped.quote_mint.total:0|g\n\
+             hoho_recv.packets_dropped.failed:0|c\n\
+             hoho_recv.packets_dropped.failed.total:0|g\n\
+             hoho_recv.packets_dropped.panicked:0|c\n\
+             hoho_recv.packets_dropped.panicked.total:0|g\n\
+             hoho_recv.packets_dropped.shed:0|c\n\
+             hoho_recv.packets_dropped.shed.total:0|g\n\
+             hoho_recv.packets_dropped.rate_limited:0|c\n\
+             hoho_recv.packets_dropped.rate_limited.total:0|g\n\
+             hoho_recv.packets_dropped.too_many_accounts:0|c\n\
+             hoho_recv.packets_dropped.too_many_accounts.total:0|g\n\
+             hoho_recv.swaps_matched.raydium_v4:2|c\n\
+             hoho_recv.swaps_matched.raydium_v4.total:2|g"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Parsing of the thin payload the forwarder sends in `thin` mode:
+//!
+//! ```text
+//! signature:   [u8; 64]  (the transaction's first signature)
+//! count:       u8
+//! program_ids: [[u8; 32]; count] (distinct invoked programs, in instruction order)
+//! ```
+use solana_sdk::pubkey::{Pubkey, PUBKEY_BYTES};
+use solana_sdk::signature::{Signature, SIGNATURE_BYTES};
+use crate::amm::AmmRegistry;
+use crate::analyzer::Dex;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThinTransaction {
+    pub signature: Signature,
+    pub program_ids: Vec<Pubkey>,
+}
+
+impl ThinTransaction {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let signature = Signature::try_from(data.get(..SIGNATURE_BYTES)?).ok()?;
+        let count = *data.get(SIGNATURE_BYTES)? as usize;
+        let program_ids = data.get(SIGNATURE_BYTES + 1..)?;
+        if program_ids.len() != count * PUBKEY_BYTES {
+            return None;
+        }
+        let program_ids = program_ids
+            .chunks_exact(PUBKEY_BYTES)
+            .map(|key| Pubkey::try_from(key).unwrap())
+            .collect();
+        Some(Self { signature, program_ids })
+    }
+
+    /// Returns the first recognized DEX the transaction invokes.
+    pub fn dex(&self, amms: &AmmRegistry) -> Option<Dex> {
+        self.program_ids
+            .iter()
+            .find_map(|program_id| Dex::from_program_id(program_id, amms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_thin_transaction() {
+        let signature = Signature::from([7; SIGNATURE_BYTES]);
+        let other = Pubkey::new_unique();
+        let mut data = signature.as_ref().to_vec();
+        data.push(2);
+        data.extend_from_slice(other.as_ref());
+        data.extend_from_slice(Dex::RaydiumStable.program_id().as_ref());
+
+        let thin = ThinTransaction::parse(&data).unwrap();
+        assert_eq!(thin.signature, signature);
+        assert_eq!(thin.program_ids, vec![other, Dex::RaydiumStable.program_id()]);
+        assert_eq!(thin.dex(&AmmRegistry::default()), Some(Dex::RaydiumStable));
+
+        assert_eq!(ThinTransaction::parse(&data[..data.len() - 1]), None);
+        data[SIGNATURE_BYTES] = 1;
+        assert_eq!(ThinTransaction::parse(&data), None);
+    }
+}
@@ -0,0 +1,235 @@
+//! Per-stage timing of the analyzer (`--analyzer-timing`, feature `analyzer-timing`).
+//!
+//! Each analyzer stage is timed with the monotonic [`Instant`] clock and recorded
+//! in a log2 histogram of nanoseconds. Decoding is keyed by DEX, so the report
+//! shows which analyzers are expensive and how often each one runs, which is what
+//! the dispatch order in [`Dex::from_program_id`] should follow.
+//!
+//! Timings are collected on the thread running the analyzer once [`enable`] has
+//! been called there, and printed every report interval. Without the feature
+//! [`time`] only calls its closure, so the instrumentation compiles away.
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::time::Duration;
+#[cfg(feature = "analyzer-timing")]
+use std::cell::RefCell;
+#[cfg(feature = "analyzer-timing")]
+use std::time::Instant;
+use crate::analyzer::Dex;
+
+const BUCKETS: usize = 64;
+
+/// A timed part of [`analyze_transaction`](crate::analyzer::analyze_transaction).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Deserialize,
+    Sanitize,
+    LookupTables,
+    /// Matching one instruction's program id against the known DEXes
+    Dispatch,
+    /// Decoding a swap instruction of a DEX
+    Decode(Dex),
+    Transfers,
+    TokenTransfers,
+    TokenMints,
+    PoolFees,
+    PayerBalance,
+    /// Fetching a confirmed transaction to find a swap made through CPI
+    Cpi,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stage::Deserialize => write!(f, "deserialize"),
+            Stage::Sanitize => write!(f, "sanitize"),
+            Stage::LookupTables => write!(f, "lookup_tables"),
+            Stage::Dispatch => write!(f, "dispatch"),
+            Stage::Decode(dex) => write!(f, "decode.{}", dex.name()),
+            Stage::Transfers => write!(f, "transfers"),
+            Stage::TokenTransfers => write!(f, "token_transfers"),
+            Stage::TokenMints => write!(f, "token_mints"),
+            Stage::PoolFees => write!(f, "pool_fees"),
+            Stage::PayerBalance => write!(f, "payer_balance"),
+            Stage::Cpi => write!(f, "cpi"),
+        }
+    }
+}
+
+/// Durations bucketed by the position of their highest set bit in nanoseconds.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    buckets: [u64; BUCKETS],
+    count: u64,
+    total_ns: u64,
+    max_ns: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; BUCKETS],
+            count: 0,
+            total_ns: 0,
+            max_ns: 0,
+        }
+    }
+}
+
+impl Histogram {
+    pub fn record(&mut self, elapsed: Duration) {
+        let ns = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        let bucket = (u64::BITS - ns.leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)] += 1;
+        self.count += 1;
+        self.total_ns = self.total_ns.saturating_add(ns);
+        self.max_ns = self.max_ns.max(ns);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean_ns(&self) -> u64 {
+        self.total_ns.checked_div(self.count).unwrap_or_default()
+    }
+
+    /// Upper bound of the bucket containing the `percentile`th duration.
+    pub fn percentile_ns(&self, percentile: f64) -> u64 {
+        let rank = ((self.count as f64 * percentile / 100.0).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return (1u64 << bucket).saturating_sub(1).min(self.max_ns);
+            }
+        }
+        self.max_ns
+    }
+}
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "count {:8} mean {:8}ns p50 {:8}ns p99 {:8}ns max {:8}ns",
+            self.count(),
+            self.mean_ns(),
+            self.percentile_ns(50.0),
+            self.percentile_ns(99.0),
+            self.max_ns,
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct AnalyzerTimings {
+    stages: HashMap<Stage, Histogram>,
+}
+
+impl AnalyzerTimings {
+    pub fn record(&mut self, stage: Stage, elapsed: Duration) {
+        self.stages.entry(stage).or_default().record(elapsed);
+    }
+
+    /// One line per stage, most total time first.
+    pub fn report(&self) -> String {
+        let mut stages: Vec<_> = self.stages.iter().collect();
+        stages.sort_by_key(|(_, histogram)| std::cmp::Reverse(histogram.total_ns));
+        let mut report = String::new();
+        for (stage, histogram) in stages {
+            let _ = writeln!(report, "  {:24} {}", stage.to_string(), histogram);
+        }
+        report
+    }
+}
+
+#[cfg(feature = "analyzer-timing")]
+struct Collector {
+    timings: AnalyzerTimings,
+    interval: Duration,
+    last_report: Instant,
+}
+
+#[cfg(feature = "analyzer-timing")]
+thread_local! {
+    static COLLECTOR: RefCell<Option<Collector>> = const { RefCell::new(None) };
+}
+
+/// Starts collecting timings on the calling thread, printing them every `interval`.
+#[cfg(feature = "analyzer-timing")]
+pub fn enable(interval: Duration) {
+    COLLECTOR.with_borrow_mut(|collector| {
+        *collector = Some(Collector {
+            timings: AnalyzerTimings::default(),
+            interval,
+            last_report: Instant::now(),
+        })
+    });
+}
+
+/// Runs `f`, recording its duration under `stage` if timing is enabled.
+#[cfg(feature = "analyzer-timing")]
+#[inline]
+pub fn time<T>(stage: Stage, f: impl FnOnce() -> T) -> T {
+    if COLLECTOR.with_borrow(Option::is_none) {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    COLLECTOR.with_borrow_mut(|collector| {
+        let Some(collector) = collector else {
+            return;
+        };
+        collector.timings.record(stage, elapsed);
+        if collector.last_report.elapsed() >= collector.interval {
+            print!(
+                "Analyzer timing over the last {}s:\n{}",
+                collector.last_report.elapsed().as_secs(),
+                collector.timings.report()
+            );
+            collector.timings = AnalyzerTimings::default();
+            collector.last_report = Instant::now();
+        }
+    });
+    result
+}
+
+/// Stops collecting on the calling thread, returning the timings not yet reported.
+#[cfg(feature = "analyzer-timing")]
+pub fn take() -> Option<AnalyzerTimings> {
+    COLLECTOR.with_borrow_mut(|collector| collector.take().map(|collector| collector.timings))
+}
+
+#[cfg(not(feature = "analyzer-timing"))]
+#[inline(always)]
+pub fn time<T>(_stage: Stage, f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::default();
+        for ns in [100, 120, 130, 900, 5_000] {
+            histogram.record(Duration::from_nanos(ns));
+        }
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.mean_ns(), 1_250);
+        // The median, 130ns, is reported as its bucket's upper bound
+        assert_eq!(histogram.percentile_ns(50.0), 255);
+        assert_eq!(histogram.percentile_ns(99.0), 5_000);
+        assert_eq!(Histogram::default().percentile_ns(50.0), 0);
+
+        let mut timings = AnalyzerTimings::default();
+        timings.record(Stage::Decode(Dex::RaydiumV4), Duration::from_nanos(10));
+        timings.record(Stage::Deserialize, Duration::from_micros(10));
+        let report = timings.report();
+        assert!(report.starts_with("  deserialize "));
+        assert!(report.contains("decode.raydium_v4"));
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use serde_derive::{Deserialize, Serialize};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use crate::program_ids;
+
+// Metaplex Token Metadata program
+pub const TOKEN_METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+// SPL token account layout: mint (32) | owner (32) | amount (8) | ...
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+
+// SPL mint layout: mint_authority (4 + 32) | supply (8) | decimals (1) | ...
+const MINT_DECIMALS_OFFSET: usize = 4 + 32 + 8;
+
+// Metadata account layout: key (1) | update_authority (32) | mint (32) | name | symbol | ...
+const METADATA_NAME_OFFSET: usize = 1 + 32 + 32;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+}
+
+impl fmt::Display for TokenMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {}", self.symbol, self.name)
+    }
+}
+
+/// Derives the Metaplex metadata PDA for `mint`.
+pub fn find_metadata_address(mint: &Pubkey) -> Pubkey {
+    let program_id = program_ids::builtin_program_id(TOKEN_METADATA_PROGRAM_ID);
+    Pubkey::find_program_address(
+        &[b"metadata", program_id.as_ref(), mint.as_ref()],
+        &program_id,
+    )
+    .0
+}
+
+/// Decodes the name and symbol from a Metaplex metadata account.
+///
+/// Both fields are borsh strings (u32 length prefix) padded with trailing NULs.
+pub fn decode_metadata(data: &[u8]) -> Option<TokenMetadata> {
+    let (name, rest) = read_borsh_string(data.get(METADATA_NAME_OFFSET..)?)?;
+    let (symbol, _) = read_borsh_string(rest)?;
+    Some(TokenMetadata { name, symbol })
+}
+
+fn read_borsh_string(data: &[u8]) -> Option<(String, &[u8])> {
+    let len = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let bytes = data.get(4..4usize.checked_add(len)?)?;
+    let value = String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .trim()
+        .to_string();
+    Some((value, &data[4 + len..]))
+}
+
+/// The lookups a [`TokenMetadataResolver`] found, to carry its cache across
+/// restarts. Misses aren't kept, as the account may exist by the next run.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MintCache {
+    pub token_account_mints: Vec<(Pubkey, Pubkey)>,
+    pub decimals: Vec<(Pubkey, u8)>,
+    pub metadata: Vec<(Pubkey, TokenMetadata)>,
+}
+
+/// Resolves token accounts to their mint and mints to their Metaplex metadata,
+/// caching every lookup (including misses) so each key costs at most one RPC call.
+pub struct TokenMetadataResolver {
+    rpc_client: Arc<RpcClient>,
+    mints: HashMap<Pubkey, Option<Pubkey>>,
+    decimals: HashMap<Pubkey, Option<u8>>,
+    metadata: HashMap<Pubkey, Option<TokenMetadata>>,
+}
+
+impl TokenMetadataResolver {
+    pub fn new(rpc_client: Arc<RpcClient>) -> Self {
+        Self::with_capacity(rpc_client, 0)
+    }
+
+    /// Sizes each cache for `capacity` keys up front.
+    pub fn with_capacity(rpc_client: Arc<RpcClient>, capacity: usize) -> Self {
+        Self {
+            rpc_client,
+            mints: HashMap::with_capacity(capacity),
+            decimals: HashMap::with_capacity(capacity),
+            metadata: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// The cache's hits, at most `max_entries` of each kind.
+    pub fn cached(&self, max_entries: usize) -> MintCache {
+        fn hits<K: Copy, V: Clone>(cache: &HashMap<K, Option<V>>, max_entries: usize) -> Vec<(K, V)> {
+            cache
+                .iter()
+                .filter_map(|(key, value)| Some((*key, value.clone()?)))
+                .take(max_entries)
+                .collect()
+        }
+        MintCache {
+            token_account_mints: hits(&self.mints, max_entries),
+            decimals: hits(&self.decimals, max_entries),
+            metadata: hits(&self.metadata, max_entries),
+        }
+    }
+
+    /// Fills the cache with lookups from an earlier run.
+    pub fn restore(&mut self, cache: MintCache) {
+        self.mints
+            .extend(cache.token_account_mints.into_iter().map(|(account, mint)| (account, Some(mint))));
+        self.decimals.extend(cache.decimals.into_iter().map(|(mint, decimals)| (mint, Some(decimals))));
+        self.metadata.extend(cache.metadata.into_iter().map(|(mint, metadata)| (mint, Some(metadata))));
+    }
+
+    /// Returns the mint of an SPL token account.
+    pub fn token_account_mint(&mut self, token_account: &Pubkey) -> Option<Pubkey> {
+        if let Some(mint) = self.mints.get(token_account) {
+            return *mint;
+        }
+        let mint = self.fetch_account_data(token_account)?.and_then(|data| {
+            let bytes = data.get(TOKEN_ACCOUNT_MINT_OFFSET..TOKEN_ACCOUNT_MINT_OFFSET + 32)?;
+            Pubkey::try_from(bytes).ok()
+        });
+        self.mints.insert(*token_account, mint);
+        mint
+    }
+
+    /// Returns the decimals of an SPL mint.
+    pub fn mint_decimals(&mut self, mint: &Pubkey) -> Option<u8> {
+        if let Some(decimals) = self.decimals.get(mint) {
+            return *decimals;
+        }
+        let decimals = self
+            .fetch_account_data(mint)?
+            .and_then(|data| data.get(MINT_DECIMALS_OFFSET).copied());
+        self.decimals.insert(*mint, decimals);
+        decimals
+    }
+
+    /// Returns the metadata of `mint`, or `None` if the mint has no metadata account.
+    pub fn metadata(&mut self, mint: &Pubkey) -> Option<&TokenMetadata> {
+        if !self.metadata.contains_key(mint) {
+            let metadata = self
+                .fetch_account_data(&find_metadata_address(mint))?
+                .and_then(|data| decode_metadata(&data));
+            self.metadata.insert(*mint, metadata);
+        }
+        self.metadata.get(mint)?.as_ref()
+    }
+
+    /// Formats `mint` with its symbol and name when known, e.g. `<mint> (BONK - Bonk)`.
+    pub fn describe_mint(&mut self, mint: &Pubkey) -> String {
+        match self.metadata(mint) {
+            Some(metadata) => format!("{} ({})", mint, metadata),
+            None => mint.to_string(),
+        }
+    }
+
+    // Returns `None` on RPC errors (not cached, retried next time) and
+    // `Some(None)` when the account does not exist (cached as a miss).
+    fn fetch_account_data(&self, pubkey: &Pubkey) -> Option<Option<Vec<u8>>> {
+        match self
+            .rpc_client
+            .get_account_with_commitment(pubkey, self.rpc_client.commitment())
+        {
+            Ok(response) => Some(response.value.map(|account| account.data)),
+            Err(e) => {
+                eprintln!("Error fetching account {}: {}", pubkey, e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! Decoding of SPL Token `transfer` and `transferChecked` instructions, the token
+//! movements behind a swap.
+//!
+//! `transferChecked` carries the mint and its decimals next to the amount, so a
+//! swap whose movements use it can be scaled without looking the mint up over RPC.
+//! Plain `transfer` only carries the raw amount. The movements are usually inner
+//! instructions of the swap, known only for confirmed transactions, but wallets
+//! often move tokens with top-level `transferChecked` too.
+//!
+//! `mintTo` and `mintToChecked` are decoded alongside them as [`MintEvent`]s: new
+//! supply landing in one account next to a swap is how launches and rug pulls look.
+//! So is `setAuthority` on a mint, decoded as an [`AuthorityChange`]: revoking the
+//! mint or freeze authority is what a launch does to look safe, and handing it to a
+//! new key right before a swap is a warning sign. `burn` and `burnChecked`, decoded
+//! as [`BurnEvent`]s, round out the supply changes: a large burn reshapes a token's
+//! supply and often comes before its price moves.
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::pubkey::Pubkey;
+use crate::program_ids;
+
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+
+// Token instruction discriminators, shared by Token-2022
+const TRANSFER: u8 = 3;
+const SET_AUTHORITY: u8 = 6;
+const MINT_TO: u8 = 7;
+const BURN: u8 = 8;
+const TRANSFER_CHECKED: u8 = 12;
+const MINT_TO_CHECKED: u8 = 14;
+const BURN_CHECKED: u8 = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenTransfer {
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    /// `transferChecked` only
+    pub mint: Option<Pubkey>,
+    /// `transferChecked` only
+    pub decimals: Option<u8>,
+}
+
+/// New supply of `mint` credited to `destination`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MintEvent {
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    /// `mintToChecked` only
+    pub decimals: Option<u8>,
+}
+
+/// Supply of `mint` destroyed from the token account `account`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BurnEvent {
+    pub mint: Pubkey,
+    pub account: Pubkey,
+    pub amount: u64,
+    /// `burnChecked` only
+    pub decimals: Option<u8>,
+}
+
+/// The `AuthorityType` of a `setAuthority` instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthorityType {
+    MintTokens,
+    FreezeAccount,
+    AccountOwner,
+    CloseAccount,
+    /// One of Token-2022's extension authorities, by its `AuthorityType` value
+    Extension(u8),
+}
+
+impl AuthorityType {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => AuthorityType::MintTokens,
+            1 => AuthorityType::FreezeAccount,
+            2 => AuthorityType::AccountOwner,
+            3 => AuthorityType::CloseAccount,
+            extension => AuthorityType::Extension(extension),
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            AuthorityType::MintTokens => "mint_tokens".to_string(),
+            AuthorityType::FreezeAccount => "freeze_account".to_string(),
+            AuthorityType::AccountOwner => "account_owner".to_string(),
+            AuthorityType::CloseAccount => "close_account".to_string(),
+            AuthorityType::Extension(value) => format!("extension_{}", value),
+        }
+    }
+}
+
+/// The authority of `account`, a mint or token account, changing to
+/// `new_authority`, or revoked for `None`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuthorityChange {
+    pub account: Pubkey,
+    pub authority_type: AuthorityType,
+    pub new_authority: Option<Pubkey>,
+}
+
+impl AuthorityChange {
+    /// Whether it changes who can mint or freeze the token.
+    pub fn is_mint_or_freeze(&self) -> bool {
+        matches!(self.authority_type, AuthorityType::MintTokens | AuthorityType::FreezeAccount)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenEvent {
+    Transfer(TokenTransfer),
+    Mint(MintEvent),
+    Burn(BurnEvent),
+    Authority(AuthorityChange),
+}
+
+/// Returns every Token or Token-2022 transfer in `instructions` whose accounts resolve.
+pub fn decode_token_transfers<'a>(
+    account_keys: &[Pubkey],
+    instructions: impl IntoIterator<Item = &'a CompiledInstruction>,
+) -> Vec<TokenTransfer> {
+    decode_token_events(account_keys, instructions)
+        .into_iter()
+        .filter_map(|event| match event {
+            TokenEvent::Transfer(transfer) => Some(transfer),
+            TokenEvent::Mint(_) | TokenEvent::Burn(_) | TokenEvent::Authority(_) => None,
+        })
+        .collect()
+}
+
+/// Returns every Token or Token-2022 transfer, mint, burn and authority change in
+/// `instructions` whose accounts resolve, in order.
+pub fn decode_token_events<'a>(
+    account_keys: &[Pubkey],
+    instructions: impl IntoIterator<Item = &'a CompiledInstruction>,
+) -> Vec<TokenEvent> {
+    let token_programs = [
+        program_ids::builtin_program_id(TOKEN_PROGRAM_ID),
+        program_ids::builtin_program_id(TOKEN_2022_PROGRAM_ID),
+    ];
+    instructions
+        .into_iter()
+        .filter(|ix| {
+            account_keys
+                .get(ix.program_id_index as usize)
+                .is_some_and(|program_id| token_programs.contains(program_id))
+        })
+        .filter_map(|ix| {
+            let account = |position: usize| account_keys.get(*ix.accounts.get(position)? as usize).copied();
+            let amount = || Some(u64::from_le_bytes(ix.data.get(1..9)?.try_into().unwrap()));
+            match *ix.data.first()? {
+                // source | destination | authority
+                TRANSFER if ix.data.len() == 9 => Some(TokenEvent::Transfer(TokenTransfer {
+                    source: account(0)?,
+                    destination: account(1)?,
+                    amount: amount()?,
+                    mint: None,
+                    decimals: None,
+                })),
+                // source | mint | destination | authority
+                TRANSFER_CHECKED if ix.data.len() == 10 => Some(TokenEvent::Transfer(TokenTransfer {
+                    source: account(0)?,
+                    destination: account(2)?,
+                    amount: amount()?,
+                    mint: Some(account(1)?),
+                    decimals: Some(ix.data[9]),
+                })),
+                // mint | destination | authority
+                MINT_TO if ix.data.len() == 9 => Some(TokenEvent::Mint(MintEvent {
+                    mint: account(0)?,
+                    destination: account(1)?,
+                    amount: amount()?,
+                    decimals: None,
+                })),
+                MINT_TO_CHECKED if ix.data.len() == 10 => Some(TokenEvent::Mint(MintEvent {
+                    mint: account(0)?,
+                    destination: account(1)?,
+                    amount: amount()?,
+                    decimals: Some(ix.data[9]),
+                })),
+                // account | mint | authority
+                BURN if ix.data.len() == 9 => Some(TokenEvent::Burn(BurnEvent {
+                    mint: account(1)?,
+                    account: account(0)?,
+                    amount: amount()?,
+                    decimals: None,
+                })),
+                BURN_CHECKED if ix.data.len() == 10 => Some(TokenEvent::Burn(BurnEvent {
+                    mint: account(1)?,
+                    account: account(0)?,
+                    amount: amount()?,
+                    decimals: Some(ix.data[9]),
+                })),
+                // account | current authority; the new authority is a COption tag and key
+                SET_AUTHORITY => {
+                    let new_authority = match ix.data.get(2..)? {
+                        [0] => None,
+                        [1, key @ ..] if key.len() == 32 => Some(Pubkey::try_from(key).unwrap()),
+                        _ => return None,
+                    };
+                    Some(TokenEvent::Authority(AuthorityChange {
+                        account: account(0)?,
+                        authority_type: AuthorityType::from_u8(ix.data[1]),
+                        new_authority,
+                    }))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Formats a raw token amount in whole tokens, exactly, e.g. `1500000` with 6
+/// decimals is `1.5`.
+pub fn ui_amount(amount: u64, decimals: u8) -> String {
+    let decimals = decimals as usize;
+    let digits = format!("{:0>width$}", amount, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_token_transfers() {
+        let source = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let token_program = program_ids::builtin_program_id(TOKEN_PROGRAM_ID);
+        let account_keys = vec![source, destination, mint, owner, token_program];
+        let data = |discriminator: u8, decimals: Option<u8>| {
+            let mut data = vec![discriminator];
+            data.extend_from_slice(&2_500_000u64.to_le_bytes());
+            data.extend(decimals);
+            data
+        };
+        let instructions = vec![
+            CompiledInstruction::new_from_raw_parts(4, data(TRANSFER_CHECKED, Some(6)), vec![0, 2, 1, 3]),
+            CompiledInstruction::new_from_raw_parts(4, data(TRANSFER, None), vec![1, 0, 3]),
+            // Same data under another program is not a token transfer
+            CompiledInstruction::new_from_raw_parts(3, data(TRANSFER, None), vec![1, 0, 3]),
+            // transferChecked without its decimals
+            CompiledInstruction::new_from_raw_parts(4, data(TRANSFER_CHECKED, None), vec![0, 2, 1, 3]),
+            CompiledInstruction::new_from_raw_parts(4, data(MINT_TO_CHECKED, Some(6)), vec![2, 1, 3]),
+        ];
+        assert_eq!(
+            decode_token_transfers(&account_keys, &instructions),
+            vec![
+                TokenTransfer {
+                    source,
+                    destination,
+                    amount: 2_500_000,
+                    mint: Some(mint),
+                    decimals: Some(6),
+                },
+                TokenTransfer {
+                    source: destination,
+                    destination: source,
+                    amount: 2_500_000,
+                    mint: None,
+                    decimals: None,
+                },
+            ]
+        );
+        assert_eq!(
+            decode_token_events(&account_keys, &instructions).last(),
+            Some(&TokenEvent::Mint(MintEvent {
+                mint,
+                destination,
+                amount: 2_500_000,
+                decimals: Some(6),
+            }))
+        );
+        let mint_to = [CompiledInstruction::new_from_raw_parts(4, data(MINT_TO, None), vec![2, 1, 3])];
+        assert_eq!(decode_token_transfers(&account_keys, &mint_to), vec![]);
+    }
+
+    #[test]
+    fn test_decode_burn_events() {
+        let account = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let account_keys = vec![
+            account,
+            mint,
+            owner,
+            program_ids::builtin_program_id(TOKEN_PROGRAM_ID),
+            program_ids::builtin_program_id(TOKEN_2022_PROGRAM_ID),
+        ];
+        let amount = 7_000u64.to_le_bytes();
+        let instructions = vec![
+            CompiledInstruction::new_from_raw_parts(3, [&[BURN][..], &amount].concat(), vec![0, 1, 2]),
+            CompiledInstruction::new_from_raw_parts(4, [&[BURN_CHECKED][..], &amount, &[9]].concat(), vec![0, 1, 2]),
+            // burnChecked without its decimals
+            CompiledInstruction::new_from_raw_parts(3, [&[BURN_CHECKED][..], &amount].concat(), vec![0, 1, 2]),
+        ];
+        let burn = |decimals| {
+            TokenEvent::Burn(BurnEvent {
+                mint,
+                account,
+                amount: 7_000,
+                decimals,
+            })
+        };
+        assert_eq!(decode_token_events(&account_keys, &instructions), vec![burn(None), burn(Some(9))]);
+    }
+
+    #[test]
+    fn test_decode_authority_changes() {
+        let mint = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let new_authority = Pubkey::new_unique();
+        let account_keys = vec![
+            mint,
+            authority,
+            program_ids::builtin_program_id(TOKEN_PROGRAM_ID),
+            program_ids::builtin_program_id(TOKEN_2022_PROGRAM_ID),
+        ];
+        let instructions = vec![
+            // Revoke the mint authority
+            CompiledInstruction::new_from_raw_parts(2, vec![SET_AUTHORITY, 0, 0], vec![0, 1]),
+            // Hand the freeze authority to a new key
+            CompiledInstruction::new_from_raw_parts(
+                2,
+                [&[SET_AUTHORITY, 1, 1][..], new_authority.as_ref()].concat(),
+                vec![0, 1],
+            ),
+            // A Token-2022 extension authority
+            CompiledInstruction::new_from_raw_parts(3, vec![SET_AUTHORITY, 8, 0], vec![0, 1]),
+            // Truncated new authority
+            CompiledInstruction::new_from_raw_parts(2, vec![SET_AUTHORITY, 0, 1, 7], vec![0, 1]),
+        ];
+        let changes: Vec<AuthorityChange> = decode_token_events(&account_keys, &instructions)
+            .into_iter()
+            .filter_map(|event| match event {
+                TokenEvent::Authority(change) => Some(change),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                AuthorityChange {
+                    account: mint,
+                    authority_type: AuthorityType::MintTokens,
+                    new_authority: None,
+                },
+                AuthorityChange {
+                    account: mint,
+                    authority_type: AuthorityType::FreezeAccount,
+                    new_authority: Some(new_authority),
+                },
+                AuthorityChange {
+                    account: mint,
+                    authority_type: AuthorityType::Extension(8),
+                    new_authority: None,
+                },
+            ]
+        );
+        assert!(changes[0].is_mint_or_freeze() && changes[1].is_mint_or_freeze());
+        assert!(!changes[2].is_mint_or_freeze());
+        assert_eq!(changes[2].authority_type.name(), "extension_8");
+    }
+
+    #[test]
+    fn test_ui_amount() {
+        assert_eq!(ui_amount(2_500_000, 6), "2.5");
+        assert_eq!(ui_amount(1, 9), "0.000000001");
+        assert_eq!(ui_amount(42, 0), "42");
+        assert_eq!(ui_amount(7_000_000, 6), "7");
+        assert_eq!(ui_amount(u64::MAX, 30), "0.000000000018446744073709551615");
+    }
+}
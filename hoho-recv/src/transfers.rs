@@ -0,0 +1,68 @@
+//! Decoding of System program SOL transfers, run as a supplementary pass over
+//! transactions that already matched a swap so funding flows and tips show up
+//! next to the trade. Transfers alone never make a transaction match.
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::program_utils::limited_deserialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::system_program;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SolTransfer {
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub lamports: u64,
+}
+
+/// Returns every System `transfer` in `instructions` whose accounts resolve.
+pub fn decode_sol_transfers(
+    account_keys: &[Pubkey],
+    instructions: &[CompiledInstruction],
+) -> Vec<SolTransfer> {
+    instructions
+        .iter()
+        .filter(|ix| account_keys.get(ix.program_id_index as usize) == Some(&system_program::id()))
+        .filter_map(|ix| {
+            let SystemInstruction::Transfer { lamports } = limited_deserialize(&ix.data).ok()? else {
+                return None;
+            };
+            let account = |position: usize| account_keys.get(*ix.accounts.get(position)? as usize).copied();
+            Some(SolTransfer {
+                source: account(0)?,
+                destination: account(1)?,
+                lamports,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::system_instruction;
+
+    #[test]
+    fn test_decode_sol_transfers() {
+        let payer = Pubkey::new_unique();
+        let tip = Pubkey::new_unique();
+        let account_keys = vec![payer, tip, system_program::id()];
+        let transfer = system_instruction::transfer(&payer, &tip, 10_000);
+        let assign = system_instruction::assign(&payer, &tip);
+        let instructions = vec![
+            CompiledInstruction::new_from_raw_parts(2, assign.data, vec![0]),
+            CompiledInstruction::new_from_raw_parts(2, transfer.data.clone(), vec![0, 1]),
+            // Same data under another program is not a SOL transfer
+            CompiledInstruction::new_from_raw_parts(1, transfer.data.clone(), vec![0, 1]),
+            // Unresolved destination
+            CompiledInstruction::new_from_raw_parts(2, transfer.data, vec![0, 9]),
+        ];
+        assert_eq!(
+            decode_sol_transfers(&account_keys, &instructions),
+            vec![SolTransfer {
+                source: payer,
+                destination: tip,
+                lamports: 10_000,
+            }]
+        );
+    }
+}
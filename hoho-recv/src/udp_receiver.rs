@@ -0,0 +1,159 @@
+//! Receiving forwarded datagrams on the receiver's UDP socket.
+//!
+//! [`spawn`] receives on the bound socket from a thread of its own, recording each
+//! datagram to the capture file if there is one and handing it to the analyzer
+//! through a channel the analyzer owns outright, and other feeds may share. With the default `async-recv`
+//! feature the socket is a `tokio::net::UdpSocket` driven by a single-threaded
+//! runtime; without it, as for constrained builds that leave out Tokio, it is a
+//! blocking `std::net::UdpSocket`. Either way the analyzer thread sees the same
+//! channel. With `--max-source-rate`, datagrams over their source's rate are
+//! dropped here, before they are captured, see [`rate_limit`](crate::rate_limit).
+//!
+//! To compare the two under load, build both and replay a capture at full speed
+//! into each with `hoho-recv replay --speed 0 --loop`, watching the statsd
+//! packet counters.
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use crate::capture::{self, FileCaptureWriter};
+use crate::rate_limit::SourceRateLimiter;
+use crate::stats::{DropReason, ReceiverStats};
+
+// Larger than any UDP datagram
+const MAX_DATAGRAM_SIZE: usize = 64 * 1024;
+
+/// A datagram handed to the analyzer, with the address it came from; `None` for
+/// feeds other than the receive socket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Datagram {
+    pub data: Vec<u8>,
+    pub source: Option<SocketAddr>,
+}
+
+/// What happens to a received datagram before it is sent to the analyzer.
+pub struct Intake {
+    pub capture: Option<FileCaptureWriter>,
+    pub rate_limiter: Option<SourceRateLimiter>,
+    /// Counts the datagrams dropped over their source's rate
+    pub stats: Arc<ReceiverStats>,
+}
+
+/// Starts receiving on `socket`, sending datagrams to `sender`. The thread exits
+/// once the receiving end of the channel is dropped.
+pub fn spawn(socket: UdpSocket, intake: Intake, sender: Sender<Datagram>) -> io::Result<JoinHandle<()>> {
+    #[cfg(feature = "async-recv")]
+    let receive = {
+        socket.set_nonblocking(true)?;
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build()?;
+        move || runtime.block_on(receive_async(socket, intake, sender))
+    };
+    #[cfg(not(feature = "async-recv"))]
+    let receive = move || receive_blocking(socket, intake, sender);
+    thread::Builder::new().name("udpReceiver".to_string()).spawn(receive)
+}
+
+#[cfg(feature = "async-recv")]
+async fn receive_async(socket: UdpSocket, mut intake: Intake, sender: Sender<Datagram>) {
+    let socket = match tokio::net::UdpSocket::from_std(socket) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Error registering the receive socket: {}", e);
+            return;
+        }
+    };
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+    loop {
+        match socket.recv_from(&mut buf).await {
+            Ok((size, source)) => {
+                if !deliver(&buf[..size], source, &mut intake, &sender) {
+                    break;
+                }
+            }
+            Err(e) => eprintln!("Error receiving data: {}", e),
+        }
+    }
+}
+
+#[cfg(not(feature = "async-recv"))]
+fn receive_blocking(socket: UdpSocket, mut intake: Intake, sender: Sender<Datagram>) {
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+    loop {
+        match socket.recv_from(&mut buf) {
+            Ok((size, source)) => {
+                if !deliver(&buf[..size], source, &mut intake, &sender) {
+                    break;
+                }
+            }
+            Err(e) => eprintln!("Error receiving data: {}", e),
+        }
+    }
+}
+
+/// Captures and sends one datagram, unless over its source's rate, returning false
+/// once the analyzer is gone.
+fn deliver(data: &[u8], source: SocketAddr, intake: &mut Intake, sender: &Sender<Datagram>) -> bool {
+    if let Some(limiter) = intake.rate_limiter.as_mut() {
+        if !limiter.allow(source.ip(), capture::now_us()) {
+            let dropped = intake.stats.record_drop(DropReason::RateLimited);
+            // A flood would otherwise flood the log too
+            if dropped.is_power_of_two() {
+                eprintln!("Dropping datagrams from {} over --max-source-rate, {} dropped so far", source.ip(), dropped);
+            }
+            return true;
+        }
+    }
+    let capture = &mut intake.capture;
+    if let Some(writer) = capture.as_mut() {
+        // Flush per frame so the capture survives the process exiting on a match
+        if let Err(e) = writer
+            .write_frame(capture::now_us(), data)
+            .and_then(|()| writer.flush())
+        {
+            eprintln!("Error writing capture, capture disabled: {}", e);
+            *capture = None;
+        }
+    }
+    let datagram = Datagram {
+        data: data.to_vec(),
+        source: Some(source),
+    };
+    if let Err(e) = sender.send(datagram) {
+        eprintln!("Error sending to channel: {}", e);
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_spawn_receives_datagrams() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let stats = Arc::new(ReceiverStats::default());
+        let intake = Intake {
+            capture: None,
+            rate_limiter: Some(SourceRateLimiter::new(2, 16)),
+            stats: stats.clone(),
+        };
+        let _receiver_thread = spawn(socket, intake, sender).unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for datagram in [&b"swap"[..], &[7; 1232]] {
+            sender.send_to(datagram, address).unwrap();
+            let received = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(received.data, datagram);
+            assert_eq!(received.source, Some(sender.local_addr().unwrap()));
+        }
+        // The source's burst is used up
+        sender.send_to(b"flood", address).unwrap();
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+        assert_eq!(stats.snapshot().dropped(DropReason::RateLimited), 1);
+    }
+}
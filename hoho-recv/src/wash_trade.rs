@@ -0,0 +1,296 @@
+//! Detection of suspected wash trades (`--detect-wash-trades`).
+//!
+//! A wallet that buys a mint and sells about the same amount of it back within a
+//! short window moves volume without changing its position, the pattern of trading
+//! with oneself to inflate a token's activity. Every swap sells its source mint and
+//! buys its destination mint, so each is recorded as two legs keyed by the signing
+//! owner and the mint. A leg that meets an opposite leg of the same key within the
+//! window, with amounts within the size tolerance of each other, raises a
+//! [`WashTradeSuspected`] linking both signatures. The matched leg is used up, so a
+//! buy, sell, buy sequence raises two events chaining the three swaps.
+//!
+//! SOL and the USD stablecoins are what such trades are paid in rather than what
+//! they inflate, so legs of those mints are not tracked. Swaps with an unknown
+//! owner or mint are skipped, which makes `--resolve-token-metadata` worthwhile.
+//! Like the rate-of-change alerts, amounts are the ones signed for (`amount_in` and
+//! `min_amount_out`) unless the realized amount is known.
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use crate::analyzer::AnalyzedSwap;
+use crate::quote_filter::{USDC_MINT, USDT_MINT, WSOL_MINT};
+
+// Bounds memory during bursts regardless of the window length
+const MAX_TRACKED_LEGS: usize = 100_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WashTradeConfig {
+    pub window_us: u64,
+    /// How far apart, in percent of the larger, a buy and a sell can be and still match
+    pub size_tolerance_percent: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WashTradeSuspected {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    /// The earlier swap's side, the later one took the other
+    pub first_side: Side,
+    /// The earlier and the later swap
+    pub signatures: [Signature; 2],
+    pub amounts: [u64; 2],
+    pub elapsed_us: u64,
+}
+
+impl fmt::Display for WashTradeSuspected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (first, second) = match self.first_side {
+            Side::Buy => ("bought", "sold"),
+            Side::Sell => ("sold", "bought"),
+        };
+        write!(
+            f,
+            "WASH TRADE SUSPECTED {} {} {} {} then {} {} {}ms later ({} -> {})",
+            self.wallet,
+            first,
+            self.amounts[0],
+            self.mint,
+            second,
+            self.amounts[1],
+            self.elapsed_us / 1_000,
+            self.signatures[0],
+            self.signatures[1]
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Leg {
+    id: u64,
+    at_us: u64,
+    side: Side,
+    amount: u64,
+    signature: Signature,
+}
+
+// (owner, mint)
+type LegKey = (Pubkey, Pubkey);
+
+pub struct WashTradeDetector {
+    config: WashTradeConfig,
+    untracked_mints: [Pubkey; 3],
+    legs: HashMap<LegKey, VecDeque<Leg>>,
+    // (id, recorded at, key) in recording order, for expiry
+    recorded: VecDeque<(u64, u64, LegKey)>,
+    next_id: u64,
+}
+
+impl WashTradeDetector {
+    pub fn new(config: WashTradeConfig) -> Self {
+        Self {
+            config,
+            untracked_mints: [WSOL_MINT, USDC_MINT, USDT_MINT].map(|mint| Pubkey::from_str(mint).unwrap()),
+            legs: HashMap::new(),
+            recorded: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Records the legs of `swap`, returning the suspected wash trades it completes.
+    pub fn observe(&mut self, swap: &AnalyzedSwap, now_us: u64) -> Vec<WashTradeSuspected> {
+        self.expire(now_us);
+        let Some(wallet) = swap.user_owner else {
+            return vec![];
+        };
+        let legs = [
+            (swap.source_mint, Side::Sell, swap.realized_amount_in.unwrap_or(swap.amount_in)),
+            (swap.destination_mint, Side::Buy, swap.realized_amount_out.unwrap_or(swap.min_amount_out)),
+        ];
+        let mut suspected = vec![];
+        for (mint, side, amount) in legs {
+            let Some(mint) = mint.filter(|mint| !self.untracked_mints.contains(mint)) else {
+                continue;
+            };
+            let leg = Leg {
+                id: self.next_id,
+                at_us: now_us,
+                side,
+                amount,
+                signature: swap.signature,
+            };
+            self.next_id += 1;
+            if let Some(earlier) = self.take_match(&(wallet, mint), &leg) {
+                // A round trip between two tracked mints matches on both; report it once
+                if !suspected
+                    .iter()
+                    .any(|event: &WashTradeSuspected| event.signatures[0] == earlier.signature)
+                {
+                    suspected.push(WashTradeSuspected {
+                        wallet,
+                        mint,
+                        first_side: earlier.side,
+                        signatures: [earlier.signature, leg.signature],
+                        amounts: [earlier.amount, leg.amount],
+                        elapsed_us: now_us.saturating_sub(earlier.at_us),
+                    });
+                }
+            }
+            self.push((wallet, mint), leg);
+        }
+        suspected
+    }
+
+    /// Removes and returns the latest opposite leg of `key` matching `leg` in size.
+    fn take_match(&mut self, key: &LegKey, leg: &Leg) -> Option<Leg> {
+        let legs = self.legs.get_mut(key)?;
+        let position = legs
+            .iter()
+            .rposition(|earlier| earlier.side != leg.side && self.config.sizes_match(earlier.amount, leg.amount))?;
+        legs.remove(position)
+    }
+
+    fn push(&mut self, key: LegKey, leg: Leg) {
+        if self.recorded.len() == MAX_TRACKED_LEGS {
+            self.pop_oldest();
+        }
+        self.recorded.push_back((leg.id, leg.at_us, key));
+        self.legs.entry(key).or_default().push_back(leg);
+    }
+
+    fn expire(&mut self, now_us: u64) {
+        while self
+            .recorded
+            .front()
+            .is_some_and(|(_, at_us, _)| now_us.saturating_sub(*at_us) > self.config.window_us)
+        {
+            self.pop_oldest();
+        }
+    }
+
+    fn pop_oldest(&mut self) {
+        let Some((id, _, key)) = self.recorded.pop_front() else {
+            return;
+        };
+        if let Some(legs) = self.legs.get_mut(&key) {
+            // Unless a match already took it, the oldest leg of its key
+            if legs.front().is_some_and(|leg| leg.id == id) {
+                legs.pop_front();
+            }
+            if legs.is_empty() {
+                self.legs.remove(&key);
+            }
+        }
+    }
+}
+
+impl WashTradeConfig {
+    fn sizes_match(&self, a: u64, b: u64) -> bool {
+        let larger = a.max(b);
+        larger > 0 && (larger - a.min(b)) as f64 <= larger as f64 * self.size_tolerance_percent / 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::transaction::TransactionVersion;
+    use crate::analyzer::Dex;
+    use crate::compute_budget::PriorityFee;
+
+    fn swap(owner: Pubkey, source_mint: Pubkey, destination_mint: Pubkey, amount_in: u64, min_amount_out: u64) -> AnalyzedSwap {
+        AnalyzedSwap {
+            signature: Signature::new_unique(),
+            co_signatures: vec![],
+            dex: Dex::RaydiumV4,
+            discriminator: 9,
+            amount_in,
+            min_amount_out,
+            pool_coin_account: None,
+            pool_pc_account: None,
+            model_data_account: None,
+            user_source_account: None,
+            user_destination_account: None,
+            user_owner: Some(owner),
+            source_mint: Some(source_mint),
+            destination_mint: Some(destination_mint),
+            source_decimals: None,
+            destination_decimals: None,
+            transfers: vec![],
+            priority_fee: PriorityFee::default(),
+            compute_units_consumed: None,
+            lookup_tables: vec![],
+            transaction_size: 0,
+            version: TransactionVersion::LEGACY,
+            front_run_protected: false,
+            zero_min_out: false,
+            pool_fee_bps: None,
+            expected_amount_out: None,
+            realized_amount_out: None,
+            realized_amount_in: None,
+            mint_events: vec![],
+            burn_events: vec![],
+            authority_changes: vec![],
+            payer_sol_balance: None,
+            observers: None,
+            price_impact_bps: None,
+            pool_reserves: None,
+        }
+    }
+
+    #[test]
+    fn test_wash_trade_detector() {
+        let wallet = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let sol = Pubkey::from_str(WSOL_MINT).unwrap();
+        let mut detector = WashTradeDetector::new(WashTradeConfig {
+            window_us: 1_000,
+            size_tolerance_percent: 5.0,
+        });
+        let buy = swap(wallet, sol, mint, 10, 1_000);
+        assert!(detector.observe(&buy, 0).is_empty());
+        // Another wallet, and a sell too far off in size
+        assert!(detector.observe(&swap(Pubkey::new_unique(), mint, sol, 1_000, 10), 1).is_empty());
+        assert!(detector.observe(&swap(wallet, mint, sol, 900, 9), 2).is_empty());
+        let sell = swap(wallet, mint, sol, 980, 9);
+        assert_eq!(
+            detector.observe(&sell, 100),
+            vec![WashTradeSuspected {
+                wallet,
+                mint,
+                first_side: Side::Buy,
+                signatures: [buy.signature, sell.signature],
+                amounts: [1_000, 980],
+                elapsed_us: 100,
+            }]
+        );
+        // The buy is used up; the sell remains to match a later buy within the window
+        let rebuy = swap(wallet, sol, mint, 10, 1_000);
+        assert_eq!(detector.observe(&rebuy, 1_050)[0].signatures, [sell.signature, rebuy.signature]);
+        // Everything before 1_050 has expired
+        assert!(detector.observe(&swap(wallet, mint, sol, 1_000, 10), 2_100).is_empty());
+        assert_eq!(detector.recorded.len(), 1);
+        assert_eq!(detector.legs.len(), 1);
+    }
+
+    #[test]
+    fn test_wash_trade_between_tracked_mints() {
+        let wallet = Pubkey::new_unique();
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut detector = WashTradeDetector::new(WashTradeConfig {
+            window_us: 1_000,
+            size_tolerance_percent: 0.0,
+        });
+        assert!(detector.observe(&swap(wallet, a, b, 100, 200), 0).is_empty());
+        let suspected = detector.observe(&swap(wallet, b, a, 200, 100), 1);
+        assert_eq!(suspected.len(), 1);
+        assert_eq!((suspected[0].mint, suspected[0].first_side), (b, Side::Buy));
+    }
+}
@@ -0,0 +1,191 @@
+//! HTTP webhook output of matched swaps (`--webhook-url`, feature `webhook`).
+//!
+//! Each matched swap is POSTed as the same JSON object `--json` writes, one request
+//! per swap. Requests are made by an async client on their own thread, fed through a
+//! bounded queue: when the endpoint falls behind the queue fills up and further
+//! swaps are dropped and counted, so a slow webhook never stalls analysis.
+//! `--top-mints` reports are POSTed the same way.
+//!
+//! Connection errors, timeouts, `429` and `5xx` responses are retried with
+//! exponential backoff up to `--webhook-retries` times; other responses are final.
+use std::io;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, StatusCode};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use crate::analyzer::AnalyzedSwap;
+use crate::json_output::{mint_activity_json, swap_json};
+use crate::mint_activity::MintActivityReport;
+use crate::output::SwapSink;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(100);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Swaps waiting to be posted before new ones are dropped
+    pub queue_size: usize,
+    /// Attempts after the first for a transient failure
+    pub retries: u32,
+}
+
+pub struct WebhookSink {
+    sender: Option<mpsc::Sender<Vec<u8>>>,
+    poster: Option<JoinHandle<()>>,
+    dropped: u64,
+}
+
+impl WebhookSink {
+    /// Starts the thread posting to `config.url`.
+    pub fn spawn(config: WebhookConfig) -> io::Result<Self> {
+        let client = Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(io::Error::other)?;
+        let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let poster = thread::Builder::new()
+            .name("webhookPoster".to_string())
+            .spawn(move || runtime.block_on(post_all(client, config, receiver)))?;
+        Ok(Self {
+            sender: Some(sender),
+            poster: Some(poster),
+            dropped: 0,
+        })
+    }
+
+    /// Queues `body` for posting, dropping it if the queue is full.
+    fn send(&mut self, body: Vec<u8>) -> io::Result<()> {
+        let Some(sender) = &self.sender else {
+            return Ok(());
+        };
+        match sender.try_send(body) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                if self.dropped.is_power_of_two() {
+                    eprintln!("Webhook queue full, {} swaps dropped so far", self.dropped);
+                }
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => Err(io::Error::other("webhook poster exited")),
+        }
+    }
+}
+
+impl SwapSink for WebhookSink {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn write_swap(&mut self, received_at_us: u64, swap: &AnalyzedSwap, _packet: &[u8]) -> io::Result<()> {
+        self.send(serde_json::to_vec(&swap_json(received_at_us, swap))?)
+    }
+
+    fn write_report(&mut self, report: &MintActivityReport) -> io::Result<()> {
+        self.send(serde_json::to_vec(&mint_activity_json(report))?)
+    }
+
+    /// Posts the swaps still queued.
+    fn finish(&mut self) -> io::Result<()> {
+        self.sender = None;
+        if let Some(poster) = self.poster.take() {
+            poster.join().map_err(|_| io::Error::other("webhook poster panicked"))?;
+        }
+        if self.dropped > 0 {
+            println!("Webhook dropped {} swaps", self.dropped);
+        }
+        Ok(())
+    }
+}
+
+async fn post_all(client: Client, config: WebhookConfig, mut bodies: mpsc::Receiver<Vec<u8>>) {
+    while let Some(body) = bodies.recv().await {
+        if let Err(e) = post_with_retries(&client, &config, body).await {
+            eprintln!("Error posting swap to webhook: {}", e);
+        }
+    }
+}
+
+async fn post_with_retries(client: &Client, config: &WebhookConfig, body: Vec<u8>) -> Result<(), String> {
+    let mut delay = MIN_RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        let result = client
+            .post(&config.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone())
+            .send()
+            .await;
+        let error = match result {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) if !is_transient(response.status()) => {
+                return Err(format!("status {}", response.status()));
+            }
+            Ok(response) => format!("status {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+        if attempt == config.retries {
+            return Err(format!("{} after {} attempts", error, attempt + 1));
+        }
+        attempt += 1;
+        tokio::time::sleep(delay).await;
+        delay = delay.saturating_mul(2).min(MAX_RETRY_DELAY);
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_post_with_retries() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/swaps", listener.local_addr().unwrap());
+        // Fails the first request with a 503, accepts the retry, and returns its body
+        let server = thread::spawn(move || {
+            let mut body = vec![];
+            for status in ["503 Service Unavailable", "200 OK"] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_ascii_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(length) = line.strip_prefix("content-length: ") {
+                        content_length = length.parse().unwrap();
+                    }
+                }
+                body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+            body
+        });
+        let mut sink = WebhookSink::spawn(WebhookConfig {
+            url,
+            queue_size: 1,
+            retries: 1,
+        })
+        .unwrap();
+        sink.send(b"{\"amount_in\":1}".to_vec()).unwrap();
+        sink.finish().unwrap();
+        assert_eq!(server.join().unwrap(), b"{\"amount_in\":1}");
+        assert!(is_transient(StatusCode::BAD_GATEWAY));
+        assert!(!is_transient(StatusCode::BAD_REQUEST));
+    }
+}
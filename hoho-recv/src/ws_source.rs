@@ -0,0 +1,161 @@
+//! A secondary transaction feed from an RPC node's WebSocket (`--ws-url`).
+//!
+//! Lets the receiver run without a co-located validator. The feed subscribes with
+//! `logsSubscribe` to the transactions mentioning each DEX program, fetches every
+//! notified transaction with `getTransaction` and hands it to the analyzer through
+//! the UDP feed's channel, behind a forward header stamped when it was fetched.
+//! The transactions are confirmed, so they arrive well after their TPU packets
+//! would; the analyzer only analyzes a signature on its first arrival from either
+//! feed within [`DEDUP_WINDOW_MS`]. A dropped connection is reopened and
+//! resubscribed after the configured delay.
+use std::io;
+use std::net::TcpStream;
+use std::str::FromStr;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use serde_json::{json, Value};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+use crate::backfill;
+use crate::capture;
+use crate::forward_header::{ForwardClock, ForwardHeader, ForwardMode};
+use crate::udp_receiver::Datagram;
+
+/// Longer than a transaction takes to confirm after its packet is forwarded.
+pub const DEDUP_WINDOW_MS: u64 = 60_000;
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+#[derive(Clone, Debug)]
+pub struct WsSourceConfig {
+    /// `ws://` or `wss://` URL of the RPC node's PubSub endpoint
+    pub url: String,
+    pub program_ids: Vec<Pubkey>,
+    pub reconnect_delay: Duration,
+}
+
+/// Starts the feed, sending datagrams to `sender`. The thread exits once the
+/// receiving end of the channel is dropped.
+pub fn spawn(config: WsSourceConfig, rpc_client: Arc<RpcClient>, sender: Sender<Datagram>) -> io::Result<JoinHandle<()>> {
+    thread::Builder::new().name("wsSource".to_string()).spawn(move || loop {
+        match subscribe(&config) {
+            Ok(mut socket) => {
+                if !forward_notifications(&mut socket, &rpc_client, &sender) {
+                    return;
+                }
+            }
+            Err(e) => eprintln!("Unable to subscribe at {}: {}", config.url, e),
+        }
+        thread::sleep(config.reconnect_delay);
+    })
+}
+
+fn subscribe(config: &WsSourceConfig) -> Result<Socket, String> {
+    let (mut socket, _) = tungstenite::connect(config.url.as_str()).map_err(|e| e.to_string())?;
+    for (id, program_id) in config.program_ids.iter().enumerate() {
+        socket
+            .send(Message::Text(logs_subscribe_request(id, program_id)))
+            .map_err(|e| e.to_string())?;
+    }
+    println!("Subscribed to {} programs at {}", config.program_ids.len(), config.url);
+    Ok(socket)
+}
+
+/// Fetches and forwards notified transactions until the connection drops,
+/// returning false once the analyzer is gone.
+fn forward_notifications(socket: &mut Socket, rpc_client: &RpcClient, sender: &Sender<Datagram>) -> bool {
+    loop {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(_) => continue,
+            Err(e) => {
+                eprintln!("WebSocket feed disconnected: {}", e);
+                return true;
+            }
+        };
+        let Some((slot, signature)) = parse_logs_notification(&text) else {
+            continue;
+        };
+        let transaction = match backfill::fetch_transaction(rpc_client, &signature) {
+            Ok(historical) => historical.transaction,
+            Err(e) => {
+                eprintln!("Unable to fetch {}: {}", signature, e);
+                continue;
+            }
+        };
+        let header = ForwardHeader {
+            clock: ForwardClock::Wall,
+            mode: ForwardMode::Full,
+            timestamp_us: capture::now_us(),
+            slot: Some(slot),
+            leader: None,
+        };
+        let datagram = Datagram {
+            data: header.encode(&bincode::serialize(&transaction).unwrap()),
+            source: None,
+        };
+        if let Err(e) = sender.send(datagram) {
+            eprintln!("Error sending to channel: {}", e);
+            return false;
+        }
+    }
+}
+
+pub fn logs_subscribe_request(id: usize, program_id: &Pubkey) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "logsSubscribe",
+        "params": [{"mentions": [program_id.to_string()]}, {"commitment": "confirmed"}],
+    })
+    .to_string()
+}
+
+/// Returns the slot and signature of a `logsNotification`, or `None` for any other
+/// message. Rejected subscriptions are reported.
+pub fn parse_logs_notification(text: &str) -> Option<(u64, Signature)> {
+    let message: Value = serde_json::from_str(text).ok()?;
+    if let Some(error) = message.get("error") {
+        eprintln!("WebSocket request {} failed: {}", message["id"], error);
+        return None;
+    }
+    if message["method"] != "logsNotification" {
+        return None;
+    }
+    let result = &message["params"]["result"];
+    let signature = Signature::from_str(result["value"]["signature"].as_str()?).ok()?;
+    Some((result["context"]["slot"].as_u64()?, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_logs_notification() {
+        let signature = Signature::from([7; 64]);
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "logsNotification",
+            "params": {
+                "result": {
+                    "context": {"slot": 5208469},
+                    "value": {"signature": signature.to_string(), "err": null, "logs": []},
+                },
+                "subscription": 24040,
+            },
+        });
+        assert_eq!(parse_logs_notification(&notification.to_string()), Some((5208469, signature)));
+        assert_eq!(parse_logs_notification(r#"{"jsonrpc":"2.0","result":24040,"id":0}"#), None);
+        assert_eq!(parse_logs_notification("not json"), None);
+
+        let request: Value = serde_json::from_str(&logs_subscribe_request(3, &Pubkey::default())).unwrap();
+        assert_eq!(request["id"], 3);
+        assert_eq!(request["params"][0]["mentions"][0], Pubkey::default().to_string());
+    }
+}